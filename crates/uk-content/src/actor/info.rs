@@ -43,10 +43,84 @@ impl TryFrom<&Byml> for ActorInfo {
     }
 }
 
+/// The name hash of an actor info entry, which the game looks actors up by.
+fn name_hash(actor: &Byml) -> Option<u32> {
+    actor
+        .as_hash()
+        .ok()?
+        .get("name")?
+        .as_string()
+        .ok()
+        .map(|name| roead::aamp::hash_name(name))
+}
+
+impl ActorInfo {
+    /// Checks that the `Hashes` array of actor info BYML matches its
+    /// `Actors`: one hash per actor, each the hash of that actor's name, in
+    /// ascending order. The game binary searches the hashes, so anything else
+    /// makes actors fail to load.
+    pub fn validate_hashes(byml: &Byml) -> Result<()> {
+        let actorinfo = byml.as_hash()?;
+        let actors = actorinfo
+            .get("Actors")
+            .ok_or(UKError::MissingBymlKey("Actor info missing Actors"))?
+            .as_array()?;
+        let hashes = actorinfo
+            .get("Hashes")
+            .ok_or(UKError::MissingBymlKey("Actor info missing Hashes"))?
+            .as_array()?;
+        if actors.len() != hashes.len() {
+            return Err(UKError::OtherD(format!(
+                "Actor info has {} actors but {} hashes",
+                actors.len(),
+                hashes.len()
+            )));
+        }
+        let mut last = None;
+        for (i, (actor, hash)) in actors.iter().zip(hashes).enumerate() {
+            let hash = match hash {
+                Byml::I32(hash) => *hash as u32,
+                Byml::U32(hash) => *hash,
+                _ => {
+                    return Err(UKError::WrongBymlType(
+                        "not an integer".into(),
+                        "an integer",
+                    ));
+                }
+            };
+            let expected = name_hash(actor).ok_or(UKError::MissingBymlKey(
+                "Actor info entry missing name",
+            ))?;
+            if hash != expected {
+                return Err(UKError::OtherD(format!(
+                    "Actor info hash {i} is {hash:#x}, but the actor's name hashes to \
+                     {expected:#x}"
+                )));
+            }
+            if last.map_or(false, |last| last >= hash) {
+                return Err(UKError::OtherD(format!(
+                    "Actor info hash {i} ({hash:#x}) is out of order"
+                )));
+            }
+            last = Some(hash);
+        }
+        Ok(())
+    }
+}
+
 impl From<ActorInfo> for Byml {
     fn from(val: ActorInfo) -> Self {
-        let (hashes, actors) = val
+        // Rebuild the index from the actor names rather than trusting the
+        // keys, so merged entries always come out sorted with the right
+        // hashes.
+        let mut entries: Vec<(u32, Byml)> = val
             .0
+            .into_iter()
+            .map(|(hash, actor)| (name_hash(&actor).unwrap_or(hash), actor))
+            .collect();
+        entries.sort_by_key(|(hash, _)| *hash);
+        entries.dedup_by_key(|(hash, _)| *hash);
+        let (hashes, actors) = entries
             .into_iter()
             .map(|(hash, actor)| {
                 (
@@ -147,6 +221,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn rebuild_hashes() {
+        let byml = load_actorinfo();
+        super::ActorInfo::validate_hashes(&byml).unwrap();
+        let mut actorinfo = super::ActorInfo::try_from(&byml).unwrap();
+        let mut actor = actorinfo.0.values().next().unwrap().clone();
+        if let Byml::Hash(actor) = &mut actor {
+            actor.insert("name".into(), Byml::String("Dummy_UKMM_Test".into()));
+        }
+        // A stale key should not end up in the hashes
+        actorinfo.0.insert(0u32, actor);
+        let byml = Byml::from(actorinfo);
+        super::ActorInfo::validate_hashes(&byml).unwrap();
+        let hashes = byml.as_hash().unwrap().get("Hashes").unwrap().as_array().unwrap();
+        let dummy = roead::aamp::hash_name("Dummy_UKMM_Test");
+        assert!(hashes.iter().any(|hash| {
+            match hash {
+                Byml::I32(hash) => *hash as u32 == dummy,
+                Byml::U32(hash) => *hash == dummy,
+                _ => false,
+            }
+        }));
+        let mut bad = byml.clone();
+        if let Byml::Hash(bad) = &mut bad {
+            if let Some(Byml::Array(hashes)) = bad.get_mut("Hashes") {
+                hashes.swap(0, 1);
+            }
+        }
+        assert!(super::ActorInfo::validate_hashes(&bad).is_err());
+    }
+
     #[test]
     fn identify() {
         let path = std::path::Path::new("content/Actor/ActorInfo.product.sbyml");
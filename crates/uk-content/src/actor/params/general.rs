@@ -34,6 +34,77 @@ impl From<GeneralParamList> for ParameterIO {
 
 impl_simple_aamp!(GeneralParamList, 0);
 
+/// How prices and other numeric economy fields changed by more than one mod
+/// are combined. Other parameters are always merged normally.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EconomyMergePolicy {
+    /// The last mod in the load order wins, like any other parameter.
+    #[default]
+    LastWins,
+    /// Each mod's change is applied as a percentage of the vanilla value, so
+    /// two mods which each raise a price by 10% raise it by 21%.
+    Percentage,
+    /// Each mod's change is added as a difference from the vanilla value, so
+    /// two mods which each raise a price by 10 raise it by 20.
+    Additive,
+}
+
+impl EconomyMergePolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EconomyMergePolicy::LastWins => "Last Wins",
+            EconomyMergePolicy::Percentage => "Percentage",
+            EconomyMergePolicy::Additive => "Additive",
+        }
+    }
+}
+
+/// The object and parameter names of the integer fields covered by
+/// [`EconomyMergePolicy`].
+pub static ECONOMY_FIELDS: &[(&str, &str)] = &[
+    ("Item", "BuyingPrice"),
+    ("Item", "CreatingPrice"),
+    ("Item", "SellingPrice"),
+    ("MonsterShop", "BuyMamo"),
+    ("MonsterShop", "SellMamo"),
+    ("Rupee", "RupeeValue"),
+];
+
+impl GeneralParamList {
+    /// Merges a diff like [`Mergeable::merge`], then recalculates the economy
+    /// fields the diff changes from the vanilla values according to `policy`.
+    pub fn merge_economy(&self, diff: &Self, vanilla: &Self, policy: EconomyMergePolicy) -> Self {
+        let mut merged = self.merge(diff);
+        if policy == EconomyMergePolicy::LastWins {
+            return merged;
+        }
+        let get = |pio: &ParameterIO, obj: &str, param: &str| -> Option<i32> {
+            pio.object(obj)?.get(param)?.as_int().ok()
+        };
+        for (obj, param) in ECONOMY_FIELDS {
+            let (Some(modded), Some(base), Some(current)) = (
+                get(&diff.0, obj, param),
+                get(&vanilla.0, obj, param),
+                get(&self.0, obj, param),
+            ) else {
+                continue;
+            };
+            let value = match policy {
+                EconomyMergePolicy::Percentage if base != 0 => {
+                    (current as f64 * modded as f64 / base as f64).round() as i32
+                }
+                EconomyMergePolicy::Percentage => modded,
+                EconomyMergePolicy::Additive => current.saturating_add(modded - base),
+                EconomyMergePolicy::LastWins => unreachable!(),
+            };
+            if let Some(obj) = merged.0.object_mut(obj) {
+                obj.insert(*param, Parameter::I32(value));
+            }
+        }
+        merged
+    }
+}
+
 impl InfoSource for GeneralParamList {
     fn update_info(&self, info: &mut roead::byml::Hash) -> crate::Result<()> {
         if let Some(obj) = self.0.object("AnimalUnit") {
@@ -330,6 +401,34 @@ mod tests {
         assert_eq!(gparamlist2, merged);
     }
 
+    #[test]
+    fn merge_economy() {
+        use roead::aamp::{Parameter, ParameterIO};
+
+        use super::{EconomyMergePolicy, GeneralParamList};
+        use crate::util::params;
+
+        let price = |value: i32| -> GeneralParamList {
+            let mut pio = ParameterIO::new();
+            pio.objects_mut()
+                .insert("Item", params!("BuyingPrice" => Parameter::I32(value)));
+            pio.into()
+        };
+        let vanilla = price(100);
+        let diff_a = vanilla.diff(&price(110));
+        let diff_b = vanilla.diff(&price(120));
+        for (policy, expected) in [
+            (EconomyMergePolicy::LastWins, 120),
+            (EconomyMergePolicy::Percentage, 132),
+            (EconomyMergePolicy::Additive, 130),
+        ] {
+            let merged = vanilla
+                .merge_economy(&diff_a, &vanilla, policy)
+                .merge_economy(&diff_b, &vanilla, policy);
+            assert_eq!(merged, price(expected), "{}", policy.name());
+        }
+    }
+
     #[test]
     fn info() {
         use roead::byml::Byml;
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "ui")]
 use uk_ui_derive::Editable;
 
-use crate::{prelude::*, resource::SortedDeleteMap, Result, UKError};
+use crate::{prelude::*, util::DeleteMap, Result, UKError};
 
 #[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 #[cfg_attr(feature = "ui", derive(Editable))]
@@ -30,7 +30,7 @@ impl TryFrom<&Hash> for ResidentActorData {
 
 #[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 #[cfg_attr(feature = "ui", derive(Editable))]
-pub struct ResidentActors(pub SortedDeleteMap<String, ResidentActorData>);
+pub struct ResidentActors(pub DeleteMap<String, ResidentActorData>);
 
 impl TryFrom<&Byml> for ResidentActors {
     type Error = UKError;
@@ -93,6 +93,17 @@ impl Mergeable for ResidentActors {
     }
 }
 
+impl ResidentActors {
+    /// Roughly how many resident actors mods can add before the extra memory
+    /// they hold for the whole game is likely to cause trouble on console.
+    pub const ADDED_BUDGET: usize = 24;
+
+    /// Names of the actors in this list which are not in `base`.
+    pub fn added_since<'a>(&'a self, base: &Self) -> Vec<&'a String> {
+        self.0.keys().filter(|name| !base.0.contains_key(*name)).collect()
+    }
+}
+
 impl Resource for ResidentActors {
     fn from_binary(data: impl AsRef<[u8]>) -> crate::Result<Self> {
         (&Byml::from_binary(data.as_ref())?).try_into()
@@ -152,6 +163,24 @@ mod tests {
         assert_eq!(merged, residents2);
     }
 
+    #[test]
+    fn merge_appends() {
+        let residents = |names: &[&str]| {
+            super::ResidentActors(
+                names
+                    .iter()
+                    .map(|n| ((*n).into(), super::ResidentActorData::default()))
+                    .collect(),
+            )
+        };
+        let base = residents(&["PlayerStole2", "Armor_Default_Extra_00"]);
+        let mod1 = residents(&["PlayerStole2", "Armor_Default_Extra_00", "Obj_Mod1"]);
+        let mod2 = residents(&["PlayerStole2", "Obj_Mod2"]);
+        let merged = base.merge(&base.diff(&mod1)).merge(&base.diff(&mod2));
+        assert_eq!(merged, residents(&["PlayerStole2", "Obj_Mod1", "Obj_Mod2"]));
+        assert_eq!(merged.added_since(&base).len(), 2);
+    }
+
     #[test]
     fn identify() {
         let path = std::path::Path::new("content/Pack/TitleBG.pack//Actor/ResidentActors.byml");
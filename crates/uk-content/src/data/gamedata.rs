@@ -12,7 +12,7 @@ use uk_content_derive::BymlData;
 
 use crate::{
     prelude::*,
-    util::{bhash, DeleteMap},
+    util::{bhash, DeleteMap, HashMap},
     Result, UKError,
 };
 #[cfg(feature = "ui")]
@@ -467,6 +467,136 @@ impl GameDataPack {
     }
 }
 
+/// A flag which a mod defines with a different type or hash than a previous
+/// mod already did. The game looks up flags by hash, so merging both would
+/// corrupt saves.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FlagConflict {
+    pub name: String,
+    /// The data type and hash already in the merged gamedata
+    pub existing: (String, i32),
+    /// The data type and hash the incoming mod uses
+    pub incoming: (String, i32),
+}
+
+/// How to handle a mod's side of a flag conflict.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum FlagResolution {
+    /// Drop the mod's definition and keep the one already merged.
+    Skip,
+    /// Rename the mod's flag. Only the gamedata definition is renamed, so
+    /// this is only safe if nothing else needs the mod's flag by name.
+    Remap(String),
+}
+
+macro_rules! gamedata_types {
+    (mut $data:expr, $($type:ident),* $(,)?) => {
+        [$(&mut $data.$type),*]
+    };
+    ($data:expr, $($type:ident),* $(,)?) => {
+        [$(&$data.$type),*]
+    };
+}
+
+macro_rules! all_gamedata_types {
+    ($($args:tt)*) => {
+        gamedata_types!(
+            $($args)*,
+            bool_array_data,
+            bool_data,
+            f32_array_data,
+            f32_data,
+            revival_bool_data,
+            revival_s32_data,
+            s32_array_data,
+            s32_data,
+            string32_data,
+            string64_array_data,
+            string64_data,
+            string256_array_data,
+            string256_data,
+            vector2f_array_data,
+            vector2f_data,
+            vector3f_array_data,
+            vector3f_data,
+            vector4f_data,
+        )
+    };
+}
+
+impl GameDataPack {
+    /// Name, data type, and hash of every flag defined in this pack.
+    fn flag_defs(&self) -> impl Iterator<Item = (&String, &String, i32)> {
+        all_gamedata_types!(self).into_iter().flat_map(|data| {
+            data.flags
+                .iter()
+                .map(move |(name, flag)| (name, &data.data_type, flag.hash_value))
+        })
+    }
+
+    /// Finds flags in `other` which this pack already defines with a
+    /// different type or hash.
+    pub fn flag_conflicts(&self, other: &Self) -> Vec<FlagConflict> {
+        let existing: HashMap<&String, (&String, i32)> = self
+            .flag_defs()
+            .map(|(name, data_type, hash)| (name, (data_type, hash)))
+            .collect();
+        other
+            .flag_defs()
+            .filter_map(|(name, data_type, hash)| {
+                existing
+                    .get(name)
+                    .filter(|(other_type, other_hash)| {
+                        *other_type != data_type || *other_hash != hash
+                    })
+                    .map(|&(other_type, other_hash)| {
+                        FlagConflict {
+                            name:     name.clone(),
+                            existing: (other_type.clone(), other_hash),
+                            incoming: (data_type.clone(), hash),
+                        }
+                    })
+            })
+            .collect()
+    }
+
+    /// Removes a flag from every data type.
+    pub fn remove_flag(&mut self, name: &str) {
+        let key = String::from(name);
+        for data in all_gamedata_types!(mut self) {
+            if data.flags.contains_key(&key) {
+                data.flags = data
+                    .flags
+                    .iter_full()
+                    .filter(|(k, _)| k.as_str() != name)
+                    .map(|(k, (v, del))| (k.clone(), v.clone(), *del))
+                    .collect();
+            }
+        }
+    }
+
+    /// Applies a conflict resolution to this pack's definition of a flag.
+    pub fn resolve_flag(&mut self, name: &str, resolution: &FlagResolution) {
+        match resolution {
+            FlagResolution::Skip => self.remove_flag(name),
+            FlagResolution::Remap(new_name) if new_name != name => {
+                let key = String::from(name);
+                for data in all_gamedata_types!(mut self) {
+                    if let Some(flag) = data.flags.get(&key).cloned() {
+                        data.flags.insert(new_name.clone(), FlagData {
+                            data_name: new_name.clone(),
+                            hash_value: roead::aamp::hash_name(new_name) as i32,
+                            ..flag
+                        });
+                    }
+                }
+                self.remove_flag(name);
+            }
+            FlagResolution::Remap(_) => (),
+        }
+    }
+}
+
 impl Mergeable for GameDataPack {
     fn diff(&self, other: &Self) -> Self {
         Self {
@@ -591,6 +721,53 @@ mod tests {
         assert_eq!(gamedata, gamedata2);
     }
 
+    #[test]
+    fn flag_conflicts() {
+        use super::{FlagData, FlagResolution, GameData, GameDataPack};
+        fn flag(name: &str, hash: i32) -> (smartstring::alias::String, FlagData) {
+            (name.into(), FlagData {
+                data_name: name.into(),
+                hash_value: hash,
+                ..Default::default()
+            })
+        }
+        let first = GameDataPack {
+            bool_data: GameData {
+                data_type: "bool_data".into(),
+                flags:     [flag("Mod_Flag", 1), flag("Mod_Other", 2)]
+                    .into_iter()
+                    .collect(),
+            },
+            ..Default::default()
+        };
+        let mut second = GameDataPack {
+            s32_data: GameData {
+                data_type: "s32_data".into(),
+                flags:     [flag("Mod_Flag", 1)].into_iter().collect(),
+            },
+            bool_data: GameData {
+                data_type: "bool_data".into(),
+                flags:     [flag("Mod_Other", 2)].into_iter().collect(),
+            },
+            ..Default::default()
+        };
+        let conflicts = first.flag_conflicts(&second);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].name, "Mod_Flag");
+        assert_eq!(conflicts[0].incoming.0, "s32_data");
+        second.resolve_flag("Mod_Flag", &FlagResolution::Remap("Mod_Flag2".into()));
+        assert!(first.flag_conflicts(&second).is_empty());
+        assert_eq!(
+            second
+                .s32_data
+                .flags
+                .get(smartstring::alias::String::from("Mod_Flag2"))
+                .unwrap()
+                .hash_value,
+            roead::aamp::hash_name("Mod_Flag2") as i32
+        );
+    }
+
     #[test]
     fn identify() {
         let path = std::path::Path::new("content/Pack/Bootup.pack//GameData/gamedata.ssarc");
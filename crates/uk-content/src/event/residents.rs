@@ -61,11 +61,38 @@ impl From<ResidentEvents> for Byml {
 
 impl Mergeable for ResidentEvents {
     fn diff(&self, other: &Self) -> Self {
-        Self(self.0.diff(&other.0))
+        Self(self.0.deep_diff(&other.0))
     }
 
     fn merge(&self, diff: &Self) -> Self {
-        Self(self.0.merge(&diff.0))
+        Self(self.0.deep_merge(&diff.0))
+    }
+}
+
+impl ResidentEvents {
+    /// Roughly how many resident event files mods can add before the extra
+    /// memory they hold for the whole game is likely to cause trouble on
+    /// console.
+    pub const ADDED_BUDGET: usize = 16;
+
+    /// The event files in this list which are not in `base`, as
+    /// `(entry, file)` pairs.
+    pub fn added_since<'a>(&'a self, base: &Self) -> Vec<(&'a String, &'a String)> {
+        self.0
+            .iter()
+            .flat_map(|(entry, files)| {
+                files
+                    .iter()
+                    .filter(move |file| {
+                        !base
+                            .0
+                            .get(entry)
+                            .map(|base| base.contains(*file))
+                            .unwrap_or(false)
+                    })
+                    .map(move |file| (entry, file))
+            })
+            .collect()
     }
 }
 
@@ -128,6 +155,31 @@ mod tests {
         assert_eq!(merged, residents2);
     }
 
+    #[test]
+    fn merge_files() {
+        use crate::util::bhash;
+        let events = |files: &[&str]| {
+            super::ResidentEvents::try_from(&Byml::Array(
+                files
+                    .iter()
+                    .map(|file| {
+                        bhash!(
+                            "entry" => Byml::String("Resident".into()),
+                            "file" => Byml::String((*file).into())
+                        )
+                    })
+                    .collect(),
+            ))
+            .unwrap()
+        };
+        let base = events(&["Demo000"]);
+        let mod1 = events(&["Demo000", "Mod1"]);
+        let mod2 = events(&["Demo000", "Mod2"]);
+        let merged = base.merge(&base.diff(&mod1)).merge(&base.diff(&mod2));
+        assert_eq!(merged, events(&["Demo000", "Mod1", "Mod2"]));
+        assert_eq!(merged.added_since(&base).len(), 2);
+    }
+
     #[test]
     fn identify() {
         let path = std::path::Path::new("content/Pack/TitleBG.pack//Event/ResidentEvent.byml");
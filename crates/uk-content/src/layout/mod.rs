@@ -1,8 +1,12 @@
-use roead::sarc::*;
+use roead::{aamp::ParameterIO, byml::Byml, sarc::*};
 use serde::{Deserialize, Serialize};
 use smartstring::alias::String;
 
-use crate::{prelude::*, util::IndexMap, Result, UKError};
+use crate::{
+    prelude::*,
+    util::{diff_byml_shallow, diff_plist, merge_byml_shallow, merge_plist, IndexMap},
+    Result, UKError,
+};
 
 #[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 pub struct LayoutArchive(pub IndexMap<String, Vec<u8>>);
@@ -19,13 +23,80 @@ impl TryFrom<&'_ Sarc<'_>> for LayoutArchive {
     }
 }
 
+/// Layout archives mix binary layouts and animations with a few BYML and AAMP
+/// config files. The binary files can only be replaced whole, but the configs
+/// are diffed and merged by key, so separate HUD tweaks to the same config
+/// file stack instead of overwriting each other.
+fn diff_entry(base: &[u8], other: &[u8]) -> Vec<u8> {
+    if base.starts_with(b"AAMP")
+        && other.starts_with(b"AAMP")
+        && let (Ok(base), Ok(other)) = (ParameterIO::from_binary(base), ParameterIO::from_binary(other))
+    {
+        ParameterIO {
+            param_root: diff_plist(&base.param_root, &other.param_root),
+            ..other
+        }
+        .to_binary()
+    } else if let Some(endian) = byml_endian(base)
+        && let (Ok(base @ Byml::Hash(_)), Ok(other @ Byml::Hash(_))) =
+            (Byml::from_binary(base), Byml::from_binary(other))
+    {
+        diff_byml_shallow(&base, &other).to_binary(endian)
+    } else {
+        other.to_vec()
+    }
+}
+
+fn merge_entry(base: &[u8], diff: &[u8]) -> Vec<u8> {
+    if base.starts_with(b"AAMP")
+        && diff.starts_with(b"AAMP")
+        && let (Ok(base), Ok(diff)) = (ParameterIO::from_binary(base), ParameterIO::from_binary(diff))
+    {
+        ParameterIO {
+            param_root: merge_plist(&base.param_root, &diff.param_root),
+            ..base
+        }
+        .to_binary()
+    } else if let Some(endian) = byml_endian(base)
+        && let (Ok(base @ Byml::Hash(_)), Ok(diff @ Byml::Hash(_))) =
+            (Byml::from_binary(base), Byml::from_binary(diff))
+    {
+        match merge_byml_shallow(&base, &diff) {
+            Byml::Hash(hash) => {
+                Byml::Hash(hash.into_iter().filter(|(_, v)| *v != Byml::Null).collect())
+            }
+            other => other,
+        }
+        .to_binary(endian)
+    } else {
+        diff.to_vec()
+    }
+}
+
+#[inline]
+fn byml_endian(data: &[u8]) -> Option<roead::Endian> {
+    if data.starts_with(b"BY") {
+        Some(roead::Endian::Big)
+    } else if data.starts_with(b"YB") {
+        Some(roead::Endian::Little)
+    } else {
+        None
+    }
+}
+
 impl Mergeable for LayoutArchive {
     fn diff(&self, other: &Self) -> Self {
         Self(
             other
                 .0
                 .iter()
-                .filter_map(|(n, d)| (self.0.get(n) != Some(d)).then(|| (n.clone(), d.clone())))
+                .filter_map(|(n, d)| {
+                    match self.0.get(n) {
+                        Some(base) if base == d => None,
+                        Some(base) => Some((n.clone(), diff_entry(base, d))),
+                        None => Some((n.clone(), d.clone())),
+                    }
+                })
                 .collect(),
         )
     }
@@ -34,7 +105,18 @@ impl Mergeable for LayoutArchive {
         Self(
             self.0
                 .iter()
-                .map(|(k, v)| (k.clone(), diff.0.get(k).unwrap_or(v).to_vec()))
+                .map(|(k, v)| {
+                    (
+                        k.clone(),
+                        diff.0.get(k).map(|d| merge_entry(v, d)).unwrap_or_else(|| v.clone()),
+                    )
+                })
+                .chain(
+                    diff.0
+                        .iter()
+                        .filter(|(k, _)| !self.0.contains_key(*k))
+                        .map(|(k, v)| (k.clone(), v.clone())),
+                )
                 .collect(),
         )
     }
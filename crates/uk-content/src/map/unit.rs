@@ -3,7 +3,11 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "ui")]
 use uk_ui_derive::Editable;
 
-use crate::{prelude::*, util::SortedDeleteMap, Result, UKError};
+use crate::{
+    prelude::*,
+    util::{bhash, SortedDeleteMap},
+    Result, UKError,
+};
 
 #[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 #[cfg_attr(feature = "ui", derive(Editable))]
@@ -13,6 +17,26 @@ pub struct MapUnit {
     pub size:    Option<f32>,
     pub objects: SortedDeleteMap<u32, Byml>,
     pub rails:   SortedDeleteMap<u32, Byml>,
+    /// Any other arrays keyed by hash ID, such as the gimmick linkage tables
+    /// in dungeon map units.
+    #[serde(default)]
+    pub tables:  SortedDeleteMap<String, SortedDeleteMap<u32, Byml>>,
+    /// Any other top-level values, which are merged whole.
+    #[serde(default)]
+    pub other:   SortedDeleteMap<String, Byml>,
+}
+
+const KNOWN_KEYS: &[&str] = &["Objs", "Rails", "LocationPosX", "LocationPosZ", "LocationSize"];
+
+/// Keys the entries of an array by hash ID, if every entry has one.
+fn hash_id_table(array: &[Byml]) -> Option<SortedDeleteMap<u32, Byml>> {
+    array
+        .iter()
+        .map(|entry| {
+            let id = entry.as_hash().ok()?.get("HashId")?.as_int().ok()?;
+            Some((id, entry.clone()))
+        })
+        .collect()
 }
 
 impl TryFrom<&Byml> for MapUnit {
@@ -61,6 +85,29 @@ impl TryFrom<&Byml> for MapUnit {
                     Ok((id, obj.clone()))
                 })
                 .collect::<Result<_>>()?,
+            tables:  hash
+                .iter()
+                .filter(|(k, _)| !KNOWN_KEYS.contains(&k.as_str()))
+                .filter_map(|(k, v)| {
+                    v.as_array()
+                        .ok()
+                        .filter(|a| !a.is_empty())
+                        .and_then(|a| hash_id_table(a))
+                        .map(|table| (k.clone(), table))
+                })
+                .collect(),
+            other:   hash
+                .iter()
+                .filter(|(k, v)| {
+                    !KNOWN_KEYS.contains(&k.as_str())
+                        && v.as_array()
+                            .ok()
+                            .filter(|a| !a.is_empty())
+                            .and_then(|a| hash_id_table(a))
+                            .is_none()
+                })
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
         })
     }
 }
@@ -75,6 +122,13 @@ impl From<MapUnit> for Byml {
             ("Rails", val.rails.into_iter().map(|(_, obj)| obj).collect()),
         ]
         .into_iter()
+        .map(|(k, v)| (String::from(k), v))
+        .chain(
+            val.tables
+                .into_iter()
+                .map(|(k, table)| (k, table.into_iter().map(|(_, v)| v).collect())),
+        )
+        .chain(val.other)
         .chain(
             [
                 ("LocationPosX", val.pos_x),
@@ -82,30 +136,128 @@ impl From<MapUnit> for Byml {
                 ("LocationSize", val.size),
             ]
             .into_iter()
-            .filter_map(|(k, v)| v.map(|v| (k, Byml::Float(v)))),
+            .filter_map(|(k, v)| v.map(|v| (k.into(), Byml::Float(v)))),
         )
         .collect()
     }
 }
 
+/// Key used in diffs to record the links a mod removed from an object, so
+/// that link changes can be merged instead of replacing the whole object.
+static DELETED_LINKS: &str = "UKMM_DeletedLinks";
+
+fn link_key(link: &Byml) -> Option<(u32, String)> {
+    let link = link.as_hash().ok()?;
+    Some((
+        link.get("DestUnitHashId")?.as_int().ok()?,
+        link.get("DefinitionName")?.as_string().ok()?.clone(),
+    ))
+}
+
+fn links(obj: &Byml) -> &[Byml] {
+    obj.as_hash()
+        .ok()
+        .and_then(|h| h.get("LinksToObj"))
+        .and_then(|l| l.as_array().ok())
+        .map(|l| l.as_slice())
+        .unwrap_or_default()
+}
+
+/// Marks any links removed from a modified object. Enemy camps and other
+/// object groups are defined by the links on their leader object, so this
+/// lets edits from different mods to the same group combine. Map units are
+/// the only place groups are defined: AI schedules belong to single actors
+/// and are still merged as a whole.
+fn diff_links(base: &Byml, other: &Byml) -> Byml {
+    let other_keys: Vec<_> = links(other).iter().filter_map(link_key).collect();
+    let deleted: Vec<Byml> = links(base)
+        .iter()
+        .filter_map(link_key)
+        .filter(|key| !other_keys.contains(key))
+        .map(|(id, name)| {
+            bhash!(
+                "DestUnitHashId" => Byml::U32(id),
+                "DefinitionName" => Byml::String(name),
+            )
+        })
+        .collect();
+    let mut other = other.clone();
+    if !deleted.is_empty() && let Byml::Hash(hash) = &mut other {
+        hash.insert(DELETED_LINKS.into(), Byml::Array(deleted));
+    }
+    other
+}
+
+fn merge_links(base: &Byml, diff: &Byml) -> Byml {
+    let mut merged = diff.clone();
+    let Byml::Hash(hash) = &mut merged else { return diff.clone() };
+    let deleted: Vec<_> = hash
+        .remove(DELETED_LINKS)
+        .map(|d| {
+            d.as_array()
+                .map(|d| d.iter().filter_map(link_key).collect())
+                .unwrap_or_default()
+        })
+        .unwrap_or_default();
+    let diff_links = links(diff);
+    let diff_keys: Vec<_> = diff_links.iter().filter_map(link_key).collect();
+    let links: Vec<Byml> = diff_links
+        .iter()
+        .cloned()
+        .chain(
+            links(base)
+                .iter()
+                .filter(|link| {
+                    link_key(link)
+                        .map(|key| !diff_keys.contains(&key) && !deleted.contains(&key))
+                        .unwrap_or(false)
+                })
+                .cloned(),
+        )
+        .collect();
+    if !links.is_empty() {
+        hash.insert("LinksToObj".into(), Byml::Array(links));
+    }
+    merged
+}
+
 impl Mergeable for MapUnit {
     fn diff(&self, other: &Self) -> Self {
+        let mut objects = self.objects.diff(&other.objects);
+        for (id, obj) in objects.iter_mut() {
+            if let Some(base) = self.objects.get(id) {
+                *obj = diff_links(base, obj);
+            }
+        }
         Self {
-            pos_x:   other.pos_x,
-            pos_z:   other.pos_z,
-            size:    other.size,
-            objects: self.objects.diff(&other.objects),
-            rails:   self.rails.diff(&other.rails),
+            pos_x: other.pos_x,
+            pos_z: other.pos_z,
+            size: other.size,
+            objects,
+            rails: self.rails.diff(&other.rails),
+            tables: self.tables.deep_diff(&other.tables),
+            other: self.other.diff(&other.other),
         }
     }
 
     fn merge(&self, diff: &Self) -> Self {
+        let mut objects = self.objects.merge(&diff.objects);
+        for (id, obj) in objects.iter_mut() {
+            if let Some(diff) = diff.objects.get(id) {
+                *obj = match self.objects.get(id) {
+                    Some(base) => merge_links(base, diff),
+                    None => merge_links(&Byml::Null, diff),
+                };
+            }
+        }
         Self {
-            pos_x:   diff.pos_x,
-            pos_z:   diff.pos_z,
-            size:    diff.size,
-            objects: self.objects.merge(&diff.objects),
-            rails:   self.rails.merge(&diff.rails),
+            pos_x: diff.pos_x,
+            pos_z: diff.pos_z,
+            size: diff.size,
+            objects,
+            rails: self.rails.merge(&diff.rails),
+            tables: self.tables.deep_merge(&diff.tables),
+            other: self.other.merge(&diff.other),
         }
     }
 }
@@ -239,6 +391,59 @@ mod tests {
         assert_eq!(merged, munt2);
     }
 
+    #[test]
+    fn merge_group_links() {
+        use crate::util::bhash;
+        let link = |id: u32| {
+            bhash!(
+                "DefinitionName" => Byml::String("Reference".into()),
+                "DestUnitHashId" => Byml::U32(id),
+            )
+        };
+        let munt = |links: Vec<Byml>| {
+            super::MapUnit::try_from(&bhash!(
+                "Objs" => Byml::Array(vec![bhash!(
+                    "HashId" => Byml::U32(1),
+                    "UnitConfigName" => Byml::String("Enemy_Bokoblin_Junior".into()),
+                    "LinksToObj" => Byml::Array(links),
+                )]),
+                "Rails" => Byml::Array(vec![]),
+            ))
+            .unwrap()
+        };
+        let base = munt(vec![link(2), link(3)]);
+        let mod1 = munt(vec![link(2), link(3), link(4)]);
+        let mod2 = munt(vec![link(2), link(5)]);
+        let merged = base.merge(&base.diff(&mod1)).merge(&base.diff(&mod2));
+        assert_eq!(merged, munt(vec![link(2), link(5), link(4)]));
+    }
+
+    #[test]
+    fn merge_gimmick_tables() {
+        use crate::util::bhash;
+        let link = |id: u32, dest: u32| {
+            bhash!(
+                "HashId" => Byml::U32(id),
+                "DestHashId" => Byml::U32(dest),
+            )
+        };
+        let munt = |links: Vec<Byml>| {
+            super::MapUnit::try_from(&bhash!(
+                "Objs" => Byml::Array(vec![]),
+                "Rails" => Byml::Array(vec![]),
+                "StaticGimmick" => Byml::Array(links),
+            ))
+            .unwrap()
+        };
+        let base = munt(vec![link(1, 10), link(2, 20)]);
+        let mod1 = munt(vec![link(1, 11), link(2, 20)]);
+        let mod2 = munt(vec![link(1, 10), link(2, 20), link(3, 30)]);
+        let merged = base.merge(&base.diff(&mod1)).merge(&base.diff(&mod2));
+        assert_eq!(merged, munt(vec![link(1, 11), link(2, 20), link(3, 30)]));
+        let byml = Byml::from(merged.clone());
+        assert_eq!(super::MapUnit::try_from(&byml).unwrap(), merged);
+    }
+
     #[test]
     fn identify() {
         let path = std::path::Path::new("content/Map/MainField/F-3/F-3_Dynamic.smubin");
@@ -1,9 +1,9 @@
-use roead::byml::Byml;
+use roead::byml::{Byml, Hash};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "ui")]
 use uk_ui_derive::Editable;
 
-use crate::{prelude::*, util::DeleteMap, Result, UKError};
+use crate::{actor::info::ActorInfo, prelude::*, util::DeleteMap, Result, UKError};
 
 #[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 #[cfg_attr(feature = "ui", derive(Editable))]
@@ -38,13 +38,237 @@ impl From<QuestProduct> for Byml {
     }
 }
 
+#[inline]
+fn step_name(step: &Byml) -> Option<&str> {
+    step.as_hash().ok()?.get("Name")?.as_string().ok().map(|n| n.as_str())
+}
+
+/// Diffs the steps of a quest by name. The result is wrapped in a hash so it
+/// can't be mistaken for a full list of steps, and holds, in order, a partial
+/// step for each changed or added step and the bare name of each removed one.
+fn diff_steps(base: &[Byml], other: &[Byml]) -> Option<Byml> {
+    let base_names = base.iter().map(step_name).collect::<Option<Vec<_>>>()?;
+    let other_names = other.iter().map(step_name).collect::<Option<Vec<_>>>()?;
+    // The merged steps keep the base order with new ones at the end, so if a
+    // mod reorders them the whole list has to be replaced.
+    let common = other_names
+        .iter()
+        .filter(|n| base_names.contains(n))
+        .collect::<Vec<_>>();
+    let first_new = other_names.iter().position(|n| !base_names.contains(n));
+    let last_common = other_names.iter().rposition(|n| base_names.contains(n));
+    if !base_names
+        .iter()
+        .filter(|n| other_names.contains(n))
+        .eq(common.iter().copied())
+        || matches!((first_new, last_common), (Some(new), Some(common)) if new < common)
+    {
+        return None;
+    }
+    let changes = other
+        .iter()
+        .zip(other_names.iter())
+        .filter_map(|(step, name)| {
+            match base_names.iter().position(|n| n == name).map(|i| &base[i]) {
+                Some(base_step) if base_step == step => None,
+                Some(base_step @ Byml::Hash(_)) if step.as_hash().is_ok() => {
+                    let mut diff = crate::util::diff_byml_shallow(base_step, step);
+                    if let Byml::Hash(diff) = &mut diff {
+                        diff.insert("Name".into(), Byml::String((*name).into()));
+                    }
+                    Some(diff)
+                }
+                _ => Some(step.clone()),
+            }
+        })
+        .chain(
+            base_names
+                .iter()
+                .filter(|n| !other_names.contains(n))
+                .map(|n| Byml::String((*n).into())),
+        )
+        .collect();
+    Some(Byml::Hash([("Diff".into(), Byml::Array(changes))].into_iter().collect()))
+}
+
+fn merge_steps(base: Option<&Byml>, diff: &[Byml]) -> Byml {
+    let without_nulls = |fields: Hash| -> Hash {
+        fields.into_iter().filter(|(_, v)| *v != Byml::Null).collect()
+    };
+    match base {
+        Some(Byml::Array(steps)) => {
+            let mut steps = steps.clone();
+            for change in diff {
+                match change {
+                    Byml::String(name) => steps.retain(|s| step_name(s) != Some(name.as_str())),
+                    Byml::Hash(fields) => {
+                        let name = step_name(change);
+                        match steps.iter_mut().find(|s| name.is_some() && step_name(s) == name) {
+                            Some(Byml::Hash(step)) => {
+                                let mut merged = step.clone();
+                                merged.extend(fields.iter().map(|(k, v)| (k.clone(), v.clone())));
+                                *step = without_nulls(merged);
+                            }
+                            _ => steps.push(Byml::Hash(without_nulls(fields.clone()))),
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            Byml::Array(steps)
+        }
+        // Merging two diffs, so the changes just apply one after the other
+        Some(Byml::Hash(base)) => {
+            let changes = match base.get("Diff") {
+                Some(Byml::Array(changes)) => changes.iter().chain(diff.iter()).cloned().collect(),
+                _ => diff.to_vec(),
+            };
+            Byml::Hash([("Diff".into(), Byml::Array(changes))].into_iter().collect())
+        }
+        _ => {
+            Byml::Array(
+                diff.iter()
+                    .filter_map(|s| s.as_hash().ok().cloned().map(without_nulls).map(Byml::Hash))
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// Diffs a single quest by field, and its steps by name, so that a mod
+/// changing one step's targets doesn't undo another mod's changes to a
+/// different step.
+fn diff_quest(base: &Byml, other: &Byml) -> Byml {
+    let (Ok(base), Ok(other)) = (base.as_hash(), other.as_hash()) else {
+        return other.clone();
+    };
+    let mut diff: Hash = other
+        .iter()
+        .filter(|(k, v)| base.get(*k) != Some(*v))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .chain(
+            base.keys()
+                .filter(|k| !other.contains_key(*k))
+                .map(|k| (k.clone(), Byml::Null)),
+        )
+        .collect();
+    if let (Some(Byml::Array(base_steps)), Some(Byml::Array(other_steps))) =
+        (base.get("Steps"), diff.get("Steps"))
+        && let Some(steps) = diff_steps(base_steps, other_steps)
+    {
+        diff.insert("Steps".into(), steps);
+    }
+    if let Some(name) = other.get("Name") {
+        diff.insert("Name".into(), name.clone());
+    }
+    Byml::Hash(diff)
+}
+
+fn merge_quest(base: &Byml, diff: &Byml) -> Byml {
+    let (Ok(base), Ok(diff)) = (base.as_hash(), diff.as_hash()) else {
+        return diff.clone();
+    };
+    let mut merged = base.clone();
+    for (key, value) in diff {
+        match value {
+            Byml::Null => {
+                merged.remove(key);
+            }
+            Byml::Hash(steps) if key == "Steps" => {
+                let changes = match steps.get("Diff") {
+                    Some(Byml::Array(changes)) => changes.as_slice(),
+                    _ => &[],
+                };
+                merged.insert(key.clone(), merge_steps(base.get("Steps"), changes));
+            }
+            _ => {
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    Byml::Hash(merged)
+}
+
 impl Mergeable for QuestProduct {
     fn diff(&self, other: &Self) -> Self {
-        Self(self.0.diff(&other.0))
+        let mut diff = self.0.diff(&other.0);
+        for (name, (quest, deleted)) in diff.iter_full_mut() {
+            if !*deleted && let Some(base) = self.0.get(name) {
+                *quest = diff_quest(base, quest);
+            }
+        }
+        Self(diff)
     }
 
     fn merge(&self, diff: &Self) -> Self {
-        Self(self.0.merge(&diff.0))
+        let mut merged = self.0.merge(&diff.0);
+        for (name, quest) in merged.iter_mut() {
+            if let (Some(base), Some(diff)) = (self.0.get(name), diff.0.get(name)) {
+                *quest = merge_quest(base, diff);
+            }
+        }
+        Self(merged)
+    }
+}
+
+/// An actor a quest step refers to which isn't in ActorInfo, usually because
+/// a quest mod expects an actor from a mod which isn't installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingQuestActor {
+    pub quest: String,
+    pub step: String,
+    pub actor: String,
+}
+
+impl std::fmt::Display for MissingQuestActor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Quest {} step {} targets actor {}, which does not exist",
+            self.quest, self.step, self.actor
+        )
+    }
+}
+
+fn bound_actors<'a>(value: &'a Byml, key: Option<&str>, out: &mut Vec<&'a str>) {
+    match value {
+        Byml::String(actor) if key.map(|k| k.contains("Actor")).unwrap_or(false) => {
+            if !actor.is_empty() {
+                out.push(actor.as_str());
+            }
+        }
+        Byml::Hash(hash) => hash.iter().for_each(|(k, v)| bound_actors(v, Some(k), out)),
+        Byml::Array(items) => items.iter().for_each(|v| bound_actors(v, key, out)),
+        _ => (),
+    }
+}
+
+impl QuestProduct {
+    /// Checks every actor bound by a quest step against the merged actor
+    /// info. Any string under a step key naming an actor counts as a
+    /// binding.
+    pub fn missing_actors(&self, info: &ActorInfo) -> Vec<MissingQuestActor> {
+        let mut missing = vec![];
+        for (quest_name, quest) in self.0.iter() {
+            let Some(Byml::Array(steps)) = quest.as_hash().ok().and_then(|q| q.get("Steps"))
+            else {
+                continue;
+            };
+            for step in steps {
+                let mut actors = vec![];
+                bound_actors(step, None, &mut actors);
+                for actor in actors {
+                    if !info.0.contains_key(roead::aamp::hash_name(actor)) {
+                        missing.push(MissingQuestActor {
+                            quest: quest_name.clone(),
+                            step: step_name(step).unwrap_or_default().into(),
+                            actor: actor.into(),
+                        });
+                    }
+                }
+            }
+        }
+        missing
     }
 }
 
@@ -117,6 +341,39 @@ mod tests {
         assert_eq!(merged, quests2);
     }
 
+    #[test]
+    fn merge_steps() {
+        let byml = load_quests();
+        let quests = super::QuestProduct::try_from(&byml).unwrap();
+        let (name, quest) = quests.0.iter().next().unwrap();
+        let mut first = quests.clone();
+        let mut second = quests.clone();
+        let edit = |res: &mut super::QuestProduct, step: usize, key: &str| {
+            let quest = res.0.get_mut(name).unwrap().as_mut_hash().unwrap();
+            let steps = quest.get_mut("Steps").unwrap().as_mut_array().unwrap();
+            steps[step]
+                .as_mut_hash()
+                .unwrap()
+                .insert(key.into(), Byml::String("Test".into()));
+        };
+        edit(&mut first, 0, "TestA");
+        edit(&mut second, 0, "TestB");
+        let merged = quests
+            .merge(&quests.diff(&first))
+            .merge(&quests.diff(&second));
+        let steps = |quest: &Byml| {
+            quest.as_hash().unwrap()["Steps"]
+                .as_array()
+                .unwrap()
+                .clone()
+        };
+        let merged_steps = steps(merged.0.get(name).unwrap());
+        let step = merged_steps[0].as_hash().unwrap();
+        assert!(step.contains_key("TestA"));
+        assert!(step.contains_key("TestB"));
+        assert_eq!(steps(quest).len(), merged_steps.len());
+    }
+
     #[test]
     fn identify() {
         let path =
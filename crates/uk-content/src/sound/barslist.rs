@@ -5,13 +5,151 @@ use uk_ui_derive::Editable;
 
 use crate::{
     prelude::*,
-    util::{DeleteSet, SortedDeleteMap},
+    util::{DeleteMap, DeleteSet, SortedDeleteMap},
     Result, UKError,
 };
 
+/// An entry in the sound resource list. Most entries are just the list of
+/// `.bars` archives for a sound group, but music mods may instead provide a
+/// table of per-track metadata (loop points and the like), which is merged by
+/// track name and then by field so that separate fixes to the same track
+/// combine.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "ui", derive(Editable))]
+#[serde(from = "BarslistEntryCompat")]
+pub enum BarslistEntry {
+    Files(DeleteSet<String>),
+    Tracks(SortedDeleteMap<String, DeleteMap<String, Byml>>),
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "BarslistEntry")]
+enum BarslistEntryTagged {
+    Files(DeleteSet<String>),
+    Tracks(SortedDeleteMap<String, DeleteMap<String, Byml>>),
+}
+
+/// Mods packaged before track tables were supported store each entry as a
+/// bare file list, so those still need to deserialize.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BarslistEntryCompat {
+    Tagged(BarslistEntryTagged),
+    Files(DeleteSet<String>),
+}
+
+impl From<BarslistEntryCompat> for BarslistEntry {
+    fn from(entry: BarslistEntryCompat) -> Self {
+        match entry {
+            BarslistEntryCompat::Tagged(BarslistEntryTagged::Files(files))
+            | BarslistEntryCompat::Files(files) => Self::Files(files),
+            BarslistEntryCompat::Tagged(BarslistEntryTagged::Tracks(tracks)) => {
+                Self::Tracks(tracks)
+            }
+        }
+    }
+}
+
+impl Default for BarslistEntry {
+    fn default() -> Self {
+        Self::Files(Default::default())
+    }
+}
+
+impl TryFrom<&Byml> for BarslistEntry {
+    type Error = UKError;
+
+    fn try_from(byml: &Byml) -> Result<Self> {
+        match byml {
+            Byml::Array(files) => {
+                Ok(Self::Files(
+                    files
+                        .iter()
+                        .filter_map(|v| v.as_string().ok().cloned())
+                        .collect(),
+                ))
+            }
+            Byml::Hash(tracks) => {
+                Ok(Self::Tracks(
+                    tracks
+                        .iter()
+                        .map(|(track, meta)| -> Result<(String, DeleteMap<String, Byml>)> {
+                            Ok((
+                                track.clone(),
+                                meta.as_hash()?
+                                    .iter()
+                                    .map(|(k, v)| (k.clone(), v.clone()))
+                                    .collect(),
+                            ))
+                        })
+                        .collect::<Result<_>>()?,
+                ))
+            }
+            _ => {
+                Err(UKError::WrongBymlType(
+                    "not an array or hash".into(),
+                    "an array or hash",
+                ))
+            }
+        }
+    }
+}
+
+impl From<BarslistEntry> for Byml {
+    fn from(val: BarslistEntry) -> Self {
+        match val {
+            BarslistEntry::Files(files) => {
+                files
+                    .into_iter()
+                    .map(|s| Byml::from(s.to_string()))
+                    .collect()
+            }
+            BarslistEntry::Tracks(tracks) => {
+                tracks
+                    .into_iter()
+                    .map(|(track, meta)| {
+                        (
+                            track.to_string(),
+                            meta.into_iter()
+                                .map(|(k, v)| (k.to_string(), v))
+                                .collect::<Byml>(),
+                        )
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl Mergeable for BarslistEntry {
+    fn diff(&self, other: &Self) -> Self {
+        match (self, other) {
+            (Self::Files(self_files), Self::Files(other_files)) => {
+                Self::Files(self_files.diff(other_files))
+            }
+            (Self::Tracks(self_tracks), Self::Tracks(other_tracks)) => {
+                Self::Tracks(self_tracks.deep_diff(other_tracks))
+            }
+            _ => other.clone(),
+        }
+    }
+
+    fn merge(&self, diff: &Self) -> Self {
+        match (self, diff) {
+            (Self::Files(self_files), Self::Files(diff_files)) => {
+                Self::Files(self_files.merge(diff_files))
+            }
+            (Self::Tracks(self_tracks), Self::Tracks(diff_tracks)) => {
+                Self::Tracks(self_tracks.deep_merge(diff_tracks))
+            }
+            _ => diff.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 #[cfg_attr(feature = "ui", derive(Editable))]
-pub struct BarslistInfo(pub SortedDeleteMap<String, DeleteSet<String>>);
+pub struct BarslistInfo(pub SortedDeleteMap<String, BarslistEntry>);
 
 impl TryFrom<&Byml> for BarslistInfo {
     type Error = UKError;
@@ -20,15 +158,7 @@ impl TryFrom<&Byml> for BarslistInfo {
         let hash = byml.as_hash()?;
         Ok(Self(
             hash.iter()
-                .map(|(k, v)| -> Result<(String, DeleteSet<String>)> {
-                    Ok((
-                        k.clone(),
-                        v.as_array()?
-                            .iter()
-                            .filter_map(|v| v.as_string().ok().cloned())
-                            .collect(),
-                    ))
-                })
+                .map(|(k, v)| -> Result<(String, BarslistEntry)> { Ok((k.clone(), v.try_into()?)) })
                 .collect::<Result<_>>()?,
         ))
     }
@@ -38,12 +168,7 @@ impl From<BarslistInfo> for Byml {
     fn from(val: BarslistInfo) -> Self {
         val.0
             .into_iter()
-            .map(|(k, v)| {
-                (
-                    k.to_string(),
-                    v.into_iter().map(|s| Byml::from(s.to_string())).collect(),
-                )
-            })
+            .map(|(k, v)| (k.to_string(), Byml::from(v)))
             .collect()
     }
 }
@@ -128,6 +253,84 @@ mod tests {
         assert_eq!(merged, barslist2);
     }
 
+    #[test]
+    fn merge_tracks() {
+        let tracks = |fields: &[(&str, Byml)]| -> Byml {
+            Byml::Hash(
+                [(
+                    "FieldDay".into(),
+                    Byml::Hash(
+                        fields
+                            .iter()
+                            .map(|(k, v)| ((*k).into(), v.clone()))
+                            .collect(),
+                    ),
+                )]
+                .into_iter()
+                .collect(),
+            )
+        };
+        let info = |entry: Byml| -> super::BarslistInfo {
+            (&Byml::Hash([("FieldBgm".into(), entry)].into_iter().collect()))
+                .try_into()
+                .unwrap()
+        };
+        let base = info(tracks(&[
+            ("LoopStart", Byml::I32(0)),
+            ("LoopEnd", Byml::I32(1000)),
+        ]));
+        let loop_fix = info(tracks(&[
+            ("LoopStart", Byml::I32(250)),
+            ("LoopEnd", Byml::I32(1000)),
+        ]));
+        let replacement = info(tracks(&[
+            ("LoopStart", Byml::I32(0)),
+            ("LoopEnd", Byml::I32(4000)),
+        ]));
+        let merged = base
+            .merge(&base.diff(&loop_fix))
+            .merge(&base.diff(&replacement));
+        assert_eq!(
+            merged,
+            info(tracks(&[
+                ("LoopStart", Byml::I32(250)),
+                ("LoopEnd", Byml::I32(4000)),
+            ]))
+        );
+    }
+
+    #[test]
+    fn deserialize_file_lists() {
+        use crate::util::{DeleteSet, SortedDeleteMap};
+
+        #[derive(serde::Serialize)]
+        struct OldBarslistInfo(SortedDeleteMap<String, DeleteSet<String>>);
+
+        let byml = load_barslist();
+        let old = OldBarslistInfo(
+            byml.as_hash()
+                .unwrap()
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        k.clone(),
+                        v.as_array()
+                            .unwrap()
+                            .iter()
+                            .map(|f| f.as_string().unwrap().clone())
+                            .collect(),
+                    )
+                })
+                .collect(),
+        );
+        let data = minicbor_ser::to_vec(&old).unwrap();
+        let barslist: super::BarslistInfo = minicbor_ser::from_slice(&data).unwrap();
+        assert_eq!(barslist, super::BarslistInfo::try_from(&byml).unwrap());
+        let data = minicbor_ser::to_vec(&barslist).unwrap();
+        let barslist2: super::BarslistInfo = minicbor_ser::from_slice(&data).unwrap();
+        assert_eq!(barslist, barslist2);
+    }
+
     #[test]
     fn identify() {
         let path =
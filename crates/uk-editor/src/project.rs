@@ -27,6 +27,22 @@ impl Project {
                 category: Default::default(),
                 description: Default::default(),
                 masters: IndexMap::default(),
+                emulator_flags: Default::default(),
+                dependencies: Default::default(),
+                thumbnail: None,
+                author_url: None,
+                homepage: None,
+                long_description: Default::default(),
+                changelog: Default::default(),
+                game_version: None,
+                requires_dlc: false,
+                selectable: Default::default(),
+                update_url: None,
+                translations: Default::default(),
+                license: None,
+                credits: Default::default(),
+                permissions: Default::default(),
+                delta: None,
                 options: Default::default(),
                 platform: uk_mod::ModPlatform::Specific(platform.into()),
                 url: Default::default(),
@@ -80,6 +96,10 @@ impl Project {
             .par_bridge()
             .filter_map(|file| -> Option<Result<PathBuf>> {
                 let do_it = || -> Result<Option<PathBuf>> {
+                    // The hashes won't hold once the project is edited
+                    if file == Path::new(uk_mod::integrity::INTEGRITY_FILE) {
+                        return Ok(None);
+                    }
                     let dest = path.join(file);
                     dest.parent().map(fs::create_dir_all).transpose()?;
                     let data = zip.get_file(file).with_context(|| {
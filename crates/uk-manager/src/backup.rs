@@ -0,0 +1,110 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+
+/// How many backups to keep of each settings or profile file. Older ones are
+/// deleted.
+pub const MAX_BACKUPS: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    pub path: PathBuf,
+    /// When the backup was taken, in milliseconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+impl Backup {
+    #[inline]
+    pub fn time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.timestamp)
+    }
+
+    /// How long ago the backup was taken, roughly, for display.
+    pub fn age(&self) -> String {
+        let secs = self.time().elapsed().unwrap_or_default().as_secs();
+        match secs {
+            0..=59 => "just now".into(),
+            60..=3599 => format!("{} minutes ago", secs / 60),
+            3600..=86399 => format!("{} hours ago", secs / 3600),
+            _ => format!("{} days ago", secs / 86400),
+        }
+    }
+}
+
+/// Backups of `file` are named after it with a timestamp, e.g.
+/// `settings-1700000000000.yml`.
+fn parts(file: &Path) -> (&str, &str) {
+    (
+        file.file_stem().and_then(|s| s.to_str()).unwrap_or_default(),
+        file.extension().and_then(|s| s.to_str()).unwrap_or_default(),
+    )
+}
+
+/// Lists the backups of `file` in `dir`, newest first.
+pub fn list(file: &Path, dir: &Path) -> Result<Vec<Backup>> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let (stem, ext) = parts(file);
+    let mut backups = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let timestamp = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix(stem)?
+                .strip_prefix('-')?
+                .strip_suffix(ext)?
+                .strip_suffix('.')?
+                .parse()
+                .ok()?;
+            Some(Backup { path, timestamp })
+        })
+        .collect::<Vec<_>>();
+    backups.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    Ok(backups)
+}
+
+/// Copies `file` into `dir` under a timestamped name, unless it is missing
+/// or the newest backup already matches it, then deletes backups beyond
+/// [`MAX_BACKUPS`].
+pub fn backup(file: &Path, dir: &Path) -> Result<()> {
+    if !file.exists() {
+        return Ok(());
+    }
+    let data = fs::read(file)?;
+    let backups = list(file, dir)?;
+    if let Some(newest) = backups.first()
+        && fs::read(&newest.path).map(|old| old == data).unwrap_or(false)
+    {
+        return Ok(());
+    }
+    fs::create_dir_all(dir)?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let (stem, ext) = parts(file);
+    fs::write(dir.join(format!("{stem}-{timestamp}.{ext}")), data)
+        .with_context(|| format!("Failed to back up {}", file.display()))?;
+    for old in backups.iter().skip(MAX_BACKUPS - 1) {
+        if let Err(e) = fs::remove_file(&old.path) {
+            log::warn!("Failed to remove old backup: {e}");
+        }
+    }
+    Ok(())
+}
+
+/// Puts a backup back in place of `file`. The current contents of `file`
+/// are backed up first, so a restore can itself be undone.
+pub fn restore(backup: &Backup, file: &Path, dir: &Path) -> Result<()> {
+    self::backup(file, dir)?;
+    fs::copy(&backup.path, file)
+        .with_context(|| format!("Failed to restore backup {}", backup.path.display()))?;
+    log::info!("Restored {} from backup", file.display());
+    Ok(())
+}
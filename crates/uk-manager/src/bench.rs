@@ -0,0 +1,262 @@
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use rayon::prelude::*;
+use roead::yaz0::{compress, decompress_if};
+use uk_content::{prelude::Mergeable, resource::MergeableResource};
+
+use crate::{core, settings::Platform};
+
+/// Vanilla files the benchmark runs on, picked to cover the big BYML, AAMP,
+/// and SARC resources which dominate real merges. Every user measures the
+/// same files, so reports can be compared with each other.
+static SAMPLE_FILES: &[&str] = &[
+    "Actor/ActorInfo.product.sbyml",
+    "Actor/Pack/Enemy_Lynel_Dark.sbactorpack",
+    "Event/EventInfo.product.sbyml",
+    "Map/MainField/Static.smubin",
+    "Map/MainField/A-1/A-1.00_Clustering.sblwp",
+    "Pack/Bootup.pack",
+    "Pack/TitleBG.pack",
+    "Quest/QuestProduct.sbquestpack",
+];
+
+/// How many workers to try when measuring deployment.
+static DEPLOY_WORKERS: &[usize] = &[1, 2, 4, 8, 16];
+
+/// The time one benchmark stage took over some amount of data.
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub name:    &'static str,
+    pub files:   usize,
+    pub bytes:   u64,
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    /// Throughput in MB per second.
+    pub fn throughput(&self) -> f64 {
+        self.bytes as f64 / 1024.0 / 1024.0 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl std::fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:<10} {:>4} files {:>8.1} MB {:>8.0} ms {:>8.1} MB/s",
+            self.name,
+            self.files,
+            self.bytes as f64 / 1024.0 / 1024.0,
+            self.elapsed.as_secs_f64() * 1000.0,
+            self.throughput()
+        )
+    }
+}
+
+/// A benchmark of merging and deploying on this machine, meant to be shared
+/// when asking why applying mods is slow.
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    pub platform: Platform,
+    pub threads:  usize,
+    pub results:  Vec<BenchResult>,
+    /// Deployment measured with each number of workers
+    pub deploy: Vec<(usize, BenchResult)>,
+    /// The fastest number of deploy workers
+    pub recommended_workers: Option<usize>,
+}
+
+impl std::fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "UKMM benchmark")?;
+        writeln!(f, "Version: {}", env!("CARGO_PKG_VERSION"))?;
+        writeln!(f, "OS: {} ({})", std::env::consts::OS, std::env::consts::ARCH)?;
+        writeln!(f, "Platform: {}", self.platform)?;
+        writeln!(f, "Threads: {}", self.threads)?;
+        writeln!(f)?;
+        for result in &self.results {
+            writeln!(f, "{result}")?;
+        }
+        writeln!(f)?;
+        for (workers, result) in &self.deploy {
+            writeln!(f, "{result} ({workers} workers)")?;
+        }
+        if let Some(workers) = self.recommended_workers {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "Deploying was fastest with {workers} workers. Set \"Deploy Workers\" to \
+                 {workers} for this target."
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn timed<T>(f: impl FnOnce() -> Result<T>) -> Result<(T, Duration)> {
+    let start = Instant::now();
+    let value = f()?;
+    Ok((value, start.elapsed()))
+}
+
+fn total_bytes(files: &[(String, Vec<u8>)]) -> u64 {
+    files.iter().map(|(_, data)| data.len() as u64).sum()
+}
+
+/// Copies every file in `source` to `dest` with the given number of workers.
+fn copy_files(
+    files: &[(String, Vec<u8>)],
+    source: &Path,
+    dest: &Path,
+    workers: usize,
+) -> Result<()> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(workers)
+        .build()
+        .context("Failed to start benchmark workers")?
+        .install(|| {
+            files.par_iter().try_for_each(|(name, _)| -> Result<()> {
+                let out = dest.join(name);
+                out.parent().map(fs::create_dir_all).transpose()?;
+                fs::copy(source.join(name), out)?;
+                Ok(())
+            })
+        })
+}
+
+/// Measures how fast this machine parses, merges, compresses, and deploys a
+/// fixed set of vanilla files from the game dump. Deployment goes to a
+/// temporary folder next to the first deploy target, if there is one, so the
+/// numbers reflect the real drive. The report is also saved as
+/// `benchmark.txt` in the platform folder.
+pub fn run(core: &core::Manager) -> Result<BenchReport> {
+    let settings = core.settings();
+    let dump = settings
+        .dump()
+        .context("No dump available for current platform")?;
+    let endian = settings.current_mode.into();
+    log::info!("Loading benchmark files");
+    let files: Vec<(String, Vec<u8>)> = SAMPLE_FILES
+        .iter()
+        .filter_map(|file| {
+            match dump.get_bytes_uncached(file) {
+                Ok(data) => Some((file.to_string(), data)),
+                Err(e) => {
+                    log::warn!("Skipping {file} in benchmark: {e}");
+                    None
+                }
+            }
+        })
+        .collect();
+    if files.is_empty() {
+        anyhow_ext::bail!("None of the benchmark files could be read from the game dump");
+    }
+    let mut results = vec![];
+
+    log::info!("Benchmarking parsing");
+    let compressed_bytes = total_bytes(&files);
+    let (parsed, elapsed) = timed(|| {
+        files
+            .par_iter()
+            .map(|(name, data)| -> Result<_> {
+                let data = decompress_if(data).to_vec();
+                let res = MergeableResource::from_binary(name.as_ref(), &data)?;
+                Ok((name.clone(), data, res))
+            })
+            .collect::<Result<Vec<_>>>()
+    })?;
+    results.push(BenchResult {
+        name:  "Parse",
+        files: parsed.len(),
+        bytes: compressed_bytes,
+        elapsed,
+    });
+    let decompressed: Vec<(String, Vec<u8>)> = parsed
+        .iter()
+        .map(|(name, data, _)| (name.clone(), data.clone()))
+        .collect();
+    let merge_bytes = parsed
+        .iter()
+        .filter(|(_, _, res)| res.is_some())
+        .map(|(_, data, _)| data.len() as u64)
+        .sum();
+    let resources: Vec<MergeableResource> =
+        parsed.into_iter().filter_map(|(_, _, res)| res).collect();
+
+    log::info!("Benchmarking merging");
+    let ((), elapsed) = timed(|| {
+        resources.par_iter().for_each(|res| {
+            // Merging a resource into itself walks every entry, like a mod
+            // which changes all of it.
+            let merged = res.merge(res);
+            let _ = merged.into_binary(endian);
+        });
+        Ok(())
+    })?;
+    results.push(BenchResult {
+        name:  "Merge",
+        files: resources.len(),
+        bytes: merge_bytes,
+        elapsed,
+    });
+
+    log::info!("Benchmarking compression");
+    let ((), elapsed) = timed(|| {
+        decompressed.par_iter().for_each(|(_, data)| {
+            let _ = compress(data);
+        });
+        Ok(())
+    })?;
+    results.push(BenchResult {
+        name:  "Compress",
+        files: decompressed.len(),
+        bytes: total_bytes(&decompressed),
+        elapsed,
+    });
+
+    let target = settings
+        .platform_config()
+        .and_then(|c| c.deploy_config())
+        .map(|c| c.output)
+        .and_then(|out| out.parent().filter(|p| p.exists()).map(|p| p.to_path_buf()))
+        .unwrap_or_else(|| settings.platform_dir());
+    let source = tempfile::tempdir_in(settings.platform_dir())?;
+    for (name, data) in &files {
+        let out = source.path().join(name);
+        out.parent().map(fs::create_dir_all).transpose()?;
+        fs::write(out, data)?;
+    }
+    let mut deploy = vec![];
+    for workers in DEPLOY_WORKERS.iter().copied() {
+        log::info!("Benchmarking deployment with {workers} workers");
+        let dest = tempfile::tempdir_in(&target)
+            .with_context(|| format!("Failed to write benchmark files to {}", target.display()))?;
+        let ((), elapsed) = timed(|| copy_files(&files, source.path(), dest.path(), workers))?;
+        deploy.push((workers, BenchResult {
+            name:  "Deploy",
+            files: files.len(),
+            bytes: compressed_bytes,
+            elapsed,
+        }));
+    }
+    let recommended_workers = deploy
+        .iter()
+        .min_by_key(|(_, result)| result.elapsed)
+        .map(|(workers, _)| *workers);
+    let report = BenchReport {
+        platform: settings.current_mode,
+        threads:  rayon::current_num_threads(),
+        results,
+        deploy,
+        recommended_workers,
+    };
+    let report_path = settings.platform_dir().join("benchmark.txt");
+    fs::write(&report_path, report.to_string())?;
+    log::info!("Benchmark report saved to {}", report_path.display());
+    Ok(report)
+}
@@ -1,21 +1,26 @@
 use std::{
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
 
 use anyhow_ext::{Context, Result};
 use dashmap::DashSet;
 use fs_err as fs;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use rayon::prelude::*;
 use roead::{
     aamp::{ParameterIO, ParameterList, ParameterListing},
+    byml::Byml,
     sarc::{File, Sarc, SarcWriter},
     yaz0::{compress_if, decompress_if},
 };
 use rustc_hash::FxHashMap;
+use serde::Serialize;
 use uk_content::{constants::Language, util::HashSet};
-use uk_mod::pack::ModPacker;
+use uk_mod::{pack::ModPacker, ModOptionGroup};
 use uk_reader::ResourceReader;
 
 use crate::{settings::Platform, util::extract_7z};
@@ -31,6 +36,7 @@ mod gamedata;
 mod mainstatic;
 mod maps;
 mod old;
+mod platform;
 mod quests;
 mod residents;
 mod savedata;
@@ -94,7 +100,115 @@ pub fn parse_aamp_diff(header_name: &str, pio: &ParameterIO) -> Result<AampDiffM
         )
 }
 
-#[derive(Debug)]
+/// A progress update from BNP conversion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BnpProgress {
+    /// Started processing a log for the mod root or an option.
+    Stage {
+        root:    std::string::String,
+        name:    &'static str,
+        current: usize,
+        total:   usize,
+    },
+    /// Rebuilt one of the SARC packs touched by the logs.
+    Packs { done: usize, total: usize },
+}
+
+impl std::fmt::Display for BnpProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stage {
+                root,
+                name,
+                current,
+                total,
+            } => write!(f, "Processing {name} log for {root} ({current}/{total})"),
+            Self::Packs { done, total } => write!(f, "Rebuilding BNP packs ({done}/{total})"),
+        }
+    }
+}
+
+pub type ProgressCallback = Arc<dyn Fn(BnpProgress) + Send + Sync>;
+
+/// The default progress handler, which reports through the log so it shows
+/// up in the GUI's busy dialog.
+pub fn log_progress() -> ProgressCallback {
+    Arc::new(|progress| log::info!("PROGRESS{progress}"))
+}
+
+type LogHandler = fn(&BnpConverter) -> Result<()>;
+
+static LOG_STAGES: &[(&str, &str, LogHandler)] = &[
+    ("actor info", "actorinfo.yml", BnpConverter::handle_actorinfo),
+    ("AS list", "aslist.aamp", BnpConverter::handle_aslist),
+    ("areadata", "areadata.yml", BnpConverter::handle_areadata),
+    ("deepmerge", "deepmerge.aamp", BnpConverter::handle_deepmerge),
+    ("drops", "drops.json", BnpConverter::handle_drops),
+    ("dungeon static", "dstatic.yml", BnpConverter::handle_dungeon_static),
+    ("eventinfo", "eventinfo.yml", BnpConverter::handle_events),
+    ("gamedata", "gamedata.yml", BnpConverter::handle_gamedata),
+    ("mainfield static", "mainstatic.yml", BnpConverter::handle_mainfield_static),
+    ("maps", "map.yml", BnpConverter::handle_maps),
+    ("quests", "quests.yml", BnpConverter::handle_quests),
+    ("residents", "residents.yml", BnpConverter::handle_residents),
+    ("savedata", "savedata.yml", BnpConverter::handle_savedata),
+    ("shops", "shop.aamp", BnpConverter::handle_shops),
+    ("status effect", "effects.yml", BnpConverter::handle_effects),
+    ("texts", "texts.json", BnpConverter::handle_texts),
+];
+
+/// The outcome of converting one BNP log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum LogStatus {
+    Converted,
+    Failed(std::string::String),
+}
+
+/// The result of converting a single log (or rebuilding a pack) for the mod
+/// root or one of its options.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogReport {
+    /// "mod root" or "option <name>"
+    pub root:   std::string::String,
+    pub log:    &'static str,
+    /// The files whose changes were lost if this log failed, relative to the
+    /// root they belong to.
+    pub files:  Vec<PathBuf>,
+    pub status: LogStatus,
+}
+
+/// A summary of a BNP conversion. A log which fails to convert does not stop
+/// the rest of the mod from converting, so this records what was skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BnpReport {
+    pub logs: Vec<LogReport>,
+}
+
+impl BnpReport {
+    pub fn failures(&self) -> impl Iterator<Item = &LogReport> {
+        self.logs
+            .iter()
+            .filter(|log| matches!(log.status, LogStatus::Failed(_)))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.failures().next().is_none()
+    }
+}
+
+impl std::fmt::Display for BnpReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for log in self.failures() {
+            let LogStatus::Failed(err) = &log.status else { unreachable!() };
+            writeln!(f, "{} log for {}: {err}", log.log, log.root)?;
+            for file in &log.files {
+                writeln!(f, "    {}", file.display())?;
+            }
+        }
+        Ok(())
+    }
+}
+
 struct BnpConverter {
     dump: Arc<ResourceReader>,
     game_lang: Language,
@@ -105,6 +219,18 @@ struct BnpConverter {
     aoc: &'static str,
     packs: Arc<DashSet<PathBuf>>,
     parent_packs: RwLock<HashSet<PathBuf>>,
+    progress: ProgressCallback,
+    report: Mutex<BnpReport>,
+}
+
+impl std::fmt::Debug for BnpConverter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BnpConverter")
+            .field("platform", &self.platform)
+            .field("path", &self.path)
+            .field("current_root", &self.current_root)
+            .finish_non_exhaustive()
+    }
 }
 
 impl BnpConverter {
@@ -247,6 +373,136 @@ impl BnpConverter {
         Ok(())
     }
 
+    /// BCML mods sometimes leave a modified file loose when the game only
+    /// loads it from inside a pack. If a pack still has the vanilla copy of a
+    /// file and the mod has a loose version of it, the loose version is moved
+    /// into the pack. The loose file is kept if the game has it loose as well.
+    fn absorb_loose_files(
+        &self,
+        pack_path: &Path,
+        sarc: &mut SarcWriter,
+        absorbed: &DashSet<PathBuf>,
+    ) -> Result<()> {
+        let rel_path = pack_path
+            .strip_prefix(&self.current_root)
+            .expect("Impossible");
+        let is_aoc = rel_path.starts_with(self.aoc);
+        let Ok(base) = self
+            .dump
+            .get_bytes_uncached(self.trim_prefixes(rel_path.to_str().unwrap_or_default()))
+        else {
+            return Ok(());
+        };
+        let base = Sarc::new(base)?;
+        let loose_root = self
+            .current_root
+            .join(if is_aoc { self.aoc } else { self.content });
+        for file in base.files() {
+            let Some(name) = file.name() else { continue };
+            let loose_path = loose_root.join(name);
+            if sarc.get_file(name).map(|d| &d[..]) != Some(file.data) || !loose_path.exists() {
+                continue;
+            }
+            let data = fs::read(&loose_path)?;
+            if data == file.data {
+                continue;
+            }
+            log::debug!("Moving loose {name} into {}", rel_path.display());
+            sarc.add_file(name, data);
+            let vanilla_loose = if is_aoc {
+                self.dump.get_aoc_bytes_uncached(name)
+            } else {
+                self.dump.get_bytes_uncached(name)
+            };
+            if vanilla_loose.is_err() {
+                absorbed.insert(loose_path);
+            }
+        }
+        Ok(())
+    }
+
+    /// The files a log would have changed, relative to the current root. Logs
+    /// which name their files are read for them, the rest always target the
+    /// same resource.
+    fn logged_files(&self, file: &str) -> Result<Vec<PathBuf>> {
+        let log_path = self.current_root.join("logs").join(file);
+        let (root, path) = match file {
+            "aslist.aamp" | "deepmerge.aamp" | "shop.aamp" => {
+                let pio = ParameterIO::from_binary(fs::read(log_path)?)?;
+                let header = if file == "shop.aamp" {
+                    "Filenames"
+                } else {
+                    "FileTable"
+                };
+                return Ok(pio
+                    .object(header)
+                    .context("Log missing file table")?
+                    .0
+                    .values()
+                    .filter_map(|s| s.as_str().ok())
+                    .map(PathBuf::from)
+                    .collect());
+            }
+            "drops.json" => {
+                let drops: FxHashMap<String, serde_json::Value> =
+                    serde_json::from_str(&fs::read_to_string(log_path)?)?;
+                return Ok(drops.into_keys().map(PathBuf::from).collect());
+            }
+            "map.yml" => {
+                let diff = Byml::from_text(fs::read_to_string(log_path)?)?.into_hash()?;
+                return Ok(diff
+                    .keys()
+                    .map(|section| {
+                        let unit = section.split('_').next().unwrap_or_default();
+                        Path::new(self.aoc).join(format!(
+                            "Pack/AocMainField.pack//Map/MainField/{unit}/{section}.smubin"
+                        ))
+                    })
+                    .collect());
+            }
+            "actorinfo.yml" => (self.content, "Actor/ActorInfo.product.sbyml".into()),
+            "areadata.yml" => (
+                self.content,
+                "Pack/Bootup.pack//Ecosystem/AreaData.sbyml".into(),
+            ),
+            "dstatic.yml" => (self.aoc, "Map/CDungeon/Static.smubin".into()),
+            "effects.yml" => (
+                self.content,
+                "Pack/Bootup.pack//Ecosystem/StatusEffectList.sbyml".into(),
+            ),
+            "eventinfo.yml" => (
+                self.content,
+                "Pack/Bootup.pack//Event/EventInfo.product.sbyml".into(),
+            ),
+            "gamedata.yml" => (
+                self.content,
+                "Pack/Bootup.pack//GameData/gamedata.ssarc".into(),
+            ),
+            "mainstatic.yml" => (self.aoc, "Map/MainField/Static.smubin".into()),
+            "quests.yml" => (
+                self.content,
+                "Pack/TitleBG.pack//Quest/QuestProduct.sbquestpack".into(),
+            ),
+            "residents.yml" => (
+                self.content,
+                "Pack/Bootup.pack//Actor/ResidentActors.byml".into(),
+            ),
+            "savedata.yml" => (
+                self.content,
+                "Pack/Bootup.pack//GameData/savedataformat.ssarc".into(),
+            ),
+            "texts.json" => (
+                self.content,
+                format!(
+                    "Pack/Bootup_{lang}.pack//Message/Msg_{lang}.product.ssarc",
+                    lang = self.game_lang
+                ),
+            ),
+            _ => anyhow_ext::bail!("Unknown BNP log {file}"),
+        };
+        Ok(vec![Path::new(root).join(path)])
+    }
+
     fn convert_root(&self) -> Result<()> {
         let packs_path = self.current_root.join("logs/packs.json");
         if packs_path.exists() {
@@ -273,57 +529,93 @@ impl BnpConverter {
             }
         };
 
-        self.handle_actorinfo()
-            .context("Failed to process actor info log")?;
-        self.handle_aslist()
-            .context("Failed to process AS list log")?;
-        self.handle_areadata()
-            .context("Failed to process areadata log")?;
-        self.handle_deepmerge()
-            .context("Failed to process deepmerge log")?;
-        self.handle_drops().context("Failed to process drops log")?;
-        self.handle_dungeon_static()
-            .context("Failed to process dungeon static log")?;
-        self.handle_events()
-            .context("Failed to process eventinfo log")?;
-        self.handle_gamedata()
-            .context("Failed to process gamedata log")?;
-        self.handle_mainfield_static()
-            .context("Failed to process mainfield static log")?;
-        self.handle_maps().context("Failed to process maps log")?;
-        self.handle_quests()
-            .context("Failed to process quests log")?;
-        self.handle_residents()
-            .context("Failed to process residents log")?;
-        self.handle_savedata()
-            .context("Failed to process savedata log")?;
-        self.handle_shops().context("Failed to process shops log")?;
-        self.handle_effects()
-            .context("Failed to process status effect log")?;
-        self.handle_texts().context("Failed to process texts log")?;
+        let root = if self.current_root == self.path {
+            "mod root".to_string()
+        } else {
+            format!(
+                "option {}",
+                self.current_root
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or_default()
+            )
+        };
+        for (i, &(name, file, handler)) in LOG_STAGES.iter().enumerate() {
+            (self.progress)(BnpProgress::Stage {
+                root: root.clone(),
+                name,
+                current: i + 1,
+                total: LOG_STAGES.len(),
+            });
+            if !self.current_root.join("logs").join(file).exists() {
+                continue;
+            }
+            let status = match handler(self) {
+                Ok(()) => LogStatus::Converted,
+                Err(err) => {
+                    log::warn!("Failed to process {name} log for {root}, skipping: {err:?}");
+                    LogStatus::Failed(format!("{err:#}"))
+                }
+            };
+            self.report.lock().logs.push(LogReport {
+                root: root.clone(),
+                log: name,
+                files: match status {
+                    LogStatus::Converted => vec![],
+                    // If the log can't even be read, it is the best we can
+                    // point to.
+                    LogStatus::Failed(_) => self
+                        .logged_files(file)
+                        .unwrap_or_else(|_| vec![Path::new("logs").join(file)]),
+                },
+                status,
+            });
+        }
 
         let packs = DashSet::clone(&self.packs);
         self.packs.clear();
 
-        packs.into_par_iter().try_for_each(|file| -> Result<()> {
-            let mut sarc = self.open_or_create_sarc(
-                &file,
-                self.trim_prefixes(
-                    file.strip_prefix(&self.current_root)
-                        .expect("Impossible")
-                        .to_str()
-                        .unwrap_or_default(),
-                ),
-            )?;
-            let data = sarc.to_binary();
-            let data = compress_if(&data, &file);
-            fs::write(file, data)?;
-            Ok(())
-        })?;
+        let total = packs.len();
+        let done = AtomicUsize::new(0);
+        let absorbed = DashSet::new();
+        packs.into_par_iter().for_each(|file| {
+            let rel_path = file
+                .strip_prefix(&self.current_root)
+                .expect("Impossible")
+                .to_path_buf();
+            let result = self
+                .open_or_create_sarc(
+                    &file,
+                    self.trim_prefixes(rel_path.to_str().unwrap_or_default()),
+                )
+                .and_then(|mut sarc| {
+                    self.absorb_loose_files(&file, &mut sarc, &absorbed)?;
+                    let data = sarc.to_binary();
+                    let data = compress_if(&data, &file);
+                    fs::write(&file, data)?;
+                    Ok(())
+                });
+            if let Err(err) = result {
+                log::warn!("Failed to rebuild {} for {root}, skipping: {err:?}", rel_path.display());
+                self.report.lock().logs.push(LogReport {
+                    root: root.clone(),
+                    log: "packs",
+                    files: vec![rel_path],
+                    status: LogStatus::Failed(format!("{err:#}")),
+                });
+            }
+            (self.progress)(BnpProgress::Packs {
+                done: done.fetch_add(1, Ordering::Relaxed) + 1,
+                total,
+            });
+        });
+        for loose in absorbed {
+            fs::remove_file(loose)?;
+        }
         Ok(())
     }
 
-    fn convert(mut self) -> Result<PathBuf> {
+    fn convert(mut self) -> Result<(PathBuf, BnpReport)> {
         let root = self.current_root.clone();
         self.convert_root()?;
 
@@ -346,27 +638,88 @@ impl BnpConverter {
                 self.convert_root()?;
             }
         }
-        Ok(root)
+        Ok((root, self.report.into_inner()))
+    }
+}
+
+/// How many extracted BNPs to keep for retrying failed conversions.
+const MAX_CACHED_BNPS: usize = 3;
+
+pub(crate) fn bnp_cache_dir() -> PathBuf {
+    crate::util::temp_root().join("ukmm").join("bnp_cache")
+}
+
+/// Extracts a BNP into `dest` by way of the extraction cache, returning the
+/// cache entry used. Entries are keyed by a hash of the archive, so one is
+/// only reused for the exact same BNP. Extraction goes to a scratch folder
+/// which only becomes the cache entry once it finishes, so an interrupted
+/// extraction is never reused.
+fn extract_bnp_cached(path: &Path, dest: &Path) -> Result<PathBuf> {
+    let cache_dir = bnp_cache_dir();
+    let entry = cache_dir.join(format!("{:016x}", crate::util::hash_file(path)?));
+    if entry.exists() {
+        log::info!("Using cached BNP extraction");
+    } else {
+        log::info!("Extracting BNP…");
+        let scratch = entry.with_extension("part");
+        if scratch.exists() {
+            crate::util::remove_dir_all(&scratch)?;
+        }
+        fs::create_dir_all(&scratch)?;
+        extract_7z(path, &scratch).context("Failed to extract BNP")?;
+        fs::rename(&scratch, &entry)?;
+        let mut cached = fs::read_dir(&cache_dir)?
+            .filter_map(|e| {
+                let e = e.ok()?;
+                let modified = e.metadata().ok()?.modified().ok()?;
+                (e.path().extension().is_none()).then(|| (modified, e.path()))
+            })
+            .collect::<Vec<_>>();
+        cached.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+        for (_, old) in cached.into_iter().skip(MAX_CACHED_BNPS) {
+            crate::util::remove_dir_all(&old).unwrap_or_else(|e| {
+                log::warn!("Failed to clear old BNP cache entry {}: {e}", old.display())
+            });
+        }
     }
+    dircpy::copy_dir(&entry, dest).context("Failed to copy cached BNP to temp folder")?;
+    Ok(entry)
 }
 
 pub fn unpack_bnp(core: &crate::core::Manager, path: &Path) -> Result<PathBuf> {
+    unpack_bnp_with_progress(core, path, log_progress()).map(|(path, _)| path)
+}
+
+/// Unpacks a BNP and converts its logs, reporting each stage to `progress`.
+/// Logs which fail to convert are skipped and listed in the returned report.
+pub fn unpack_bnp_with_progress(
+    core: &crate::core::Manager,
+    path: &Path,
+    progress: ProgressCallback,
+) -> Result<(PathBuf, BnpReport)> {
     let tempdir = crate::util::get_temp_folder();
+    let mut cache_entry = None;
     if path.is_dir() {
         dircpy::copy_dir(path, tempdir.as_path()).context("Failed to copy files to temp folder")?;
     } else {
-        log::info!("Extracting BNP…");
-        extract_7z(path, &tempdir).context("Failed to extract BNP")?;
+        cache_entry = Some(extract_bnp_cached(path, &tempdir)?);
     }
     if tempdir.join("rules.txt").exists() && !tempdir.join("info.json").exists() {
         old::Bnp2xConverter::new(&tempdir)
             .convert()
             .context("Failed to upgrade 2.x BNP")?;
     }
-    let (content, aoc) = uk_content::platform_prefixes(core.settings().current_mode.into());
+    let current_mode = core.settings().current_mode;
+    if let Some(bnp_platform) = platform::bnp_platform(&tempdir)
+        && bnp_platform != current_mode
+    {
+        platform::convert_platform(&tempdir, bnp_platform, current_mode)
+            .context("Failed to convert BNP to current platform")?;
+    }
+    let (content, aoc) = uk_content::platform_prefixes(current_mode.into());
     log::info!("Processing BNP logs…");
     let converter = BnpConverter {
-        platform: core.settings().current_mode,
+        platform: current_mode,
         game_lang: core
             .settings()
             .platform_config()
@@ -379,29 +732,62 @@ pub fn unpack_bnp(core: &crate::core::Manager, path: &Path) -> Result<PathBuf> {
         parent_packs: Default::default(),
         current_root: tempdir.clone(),
         path: tempdir.clone(),
+        progress,
+        report: Default::default(),
     };
-    let path = converter.convert()?;
-    log::info!("BNP unpacked");
-    Ok(path)
+    let (path, report) = converter.convert()?;
+    if report.is_complete() {
+        // Only incomplete conversions need the extraction again, to retry
+        // once the dump is fixed.
+        if let Some(entry) = cache_entry {
+            crate::util::remove_dir_all(entry).unwrap_or_else(|e| {
+                log::warn!("Failed to clear BNP cache entry: {e}");
+            });
+        }
+        log::info!("BNP unpacked");
+    } else {
+        log::warn!("BNP unpacked, but some logs could not be converted:\n{report}");
+    }
+    Ok((path, report))
 }
 
-#[allow(irrefutable_let_patterns)]
 pub fn convert_bnp(core: &crate::core::Manager, path: &Path) -> Result<PathBuf> {
-    let tempdir = unpack_bnp(core, path).context("Failed to unpack BNP")?;
-    let tempfile = std::env::temp_dir();
-    let meta  =
+    convert_bnp_with_progress(core, path, log_progress()).map(|(path, _)| path)
+}
+
+/// Converts a BNP into a UKMM mod, reporting each stage to `progress`. Logs
+/// which fail to convert are skipped and listed in the returned report.
+#[allow(irrefutable_let_patterns)]
+pub fn convert_bnp_with_progress(
+    core: &crate::core::Manager,
+    path: &Path,
+    progress: ProgressCallback,
+) -> Result<(PathBuf, BnpReport)> {
+    let (tempdir, report) =
+        unpack_bnp_with_progress(core, path, progress).context("Failed to unpack BNP")?;
+    let tempfile = crate::util::temp_root();
+    let mut meta  =
     if let rules_path = tempdir.join("rules.txt") && rules_path.exists() {
         ModPacker::parse_rules(rules_path)?
     } else {
         ModPacker::parse_info(tempdir.join("info.json")).context("Failed to parse BNP metadata")?
     };
+    // Installed BCML mods keep the option list in info.json even though the
+    // selected options have already been applied and the folders removed.
+    let opt_dir = tempdir.join("options");
+    for group in meta.options.iter_mut() {
+        group
+            .options_mut()
+            .retain(|opt| opt_dir.join(&opt.path).exists());
+    }
+    meta.options.retain(|group| !group.options().is_empty());
     let new_mod = ModPacker::new(tempdir, tempfile.as_path(), Some(meta), vec![
         core.settings()
             .dump()
             .context("No dump for current platform")?,
     ])
     .context("Failed to package converted BNP")?;
-    new_mod.pack()
+    Ok((new_mod.pack()?, report))
 }
 
 #[cfg(test)]
@@ -4,56 +4,46 @@ use roead::{
     byml::Byml,
     yaz0::{compress, decompress},
 };
-use rustc_hash::FxHashMap;
-use smartstring::alias::String;
-use uk_content::bhash;
+use uk_content::{bhash, map::static_::Static, prelude::Mergeable};
 
 use super::BnpConverter;
 
+const DSTATIC_PATH: &str = "Map/CDungeon/Static.smubin";
+
 impl BnpConverter {
+    /// BCML logs changed shrine entry points keyed by `{Map}___{PosName}`,
+    /// with the full entry as the value. These are merged into the DLC copy
+    /// of the dungeon static if there is one, otherwise into the base game
+    /// copy, keeping every other section of the file intact.
     pub fn handle_dungeon_static(&self) -> Result<()> {
         let dstatic_path = self.current_root.join("logs/dstatic.yml");
         if dstatic_path.exists() {
             log::debug!("Processing dungeon static log");
             let dstatic_diff = Byml::from_text(fs::read_to_string(&dstatic_path)?)?;
-            let base = Byml::from_binary(decompress(
-                self.dump
-                    .get_aoc_bytes_uncached("Map/CDungeon/Static.smubin")?,
-            )?)?;
-            let mut dstatic: FxHashMap<String, Byml> = base
-                .as_hash()?
-                .get("StartPos")
-                .context("No StartPos")?
-                .as_array()?
-                .iter()
-                .map(|entry| -> Result<(String, Byml)> {
-                    let hash = entry.as_hash()?;
-                    let id = hash
-                        .get("Map")
-                        .context("Pos missing Map")?
-                        .as_string()?
-                        .clone()
-                        + "___"
-                        + hash
-                            .get("PosName")
-                            .context("Pos missing PosName")?
-                            .as_string()?;
-                    Ok((id, entry.clone()))
-                })
-                .collect::<Result<_>>()?;
-            dstatic.extend(
-                dstatic_diff
-                    .as_hash()?
-                    .iter()
-                    .map(|(k, v)| (k.clone(), v.clone())),
-            );
-            let dstatic = bhash!(
-                "StartPos" => dstatic.into_values().collect()
-            );
-            let dest_path = self
-                .current_root
-                .join(self.aoc)
-                .join("Map/CDungeon/Static.smubin");
+            let diff = Static::try_from(&bhash!(
+                "StartPos" => dstatic_diff
+                    .into_hash()
+                    .context("Invalid dungeon static log: not a map")?
+                    .into_values()
+                    .collect()
+            ))
+            .context("Invalid entry in dungeon static log")?;
+            let (base, prefix) = match self.dump.get_aoc_bytes_uncached(DSTATIC_PATH) {
+                Ok(base) => (base, self.aoc),
+                Err(e) => {
+                    log::debug!("No DLC dungeon static ({e}), using base game copy");
+                    (
+                        self.dump
+                            .get_bytes_uncached(DSTATIC_PATH)
+                            .context("Failed to get stock dungeon static")?,
+                        self.content,
+                    )
+                }
+            };
+            let base = Static::try_from(&Byml::from_binary(decompress(base)?)?)
+                .context("Failed to parse stock dungeon static")?;
+            let dstatic = Byml::from(base.merge(&diff));
+            let dest_path = self.current_root.join(prefix).join(DSTATIC_PATH);
             dest_path.parent().iter().try_for_each(fs::create_dir_all)?;
             fs::write(dest_path, compress(dstatic.to_binary(self.platform.into())))?;
         }
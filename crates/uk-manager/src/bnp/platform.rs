@@ -0,0 +1,218 @@
+use std::{
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use rayon::prelude::*;
+use roead::{
+    byml::Byml,
+    sarc::{Sarc, SarcWriter},
+    yaz0::{compress, decompress_if},
+};
+use rstb::ResourceSizeTable;
+
+use crate::settings::Platform;
+
+/// Formats which are platform specific but which we have no way to convert,
+/// mostly models, textures, and audio.
+static UNCONVERTIBLE_EXTS: &[&str] = &[
+    "bfres", "sbfres", "bntx", "sbntx", "bftex", "sbftex", "bfstm", "bars", "sbars", "bfsha",
+    "sbfsha", "bitemico", "sbitemico", "bfarc", "sbfarc",
+];
+
+/// Reads the platform a BNP was built for from its info.json, if it has one.
+pub fn bnp_platform(root: &Path) -> Option<Platform> {
+    let info: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(root.join("info.json")).ok()?).ok()?;
+    info.get("platform")?.as_str()?.parse().ok()
+}
+
+/// Replaces a path prefix in log text, matching either slash style, but only
+/// where it starts a path.
+fn replace_prefix(text: &str, from: &str, to: &str) -> String {
+    let mut text = text.to_owned();
+    for sep in ["\\\\", "\\", "/"] {
+        let (from, to) = (from.replace('/', sep) + sep, to.replace('/', sep) + sep);
+        let mut out = String::with_capacity(text.len());
+        let mut last = 0;
+        for (i, _) in text.match_indices(from.as_str()) {
+            let starts_path = text[..i]
+                .chars()
+                .next_back()
+                .map(|c| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(true);
+            if starts_path && i >= last {
+                out.push_str(&text[last..i]);
+                out.push_str(&to);
+                last = i + from.len();
+            }
+        }
+        out.push_str(&text[last..]);
+        text = out;
+    }
+    text
+}
+
+/// Converts a single file to the target platform. Returns `None` if there was
+/// nothing to change.
+fn convert_data(
+    data: &[u8],
+    name: &str,
+    to: Platform,
+    unconvertible: &AtomicUsize,
+) -> Result<Option<Vec<u8>>> {
+    let compressed = data.starts_with(b"Yaz0");
+    let data = decompress_if(data);
+    let converted = if data.starts_with(b"BY") || data.starts_with(b"YB") {
+        Some(
+            Byml::from_binary(&data)
+                .with_context(|| format!("Failed to parse BYML file {name}"))?
+                .to_binary(to.into()),
+        )
+    } else if data.starts_with(b"SARC") {
+        let sarc = Sarc::new(data.as_ref())
+            .with_context(|| format!("Failed to parse SARC file {name}"))?;
+        let mut writer =
+            SarcWriter::new(to.into()).with_min_alignment(sarc.guess_min_alignment());
+        if name.ends_with("arc") {
+            writer.set_legacy_mode(true);
+        }
+        for file in sarc.files() {
+            let Some(file_name) = file.name() else { continue };
+            let data = convert_data(file.data, file_name, to, unconvertible)?
+                .unwrap_or_else(|| file.data.to_vec());
+            writer.add_file(file_name, data);
+        }
+        Some(writer.to_binary())
+    } else if data.starts_with(b"RSTB") {
+        Some(
+            ResourceSizeTable::from_binary(data.as_ref())
+                .with_context(|| format!("Failed to parse RSTB file {name}"))?
+                .to_binary(to.into()),
+        )
+    } else {
+        if Path::new(name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| UNCONVERTIBLE_EXTS.contains(&e))
+            .unwrap_or(false)
+        {
+            log::debug!("Cannot convert {name} to {to}");
+            unconvertible.fetch_add(1, Ordering::Relaxed);
+        }
+        None
+    };
+    Ok(converted.map(|data| if compressed { compress(data) } else { data }))
+}
+
+fn convert_root(
+    root: &Path,
+    from: Platform,
+    to: Platform,
+    unconvertible: &AtomicUsize,
+) -> Result<()> {
+    let (from_content, from_aoc) = uk_content::platform_prefixes(from.into());
+    let (to_content, to_aoc) = uk_content::platform_prefixes(to.into());
+    for (from_dir, to_dir) in [(from_content, to_content), (from_aoc, to_aoc)] {
+        let source = root.join(from_dir);
+        if !source.exists() {
+            continue;
+        }
+        let dest = root.join(to_dir);
+        dest.parent().map(fs::create_dir_all).transpose()?;
+        fs::rename(&source, &dest).with_context(|| {
+            format!("Failed to move {} to {}", source.display(), dest.display())
+        })?;
+        jwalk::WalkDir::new(&dest)
+            .into_iter()
+            .filter_map(|e| e.ok().and_then(|e| e.file_type().is_file().then(|| e.path())))
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .try_for_each(|path| -> Result<()> {
+                let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+                let data = fs::read(&path)?;
+                if let Some(data) = convert_data(&data, name, to, unconvertible)? {
+                    fs::write(&path, data)?;
+                }
+                Ok(())
+            })?;
+    }
+    let logs = root.join("logs");
+    if logs.exists() {
+        for log in fs::read_dir(logs)?.filter_map(|e| e.ok().map(|e| e.path())) {
+            if !matches!(
+                log.extension().and_then(|e| e.to_str()),
+                Some("json" | "yml" | "yaml")
+            ) {
+                continue;
+            }
+            let text = fs::read_to_string(&log)?;
+            let text = replace_prefix(&text, from_content, to_content);
+            fs::write(&log, replace_prefix(&text, from_aoc, to_aoc))?;
+        }
+    }
+    Ok(())
+}
+
+/// Converts an extracted BNP from one platform to the other. This swaps the
+/// endianness of BYML, SARC, and RSTB files, moves the content and DLC folders,
+/// and fixes the paths in the BNP logs. AAMP files are the same on both
+/// platforms, but models, textures, and audio cannot be converted, so a mod
+/// which replaces those will not work properly.
+pub fn convert_platform(root: &Path, from: Platform, to: Platform) -> Result<()> {
+    log::info!("Converting BNP from {from} to {to}");
+    let unconvertible = AtomicUsize::new(0);
+    convert_root(root, from, to, &unconvertible)?;
+    let opt_dir = root.join("options");
+    if opt_dir.exists() {
+        for option in fs::read_dir(opt_dir)?.filter_map(|r| {
+            r.ok().and_then(|r| {
+                let path = r.path();
+                path.is_dir().then_some(path)
+            })
+        }) {
+            convert_root(&option, from, to, &unconvertible)?;
+        }
+    }
+    let info_path = root.join("info.json");
+    if info_path.exists() {
+        let mut info: serde_json::Value = serde_json::from_str(&fs::read_to_string(&info_path)?)
+            .context("Failed to parse info.json")?;
+        info["platform"] = match to {
+            Platform::WiiU => "wiiu",
+            Platform::Switch => "switch",
+        }
+        .into();
+        fs::write(&info_path, serde_json::to_string_pretty(&info)?)?;
+    }
+    let unconvertible = unconvertible.into_inner();
+    if unconvertible > 0 {
+        log::warn!(
+            "{unconvertible} model, texture, or audio files in this BNP cannot be converted to \
+             {to} and may not work"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn replace_prefix() {
+        let log = r#"{"Pack/TitleBG.pack": "content/Pack/TitleBG.pack", "x": "mycontent/x"}"#;
+        assert_eq!(
+            super::replace_prefix(log, "content", "01007EF00011E000/romfs"),
+            r#"{"Pack/TitleBG.pack": "01007EF00011E000/romfs/Pack/TitleBG.pack", "x": "mycontent/x"}"#
+        );
+        assert_eq!(
+            super::replace_prefix(
+                r"aoc\\0010\\Pack\\AocMainField.pack",
+                "aoc/0010",
+                "01007EF00011F001/romfs"
+            ),
+            r"01007EF00011F001\\romfs\\Pack\\AocMainField.pack"
+        );
+    }
+}
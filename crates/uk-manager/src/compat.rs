@@ -0,0 +1,170 @@
+use std::path::PathBuf;
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+
+use crate::{mods::Mod, settings::Settings};
+
+/// Where community updates to the compatibility rules are published.
+pub const COMPAT_URL: &str =
+    "https://raw.githubusercontent.com/NiceneNerd/ukmm/master/crates/uk-manager/data/compat.yml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleKind {
+    /// The two mods should not be enabled together
+    Incompatible,
+    /// The first mod needs a higher priority than the second
+    Overrides,
+}
+
+/// A known note about a pair of mods. Mods are matched by name, ignoring
+/// case, or by their hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompatRule {
+    pub mods: [String; 2],
+    pub kind: RuleKind,
+    #[serde(default)]
+    pub note: String,
+}
+
+impl CompatRule {
+    #[inline]
+    fn matches(pattern: &str, mod_: &Mod) -> bool {
+        pattern.eq_ignore_ascii_case(mod_.meta.name.as_str())
+            || pattern == mod_.hash().to_string()
+    }
+
+    /// Checks the rule against the mods in a profile, in load order, lowest
+    /// priority first.
+    fn check(&self, mods: &[Mod]) -> Option<CompatWarning> {
+        let find = |pattern: &str| mods.iter().position(|m| m.enabled && Self::matches(pattern, m));
+        let (first, second) = (find(&self.mods[0])?, find(&self.mods[1])?);
+        let broken = match self.kind {
+            RuleKind::Incompatible => true,
+            RuleKind::Overrides => first < second,
+        };
+        broken.then(|| {
+            CompatWarning {
+                mods: [
+                    mods[first].meta.name.clone(),
+                    mods[second].meta.name.clone(),
+                ],
+                kind: self.kind,
+                note: self.note.clone(),
+            }
+        })
+    }
+}
+
+/// A compatibility rule which the current mod setup breaks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatWarning {
+    pub mods: [String; 2],
+    pub kind: RuleKind,
+    pub note: String,
+}
+
+impl std::fmt::Display for CompatWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            RuleKind::Incompatible => {
+                write!(f, "{} is incompatible with {}", self.mods[0], self.mods[1])?
+            }
+            RuleKind::Overrides => {
+                write!(
+                    f,
+                    "{} needs a higher priority than {}",
+                    self.mods[0], self.mods[1]
+                )?
+            }
+        }
+        if !self.note.is_empty() {
+            write!(f, ": {}", self.note)?;
+        }
+        Ok(())
+    }
+}
+
+/// A small ruleset of known mod pairs which need special handling. One is
+/// bundled with UKMM, and a newer copy can be downloaded into the storage
+/// folder, which is used instead as long as it is at least as new.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompatDb {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub rules: Vec<CompatRule>,
+}
+
+impl CompatDb {
+    #[inline]
+    fn path(settings: &Settings) -> PathBuf {
+        settings.storage_dir.join("compat.yml")
+    }
+
+    pub fn bundled() -> Self {
+        serde_yaml::from_str(include_str!("../data/compat.yml"))
+            .expect("Bundled compatibility rules should be valid")
+    }
+
+    pub fn load(settings: &Settings) -> Self {
+        let bundled = Self::bundled();
+        match fs::read_to_string(Self::path(settings))
+            .map_err(anyhow_ext::Error::from)
+            .and_then(|text| Ok(serde_yaml::from_str::<Self>(&text)?))
+        {
+            Ok(local) if local.version >= bundled.version => local,
+            Ok(_) => bundled,
+            Err(e) => {
+                log::debug!("No local compatibility rules, using bundled copy: {e}");
+                bundled
+            }
+        }
+    }
+
+    /// Parses a downloaded ruleset and saves it to the storage folder.
+    pub fn update(settings: &Settings, text: &str) -> Result<Self> {
+        let db: Self = serde_yaml::from_str(text).context("Invalid compatibility rules")?;
+        fs::create_dir_all(&settings.storage_dir)?;
+        fs::write(Self::path(settings), text).context("Failed to save compatibility rules")?;
+        log::info!(
+            "Updated compatibility rules to version {} ({} rules)",
+            db.version,
+            db.rules.len()
+        );
+        Ok(db)
+    }
+
+    /// Lists every rule broken by a profile's mods, in load order.
+    pub fn check(&self, mods: &[Mod]) -> Vec<CompatWarning> {
+        self.rules.iter().filter_map(|rule| rule.check(mods)).collect()
+    }
+
+    /// Lists the broken rules which involve one particular mod.
+    pub fn check_mod(&self, mod_: &Mod, mods: &[Mod]) -> Vec<CompatWarning> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.mods.iter().any(|p| CompatRule::matches(p, mod_)))
+            .filter_map(|rule| rule.check(mods))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompatDb, RuleKind};
+
+    #[test]
+    fn parse_rules() {
+        assert!(CompatDb::bundled().version > 0);
+        let db: CompatDb = serde_yaml::from_str(
+            "version: 2\nrules:\n  - mods: [A, B]\n    kind: overrides\n    note: Test\n",
+        )
+        .unwrap();
+        assert_eq!(db.rules[0].kind, RuleKind::Overrides);
+        assert_eq!(db.rules[0].mods[1], "B");
+    }
+}
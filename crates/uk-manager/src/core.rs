@@ -3,7 +3,11 @@ use std::sync::Arc;
 use anyhow_ext::{Context, Result};
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-use crate::{deploy, mods, settings::Settings};
+use crate::{
+    backup::Backup,
+    deploy, mods,
+    settings::{ProfilePaths, Settings},
+};
 
 #[derive(Debug, Clone)]
 pub struct Manager {
@@ -47,6 +51,91 @@ impl Manager {
         Ok(())
     }
 
+    /// Copies a profile under a new name.
+    pub fn clone_profile(&self, profile: &str, name: &str) -> Result<()> {
+        self.mod_manager.read().clone_profile(profile, name)
+    }
+
+    /// Renames a profile. If it is the current profile or has its own
+    /// folders, the settings are updated and saved to match.
+    pub fn rename_profile(&self, profile: &str, name: &str) -> Result<()> {
+        self.mod_manager.write().rename_profile(profile, name)?;
+        let mut settings = self.settings.write();
+        if let Some(config) = settings.platform_config_mut() {
+            let current = config.profile == profile;
+            if current {
+                config.profile = name.into();
+            }
+            let paths = config.profile_paths.remove(profile);
+            let moved = paths.is_some();
+            if let Some(paths) = paths {
+                config.profile_paths.insert(name.into(), paths);
+            }
+            if current || moved {
+                settings.save()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes a profile other than the current one, along with any folders
+    /// it was set to use.
+    pub fn delete_profile(&self, profile: &str) -> Result<()> {
+        self.mod_manager.read().delete_profile(profile)?;
+        let mut settings = self.settings.write();
+        if let Some(config) = settings.platform_config_mut()
+            && config.profile_paths.remove(profile).is_some()
+        {
+            settings.save()?;
+        }
+        Ok(())
+    }
+
+    /// Puts back a backup of the settings file and reloads everything.
+    pub fn restore_settings(&self, backup: &Backup) -> Result<()> {
+        Settings::restore_backup(backup)?;
+        self.reload()
+    }
+
+    /// Puts back a backup of a profile's mod list and load order. The
+    /// profile needs remerging afterwards if it is the current one.
+    pub fn restore_profile(&self, profile: &str, backup: &Backup) -> Result<()> {
+        self.mod_manager.read().restore_profile(profile, backup)
+    }
+
+    /// Sets the folders a profile uses instead of the shared ones and saves
+    /// the settings. If it is the current profile, its mods are merged again
+    /// into a new merged folder, and everything is marked to deploy again.
+    pub fn set_profile_paths(&self, profile: &str, paths: ProfilePaths) -> Result<()> {
+        let old = {
+            let mut settings = self.settings.write();
+            let config = settings
+                .platform_config_mut()
+                .context("No config for current platform")?;
+            let current = config.profile == profile;
+            let old = if paths.is_empty() {
+                config.profile_paths.remove(profile)
+            } else {
+                config.profile_paths.insert(profile.into(), paths.clone())
+            };
+            settings.save()?;
+            if !current {
+                return Ok(());
+            }
+            old.unwrap_or_default()
+        };
+        let deploy_manager = self.deploy_manager.read();
+        if old.merged_dir != paths.merged_dir {
+            deploy_manager
+                .apply(None)
+                .context("Failed to merge mods into the new folder")?;
+        }
+        if old != paths {
+            deploy_manager.reset_pending()?;
+        }
+        Ok(())
+    }
+
     #[inline(always)]
     pub fn settings(&self) -> RwLockReadGuard<Settings> {
         self.settings.read()
@@ -1,12 +1,16 @@
 use std::{
-    collections::BTreeSet,
+    collections::{BTreeMap, BTreeSet},
     path::{Path, PathBuf},
-    sync::{Arc, Weak},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Weak,
+    },
 };
 
 use anyhow_ext::{Context, Result};
 use dashmap::DashMap;
 use fs_err as fs;
+use globset::GlobSet;
 use join_str::jstr;
 use parking_lot::RwLock;
 use path_slash::PathExt;
@@ -17,15 +21,91 @@ use serde::{Deserialize, Serialize};
 use smartstring::alias::String;
 use uk_content::{constants::Language, platform_prefixes};
 use uk_mod::{
-    unpack::{ModReader, ModUnpacker},
-    Manifest,
+    unpack::{ModFlagConflict, ModMissingResource, ModUnpacker},
+    EmulatorFlag, Manifest,
 };
 
+pub use self::progress::{DeployProgress, DeployStatus};
 use crate::{
     mods,
-    settings::{DeployMethod, Platform, Settings},
+    settings::{DeployConfig, DeployLayout, DeployMethod, Platform, Settings},
     util,
 };
+pub mod emulators;
+mod ftp;
+mod merge_backup;
+mod network;
+mod progress;
+mod rollback;
+pub mod sdcard;
+pub mod snapshot;
+mod title;
+
+static RSTB_PATH: &str = "System/Resource/ResourceSizeTable.product.srsizetable";
+
+/// Whether a deployed file, keyed by its platform folder and path, matches
+/// any of a target's exclusion patterns.
+fn excluded_key(excludes: &GlobSet, key: &str, (content, aoc): (&str, &str)) -> bool {
+    [content, aoc].into_iter().any(|dir| {
+        key.strip_prefix(dir)
+            .and_then(|file| file.strip_prefix('/'))
+            .map_or(false, |file| excludes.is_match(file))
+    })
+}
+
+/// Where to deploy a file from: the highest priority loose mod which has it,
+/// or else the merged folder. The key includes the platform folder.
+fn deploy_source(merged: &Path, loose: &[PathBuf], key: &str) -> PathBuf {
+    loose
+        .iter()
+        .map(|dir| dir.join(key))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| merged.join(key))
+}
+
+/// How many files and bytes a deployment will write, for its progress.
+fn deploy_totals(
+    syncs: &Manifest,
+    (content, aoc): (&str, &str),
+    filter: impl Fn(&&String) -> bool + Copy,
+    source: impl Fn(&str) -> PathBuf,
+) -> (usize, u64) {
+    let mut totals = (0, 0);
+    for (dir, files) in [(content, &syncs.content_files), (aoc, &syncs.aoc_files)] {
+        for file in files.iter().filter(filter) {
+            totals.0 += 1;
+            totals.1 += fs::metadata(source(&jstr!("{dir}/{file.as_str()}")))
+                .map(|meta| meta.len())
+                .unwrap_or(0);
+        }
+    }
+    totals
+}
+
+/// Removes every merged file a full remerge will not write again, and the
+/// RSTB, which has to be rebuilt from scratch. Used instead of clearing the
+/// merged folder when unchanged files are kept.
+fn prune_merged(merged: &Path, keep: &Manifest, (content, aoc): (&str, &str)) -> Result<()> {
+    for (dir, files) in [(content, &keep.content_files), (aoc, &keep.aoc_files)] {
+        let root = merged.join(dir);
+        let stale: Vec<PathBuf> = jwalk::WalkDir::new(&root)
+            .into_iter()
+            .filter_map(|file| {
+                file.ok()
+                    .and_then(|file| file.file_type().is_file().then(|| file.path()))
+            })
+            .filter(|path| {
+                let rel: String = path.strip_prefix(&root).unwrap().to_slash_lossy().into();
+                rel == RSTB_PATH || !files.contains(&rel)
+            })
+            .collect();
+        stale.par_iter().try_for_each(|path| -> Result<()> {
+            fs::remove_file(path)?;
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
 
 #[inline(always)]
 fn is_symlink(link: &Path) -> bool {
@@ -54,10 +134,190 @@ fn create_symlink(link: &Path, target: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Links a folder with an NTFS junction, without falling back to a symlink
+/// like [`create_symlink`]. Other systems have no junctions, so they get a
+/// symlink, which doesn't need special permissions there anyway.
+#[inline(always)]
+fn create_junction(link: &Path, target: &Path) -> Result<()> {
+    #[cfg(windows)]
+    junction::create(target, link).with_context(|| {
+        format!(
+            "Failed to create junction from {} to {}",
+            link.display(),
+            target.display()
+        )
+    })?;
+    #[cfg(unix)]
+    create_symlink(link, target)?;
+    Ok(())
+}
+
+/// Builds the `rules.txt` which makes a deploy folder show up in Cemu as a
+/// graphic pack. The pack is named after the folder, so deploying to more
+/// than one folder doesn't give several packs with the same name, and an
+/// existing file is only replaced if it says something different.
+fn cemu_rules(output: &Path) -> std::string::String {
+    let name = output
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n.trim_start_matches("BreathOfTheWild_"))
+        .filter(|n| !n.is_empty())
+        .unwrap_or("UKMM");
+    include_str!("../../../assets/rules.txt")
+        .lines()
+        .map(|line| {
+            if line.starts_with("name =") {
+                jstr!("name = {name}")
+            } else if line.starts_with("path =") {
+                jstr!("path = The Legend of Zelda: Breath of the Wild/Mods/{name}")
+            } else {
+                line.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n"
+}
+
+/// How many files to deploy at once. `fs::copy` already uses the fast path
+/// each OS offers (`CopyFileExW`, `copy_file_range`, `fcopyfile`), so the
+/// rest of the time is spent waiting on the disk, and using more workers
+/// than cores keeps it busy. SD cards slow down with many writers at once,
+/// so they get only a couple.
+fn deploy_workers(config: &DeployConfig) -> usize {
+    if config.workers > 0 {
+        config.workers
+    } else if config.method == DeployMethod::SdCard {
+        2
+    } else {
+        std::thread::available_parallelism()
+            .map(|n| n.get() * 2)
+            .unwrap_or(4)
+            .clamp(2, 16)
+    }
+}
+
+/// Runs a pre or post deploy hook command through the system shell, after
+/// filling in its variables. The same values are also passed as the
+/// `UKMM_OUTPUT` and `UKMM_PLATFORM` environment variables.
+fn run_hook(command: &str, output: &Path, platform: Platform) -> Result<()> {
+    let output = output.to_string_lossy();
+    let platform = platform.to_string();
+    let command = command
+        .replace("{output}", &output)
+        .replace("{platform}", &platform);
+    log::info!("Running deploy hook: {command}");
+    #[cfg(windows)]
+    let mut shell = {
+        let mut shell = std::process::Command::new("cmd");
+        shell.arg("/C");
+        shell
+    };
+    #[cfg(unix)]
+    let mut shell = {
+        let mut shell = std::process::Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let result = shell
+        .arg(&command)
+        .env("UKMM_OUTPUT", output.as_ref())
+        .env("UKMM_PLATFORM", &platform)
+        .output()
+        .with_context(|| format!("Failed to run deploy hook: {command}"))?;
+    let stdout = std::string::String::from_utf8_lossy(&result.stdout);
+    if !stdout.trim().is_empty() {
+        log::debug!("Deploy hook output:\n{}", stdout.trim());
+    }
+    if !result.status.success() {
+        anyhow_ext::bail!(
+            "Deploy hook `{command}` failed ({}):\n{}",
+            result.status,
+            std::string::String::from_utf8_lossy(&result.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 struct PendingLog {
     files:  Manifest,
     delete: Manifest,
+    #[serde(default)]
+    queued: bool,
+    /// Targets which already have the pending changes
+    #[serde(default)]
+    deployed_to: BTreeSet<String>,
+}
+
+/// A record of what copy and link deploys wrote to each output folder and the
+/// hash of each file, so the next one can skip files whose contents haven't
+/// changed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DeployedLog {
+    targets: BTreeMap<PathBuf, BTreeMap<String, u64>>,
+}
+
+/// What a deployment would do to a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeKind {
+    Add,
+    Update,
+    Remove,
+}
+
+/// A file a deployment would change, with its size in bytes. For removals the
+/// size is that of the deployed file, where it can be known.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DeployChange {
+    pub kind: ChangeKind,
+    pub path: String,
+    pub size: u64,
+}
+
+impl std::fmt::Display for DeployChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            ChangeKind::Add => "Add",
+            ChangeKind::Update => "Update",
+            ChangeKind::Remove => "Remove",
+        };
+        write!(
+            f,
+            "{kind} {} ({:.1} KB)",
+            self.path,
+            self.size as f64 / 1024.0
+        )
+    }
+}
+
+/// A deployed file whose contents don't match what was merged, or which is
+/// missing from the deploy target entirely.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DeployMismatch {
+    pub target: String,
+    pub path:   String,
+}
+
+impl std::fmt::Display for DeployMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.path, self.target)
+    }
+}
+
+/// A file a network share deployment could not write or remove, even after
+/// retrying.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DeployFailure {
+    pub target: String,
+    pub path:   String,
+    pub error:  std::string::String,
+}
+
+impl std::fmt::Display for DeployFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}): {}", self.path, self.target, self.error)
+    }
 }
 
 #[derive(Debug)]
@@ -66,6 +326,14 @@ pub struct Manager {
     mod_manager: Weak<RwLock<mods::Manager>>,
     pending_files: RwLock<Manifest>,
     pending_delete: RwLock<Manifest>,
+    queued: AtomicBool,
+    deployed_to: RwLock<BTreeSet<String>>,
+    flag_conflicts: RwLock<Vec<ModFlagConflict>>,
+    missing_resources: RwLock<Vec<ModMissingResource>>,
+    mismatches: RwLock<Vec<DeployMismatch>>,
+    failures: RwLock<Vec<DeployFailure>>,
+    progress: Arc<DeployProgress>,
+    deployed: RwLock<DeployedLog>,
 }
 
 impl Manager {
@@ -74,6 +342,29 @@ impl Manager {
         settings.platform_dir().join("pending.yml")
     }
 
+    #[inline(always)]
+    fn deployed_path(settings: &Settings) -> PathBuf {
+        settings.platform_dir().join("deployed.yml")
+    }
+
+    /// A deploy target counts as available if the output folder or at least
+    /// its parent exists. If neither does, the target is most likely on an
+    /// unmounted drive or SD card.
+    #[inline]
+    fn target_ready(output: &Path) -> bool {
+        output.exists() || output.parent().map(|p| p.exists()).unwrap_or(false)
+    }
+
+    /// Whether a deploy target can be deployed to now: its folder exists or,
+    /// for FTP, the console's server accepts connections.
+    fn config_ready(config: &DeployConfig, output: &Path) -> bool {
+        if config.method == DeployMethod::Ftp {
+            ftp::reachable(&config.ftp)
+        } else {
+            Self::target_ready(output)
+        }
+    }
+
     pub fn init(
         settings: &Arc<RwLock<Settings>>,
         mod_manager: &Arc<RwLock<mods::Manager>>,
@@ -84,6 +375,9 @@ impl Manager {
             .and_then(|text| Ok(serde_yaml::from_str::<PendingLog>(&text)?))
         {
             Ok(log) => {
+                if log.queued {
+                    log::info!("Queued deployment found, waiting for deploy target");
+                }
                 if !log.files.is_empty() || !log.delete.is_empty() {
                     log::info!("Pending deployment data found");
                     log::debug!("{:#?}", &log);
@@ -98,14 +392,148 @@ impl Manager {
                 Default::default()
             }
         };
+        let deployed = fs::read_to_string(Self::deployed_path(&settings.read()))
+            .map_err(anyhow_ext::Error::from)
+            .and_then(|text| Ok(serde_yaml::from_str::<DeployedLog>(&text)?))
+            .unwrap_or_else(|e| {
+                log::debug!("No record of deployed files: {e}");
+                Default::default()
+            });
         Ok(Self {
             settings: Arc::downgrade(settings),
             mod_manager: Arc::downgrade(mod_manager),
             pending_files: RwLock::new(pending.files),
             pending_delete: RwLock::new(pending.delete),
+            queued: AtomicBool::new(pending.queued),
+            deployed_to: RwLock::new(pending.deployed_to),
+            flag_conflicts: Default::default(),
+            missing_resources: Default::default(),
+            mismatches: Default::default(),
+            failures: Default::default(),
+            progress: Default::default(),
+            deployed: RwLock::new(deployed),
         })
     }
 
+    /// Whether a deployment is waiting for its target to become available.
+    #[inline]
+    pub fn queued(&self) -> bool {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Gamedata flag conflicts found during the last merge.
+    pub fn flag_conflicts(&self) -> Vec<ModFlagConflict> {
+        self.flag_conflicts.read().clone()
+    }
+
+    /// Resources skipped during the last merge because they are missing from
+    /// the game dump.
+    pub fn missing_resources(&self) -> Vec<ModMissingResource> {
+        self.missing_resources.read().clone()
+    }
+
+    /// Deployed files which failed the last verification.
+    pub fn mismatches(&self) -> Vec<DeployMismatch> {
+        self.mismatches.read().clone()
+    }
+
+    /// Progress of the running deployment, which can also cancel it. Shared
+    /// with the deployment, like [`ModUnpacker::flag_conflicts`].
+    pub fn progress(&self) -> Arc<DeployProgress> {
+        self.progress.clone()
+    }
+
+    /// Files the last network share deployment could not write or remove.
+    /// Targets with failures are left pending, so deploying again retries
+    /// them.
+    pub fn failures(&self) -> Vec<DeployFailure> {
+        self.failures.read().clone()
+    }
+
+    /// Emulator settings needed by the enabled mods, each with the names of
+    /// the mods that need it.
+    pub fn required_settings(&self) -> Vec<(EmulatorFlag, Vec<String>)> {
+        let Some(mod_manager) = self.mod_manager.upgrade() else {
+            return vec![];
+        };
+        let mut required: Vec<(EmulatorFlag, Vec<String>)> = vec![];
+        for mod_ in mod_manager.read().mods() {
+            for flag in mod_.meta.emulator_flags {
+                match required.iter_mut().find(|(f, _)| *f == flag) {
+                    Some((_, mods)) => mods.push(mod_.meta.name.clone()),
+                    None => required.push((flag, vec![mod_.meta.name.clone()])),
+                }
+            }
+        }
+        required
+    }
+
+    /// Logs the emulator settings the enabled mods need, warning about any
+    /// setting which two mods want set to different values.
+    fn report_required_settings(&self) {
+        let required = self.required_settings();
+        if required.is_empty() {
+            return;
+        }
+        log::info!(
+            "Enabled mods need the following emulator settings:\n{}",
+            required
+                .iter()
+                .map(|(flag, mods)| format!("{flag} ({})", mods.join(", ")))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        for (i, (flag, _)) in required.iter().enumerate() {
+            for (other, _) in &required[i + 1..] {
+                if flag.emulator == other.emulator && flag.setting == other.setting {
+                    log::warn!(
+                        "Mods disagree on emulator setting {}: {} or {}",
+                        flag.setting,
+                        flag.value,
+                        other.value
+                    );
+                }
+            }
+        }
+    }
+
+    /// Checks whether any deploy target still waiting for the pending
+    /// changes exists.
+    pub fn target_available(&self) -> bool {
+        self.settings
+            .upgrade()
+            .and_then(|settings| {
+                let settings = settings.read();
+                let deployed_to = self.deployed_to.read();
+                settings.platform_config().map(|p| {
+                    p.targets()
+                        .iter()
+                        .filter(|c| !deployed_to.contains(&c.name))
+                        .any(|c| {
+                            Self::config_ready(c, &c.target(settings.current_mode, p.language))
+                        })
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    /// Names of the deploy targets which already have the pending changes.
+    pub fn deployed_to(&self) -> BTreeSet<String> {
+        self.deployed_to.read().clone()
+    }
+
+    /// Runs a queued deployment if its target has become available. Returns
+    /// whether a deployment was actually performed.
+    pub fn retry_queued(&self) -> Result<bool> {
+        if self.queued() && self.target_available() {
+            log::info!("Deploy target is now available, running queued deployment");
+            self.deploy()?;
+            Ok(!self.queued())
+        } else {
+            Ok(false)
+        }
+    }
+
     #[inline]
     pub fn pending(&self) -> bool {
         !(self.pending_delete.read().is_empty() && self.pending_files.read().is_empty())
@@ -121,20 +549,38 @@ impl Manager {
             + files.aoc_files.len()
     }
 
+    /// Queues files changed or removed in the merged folder outside of a
+    /// merge, so the next deployment picks them up.
+    pub fn queue_changes(&self, changed: &Manifest, removed: &Manifest) -> Result<()> {
+        {
+            let mut files = self.pending_files.write();
+            let mut dels = self.pending_delete.write();
+            files.extend(changed);
+            dels.extend(removed);
+            files.content_files.retain(|f| !removed.content_files.contains(f));
+            files.aoc_files.retain(|f| !removed.aoc_files.contains(f));
+        }
+        self.deployed_to.write().clear();
+        self.save()
+    }
+
     pub fn reset_pending(&self) -> Result<()> {
         self.pending_delete.write().clear();
         self.pending_files.write().clear();
+        self.deployed_to.write().clear();
         let settings = self
             .settings
             .upgrade()
             .expect("YIKES the settings manager is gone");
         let settings = settings.read();
         let source = settings.merged_dir();
-        let config = settings
+        let platform_config = settings
             .platform_config()
-            .and_then(|c| c.deploy_config.as_ref())
             .context("No deployment config for current platform")?;
-        let dest = &config.output;
+        let config = platform_config
+            .deploy_config()
+            .context("No deployment config for current platform")?;
+        let dest = &config.target(settings.current_mode, platform_config.language);
         let (content, aoc) = platform_prefixes(settings.current_mode.into());
 
         let collect_files = |root: &str| -> BTreeSet<String> {
@@ -164,6 +610,7 @@ impl Manager {
         *self.pending_files.write() = Manifest {
             content_files: collect_files(content),
             aoc_files:     collect_files(aoc),
+            ..Default::default()
         };
 
         let collect_deletes = |root: &str| -> BTreeSet<String> {
@@ -185,6 +632,7 @@ impl Manager {
         *self.pending_delete.write() = Manifest {
             content_files: collect_deletes(content),
             aoc_files:     collect_deletes(aoc),
+            ..Default::default()
         };
 
         Ok(())
@@ -196,47 +644,320 @@ impl Manager {
             serde_yaml::to_string(&PendingLog {
                 delete: self.pending_delete.read().clone(),
                 files:  self.pending_files.read().clone(),
+                queued: self.queued(),
+                deployed_to: self.deployed_to.read().clone(),
             })?,
         )?;
         Ok(())
     }
 
+    /// Lists the files the next deployment to a target (or the first one)
+    /// would add, update, and remove, without touching the output. Files
+    /// already deployed with the same contents are left out. Over FTP the
+    /// remote files can't be checked, so every pending file is listed as an
+    /// update.
+    pub fn dry_run(&self, target: Option<&str>) -> Result<Vec<DeployChange>> {
+        let settings = self
+            .settings
+            .upgrade()
+            .expect("YIKES, the settings manager is gone");
+        let settings = settings.read();
+        let platform_config = settings
+            .platform_config()
+            .context("No deployment config for current platform")?;
+        let lang = platform_config.language;
+        let config = match target {
+            Some(name) => {
+                platform_config
+                    .deploy_target(name)
+                    .with_context(|| format!("No deploy target named {name}"))?
+            }
+            None => {
+                platform_config
+                    .deploy_config()
+                    .context("No deployment config for current platform")?
+            }
+        };
+        self.changes(&settings, &config, lang)
+    }
+
+    fn changes(
+        &self,
+        settings: &Settings,
+        config: &DeployConfig,
+        lang: Language,
+    ) -> Result<Vec<DeployChange>> {
+        let output = config.target(settings.current_mode, lang);
+        if config.method.links_folder() {
+            log::info!(
+                "Deploy method is {}, no files are copied",
+                config.method.name()
+            );
+            return Ok(vec![]);
+        }
+        let excludes = config.exclusions();
+        let filter_xbootup = |file: &&String| -> bool {
+            (!file.starts_with("Pack/Bootup_") || **file == lang.bootup_path())
+                && !excludes.is_match(file.as_str())
+        };
+        let (content, aoc) = platform_prefixes(settings.current_mode.into());
+        let deletes = self.pending_delete.read();
+        let syncs = self.pending_files.read();
+        let deployed = self.deployed.read();
+        let deployed = deployed.targets.get(&output);
+        let merged = settings.merged_dir();
+        let loose = self.loose_dirs(settings);
+        let mut changes = vec![];
+        for (dir, dels, syncs) in [
+            (content, &deletes.content_files, &syncs.content_files),
+            (aoc, &deletes.aoc_files, &syncs.aoc_files),
+        ] {
+            let dest = output.join(dir);
+            for file in dels.iter().filter(filter_xbootup) {
+                let out = dest.join(file.as_str());
+                if config.method == DeployMethod::Ftp || out.exists() {
+                    changes.push(DeployChange {
+                        kind: ChangeKind::Remove,
+                        path: jstr!("{dir}/{file.as_str()}").into(),
+                        size: fs::metadata(&out).map(|m| m.len()).unwrap_or(0),
+                    });
+                }
+            }
+            for file in syncs.iter().filter(filter_xbootup) {
+                let key: String = jstr!("{dir}/{file.as_str()}").into();
+                let from = deploy_source(&merged, &loose, &key);
+                let Ok(meta) = fs::metadata(&from) else {
+                    continue;
+                };
+                let out = dest.join(file.as_str());
+                let kind = if config.method == DeployMethod::Ftp {
+                    ChangeKind::Update
+                } else if !out.exists() {
+                    ChangeKind::Add
+                } else if deployed.and_then(|d| d.get(&key)) == Some(&util::hash_file(&from)?) {
+                    continue;
+                } else {
+                    ChangeKind::Update
+                };
+                changes.push(DeployChange {
+                    kind,
+                    path: key,
+                    size: meta.len(),
+                });
+            }
+        }
+        if let Some(deployed) = deployed {
+            for file in deployed.keys() {
+                let out = output.join(file.as_str());
+                if (!deploy_source(&merged, &loose, file).exists()
+                    || excluded_key(&excludes, file, (content, aoc)))
+                    && out.exists()
+                    && !changes.iter().any(|c| c.path == *file)
+                {
+                    changes.push(DeployChange {
+                        kind: ChangeKind::Remove,
+                        path: file.clone(),
+                        size: fs::metadata(&out).map(|m| m.len()).unwrap_or(0),
+                    });
+                }
+            }
+        }
+        changes.sort();
+        Ok(changes)
+    }
+
+    /// Deploys pending changes to every target still waiting for them.
     pub fn deploy(&self) -> Result<()> {
+        self.deploy_where(|_| true, false)
+    }
+
+    /// Deploys pending changes to the targets set to deploy automatically.
+    pub fn deploy_auto(&self) -> Result<()> {
+        self.deploy_where(|config| config.auto, false)
+    }
+
+    /// Deploys pending changes to the named target, even if it already has
+    /// them.
+    pub fn deploy_target(&self, name: &str) -> Result<()> {
+        self.deploy_where(|config| config.name == name, true)
+    }
+
+    /// Deploys to each matching target. Pending changes are only cleared once
+    /// every target has them. Unless `force` is set, targets which already
+    /// have them are skipped.
+    fn deploy_where(&self, filter: impl Fn(&DeployConfig) -> bool, force: bool) -> Result<()> {
         let settings = self
             .settings
             .upgrade()
             .expect("YIKES, the settings manager is gone");
         let settings = settings.read();
-        let mut lang = Language::USen;
-        let config = settings
+        let platform_config = settings
             .platform_config()
-            .and_then(|c| {
-                lang = c.language;
-                c.deploy_config.as_ref()
-            })
+            .filter(|c| !c.deploy_targets.is_empty())
             .context("No deployment config for current platform")?;
+        let mut queued = false;
+        self.progress.reset();
+        for config in platform_config.targets().iter().filter(|c| filter(c)) {
+            if !force && self.deployed_to.read().contains(&config.name) {
+                continue;
+            }
+            log::info!("Deploying to {}", config.name);
+            if self.deploy_to(&settings, config, platform_config.language)? {
+                if self.failures.read().iter().any(|f| f.target == config.name) {
+                    continue;
+                }
+                self.deployed_to.write().insert(config.name.clone());
+                if config.verify {
+                    let bad = self.verify_target(&settings, config, platform_config.language)?;
+                    let mut mismatches = self.mismatches.write();
+                    mismatches.retain(|m| m.target != config.name);
+                    mismatches.extend(bad);
+                }
+            } else {
+                queued = true;
+            }
+        }
+        self.report_required_settings();
+        let deployed_to = self.deployed_to.read().clone();
+        if platform_config
+            .deploy_targets
+            .iter()
+            .all(|c| deployed_to.contains(&c.name))
+        {
+            self.pending_delete.write().clear();
+            self.pending_files.write().clear();
+            self.deployed_to.write().clear();
+        }
+        self.queued.store(queued, Ordering::Relaxed);
+        self.save()?;
+        Ok(())
+    }
+
+    /// Deploys pending changes to one target. Returns false if the target
+    /// isn't available, in which case the deployment is queued.
+    fn deploy_to(&self, settings: &Settings, config: &DeployConfig, lang: Language) -> Result<bool> {
         log::debug!("Deployment config:\n{:#?}", &config);
-        if config.method == DeployMethod::Symlink {
-            log::info!("Deploy method is symlink, checking for symlink");
-            if !is_symlink(&config.output) {
-                if config.output.exists() {
+        let output = config.target(settings.current_mode, lang);
+        if !Self::config_ready(config, &output) {
+            if config.method == DeployMethod::Ftp {
+                log::warn!(
+                    "FTP server at {} is not available, deployment queued until it can connect",
+                    config.ftp.host
+                );
+            } else {
+                log::warn!(
+                    "Deploy target {} is not available, deployment queued until it appears",
+                    output.display()
+                );
+            }
+            return Ok(false);
+        }
+        if config.method == DeployMethod::SdCard {
+            sdcard::validate_target(&output, settings.current_mode)?;
+        }
+        if !config.pre_deploy.is_empty() {
+            run_hook(&config.pre_deploy, &output, settings.current_mode)
+                .context("Pre-deploy command failed, deployment cancelled")?;
+        }
+        if !config.method.links_folder() && config.method != DeployMethod::Ftp {
+            self.backup_target(settings, config, lang)?;
+        }
+        let excludes = config.exclusions();
+        let filter_xbootup = |file: &&String| -> bool {
+            (!file.starts_with("Pack/Bootup_") || **file == lang.bootup_path())
+                && !excludes.is_match(file.as_str())
+        };
+        let merged = settings.merged_dir();
+        let loose = self.loose_dirs(settings);
+        if config.method.links_folder() {
+            if !excludes.is_empty() {
+                log::warn!(
+                    "The {} deploy method links the whole merged folder, so exclusions are not \
+                     applied",
+                    config.method.name()
+                );
+            }
+            if !loose.is_empty() {
+                log::warn!(
+                    "The {} deploy method links the whole merged folder, so mods set to deploy \
+                     loose are left out",
+                    config.method.name()
+                );
+            }
+            log::info!("Deploy method links the folder, checking for link");
+            if !is_symlink(&output) {
+                if output.exists() {
                     log::warn!("Removing old stuff from deploy folder");
-                    util::remove_dir_all(&config.output)
+                    util::remove_dir_all(&output)
                         .context("Failed to remove old deployment folder")?;
                 }
-                log::info!("Creating new symlink");
-                create_symlink(&config.output, &settings.merged_dir())
-                    .context("Failed to symlink deployment folder")?;
+                if config.method == DeployMethod::Junction {
+                    log::info!("Creating new junction");
+                    create_junction(&output, &settings.merged_dir())
+                        .context("Failed to link deployment folder")?;
+                } else {
+                    log::info!("Creating new symlink");
+                    create_symlink(&output, &settings.merged_dir())
+                        .context("Failed to symlink deployment folder")?;
+                }
             } else {
-                log::info!("Symlink exists, no deployment needed")
+                log::info!("Link exists, no deployment needed")
+            }
+        } else if config.method == DeployMethod::Ftp {
+            let (content, aoc) = uk_content::platform_prefixes(settings.current_mode.into());
+            let deletes = self.pending_delete.read();
+            log::debug!("Deployed files to delete:\n{:#?}", &deletes);
+            let syncs = self.pending_files.read();
+            log::debug!("Files to deploy\n{:#?}", &syncs);
+            log::info!("Deploying by FTP");
+            let mut ftp = match ftp::FtpDeployer::connect(&config.ftp, &output) {
+                Ok(ftp) => ftp,
+                Err(e) => {
+                    log::warn!("{e:?}\nDeployment queued until it can connect");
+                    return Ok(false);
+                }
+            };
+            let (total, bytes) = deploy_totals(&syncs, (content, aoc), filter_xbootup, |key| {
+                deploy_source(&merged, &loose, key)
+            });
+            let _run = self.progress.start(total, bytes);
+            let mut done = 0;
+            for (dir, dels, syncs) in [
+                (content, &deletes.content_files, &syncs.content_files),
+                (aoc, &deletes.aoc_files, &syncs.aoc_files),
+            ] {
+                for file in dels.iter().filter(filter_xbootup) {
+                    ftp.delete(dir, file);
+                }
+                for file in syncs.iter().filter(filter_xbootup) {
+                    self.progress.check()?;
+                    done += 1;
+                    log::info!("PROGRESSUploading files ({done}/{total})");
+                    let key = jstr!("{dir}/{file.as_str()}");
+                    let from = deploy_source(&merged, &loose, &key);
+                    if from.exists() {
+                        ftp.upload(&from, dir, file)?;
+                        self.progress
+                            .file_done(&key, fs::metadata(&from).map(|m| m.len()).unwrap_or(0));
+                    } else {
+                        self.progress.file_done(&key, 0);
+                        log::warn!(
+                            "Source file {} missing, we're assuming it was a deletion lost \
+                             track of",
+                            from.display()
+                        );
+                    }
+                }
             }
+            ftp.finish();
+            log::info!("Deployment complete");
         } else {
-            if is_symlink(&config.output) {
+            if is_symlink(&output) {
                 anyhow_ext::bail!(
                     "Deployment folder is currently a symlink or junction, but the current \
                      deployment method is not symlinking. Please manually remove the existing \
                      link at {} to prevent unexpected results.",
-                    config.output.display()
+                    output.display()
                 );
             }
             let (content, aoc) = uk_content::platform_prefixes(settings.current_mode.into());
@@ -246,82 +967,434 @@ impl Manager {
             log::debug!("Files to deploy\n{:#?}", &syncs);
             log::info!("Deploying by {}", match config.method {
                 DeployMethod::Copy => "copy",
+                DeployMethod::SdCard => "copy to SD card",
+                DeployMethod::Network => "copy to network share",
                 DeployMethod::HardLink => "hard links",
+                DeployMethod::Reflink => "reflinks",
                 DeployMethod::Symlink => unsafe { std::hint::unreachable_unchecked() },
+                DeployMethod::Junction | DeployMethod::Ftp => unreachable!(),
             });
 
-            let filter_xbootup = |file: &&String| -> bool {
-                !file.starts_with("Pack/Bootup_") || **file == lang.bootup_path()
-            };
-
-            for (dir, dels, syncs) in [
-                (content, &deletes.content_files, &syncs.content_files),
-                (aoc, &deletes.aoc_files, &syncs.aoc_files),
-            ] {
-                let dest = config.output.join(dir);
-                let source = settings.merged_dir().join(dir);
-                dels.par_iter()
-                    .filter(filter_xbootup)
-                    .try_for_each(|f| -> Result<()> {
-                        let file = dest.join(f.as_str());
-                        if file.exists() {
-                            fs::remove_file(file)?;
-                        }
-                        Ok(())
-                    })?;
-
-                syncs.par_iter().filter(filter_xbootup).try_for_each(
-                    |f: &String| -> Result<()> {
-                        let from = source.join(f.as_str());
-                        let out = dest.join(f.as_str());
-                        if out.exists() {
-                            fs::remove_file(&out)?;
-                        }
-                        if from.exists() {
-                            out.parent().map(fs::create_dir_all).transpose()?;
-                            match config.method {
-                                DeployMethod::Copy => fs::copy(from, &out).map(|_| ()),
-                                DeployMethod::HardLink => fs::hard_link(from, &out),
-                                DeployMethod::Symlink => unreachable!(),
-                            }
-                            .with_context(|| format!("Failed to deploy {} to {}", f, out.display()))
-                            .map_err(|e| {
-                                if e.root_cause().to_string().contains("os error 17") {
-                                    e.context(
-                                        "Hard linking failed because the output folder is on a \
-                                         different disk or partition than the storage folder.",
-                                    )
-                                } else {
-                                    e
+            let mut deployed = self.deployed.write();
+            let deployed_files = deployed.targets.entry(output.clone()).or_default();
+            let updates: DashMap<String, Option<u64>> = DashMap::new();
+            let skipped = std::sync::atomic::AtomicUsize::new(0);
+            let reflink_fallbacks = std::sync::atomic::AtomicUsize::new(0);
+            let failed: DashMap<String, std::string::String> = DashMap::new();
+            let is_network = config.method == DeployMethod::Network;
+            let workers = deploy_workers(config);
+            log::debug!("Deploying with {workers} workers");
+            let (total, bytes) = deploy_totals(&syncs, (content, aoc), filter_xbootup, |key| {
+                deploy_source(&merged, &loose, key)
+            });
+            let _run = self.progress.start(total, bytes);
+            let result = rayon::ThreadPoolBuilder::new()
+                .num_threads(workers)
+                .build()
+                .context("Failed to start deployment workers")?
+                .install(|| -> Result<()> {
+                    for (dir, dels, syncs) in [
+                        (content, &deletes.content_files, &syncs.content_files),
+                        (aoc, &deletes.aoc_files, &syncs.aoc_files),
+                    ] {
+                        let dest = output.join(dir);
+                        dels.par_iter()
+                            .filter(filter_xbootup)
+                            .try_for_each(|f| -> Result<()> {
+                                self.progress.check()?;
+                                let key: String = jstr!("{dir}/{f.as_str()}").into();
+                                let file = dest.join(f.as_str());
+                                if is_network {
+                                    if let Err(e) = network::remove_file(&file) {
+                                        failed.insert(key.clone(), format!("{e:#}"));
+                                    }
+                                } else if file.exists() {
+                                    fs::remove_file(file)?;
                                 }
+                                updates.insert(key, None);
+                                Ok(())
                             })?;
+
+                        syncs.par_iter().filter(filter_xbootup).try_for_each(
+                            |f: &String| -> Result<()> {
+                                self.progress.check()?;
+                                let key: String = jstr!("{dir}/{f.as_str()}").into();
+                                let from = deploy_source(&merged, &loose, &key);
+                                let out = dest.join(f.as_str());
+                                if !from.exists() {
+                                    log::warn!(
+                                        "Source file {} missing, we're assuming it was a \
+                                         deletion lost track of",
+                                        from.display()
+                                    );
+                                    self.progress.file_done(&key, 0);
+                                    return Ok(());
+                                }
+                                let size = fs::metadata(&from)?.len();
+                                let hash = util::hash_file(&from)?;
+                                if out.exists() && deployed_files.get(&key) == Some(&hash) {
+                                    skipped.fetch_add(1, Ordering::Relaxed);
+                                    self.progress.file_done(&key, size);
+                                    return Ok(());
+                                }
+                                if is_network {
+                                    // A failed copy is recorded instead of ending the
+                                    // deployment, and the file is forgotten so the
+                                    // next deployment writes it again.
+                                    let result = network::copy_file(&from, &out);
+                                    self.progress.file_done(&key, size);
+                                    match result {
+                                        Ok(()) => updates.insert(key, Some(hash)),
+                                        Err(e) => {
+                                            failed.insert(key.clone(), format!("{e:#}"));
+                                            updates.insert(key, None)
+                                        }
+                                    };
+                                    return Ok(());
+                                }
+                                if out.exists() {
+                                    fs::remove_file(&out)?;
+                                }
+                                out.parent().map(fs::create_dir_all).transpose()?;
+                                match config.method {
+                                    DeployMethod::Copy | DeployMethod::SdCard => {
+                                        fs::copy(from, &out).map(|_| ())
+                                    }
+                                    DeployMethod::HardLink => fs::hard_link(from, &out),
+                                    DeployMethod::Reflink => {
+                                        reflink_copy::reflink_or_copy(&from, &out).map(|copied| {
+                                            if copied.is_some() {
+                                                reflink_fallbacks.fetch_add(1, Ordering::Relaxed);
+                                            }
+                                        })
+                                    }
+                                    DeployMethod::Symlink
+                                    | DeployMethod::Junction
+                                    | DeployMethod::Ftp
+                                    | DeployMethod::Network => unreachable!(),
+                                }
+                                .with_context(|| {
+                                    format!("Failed to deploy {} to {}", f, out.display())
+                                })
+                                .map_err(|e| {
+                                    if e.root_cause().to_string().contains("os error 17") {
+                                        e.context(
+                                            "Hard linking failed because the output folder is on \
+                                             a different disk or partition than the storage \
+                                             folder.",
+                                        )
+                                    } else {
+                                        e
+                                    }
+                                })?;
+                                self.progress.file_done(&key, size);
+                                updates.insert(key, Some(hash));
+                                Ok(())
+                            },
+                        )?;
+                    }
+                    // Anything deployed before which is no longer in the merged
+                    // folder is left over from a deletion we lost track of, and
+                    // anything newly excluded has to go as well.
+                    deployed_files
+                        .par_iter()
+                        .filter(|(file, _)| {
+                            !updates.contains_key(*file)
+                                && (!deploy_source(&merged, &loose, file).exists()
+                                    || excluded_key(&excludes, file, (content, aoc)))
+                        })
+                        .try_for_each(|(file, _)| -> Result<()> {
+                            let out = output.join(file.as_str());
+                            if is_network {
+                                log::debug!("Removing stale deployed file {file}");
+                                if let Err(e) = network::remove_file(&out) {
+                                    failed.insert(file.clone(), format!("{e:#}"));
+                                }
+                            } else if out.exists() {
+                                log::debug!("Removing stale deployed file {file}");
+                                fs::remove_file(out)?;
+                            }
+                            updates.insert(file.clone(), None);
                             Ok(())
-                        } else {
-                            log::warn!(
-                                "Source file {} missing, we're assuming it was a deletion lost \
-                                 track of",
-                                from.display()
-                            );
-                            Ok(())
-                        }
-                    },
+                        })?;
+                    Ok(())
+                });
+            for (file, hash) in updates {
+                match hash {
+                    Some(hash) => deployed_files.insert(file, hash),
+                    None => deployed_files.remove(&file),
+                };
+            }
+            // Record whatever was deployed before a cancellation or error, so
+            // the next deployment can skip it.
+            if let Err(e) = result {
+                fs::write(
+                    Self::deployed_path(settings),
+                    serde_yaml::to_string(&*deployed)?,
                 )?;
+                return Err(e);
+            }
+            let skipped = skipped.into_inner();
+            if skipped > 0 {
+                log::info!("Skipped {skipped} unchanged files");
+            }
+            let reflink_fallbacks = reflink_fallbacks.into_inner();
+            if reflink_fallbacks > 0 {
+                log::warn!(
+                    "The deploy target does not support reflinks, so {reflink_fallbacks} files \
+                     were copied instead"
+                );
+            }
+            let mut failures: Vec<DeployFailure> = failed
+                .into_iter()
+                .map(|(path, error)| {
+                    DeployFailure {
+                        target: config.name.clone(),
+                        path,
+                        error,
+                    }
+                })
+                .collect();
+            failures.sort();
+            if !failures.is_empty() {
+                log::warn!(
+                    "{} files could not be written to {}, deploy again to retry them:\n{}",
+                    failures.len(),
+                    config.name,
+                    failures
+                        .iter()
+                        .map(|f| f.to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                );
+            }
+            {
+                let mut all = self.failures.write();
+                all.retain(|f| f.target != config.name);
+                all.extend(failures);
             }
+            fs::write(
+                Self::deployed_path(settings),
+                serde_yaml::to_string(&*deployed)?,
+            )?;
             log::info!("Deployment complete");
         }
+        if config.layout == DeployLayout::Title && settings.current_mode == Platform::WiiU {
+            if config.method.links_folder() || config.method == DeployMethod::Ftp {
+                log::warn!(
+                    "The title layout needs files copied to the output, so it does not work with \
+                     the {} deploy method",
+                    config.method.name()
+                );
+            } else {
+                log::info!("Completing title folder");
+                let dump = settings
+                    .dump()
+                    .context("No dump available for current platform")?;
+                title::complete_title(&output, &dump).context("Failed to complete title folder")?;
+            }
+        }
         if settings.current_mode == Platform::WiiU
-            && settings
-                .platform_config()
-                .and_then(|c| c.deploy_config.as_ref().map(|c| c.cemu_rules))
-                .unwrap_or(false)
-            && let rules_path = config.output.join("rules.txt") && !rules_path.exists()
+            && !matches!(config.method, DeployMethod::Ftp | DeployMethod::SdCard)
+            && config.layout != DeployLayout::Title
+            && config.cemu_rules
         {
-            fs::write(rules_path, include_str!("../../../assets/rules.txt"))?;
+            let rules_path = config.output.join("rules.txt");
+            let rules = cemu_rules(&config.output);
+            if fs::read_to_string(&rules_path).ok().as_deref() != Some(rules.as_str()) {
+                log::info!("Writing Cemu graphic pack rules");
+                fs::write(rules_path, rules)?;
+            }
         }
-        self.pending_delete.write().clear();
-        self.pending_files.write().clear();
-        self.save()?;
-        Ok(())
+        if !config.post_deploy.is_empty() {
+            run_hook(&config.post_deploy, &output, settings.current_mode)
+                .context("Post-deploy command failed")?;
+        }
+        Ok(true)
+    }
+
+    /// Re-hashes the files deployed to one target and compares them against
+    /// the hashes of the merged files they were deployed from. Only copy and
+    /// hard link deploys keep a record to check against.
+    fn verify_target(
+        &self,
+        settings: &Settings,
+        config: &DeployConfig,
+        lang: Language,
+    ) -> Result<Vec<DeployMismatch>> {
+        if !matches!(
+            config.method,
+            DeployMethod::Copy
+                | DeployMethod::SdCard
+                | DeployMethod::Network
+                | DeployMethod::HardLink
+                | DeployMethod::Reflink
+        ) {
+            log::debug!("Nothing to verify for {} deploys", config.method.name());
+            return Ok(vec![]);
+        }
+        let output = config.target(settings.current_mode, lang);
+        let deployed = self.deployed.read();
+        let Some(files) = deployed.targets.get(&output) else {
+            return Ok(vec![]);
+        };
+        log::info!("Verifying {} files deployed to {}", files.len(), config.name);
+        let mut bad: Vec<DeployMismatch> = files
+            .par_iter()
+            .filter(|(file, hash)| {
+                util::hash_file(&output.join(file.as_str())).ok().as_ref() != Some(*hash)
+            })
+            .map(|(file, _)| {
+                DeployMismatch {
+                    target: config.name.clone(),
+                    path:   file.clone(),
+                }
+            })
+            .collect();
+        bad.sort();
+        if bad.is_empty() {
+            log::info!("All files deployed to {} verified", config.name);
+        } else {
+            log::warn!(
+                "{} files deployed to {} do not match the merged files",
+                bad.len(),
+                config.name
+            );
+            log::debug!("{:#?}", &bad);
+        }
+        Ok(bad)
+    }
+
+    /// Verifies the files deployed to every target of the current platform.
+    pub fn verify(&self) -> Result<Vec<DeployMismatch>> {
+        let settings = self
+            .settings
+            .upgrade()
+            .expect("YIKES, the settings manager is gone");
+        let settings = settings.read();
+        let platform_config = settings
+            .platform_config()
+            .context("No deployment config for current platform")?;
+        let mut mismatches = vec![];
+        for config in platform_config.targets().iter() {
+            mismatches.extend(self.verify_target(&settings, config, platform_config.language)?);
+        }
+        *self.mismatches.write() = mismatches.clone();
+        Ok(mismatches)
+    }
+
+    /// Copies or links the merged files again for each mismatch found by the
+    /// last verification, then verifies again. Returns whatever still
+    /// doesn't match.
+    pub fn repair(&self) -> Result<Vec<DeployMismatch>> {
+        let mismatches = self.mismatches();
+        {
+            let settings = self
+                .settings
+                .upgrade()
+                .expect("YIKES, the settings manager is gone");
+            let settings = settings.read();
+            let platform_config = settings
+                .platform_config()
+                .context("No deployment config for current platform")?;
+            let merged = settings.merged_dir();
+            let loose = self.loose_dirs(&settings);
+            log::info!("Repairing {} deployed files", mismatches.len());
+            mismatches.par_iter().try_for_each(|mismatch| -> Result<()> {
+                let config = platform_config
+                    .deploy_target(&mismatch.target)
+                    .with_context(|| format!("Deploy target {} no longer exists", mismatch.target))?;
+                let from = deploy_source(&merged, &loose, &mismatch.path);
+                let out = config
+                    .target(settings.current_mode, platform_config.language)
+                    .join(mismatch.path.as_str());
+                if config.method == DeployMethod::Network {
+                    return network::copy_file(&from, &out)
+                        .with_context(|| format!("Failed to repair {}", out.display()));
+                }
+                if out.exists() {
+                    fs::remove_file(&out)?;
+                }
+                out.parent().map(fs::create_dir_all).transpose()?;
+                match config.method {
+                    DeployMethod::HardLink => fs::hard_link(&from, &out),
+                    DeployMethod::Reflink => reflink_copy::reflink_or_copy(&from, &out).map(|_| ()),
+                    _ => fs::copy(&from, &out).map(|_| ()),
+                }
+                .with_context(|| format!("Failed to repair {}", out.display()))
+            })?;
+        }
+        self.verify()
+    }
+
+    /// Unpacks each enabled loose mod into its own folder, with nothing
+    /// merged into it, if it has files in the change manifest or no manifest
+    /// is given. Returns the files of every enabled loose mod.
+    fn unpack_loose(&self, settings: &Settings, manifest: Option<&Manifest>) -> Result<Manifest> {
+        let mod_manager = self
+            .mod_manager
+            .upgrade()
+            .expect("YIKES, the mod manager system is gone");
+        let loose_dir = settings.loose_dir();
+        if manifest.is_none() && loose_dir.exists() {
+            util::remove_dir_all(&loose_dir).context("Failed to clear loose mods folder")?;
+        }
+        let dump = settings
+            .dump()
+            .context("No dump available for current platform")?;
+        let mut total = Manifest::default();
+        let mods = mod_manager.read().loose_mods().collect::<Vec<_>>();
+        for mod_ in mods {
+            let reader = mod_
+                .open()
+                .with_context(|| jstr!("Failed to open mod: {&mod_.meta.name}"))?;
+            total.extend(&reader.manifest);
+            let out_dir = loose_dir.join(mod_.hash().to_string());
+            let changed = manifest
+                .map(|manifest| {
+                    !manifest
+                        .content_files
+                        .is_disjoint(&reader.manifest.content_files)
+                        || !manifest.aoc_files.is_disjoint(&reader.manifest.aoc_files)
+                })
+                .unwrap_or(true);
+            if !changed && out_dir.exists() {
+                continue;
+            }
+            log::info!("Unpacking {} to deploy loose", mod_.meta.name);
+            if out_dir.exists() {
+                util::remove_dir_all(&out_dir)?;
+            }
+            ModUnpacker::new(
+                dump.clone(),
+                settings.current_mode.into(),
+                settings.platform_config().unwrap().language,
+                vec![reader],
+                out_dir,
+            )
+            .with_compression_level(settings.compression_level)
+            .with_language_fallbacks(settings.fallback_languages())
+            .with_rstb_config(settings.rstb_config())
+            .unpack()
+            .with_context(|| jstr!("Failed to unpack loose mod: {&mod_.meta.name}"))?;
+        }
+        Ok(total)
+    }
+
+    /// Folders of the enabled loose mods, last in load order first, so the
+    /// first one that has a file wins.
+    fn loose_dirs(&self, settings: &Settings) -> Vec<PathBuf> {
+        let Some(mod_manager) = self.mod_manager.upgrade() else {
+            return vec![];
+        };
+        let loose_dir = settings.loose_dir();
+        let mut dirs: Vec<PathBuf> = mod_manager
+            .read()
+            .loose_mods()
+            .map(|m| loose_dir.join(m.hash().to_string()))
+            .collect();
+        dirs.reverse();
+        dirs
     }
 
     fn handle_orphans(
@@ -390,7 +1463,19 @@ impl Manager {
         platform: Platform,
         updates: DashMap<String, Option<u32>>,
     ) -> Result<()> {
-        static RSTB_PATH: &str = "System/Resource/ResourceSizeTable.product.srsizetable";
+        Self::write_rstb(merged, platform, updates)?;
+        self.pending_files
+            .write()
+            .content_files
+            .insert(RSTB_PATH.into());
+        Ok(())
+    }
+
+    fn write_rstb(
+        merged: &Path,
+        platform: Platform,
+        updates: DashMap<String, Option<u32>>,
+    ) -> Result<()> {
         log::debug!("RSTB updates:\n{:#?}", &updates);
         let content = uk_content::platform_content(platform.into());
         let table_path = merged.join(content).join(RSTB_PATH);
@@ -421,10 +1506,6 @@ impl Manager {
         fs::create_dir_all(table_path.parent().unwrap())?;
         fs::write(table_path, compress(table.to_binary(platform.into())))
             .context("Failed to write merged RSTB")?;
-        self.pending_files
-            .write()
-            .content_files
-            .insert(RSTB_PATH.into());
         Ok(())
     }
 
@@ -443,26 +1524,52 @@ impl Manager {
             .context("No dump available for current platform")?;
         let endian = settings.current_mode.into();
         let out_dir = settings.merged_dir();
+        let flag_resolutions = mod_manager.read().profile().flag_resolutions().clone();
+        let economy_merge = mod_manager.read().profile().economy_merge();
+        self.deployed_to.write().clear();
+        self.backup_merged(
+            &settings,
+            &out_dir,
+            manifest.is_none() && !settings.skip_unchanged,
+        )?;
         let unpacker = if let Some(mut manifest) = manifest {
             log::info!("Manifest provided, applying limited changes");
+            let loose = self.unpack_loose(&settings, Some(&manifest))?;
             let mut total_manifest = Manifest::default();
             let mods = mod_manager
                 .read()
                 .mods_by_manifest(&manifest)
                 .map(|m| {
-                    ModReader::open(&m.path, m.enabled_options.clone())
+                    m.open()
                         .inspect(|m| total_manifest.extend(&m.manifest))
                         .with_context(|| jstr!("Failed to open mod: {&m.meta.name}"))
                 })
                 .collect::<Result<Vec<_>>>()?;
+            let mut loose_changes = manifest.clone();
+            loose_changes
+                .content_files
+                .retain(|f| loose.content_files.contains(f));
+            loose_changes
+                .aoc_files
+                .retain(|f| loose.aoc_files.contains(f));
             self.handle_orphans(
                 total_manifest,
                 &mut manifest,
                 &out_dir,
                 settings.current_mode,
             )?;
+            // Files only loose mods have are orphans as far as the merge is
+            // concerned, but they still need deploying.
+            {
+                let mut dels = self.pending_delete.write();
+                dels.content_files
+                    .retain(|f| !loose_changes.content_files.contains(f));
+                dels.aoc_files
+                    .retain(|f| !loose_changes.aoc_files.contains(f));
+            }
             log::debug!("Change manifest: {:#?}", &manifest);
             self.pending_files.write().extend(&manifest);
+            self.pending_files.write().extend(&loose_changes);
             ModUnpacker::new(
                 dump,
                 endian,
@@ -473,18 +1580,29 @@ impl Manager {
             .with_manifest(manifest)
         } else {
             log::info!("Manifest not provided, remerging all mods");
+            let loose = self.unpack_loose(&settings, None)?;
             let mut total_manifest = Manifest::default();
             let mods = mod_manager
                 .read()
-                .mods()
+                .merged_mods()
                 .map(|m| {
-                    ModReader::open(&m.path, m.enabled_options.clone())
+                    m.open()
                         .inspect(|m| total_manifest.extend(&m.manifest))
                         .with_context(|| jstr!("Failed to open mod: {&m.meta.name}"))
                 })
                 .collect::<Result<Vec<_>>>()?;
-            util::remove_dir_all(&out_dir).context("Failed to clear merged folder")?;
+            if settings.skip_unchanged {
+                prune_merged(
+                    &out_dir,
+                    &total_manifest,
+                    platform_prefixes(settings.current_mode.into()),
+                )
+                .context("Failed to clear stale merged files")?;
+            } else if out_dir.exists() {
+                util::remove_dir_all(&out_dir).context("Failed to clear merged folder")?;
+            }
             self.pending_files.write().extend(&total_manifest);
+            self.pending_files.write().extend(&loose);
             ModUnpacker::new(
                 dump,
                 endian,
@@ -494,10 +1612,52 @@ impl Manager {
             )
         };
         log::info!("Applying changes");
+        let unpacker = unpacker
+            .with_flag_resolutions(flag_resolutions)
+            .with_economy_merge(economy_merge)
+            .with_skip_missing(settings.skip_missing_vanilla)
+            .with_compression_level(settings.compression_level)
+            .with_skip_unchanged(settings.skip_unchanged)
+            .with_language_fallbacks(settings.fallback_languages())
+            .with_rstb_config(settings.rstb_config());
+        let flag_conflicts = unpacker.flag_conflicts();
+        let missing = unpacker.missing_resources();
         let rstb_updates = unpacker.unpack()?;
+        *self.flag_conflicts.write() = std::mem::take(&mut *flag_conflicts.lock());
+        let missing = std::mem::take(&mut *missing.lock());
+        let mut by_mod: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for res in &missing {
+            by_mod
+                .entry(res.mod_name.as_str())
+                .or_default()
+                .push(res.file.as_str());
+        }
+        for (mod_name, files) in by_mod {
+            log::warn!(
+                "Skipped {} files from {mod_name} missing from the game dump:\n{}",
+                files.len(),
+                files.join("\n")
+            );
+        }
+        *self.missing_resources.write() = missing;
         self.apply_rstb(&out_dir, settings.current_mode, rstb_updates)?;
         self.save()?;
         log::info!("All changed applied successfully");
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    #[test]
+    fn cemu_rules() {
+        let rules = super::cemu_rules(Path::new("/cemu/graphicPacks/BreathOfTheWild_UKMM"));
+        assert!(rules.contains("\nname = UKMM\n"));
+        assert!(rules.contains("\npath = The Legend of Zelda: Breath of the Wild/Mods/UKMM\n"));
+        assert!(rules.contains("\nversion = 7\n"));
+        let rules = super::cemu_rules(Path::new("/cemu/graphicPacks/Modded"));
+        assert!(rules.contains("\nname = Modded\n"));
+    }
+}
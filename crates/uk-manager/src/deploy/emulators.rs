@@ -0,0 +1,205 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Platform;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Emulator {
+    Cemu,
+    Yuzu,
+    Ryujinx,
+}
+
+impl std::fmt::Display for Emulator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cemu => "Cemu",
+            Self::Yuzu => "Yuzu",
+            Self::Ryujinx => "Ryujinx",
+        }
+        .fmt(f)
+    }
+}
+
+impl Emulator {
+    #[inline]
+    pub fn platform(&self) -> Platform {
+        match self {
+            Self::Cemu => Platform::WiiU,
+            Self::Yuzu | Self::Ryujinx => Platform::Switch,
+        }
+    }
+
+    /// The names of the emulator's executable, used to spot portable
+    /// installs.
+    #[inline]
+    fn exes(&self) -> &'static [&'static str] {
+        match self {
+            Self::Cemu => &["Cemu.exe", "Cemu", "cemu"],
+            Self::Yuzu => &["yuzu.exe", "yuzu"],
+            Self::Ryujinx => &["Ryujinx.exe", "Ryujinx"],
+        }
+    }
+
+    /// The folder a portable install keeps its user data in, relative to the
+    /// executable. Cemu 1.x keeps everything next to the executable.
+    #[inline]
+    fn portable_dir(&self) -> &'static str {
+        match self {
+            Self::Cemu => "portable",
+            Self::Yuzu => "user",
+            Self::Ryujinx => "portable",
+        }
+    }
+
+    /// A folder which only exists in the emulator's user data folder.
+    #[inline]
+    fn marker(&self) -> &'static str {
+        match self {
+            Self::Cemu => "graphicPacks",
+            Self::Yuzu => "nand",
+            Self::Ryujinx => "system",
+        }
+    }
+
+    /// The usual places for the emulator's user data when it is installed
+    /// normally, including Flatpak installs on Linux.
+    fn standard_dirs(&self) -> Vec<PathBuf> {
+        let (name, flatpak) = match self {
+            Self::Cemu => ("Cemu", "info.cemu.Cemu"),
+            Self::Yuzu => ("yuzu", "org.yuzu_emu.yuzu"),
+            Self::Ryujinx => ("Ryujinx", "org.ryujinx.Ryujinx"),
+        };
+        let mut dirs = [dirs2::data_dir(), dirs2::data_local_dir(), dirs2::config_dir()]
+            .into_iter()
+            .flatten()
+            .map(|dir| dir.join(name))
+            .collect::<Vec<_>>();
+        if let Some(home) = dirs2::home_dir() {
+            dirs.push(home.join(".var/app").join(flatpak).join("data").join(name));
+            dirs.push(home.join(".var/app").join(flatpak).join("config").join(name));
+            dirs.push(home.join(name));
+        }
+        #[cfg(windows)]
+        dirs.push(PathBuf::from("C:\\").join(name));
+        dirs
+    }
+
+    /// Checks a folder for a portable install, returning its user data
+    /// folder.
+    fn portable_root(&self, dir: &Path) -> Option<PathBuf> {
+        if !self.exes().iter().any(|exe| dir.join(exe).is_file()) {
+            return None;
+        }
+        let portable = dir.join(self.portable_dir());
+        if portable.is_dir() {
+            Some(portable)
+        } else if *self == Self::Cemu && dir.join(self.marker()).is_dir() {
+            Some(dir.to_path_buf())
+        } else {
+            None
+        }
+    }
+
+    /// Where UKMM should deploy to for this emulator. Yuzu and Ryujinx both
+    /// read Atmosphere's layout from their emulated SD card, which matches
+    /// the way merged Switch files are laid out.
+    pub fn deploy_output(&self, root: &Path) -> PathBuf {
+        match self {
+            Self::Cemu => root.join("graphicPacks").join("BreathOfTheWild_UKMM"),
+            Self::Yuzu => root.join("sdmc").join("atmosphere").join("contents"),
+            Self::Ryujinx => root.join("sdcard").join("atmosphere").join("contents"),
+        }
+    }
+}
+
+/// An emulator install found by [`detect_emulators`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmulatorInstall {
+    pub emulator: Emulator,
+    /// The emulator's user data folder
+    pub root: PathBuf,
+    pub portable: bool,
+}
+
+impl EmulatorInstall {
+    #[inline]
+    pub fn deploy_output(&self) -> PathBuf {
+        self.emulator.deploy_output(&self.root)
+    }
+}
+
+impl std::fmt::Display for EmulatorInstall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{} ({})",
+            self.emulator,
+            if self.portable { " (portable)" } else { "" },
+            self.root.display()
+        )
+    }
+}
+
+/// Folders which might hold a portable emulator: the folder UKMM runs from,
+/// its parent, and anything directly inside either.
+fn portable_candidates() -> Vec<PathBuf> {
+    let Some(exe_dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|p| p.to_path_buf()))
+    else {
+        return vec![];
+    };
+    let mut dirs = vec![exe_dir.clone()];
+    if let Some(parent) = exe_dir.parent() {
+        dirs.push(parent.to_path_buf());
+    }
+    let children = dirs
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(|e| e.ok().map(|e| e.path())))
+        .filter(|p| p.is_dir())
+        .collect::<Vec<_>>();
+    dirs.extend(children);
+    dirs
+}
+
+/// Scans the standard install locations for Cemu, Yuzu, and Ryujinx, plus
+/// portable installs near UKMM, and lists those for the given platform.
+pub fn detect_emulators(platform: Platform) -> Vec<EmulatorInstall> {
+    let portable = portable_candidates();
+    let mut found: Vec<EmulatorInstall> = vec![];
+    for emulator in [Emulator::Cemu, Emulator::Yuzu, Emulator::Ryujinx]
+        .into_iter()
+        .filter(|e| e.platform() == platform)
+    {
+        let installs = emulator
+            .standard_dirs()
+            .into_iter()
+            .filter(|dir| dir.join(emulator.marker()).is_dir())
+            .map(|root| {
+                EmulatorInstall {
+                    emulator,
+                    root,
+                    portable: false,
+                }
+            })
+            .chain(portable.iter().filter_map(|dir| {
+                emulator.portable_root(dir).map(|root| {
+                    EmulatorInstall {
+                        emulator,
+                        root,
+                        portable: true,
+                    }
+                })
+            }));
+        for install in installs {
+            if !found.iter().any(|f| f.root == install.root) {
+                log::debug!("Found emulator install: {install}");
+                found.push(install);
+            }
+        }
+    }
+    found
+}
@@ -0,0 +1,98 @@
+use std::{
+    io::BufReader,
+    net::{TcpStream, ToSocketAddrs},
+    path::Path,
+    time::Duration,
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use suppaftp::{types::FileType, FtpStream};
+use uk_content::util::HashSet;
+
+use crate::settings::FtpConfig;
+
+/// How long to wait for the console to answer when checking whether its FTP
+/// server is up.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Checks whether the FTP server accepts connections, without logging in, so
+/// an offline console can be treated like an unmounted SD card.
+pub fn reachable(config: &FtpConfig) -> bool {
+    (config.host.as_str(), config.port)
+        .to_socket_addrs()
+        .map(|mut addrs| addrs.any(|addr| TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).is_ok()))
+        .unwrap_or(false)
+}
+
+/// A connection to the FTP server on a console, used to push merged files
+/// without needing the SD card or MLC to be mounted locally.
+pub struct FtpDeployer {
+    stream:  FtpStream,
+    root:    std::string::String,
+    created: HashSet<std::string::String>,
+}
+
+impl FtpDeployer {
+    pub fn connect(config: &FtpConfig, root: &Path) -> Result<Self> {
+        log::info!("Connecting to FTP server at {}:{}", config.host, config.port);
+        let mut stream = FtpStream::connect(format!("{}:{}", config.host, config.port))
+            .with_context(|| format!("Failed to connect to FTP server at {}", config.host))?;
+        stream
+            .login(config.username.as_str(), config.password.as_str())
+            .context("Failed to log in to FTP server")?;
+        stream
+            .transfer_type(FileType::Binary)
+            .context("Failed to set FTP transfer mode")?;
+        let root = root.to_string_lossy().replace('\\', "/");
+        Ok(Self {
+            stream,
+            root: root.trim_end_matches('/').to_owned(),
+            created: Default::default(),
+        })
+    }
+
+    #[inline]
+    fn remote_path(&self, dir: &str, file: &str) -> std::string::String {
+        format!("{}/{dir}/{file}", self.root)
+    }
+
+    /// Creates each missing folder leading up to a remote file. Servers
+    /// differ in whether they report an error for folders that already
+    /// exist, so failures are ignored here and surface on upload instead.
+    fn create_parents(&mut self, remote: &str) {
+        let mut current = std::string::String::new();
+        let parts = remote.split('/').collect::<Vec<_>>();
+        for part in &parts[..parts.len().saturating_sub(1)] {
+            if part.is_empty() {
+                continue;
+            }
+            current.push('/');
+            current.push_str(part);
+            if self.created.insert(current.clone()) {
+                let _ = self.stream.mkdir(current.as_str());
+            }
+        }
+    }
+
+    pub fn upload(&mut self, source: &Path, dir: &str, file: &str) -> Result<()> {
+        let remote = self.remote_path(dir, file);
+        self.create_parents(&remote);
+        let mut reader = BufReader::new(fs::File::open(source)?);
+        self.stream
+            .put_file(remote.as_str(), &mut reader)
+            .with_context(|| format!("Failed to upload {file} to {remote}"))?;
+        Ok(())
+    }
+
+    pub fn delete(&mut self, dir: &str, file: &str) {
+        let remote = self.remote_path(dir, file);
+        if let Err(e) = self.stream.rm(remote.as_str()) {
+            log::debug!("Could not delete {remote} over FTP: {e}");
+        }
+    }
+
+    pub fn finish(mut self) {
+        let _ = self.stream.quit();
+    }
+}
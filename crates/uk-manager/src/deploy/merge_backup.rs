@@ -0,0 +1,147 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use path_slash::PathExt;
+use rayon::prelude::*;
+use smartstring::alias::String;
+use uk_content::platform_prefixes;
+use uk_mod::Manifest;
+
+use super::Manager;
+use crate::{settings::Settings, util};
+
+/// The backup of a merged folder sits next to it, e.g. `merged.bak`.
+fn backup_dir(merged: &Path) -> PathBuf {
+    let mut name = merged.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    merged.with_file_name(name)
+}
+
+/// Every file in a merged folder, relative to the content and DLC roots.
+fn merged_files(dir: &Path, settings: &Settings) -> Manifest {
+    let (content, aoc) = platform_prefixes(settings.current_mode.into());
+    let collect_files = |root: &str| -> BTreeSet<String> {
+        let root = dir.join(root);
+        jwalk::WalkDir::new(&root)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| {
+                entry
+                    .path()
+                    .strip_prefix(&root)
+                    .ok()
+                    .map(|rel| rel.to_slash_lossy().into())
+            })
+            .collect()
+    };
+    Manifest {
+        content_files: collect_files(content),
+        aoc_files:     collect_files(aoc),
+        ..Default::default()
+    }
+}
+
+impl Manager {
+    /// Keeps a copy of the merged folder before a merge changes it, if the
+    /// settings ask for one. When the merge is going to clear the folder
+    /// anyway (`moving`), it is just renamed, which is instant. Otherwise the
+    /// files are reflinked where the file system supports it and copied
+    /// where it doesn't.
+    pub(super) fn backup_merged(
+        &self,
+        settings: &Settings,
+        merged: &Path,
+        moving: bool,
+    ) -> Result<()> {
+        if !settings.backup_merged || !merged.exists() {
+            return Ok(());
+        }
+        let backup = backup_dir(merged);
+        if backup.exists() {
+            util::remove_dir_all(&backup).context("Failed to clear old merged backup")?;
+        }
+        if moving && fs::rename(merged, &backup).is_ok() {
+            log::info!("Moved previous merge to {}", backup.display());
+            return Ok(());
+        }
+        log::info!("Backing up previous merge to {}", backup.display());
+        jwalk::WalkDir::new(merged)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .par_bridge()
+            .try_for_each(|entry| -> Result<()> {
+                let path = entry.path();
+                let out = backup.join(path.strip_prefix(merged)?);
+                out.parent().map(fs::create_dir_all).transpose()?;
+                reflink_copy::reflink_or_copy(&path, &out)?;
+                Ok(())
+            })
+            .context("Failed to back up merged files")?;
+        Ok(())
+    }
+
+    /// Whether the current merged folder has a backup from before the last
+    /// merge.
+    pub fn can_restore_merged(&self) -> bool {
+        let settings = self
+            .settings
+            .upgrade()
+            .expect("YIKES, the settings manager is gone");
+        let merged = settings.read().merged_dir();
+        backup_dir(&merged).exists()
+    }
+
+    /// Swaps the merged folder with its backup from before the last merge,
+    /// without merging again, and queues the difference to deploy. Restoring
+    /// again swaps them back. The mod list is left alone, so the next merge
+    /// will bring back whatever the current mods produce.
+    pub fn restore_merged(&self) -> Result<()> {
+        let settings = self
+            .settings
+            .upgrade()
+            .expect("YIKES, the settings manager is gone");
+        let settings = settings.read();
+        let merged = settings.merged_dir();
+        let backup = backup_dir(&merged);
+        anyhow_ext::ensure!(backup.exists(), "No backup of the previous merge to restore");
+        let current = merged_files(&merged, &settings);
+        let restored = merged_files(&backup, &settings);
+        let swap = merged.with_extension("swap");
+        if merged.exists() {
+            fs::rename(&merged, &swap).context("Failed to move current merge aside")?;
+        }
+        fs::rename(&backup, &merged).context("Failed to restore previous merge")?;
+        if swap.exists() {
+            fs::rename(&swap, &backup).context("Failed to keep current merge as backup")?;
+        }
+        {
+            let mut pending_delete = self.pending_delete.write();
+            pending_delete
+                .content_files
+                .retain(|file| !restored.content_files.contains(file));
+            pending_delete
+                .aoc_files
+                .retain(|file| !restored.aoc_files.contains(file));
+            pending_delete.content_files.extend(
+                current
+                    .content_files
+                    .difference(&restored.content_files)
+                    .cloned(),
+            );
+            pending_delete
+                .aoc_files
+                .extend(current.aoc_files.difference(&restored.aoc_files).cloned());
+        }
+        self.pending_files.write().extend(&restored);
+        self.deployed_to.write().clear();
+        self.save()?;
+        log::info!("Restored previous merge");
+        Ok(())
+    }
+}
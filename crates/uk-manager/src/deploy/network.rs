@@ -0,0 +1,181 @@
+use std::{
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+
+/// How many times to try each file operation before giving up on it.
+const ATTEMPTS: u32 = 5;
+/// Wait before the first retry, doubled after each failed attempt.
+const BACKOFF: Duration = Duration::from_millis(250);
+/// Files at least this big are copied through a partial file which later
+/// attempts pick up from instead of starting over.
+const RESUMABLE_SIZE: u64 = 8 * 1024 * 1024;
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Windows limits ordinary paths to 260 characters, which deep game folders
+/// on a share easily pass. The extended-length prefix lifts the limit, with
+/// its own form for UNC paths.
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> PathBuf {
+    let raw = path.as_os_str().to_string_lossy();
+    if raw.starts_with(r"\\?\") || !path.is_absolute() {
+        path.to_path_buf()
+    } else if let Some(unc) = raw.strip_prefix(r"\\") {
+        PathBuf::from(format!(r"\\?\UNC\{unc}"))
+    } else {
+        PathBuf::from(format!(r"\\?\{raw}"))
+    }
+}
+
+#[cfg(not(windows))]
+#[inline(always)]
+pub fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Runs a file operation until it succeeds, backing off between attempts.
+/// Network shares drop out briefly far more often than local drives fail,
+/// so most errors clear up on their own.
+fn retry<T>(what: impl Fn() -> String, mut op: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut wait = BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < ATTEMPTS => {
+                log::debug!("{} failed (attempt {attempt}), retrying: {e}", what());
+                std::thread::sleep(wait);
+                wait *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e.context(format!("{} failed after {ATTEMPTS} attempts", what()))),
+        }
+    }
+}
+
+/// Identifies the version of a file a partial copy was made from, by its size
+/// and modified time, so a partial copy of an older version is not resumed.
+fn source_stamp(from: &Path) -> Result<Option<String>> {
+    let meta = fs::metadata(from)?;
+    Ok(meta
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|time| format!("{} {}", meta.len(), time.as_nanos())))
+}
+
+/// Appends the rest of `from` to the partial file, starting wherever an
+/// earlier attempt left off, then moves it into place. The partial file is
+/// only picked up if the source is unchanged since it was started, which is
+/// recorded alongside it.
+fn copy_resumable(from: &Path, out: &Path, size: u64) -> Result<()> {
+    let mut part = out.as_os_str().to_owned();
+    part.push(".ukmm-part");
+    let part = PathBuf::from(part);
+    let stamp_path = part.with_extension("ukmm-part-source");
+    let stamp = source_stamp(from)?;
+    let mut dest = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&part)?;
+    let mut done = dest.metadata()?.len();
+    let resumable = stamp.is_some()
+        && fs::read_to_string(&stamp_path)
+            .map(|last| Some(last) == stamp)
+            .unwrap_or(false);
+    if done > size || (done > 0 && !resumable) {
+        log::debug!(
+            "Discarding partial copy of {}, which has changed since",
+            from.display()
+        );
+        dest.set_len(0)?;
+        done = 0;
+    }
+    if done > 0 {
+        log::debug!("Resuming copy of {} at {done} bytes", from.display());
+    } else if let Some(stamp) = stamp.as_ref() {
+        fs::write(&stamp_path, stamp)?;
+    }
+    let mut source = fs::File::open(from)?;
+    source.seek(SeekFrom::Start(done))?;
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        let read = source.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        dest.write_all(&buf[..read])?;
+    }
+    dest.sync_all()?;
+    let copied = dest.metadata()?.len();
+    drop(dest);
+    if copied != size || source_stamp(from)? != stamp {
+        fs::remove_file(&part)?;
+        anyhow_ext::bail!("{} changed while it was being copied", from.display());
+    }
+    fs::rename(&part, out)?;
+    if stamp_path.exists() {
+        fs::remove_file(&stamp_path)?;
+    }
+    Ok(())
+}
+
+/// Copies a file to a network share, retrying failures and resuming large
+/// files where the last attempt stopped.
+pub fn copy_file(from: &Path, out: &Path) -> Result<()> {
+    let out = long_path(out);
+    let size = fs::metadata(from)?.len();
+    retry(
+        || format!("Copying to {}", out.display()),
+        || {
+            out.parent().map(fs::create_dir_all).transpose()?;
+            if size >= RESUMABLE_SIZE {
+                copy_resumable(from, &out, size)
+            } else {
+                fs::copy(from, &out)
+                    .map(|_| ())
+                    .context("Failed to copy file")
+            }
+        },
+    )
+}
+
+/// Removes a file from a network share, retrying failures.
+pub fn remove_file(path: &Path) -> Result<()> {
+    let path = long_path(path);
+    retry(
+        || format!("Removing {}", path.display()),
+        || {
+            if path.exists() {
+                fs::remove_file(&path)?;
+            }
+            Ok(())
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use fs_err as fs;
+
+    #[test]
+    fn discard_stale_part() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let from = dir.join("Link.Tex1.sbfres");
+        let out = dir.join("out.sbfres");
+        let part = dir.join("out.sbfres.ukmm-part");
+        let data = (0..0x1000u32).map(|i| (i % 255) as u8).collect::<Vec<_>>();
+        fs::write(&from, &data).unwrap();
+        // Left over from copying a different version of the file
+        fs::write(&part, vec![0xff; 0x800]).unwrap();
+        fs::write(part.with_extension("ukmm-part-source"), "4096 0").unwrap();
+        super::copy_resumable(&from, &out, data.len() as u64).unwrap();
+        assert_eq!(fs::read(&out).unwrap(), data);
+        assert!(!part.exists());
+    }
+}
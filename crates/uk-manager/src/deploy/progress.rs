@@ -0,0 +1,112 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+use anyhow_ext::Result;
+use parking_lot::Mutex;
+use smartstring::alias::String;
+
+/// Live progress of a running deployment, shared with whatever displays it.
+/// Also carries the cancellation flag, which the deployment checks before
+/// each file, so cancelling never leaves a file half written.
+#[derive(Debug, Default)]
+pub struct DeployProgress {
+    active: AtomicBool,
+    cancelled: AtomicBool,
+    files_done: AtomicUsize,
+    files_total: AtomicUsize,
+    bytes_done: AtomicU64,
+    bytes_total: AtomicU64,
+    current: Mutex<String>,
+}
+
+/// A copy of the progress of a deployment at one moment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeployStatus {
+    pub active: bool,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    /// The file most recently deployed
+    pub current: String,
+}
+
+impl DeployStatus {
+    /// How much of the deployment is done, by bytes if they are known and
+    /// otherwise by files.
+    pub fn fraction(&self) -> f32 {
+        if self.bytes_total > 0 {
+            self.bytes_done as f32 / self.bytes_total as f32
+        } else if self.files_total > 0 {
+            self.files_done as f32 / self.files_total as f32
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Marks the deployment finished when dropped, however it ends.
+pub(super) struct ProgressRun<'a>(&'a DeployProgress);
+
+impl Drop for ProgressRun<'_> {
+    fn drop(&mut self) {
+        self.0.active.store(false, Ordering::Relaxed);
+    }
+}
+
+impl DeployProgress {
+    pub fn status(&self) -> DeployStatus {
+        DeployStatus {
+            active: self.active.load(Ordering::Relaxed),
+            files_done: self.files_done.load(Ordering::Relaxed),
+            files_total: self.files_total.load(Ordering::Relaxed),
+            bytes_done: self.bytes_done.load(Ordering::Relaxed),
+            bytes_total: self.bytes_total.load(Ordering::Relaxed),
+            current: self.current.lock().clone(),
+        }
+    }
+
+    /// Asks the running deployment to stop after the files it is writing
+    /// now. Whatever was already deployed is recorded, and the rest stays
+    /// pending.
+    pub fn cancel(&self) {
+        if self.active.load(Ordering::Relaxed) {
+            log::info!("Cancelling deployment");
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether the last deployment was cancelled.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Clears the cancellation flag before a new deployment.
+    pub(super) fn reset(&self) {
+        self.cancelled.store(false, Ordering::Relaxed);
+    }
+
+    pub(super) fn start(&self, files: usize, bytes: u64) -> ProgressRun<'_> {
+        self.files_done.store(0, Ordering::Relaxed);
+        self.files_total.store(files, Ordering::Relaxed);
+        self.bytes_done.store(0, Ordering::Relaxed);
+        self.bytes_total.store(bytes, Ordering::Relaxed);
+        self.current.lock().clear();
+        self.active.store(true, Ordering::Relaxed);
+        ProgressRun(self)
+    }
+
+    pub(super) fn file_done(&self, file: &str, bytes: u64) {
+        self.files_done.fetch_add(1, Ordering::Relaxed);
+        self.bytes_done.fetch_add(bytes, Ordering::Relaxed);
+        *self.current.lock() = file.into();
+    }
+
+    /// Fails if the deployment has been cancelled.
+    pub(super) fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            anyhow_ext::bail!("Deployment cancelled");
+        }
+        Ok(())
+    }
+}
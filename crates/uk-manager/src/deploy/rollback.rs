@@ -0,0 +1,185 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use rayon::prelude::*;
+use sanitise_file_name as sfn;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use uk_content::{constants::Language, platform_prefixes};
+
+use super::{ChangeKind, Manager};
+use crate::{
+    settings::{DeployConfig, Settings},
+    util,
+};
+
+/// What the last deployment to a target changed, so it can be undone. Files
+/// it replaced or removed are kept in a backup folder next to this log.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RollbackLog {
+    /// Files which existed before the deployment, saved in the backup
+    replaced: BTreeSet<String>,
+    /// Files the deployment created
+    added:    BTreeSet<String>,
+    /// Deployed file hashes from before the deployment
+    deployed: BTreeMap<String, u64>,
+}
+
+impl Manager {
+    #[inline]
+    fn rollback_dir(settings: &Settings, config: &DeployConfig) -> PathBuf {
+        settings
+            .platform_dir()
+            .join("rollback")
+            .join(sfn::sanitise(&config.name))
+    }
+
+    /// Backs up every file the next deployment to a target will replace or
+    /// remove, and records the files it will add. If the deployment changes
+    /// nothing, the backup of the deployment before it is kept.
+    pub(super) fn backup_target(
+        &self,
+        settings: &Settings,
+        config: &DeployConfig,
+        lang: Language,
+    ) -> Result<()> {
+        let changes = self.changes(settings, config, lang)?;
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let output = config.target(settings.current_mode, lang);
+        let dir = Self::rollback_dir(settings, config);
+        if dir.exists() {
+            util::remove_dir_all(&dir).context("Failed to clear old deployment backup")?;
+        }
+        let backup = dir.join("files");
+        let mut log = RollbackLog {
+            deployed: self
+                .deployed
+                .read()
+                .targets
+                .get(&output)
+                .cloned()
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        for change in changes {
+            match change.kind {
+                ChangeKind::Add => log.added.insert(change.path),
+                ChangeKind::Update | ChangeKind::Remove => log.replaced.insert(change.path),
+            };
+        }
+        log::info!("Backing up {} deployed files", log.replaced.len());
+        log.replaced
+            .par_iter()
+            .try_for_each(|file| -> Result<()> {
+                let out = backup.join(file.as_str());
+                out.parent().map(fs::create_dir_all).transpose()?;
+                fs::copy(output.join(file.as_str()), out)?;
+                Ok(())
+            })
+            .context("Failed to back up deployed files")?;
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("rollback.yml"), serde_yaml::to_string(&log)?)?;
+        Ok(())
+    }
+
+    /// Whether the named target has a previous deployment to restore.
+    pub fn can_rollback(&self, target: &str) -> bool {
+        let settings = self
+            .settings
+            .upgrade()
+            .expect("YIKES, the settings manager is gone");
+        let settings = settings.read();
+        settings
+            .platform_config()
+            .and_then(|c| c.deploy_target(target))
+            .map(|config| {
+                Self::rollback_dir(&settings, &config)
+                    .join("rollback.yml")
+                    .exists()
+            })
+            .unwrap_or(false)
+    }
+
+    /// Puts a deploy target (or the first one) back the way it was before its
+    /// last deployment, without merging again. The files involved are queued
+    /// to deploy again, so the next deployment brings the target back up to
+    /// date. Returns how many files were restored or removed.
+    pub fn rollback(&self, target: Option<&str>) -> Result<usize> {
+        let settings = self
+            .settings
+            .upgrade()
+            .expect("YIKES, the settings manager is gone");
+        let settings = settings.read();
+        let platform_config = settings
+            .platform_config()
+            .context("No deployment config for current platform")?;
+        let config = match target {
+            Some(name) => {
+                platform_config
+                    .deploy_target(name)
+                    .with_context(|| format!("No deploy target named {name}"))?
+            }
+            None => {
+                platform_config
+                    .deploy_config()
+                    .context("No deployment config for current platform")?
+            }
+        };
+        let dir = Self::rollback_dir(&settings, &config);
+        let log: RollbackLog = serde_yaml::from_str(
+            &fs::read_to_string(dir.join("rollback.yml"))
+                .with_context(|| format!("No previous deployment to {} to restore", config.name))?,
+        )?;
+        let output = config.target(settings.current_mode, platform_config.language);
+        log::info!("Restoring previous deployment to {}", config.name);
+        log.added.par_iter().try_for_each(|file| -> Result<()> {
+            let out = output.join(file.as_str());
+            if out.exists() {
+                fs::remove_file(out)?;
+            }
+            Ok(())
+        })?;
+        let backup = dir.join("files");
+        log.replaced.par_iter().try_for_each(|file| -> Result<()> {
+            let out = output.join(file.as_str());
+            if out.exists() {
+                fs::remove_file(&out)?;
+            }
+            out.parent().map(fs::create_dir_all).transpose()?;
+            fs::copy(backup.join(file.as_str()), out)?;
+            Ok(())
+        })?;
+        {
+            let mut deployed = self.deployed.write();
+            deployed.targets.insert(output, log.deployed);
+            fs::write(
+                Self::deployed_path(&settings),
+                serde_yaml::to_string(&*deployed)?,
+            )?;
+        }
+        let (content, aoc) = platform_prefixes(settings.current_mode.into());
+        {
+            let mut pending = self.pending_files.write();
+            for file in log.added.iter().chain(log.replaced.iter()) {
+                if let Some(file) = file.strip_prefix(content).and_then(|f| f.strip_prefix('/')) {
+                    pending.content_files.insert(file.into());
+                } else if let Some(file) = file.strip_prefix(aoc).and_then(|f| f.strip_prefix('/'))
+                {
+                    pending.aoc_files.insert(file.into());
+                }
+            }
+        }
+        self.deployed_to.write().remove(&config.name);
+        util::remove_dir_all(&dir).context("Failed to clear deployment backup")?;
+        self.save()?;
+        let total = log.added.len() + log.replaced.len();
+        log::info!("Restored {total} files to the previous deployment");
+        Ok(total)
+    }
+}
@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+
+use anyhow_ext::{bail, Result};
+use fs_err as fs;
+use uk_content::constants::Language;
+
+use crate::settings::Platform;
+
+/// BotW title IDs for the US, EU, and JP Wii U releases, used by SDCafiine
+/// to pick which mod folders to load.
+static WIIU_TITLE_IDS: &[&str] = &["00050000101C9400", "00050000101C9500", "00050000101C9300"];
+
+/// The Wii U title ID for the region a game language belongs to.
+pub fn wiiu_title_id(lang: Language) -> &'static str {
+    match &lang.to_str()[..2] {
+        "EU" => WIIU_TITLE_IDS[1],
+        "JP" => WIIU_TITLE_IDS[2],
+        _ => WIIU_TITLE_IDS[0],
+    }
+}
+
+/// Folders which mark the root of an SD card as being set up for modding.
+#[inline]
+fn marker(platform: Platform) -> &'static str {
+    match platform {
+        Platform::Switch => "atmosphere",
+        Platform::WiiU => "sdcafiine",
+    }
+}
+
+/// Lists the places removable drives are usually mounted. There is no
+/// portable way to ask whether a drive is removable, so this leaves that to
+/// [`detect_targets`], which only keeps drives already set up for modding.
+fn mount_points() -> Vec<PathBuf> {
+    #[cfg(windows)]
+    {
+        ('D'..='Z')
+            .map(|letter| PathBuf::from(format!("{letter}:\\")))
+            .filter(|p| p.exists())
+            .collect()
+    }
+    #[cfg(unix)]
+    {
+        let user = std::env::var("USER").unwrap_or_default();
+        [
+            PathBuf::from("/media").join(&user),
+            PathBuf::from("/run/media").join(&user),
+            PathBuf::from("/media"),
+            PathBuf::from("/Volumes"),
+        ]
+        .into_iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(|e| e.ok().map(|e| e.path())))
+        .filter(|p| p.is_dir())
+        .collect()
+    }
+}
+
+/// Finds SD cards set up for modding on the given platform and returns the
+/// deploy output folder to use on each.
+pub fn detect_targets(platform: Platform) -> Vec<PathBuf> {
+    mount_points()
+        .into_iter()
+        .filter(|root| root.join(marker(platform)).is_dir())
+        .map(|root| {
+            match platform {
+                Platform::Switch => root.join("atmosphere").join("contents"),
+                Platform::WiiU => {
+                    let sdcafiine = root.join("sdcafiine");
+                    WIIU_TITLE_IDS
+                        .iter()
+                        .map(|id| sdcafiine.join(id))
+                        .find(|p| p.exists())
+                        .unwrap_or_else(|| sdcafiine.join(WIIU_TITLE_IDS[0]))
+                        .join("UKMM")
+                }
+            }
+        })
+        .collect()
+}
+
+/// Checks that a deploy output folder sits where the console's mod loader
+/// will look for it, so a wrong path doesn't fill some other drive with
+/// files.
+pub fn validate_target(output: &Path, platform: Platform) -> Result<()> {
+    let names = output
+        .components()
+        .rev()
+        .filter_map(|c| c.as_os_str().to_str())
+        .map(|c| c.to_lowercase())
+        .collect::<Vec<_>>();
+    let valid = match platform {
+        Platform::Switch => names.len() >= 2 && names[0] == "contents" && names[1] == "atmosphere",
+        Platform::WiiU => {
+            names.len() >= 3
+                && names[2] == "sdcafiine"
+                && WIIU_TITLE_IDS.iter().any(|id| id.eq_ignore_ascii_case(&names[1]))
+        }
+    };
+    if !valid {
+        bail!(
+            "{} does not look like a mod folder on an SD card. For Switch it should be \
+             atmosphere/contents, and for Wii U sdcafiine/<title ID>/<mod name>.",
+            output.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::settings::Platform;
+
+    #[test]
+    fn validate_target() {
+        assert!(
+            super::validate_target(Path::new("/media/sd/atmosphere/contents"), Platform::Switch)
+                .is_ok()
+        );
+        assert!(super::validate_target(
+            Path::new("/media/sd/sdcafiine/00050000101C9400/UKMM"),
+            Platform::WiiU
+        )
+        .is_ok());
+        assert!(super::validate_target(Path::new("/media/sd/mods"), Platform::Switch).is_err());
+        assert!(
+            super::validate_target(Path::new("/media/sd/sdcafiine/UKMM"), Platform::WiiU).is_err()
+        );
+    }
+}
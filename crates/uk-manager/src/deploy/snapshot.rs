@@ -0,0 +1,196 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use join_str::jstr;
+use path_slash::PathExt;
+use rayon::prelude::*;
+use smartstring::alias::String;
+use uk_mod::unpack::ModUnpacker;
+
+use super::Manager;
+use crate::util;
+
+/// How a file differs between two snapshot builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SnapshotDiffKind {
+    OnlyA,
+    OnlyB,
+    Changed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SnapshotDiff {
+    pub path: String,
+    pub kind: SnapshotDiffKind,
+}
+
+/// The result of merging two profiles side by side, listing every merged
+/// file which is not identical between them.
+#[derive(Debug, Clone)]
+pub struct SnapshotReport {
+    pub a:     String,
+    pub b:     String,
+    pub dir_a: PathBuf,
+    pub dir_b: PathBuf,
+    pub diffs: Vec<SnapshotDiff>,
+}
+
+impl std::fmt::Display for SnapshotReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "A: {} ({})", self.a, self.dir_a.display())?;
+        writeln!(f, "B: {} ({})", self.b, self.dir_b.display())?;
+        writeln!(f, "{} files differ", self.diffs.len())?;
+        for diff in &self.diffs {
+            let kind = match diff.kind {
+                SnapshotDiffKind::OnlyA => "Only in A",
+                SnapshotDiffKind::OnlyB => "Only in B",
+                SnapshotDiffKind::Changed => "Changed",
+            };
+            writeln!(f, "{kind}: {}", diff.path)?;
+        }
+        Ok(())
+    }
+}
+
+fn collect_files(root: &Path) -> BTreeSet<String> {
+    jwalk::WalkDir::new(root)
+        .into_iter()
+        .filter_map(|file| {
+            file.ok().and_then(|file| {
+                file.file_type().is_file().then(|| {
+                    file.path()
+                        .strip_prefix(root)
+                        .unwrap()
+                        .to_slash_lossy()
+                        .into()
+                })
+            })
+        })
+        .collect()
+}
+
+fn compare_dirs(a: &Path, b: &Path) -> Result<Vec<SnapshotDiff>> {
+    let files_a = collect_files(a);
+    let files_b = collect_files(b);
+    let mut diffs: Vec<SnapshotDiff> = files_a
+        .par_iter()
+        .filter(|file| files_b.contains(*file))
+        .map(|file| -> Result<Option<SnapshotDiff>> {
+            let changed = util::hash_file(&a.join(file.as_str()))?
+                != util::hash_file(&b.join(file.as_str()))?;
+            Ok(changed.then(|| {
+                SnapshotDiff {
+                    path: file.clone(),
+                    kind: SnapshotDiffKind::Changed,
+                }
+            }))
+        })
+        .filter_map(Result::transpose)
+        .collect::<Result<_>>()?;
+    diffs.extend(files_a.difference(&files_b).map(|file| {
+        SnapshotDiff {
+            path: file.clone(),
+            kind: SnapshotDiffKind::OnlyA,
+        }
+    }));
+    diffs.extend(files_b.difference(&files_a).map(|file| {
+        SnapshotDiff {
+            path: file.clone(),
+            kind: SnapshotDiffKind::OnlyB,
+        }
+    }));
+    diffs.sort_by(|x, y| x.path.cmp(&y.path).then(x.kind.cmp(&y.kind)));
+    Ok(diffs)
+}
+
+impl Manager {
+    /// Merges the enabled mods of a profile into its own folder under the
+    /// snapshots directory, leaving the real merged folder and pending
+    /// deployment untouched. Returns the snapshot folder.
+    pub fn build_snapshot(&self, profile: &str) -> Result<PathBuf> {
+        let mod_manager = self
+            .mod_manager
+            .upgrade()
+            .expect("YIKES, the mod manager system is gone");
+        let settings = self
+            .settings
+            .upgrade()
+            .expect("YIKES, the settings manager is gone");
+        let settings = settings.read();
+        let dump = settings
+            .dump()
+            .context("No dump available for current platform")?;
+        let out_dir = settings.snapshots_dir().join(profile);
+        log::info!("Building snapshot of profile {profile}");
+        let (mods, flag_resolutions, economy_merge) = {
+            let mod_manager = mod_manager.read();
+            let mods = mod_manager
+                .profile_mods(profile)?
+                .into_iter()
+                .map(|m| {
+                    m.open()
+                        .with_context(|| jstr!("Failed to open mod: {&m.meta.name}"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let name: String = profile.into();
+            let profile = mod_manager.get_profile(Some(&name));
+            (mods, profile.flag_resolutions().clone(), profile.economy_merge())
+        };
+        if out_dir.exists() {
+            util::remove_dir_all(&out_dir).context("Failed to clear snapshot folder")?;
+        }
+        fs::create_dir_all(&out_dir)?;
+        let rstb_updates = ModUnpacker::new(
+            dump,
+            settings.current_mode.into(),
+            settings.platform_config().unwrap().language,
+            mods,
+            out_dir.clone(),
+        )
+        .with_flag_resolutions(flag_resolutions)
+        .with_economy_merge(economy_merge)
+        .with_skip_missing(settings.skip_missing_vanilla)
+        .with_compression_level(settings.compression_level)
+        .with_language_fallbacks(settings.fallback_languages())
+        .with_rstb_config(settings.rstb_config())
+        .unpack()?;
+        Self::write_rstb(&out_dir, settings.current_mode, rstb_updates)?;
+        log::info!("Snapshot of profile {profile} built");
+        Ok(out_dir)
+    }
+
+    /// Builds snapshots of two profiles, treated as load order variants A
+    /// and B, and reports which merged files differ between them. The report
+    /// is also saved next to the snapshots.
+    pub fn compare_profiles(&self, a: &str, b: &str) -> Result<SnapshotReport> {
+        if a == b {
+            anyhow_ext::bail!("Cannot compare profile {a} against itself");
+        }
+        let dir_a = self.build_snapshot(a)?;
+        let dir_b = self.build_snapshot(b)?;
+        log::info!("Comparing snapshots of {a} and {b}");
+        let report = SnapshotReport {
+            a:     a.into(),
+            b:     b.into(),
+            diffs: compare_dirs(&dir_a, &dir_b)?,
+            dir_a,
+            dir_b,
+        };
+        let report_path = report
+            .dir_a
+            .parent()
+            .unwrap()
+            .join(jstr!("{a}-vs-{b}.txt"));
+        fs::write(&report_path, report.to_string())?;
+        log::info!(
+            "{} files differ, report saved to {}",
+            report.diffs.len(),
+            report_path.display()
+        );
+        Ok(report)
+    }
+}
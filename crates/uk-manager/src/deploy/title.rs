@@ -0,0 +1,67 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow_ext::{bail, Context, Result};
+use fs_err as fs;
+use rayon::prelude::*;
+use uk_reader::ResourceReader;
+
+/// Copies every file under `source` which does not exist yet under `dest`.
+/// Returns how many files were copied.
+fn copy_missing(source: &Path, dest: &Path) -> Result<usize> {
+    if !source.exists() {
+        bail!("{} is missing from the game dump", source.display());
+    }
+    let files: Vec<PathBuf> = jwalk::WalkDir::new(source)
+        .into_iter()
+        .filter_map(|file| {
+            file.ok()
+                .and_then(|file| file.file_type().is_file().then(|| file.path()))
+        })
+        .collect();
+    let copied = AtomicUsize::new(0);
+    files.par_iter().try_for_each(|file| -> Result<()> {
+        let out = dest.join(file.strip_prefix(source).unwrap());
+        if !out.exists() {
+            out.parent().map(fs::create_dir_all).transpose()?;
+            fs::copy(file, &out)?;
+            copied.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(())
+    })?;
+    Ok(copied.into_inner())
+}
+
+/// Fills out a Wii U deploy target into a complete title, with the
+/// `code`/`content`/`meta` folders Loadiine and WUP installers expect. The
+/// `code` and `meta` folders come from the update in the game dump, and every
+/// game file the merged mods don't replace is copied from the dump as well.
+/// Only missing files are written, so this is cheap after the first time,
+/// and it puts back the vanilla copy of any file a mod stops changing.
+pub fn complete_title(output: &Path, dump: &ResourceReader) -> Result<()> {
+    let dirs = dump.source().content_dirs();
+    let Some(update) = dirs.last() else {
+        bail!(
+            "The title layout needs a game dump unpacked to folders, which it can copy the \
+             code and meta folders from"
+        );
+    };
+    let title = update
+        .parent()
+        .context("Game dump update folder has no parent folder")?;
+    let mut copied = 0;
+    for folder in ["code", "meta"] {
+        copied += copy_missing(&title.join(folder), &output.join(folder))?;
+    }
+    // The update overrides the base game, so it gets first pick of the
+    // missing files.
+    for dir in dirs.iter().rev() {
+        copied += copy_missing(dir, &output.join("content"))?;
+    }
+    if copied > 0 {
+        log::info!("Copied {copied} files from the game dump to complete the title");
+    }
+    Ok(())
+}
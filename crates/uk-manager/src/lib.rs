@@ -6,9 +6,14 @@
     once_cell
 )]
 
+pub mod backup;
+pub mod bench;
 pub mod bnp;
+pub mod compat;
 pub mod core;
 pub mod deploy;
 pub mod mods;
 pub mod settings;
+pub mod updates;
 pub mod util;
+pub mod watch;
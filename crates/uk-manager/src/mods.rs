@@ -10,18 +10,38 @@ use anyhow_ext::{Context, Result};
 use dashmap::{mapref::one::MappedRef, DashMap};
 use fs_err as fs;
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use rayon::prelude::*;
 use sanitise_file_name as sfn;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
 use smartstring::alias::String;
-use uk_content::platform_prefixes;
-use uk_mod::{pack::ModPacker, unpack::ModReader, Manifest, Meta, ModOption};
+use uk_content::{
+    actor::params::general::EconomyMergePolicy, data::gamedata::FlagResolution, platform_prefixes,
+    prelude::Endian,
+};
+use uk_mod::{
+    integrity::Verified,
+    pack::ModPacker,
+    unpack::{FlagResolutions, ModReader},
+    Manifest, Meta, ModOption, ModOptionGroup, ModPlatform,
+};
 
 use crate::{
+    backup::{self, Backup},
     settings::Settings,
     util::{self, extract_7z, HashMap},
 };
 
+/// The file name a mod is kept under in the storage folder, without extension.
+fn storage_name(name: &str) -> std::string::String {
+    let opts: sfn::Options<Option<char>> = sfn::Options {
+        url_safe: true,
+        collapse_replacements: true,
+        ..Default::default()
+    };
+    sfn::sanitise_with_options(name, &opts)
+}
+
 type ManifestCache = LazyLock<RwLock<HashMap<(usize, Vec<PathBuf>), Result<Arc<Manifest>>>>>;
 
 #[serde_as]
@@ -31,6 +51,18 @@ pub struct Mod {
     pub enabled_options: Vec<ModOption>,
     pub enabled: bool,
     pub path: PathBuf,
+    /// Deploy this mod's files as they are on top of the merged files instead
+    /// of merging them, so it can be toggled without remerging
+    #[serde(default)]
+    pub loose: bool,
+    /// The public key the package was signed with, if it was signed and the
+    /// signature checked out when it was installed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    /// Selectable files the user chose not to install, as listed in the
+    /// mod's manifest
+    #[serde(default, skip_serializing_if = "Manifest::is_empty")]
+    pub excluded_files: Manifest,
     #[serde_as(as = "DisplayFromStr")]
     pub(crate) hash: usize,
 }
@@ -42,6 +74,9 @@ impl std::fmt::Debug for Mod {
             .field("enabled_options", &self.enabled_options)
             .field("enabled", &self.enabled)
             .field("path", &self.path)
+            .field("loose", &self.loose)
+            .field("signer", &self.signer)
+            .field("excluded_files", &self.excluded_files)
             .field("hash", &self.hash)
             .finish()
     }
@@ -69,11 +104,44 @@ impl Mod {
             enabled_options: vec![],
             path: reader.path,
             enabled: false,
+            loose: false,
+            signer: None,
+            excluded_files: Default::default(),
         }
     }
 
+    /// Opens the mod for merging, with its enabled options and without the
+    /// files the user chose to leave out.
+    pub fn open(&self) -> Result<ModReader> {
+        Ok(ModReader::open(&self.path, self.enabled_options.clone())?
+            .with_excluded(&self.excluded_files))
+    }
+
+    /// Opens the mod like [`Mod::open`], but maps the package instead of
+    /// reading it, to look at a few files quickly.
+    pub fn peek(&self) -> Result<ModReader> {
+        Ok(
+            ModReader::open_peek(&self.path, self.enabled_options.clone())?
+                .with_excluded(&self.excluded_files),
+        )
+    }
+
     pub fn manifest(&self) -> Result<Arc<Manifest>> {
-        self.manifest_with_options(&self.enabled_options)
+        let manifest = self.manifest_with_options(&self.enabled_options)?;
+        if self.excluded_files.is_empty() {
+            Ok(manifest)
+        } else {
+            let mut manifest = manifest.as_ref().clone();
+            manifest.remove(&self.excluded_files);
+            Ok(Arc::new(manifest))
+        }
+    }
+
+    /// The files of the mod, with its enabled options, which the user may
+    /// choose not to install.
+    pub fn selectable_files(&self) -> Result<Manifest> {
+        self.meta
+            .selectable_files(&*self.manifest_with_options(&self.enabled_options)?)
     }
 
     pub fn manifest_with_options(&self, options: impl AsRef<[ModOption]>) -> Result<Arc<Manifest>> {
@@ -92,8 +160,23 @@ impl Mod {
         }
     }
 
+    /// This mod's options with the given paths, skipping any it doesn't
+    /// have.
+    pub fn options_at(&self, paths: &[PathBuf]) -> Vec<ModOption> {
+        self.meta
+            .options
+            .iter()
+            .flat_map(|group| group.options().iter())
+            .filter(|opt| paths.contains(&opt.path))
+            .cloned()
+            .collect()
+    }
+
     pub fn state_eq(&self, other: &Self) -> bool {
-        self.enabled == other.enabled && self.enabled_options == other.enabled_options
+        self.enabled == other.enabled
+            && self.enabled_options == other.enabled_options
+            && self.loose == other.loose
+            && self.excluded_files == other.excluded_files
     }
 
     #[inline(always)]
@@ -152,6 +235,14 @@ impl LookupMod for usize {
 pub struct Profile {
     mods: RwLock<HashMap<usize, Mod>>,
     load_order: RwLock<Vec<usize>>,
+    #[serde(default)]
+    flag_resolutions: RwLock<FlagResolutions>,
+    #[serde(default)]
+    economy_merge: RwLock<EconomyMergePolicy>,
+    /// The options last chosen for each mod, by mod name, so the same ones
+    /// are chosen again if the mod is reinstalled or updated
+    #[serde(default)]
+    option_choices: RwLock<HashMap<String, Vec<PathBuf>>>,
 }
 
 impl Clone for Profile {
@@ -159,6 +250,9 @@ impl Clone for Profile {
         Self {
             mods: RwLock::new(self.mods.read().clone()),
             load_order: RwLock::new(self.load_order.read().clone()),
+            flag_resolutions: RwLock::new(self.flag_resolutions.read().clone()),
+            economy_merge: RwLock::new(*self.economy_merge.read()),
+            option_choices: RwLock::new(self.option_choices.read().clone()),
         }
     }
 }
@@ -181,6 +275,59 @@ impl Profile {
         self.load_order.write()
     }
 
+    pub fn flag_resolutions(&self) -> RwLockReadGuard<FlagResolutions> {
+        self.flag_resolutions.read()
+    }
+
+    /// How prices changed by more than one mod in this profile are merged.
+    pub fn economy_merge(&self) -> EconomyMergePolicy {
+        *self.economy_merge.read()
+    }
+
+    /// The options last chosen for a mod with the same name, as far as this
+    /// version of it still has them.
+    pub fn remembered_options(&self, mod_: &Mod) -> Option<Vec<ModOption>> {
+        let choices = self.option_choices.read();
+        let paths = choices.get(&mod_.meta.name)?;
+        let options = mod_.options_at(paths);
+        (!options.is_empty() || paths.is_empty()).then_some(options)
+    }
+
+    /// Checks that every mod a mod depends on is installed and enabled in
+    /// this profile, at a version in the required range. The error lists
+    /// every dependency which is not.
+    pub fn check_dependencies(&self, meta: &Meta) -> Result<()> {
+        let mods = self.mods.read();
+        let mut problems = vec![];
+        for dep in &meta.dependencies {
+            match mods.values().find(|mod_| mod_.meta.name == dep.name) {
+                None => problems.push(format!("{dep} is not installed")),
+                Some(mod_) if !dep.matches(&mod_.meta.version)? => {
+                    problems.push(format!(
+                        "{dep} is required, but version {} is installed",
+                        mod_.meta.version
+                    ))
+                }
+                Some(mod_) if !mod_.enabled => {
+                    problems.push(format!("{dep} is installed but disabled"))
+                }
+                Some(_) => (),
+            }
+        }
+        if !problems.is_empty() {
+            anyhow_ext::bail!(
+                "{} is missing dependencies:\n{}",
+                meta.name,
+                problems
+                    .iter()
+                    .map(|p| format!("• {p}"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+        Ok(())
+    }
+
     pub fn iter<'a>(self: MappedRef<'a, String, Profile, Profile>) -> ModIterator<'a> {
         ModIterator {
             profile: self,
@@ -258,6 +405,99 @@ impl Manager {
         Ok(())
     }
 
+    /// Names of every profile for the current platform, sorted.
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.profiles.iter().map(|p| p.key().clone()).collect();
+        names.sort();
+        names
+    }
+
+    /// Checks that a new profile name can be used as a folder name and isn't
+    /// taken yet.
+    fn check_new_profile(&self, name: &str) -> Result<()> {
+        if name.trim().is_empty() || sfn::sanitise(name) != name {
+            anyhow_ext::bail!("{name} is not a valid profile name");
+        }
+        if self.profiles.contains_key(name) || self.dir.join(name).exists() {
+            anyhow_ext::bail!("A profile named {name} already exists");
+        }
+        Ok(())
+    }
+
+    /// Copies a profile under a new name, with its mod list, load order, and
+    /// merged files, so the copy can be used right away.
+    pub fn clone_profile(&self, profile: &str, name: &str) -> Result<()> {
+        self.check_new_profile(name)?;
+        let data = self
+            .profiles
+            .get(profile)
+            .with_context(|| format!("Profile {profile} does not exist"))?
+            .clone();
+        dircpy::copy_dir(self.dir.join(profile), self.dir.join(name))
+            .with_context(|| format!("Failed to copy profile {profile}"))?;
+        self.profiles.insert(name.into(), data);
+        log::info!("Copied profile {profile} to {name}");
+        Ok(())
+    }
+
+    /// Renames a profile, following it if it is the current one.
+    pub fn rename_profile(&mut self, profile: &str, name: &str) -> Result<()> {
+        self.check_new_profile(name)?;
+        let (_, data) = self
+            .profiles
+            .remove(profile)
+            .with_context(|| format!("Profile {profile} does not exist"))?;
+        if let Err(e) = fs::rename(self.dir.join(profile), self.dir.join(name)) {
+            self.profiles.insert(profile.into(), data);
+            return Err(e.into());
+        }
+        self.profiles.insert(name.into(), data);
+        if self.current_profile == profile {
+            self.current_profile = name.into();
+        }
+        log::info!("Renamed profile {profile} to {name}");
+        Ok(())
+    }
+
+    /// Backups of a profile's mod list and load order taken before each
+    /// save, newest first.
+    pub fn profile_backups(&self, profile: &str) -> Result<Vec<Backup>> {
+        backup::list(
+            &self.dir.join(profile).join("profile.yml"),
+            &self.dir.join(profile).join("backups"),
+        )
+    }
+
+    /// Replaces a profile's mod list and load order with a backup.
+    pub fn restore_profile(&self, profile: &str, backup: &Backup) -> Result<()> {
+        let data: Profile = serde_yaml::from_str(&fs::read_to_string(&backup.path)?)
+            .context("Backup is not a valid profile")?;
+        backup::restore(
+            backup,
+            &self.dir.join(profile).join("profile.yml"),
+            &self.dir.join(profile).join("backups"),
+        )?;
+        self.profiles.insert(profile.into(), data);
+        Ok(())
+    }
+
+    /// Deletes a profile along with its merged files. The current profile
+    /// can't be deleted.
+    pub fn delete_profile(&self, profile: &str) -> Result<()> {
+        if self.current_profile == profile {
+            anyhow_ext::bail!(
+                "{profile} is the current profile. Switch to another profile before deleting it."
+            );
+        }
+        if self.profiles.remove(profile).is_none() {
+            anyhow_ext::bail!("Profile {profile} does not exist");
+        }
+        util::remove_dir_all(self.dir.join(profile))
+            .with_context(|| format!("Failed to delete profile {profile}"))?;
+        log::info!("Deleted profile {profile}");
+        Ok(())
+    }
+
     pub fn init(settings: &Arc<RwLock<Settings>>) -> Result<Self> {
         log::info!("Initializing mod manager");
         let current_profile = settings
@@ -289,11 +529,18 @@ impl Manager {
         Ok(self_)
     }
 
+    /// Writes a profile's data, backing up what was there first.
+    fn write_profile(&self, profile: &str, data: &Profile) -> Result<()> {
+        let path = self.dir.join(profile).join("profile.yml");
+        if let Err(e) = backup::backup(&path, &self.dir.join(profile).join("backups")) {
+            log::warn!("Failed to back up profile {profile}: {e}");
+        }
+        fs::write(path, serde_yaml::to_string(data)?)?;
+        Ok(())
+    }
+
     pub fn save(&self) -> Result<()> {
-        fs::write(
-            self.path().join("profile.yml"),
-            serde_yaml::to_string(self.profile().deref())?,
-        )?;
+        self.write_profile(&self.current_profile, self.profile().deref())?;
         log::info!("Saved profile data");
         log::debug!("{:#?}", &self.profile());
         Ok(())
@@ -309,12 +556,33 @@ impl Manager {
         self.all_mods().filter(|m| m.enabled)
     }
 
+    /// Iterate enabled mods which are merged, skipping any deployed loose,
+    /// in load order.
+    pub fn merged_mods(&self) -> impl Iterator<Item = Mod> + '_ {
+        self.mods().filter(|m| !m.loose)
+    }
+
+    /// Iterate enabled mods which are deployed loose in load order.
+    pub fn loose_mods(&self) -> impl Iterator<Item = Mod> + '_ {
+        self.mods().filter(|m| m.loose)
+    }
+
+    /// All enabled mods of the named profile in load order.
+    pub fn profile_mods(&self, profile: &str) -> Result<Vec<Mod>> {
+        let profile = self
+            .profiles
+            .get(profile)
+            .with_context(|| format!("Profile {profile} does not exist"))?
+            .map(|f| f);
+        Ok(profile.iter().filter(|m| m.enabled).collect())
+    }
+
     /// Iterate all mods which modify any files in the given manifest.
     pub fn mods_by_manifest<'a: 'm, 'm>(
         &'a self,
         ref_manifest: &'m Manifest,
     ) -> impl Iterator<Item = Mod> + 'm {
-        self.mods().filter(|mod_| {
+        self.merged_mods().filter(|mod_| {
             match mod_.manifest() {
                 Ok(manifest) => {
                     !ref_manifest
@@ -327,12 +595,70 @@ impl Manager {
         })
     }
 
+    /// Fails if the settings lock the mod setup.
+    fn ensure_unlocked(&self) -> Result<()> {
+        if self
+            .settings
+            .upgrade()
+            .map(|settings| settings.read().locked)
+            .unwrap_or(false)
+        {
+            anyhow_ext::bail!(
+                "Mods are locked. Turn off Lock Mods in the settings to change the mod setup."
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks that the game dump for the current platform is recent enough
+    /// for a mod and has the DLC if the mod needs it, so a mod which can't
+    /// work doesn't fail later on some missing file while merging.
+    fn check_game(&self, meta: &Meta) -> Result<()> {
+        let Some(dump) = self
+            .settings
+            .upgrade()
+            .and_then(|settings| settings.read().dump())
+        else {
+            return Ok(());
+        };
+        let missing = meta.missing_requirements(dump.game_version().as_deref(), dump.has_dlc());
+        if !missing.is_empty() {
+            anyhow_ext::bail!(
+                "{} needs:\n{}",
+                meta.name,
+                missing
+                    .iter()
+                    .map(|m| format!("• {m}"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether a stored mod belongs to the shared storage.
+    fn is_shared(&self, path: &Path) -> bool {
+        self.settings
+            .upgrade()
+            .and_then(|settings| settings.read().shared_mods_dir())
+            .map(|dir| path.starts_with(dir))
+            .unwrap_or(false)
+    }
+
     /// Add a mod to the list of installed mods. This function assumes that the
     /// mod at the provided path has already been validated.
     #[allow(irrefutable_let_patterns)]
     pub fn add(&self, mod_path: &Path, profile: Option<&String>) -> Result<Mod> {
-        let mod_name = {
+        self.ensure_unlocked()?;
+        let (mod_name, dual) = {
             let peeker = ModReader::open_peek(mod_path, vec![])?;
+            if let Some(delta) = peeker.meta.delta.as_ref() {
+                anyhow_ext::bail!(
+                    "This is an update to version {} of {}, not a full mod",
+                    delta.base_version,
+                    peeker.meta.name
+                );
+            }
             if self
                 .get_profile(profile)
                 .iter()
@@ -340,26 +666,39 @@ impl Manager {
             {
                 anyhow_ext::bail!("Mod \"{}\" already installed", peeker.meta.name);
             }
-            peeker.meta.name
+            self.get_profile(profile).check_dependencies(&peeker.meta)?;
+            self.check_game(&peeker.meta)?;
+            (peeker.meta.name, peeker.meta.platform == ModPlatform::Dual)
         };
-        let san_opts: sfn::Options<Option<char>> = sfn::Options {
-            url_safe: true,
-            collapse_replacements: true,
-            ..Default::default()
+        let verified = uk_mod::integrity::verify(mod_path)
+            .with_context(|| format!("Failed to verify mod {mod_name}"))?;
+        log_verified(&mod_name, &verified);
+        let file_name = storage_name(&mod_name) + ".zip";
+        let (stored_path, shared_path, endian) = {
+            let settings = self.settings.upgrade().unwrap();
+            let settings = settings.read();
+            (
+                settings.mods_dir().join(&file_name),
+                settings.shared_mods_dir().map(|dir| dir.join(&file_name)),
+                Endian::from(settings.current_mode),
+            )
+        };
+        let stored_path = match shared_path {
+            Some(shared_path) if shared_path.exists() => {
+                log::info!("Using {mod_name} from shared storage");
+                shared_path
+            }
+            _ => stored_path,
         };
-        let sanitized = sfn::sanitise_with_options(&mod_name, &san_opts);
-        let stored_path = self
-            .settings
-            .upgrade()
-            .unwrap()
-            .read()
-            .mods_dir()
-            .join(sanitized + ".zip");
         if stored_path.exists() {
             log::debug!("Mod already stored, no need to store it");
         } else {
             stored_path.parent().map(fs::create_dir_all).transpose()?;
-            if mod_path.is_file() {
+            if dual {
+                log::info!("Keeping only the {:?} files of dual-platform mod", endian);
+                uk_mod::dual::extract(mod_path, endian, &stored_path)
+                    .context("Failed to extract files for current platform")?;
+            } else if mod_path.is_file() {
                 fs::copy(mod_path, &stored_path).context("Failed to copy mod to storage folder")?;
             } else {
                 dircpy::copy_dir(mod_path, &stored_path)
@@ -369,7 +708,18 @@ impl Manager {
         let reader = ModReader::open_peek(&stored_path, vec![])?;
         let mut mod_ = Mod::from_reader(reader);
         mod_.enabled = true;
+        // The package stored for a dual-platform mod is made here, so its
+        // signature can only be checked on install
+        if let Verified::Signed(key) = verified
+            && !dual
+        {
+            mod_.signer = Some(key.into());
+        }
         let profile_data = self.get_profile(profile);
+        if let Some(options) = profile_data.remembered_options(&mod_) {
+            log::info!("Choosing the same options for {} as last time", mod_.meta.name);
+            mod_.enabled_options = options;
+        }
         profile_data.load_order_mut().push(mod_.hash);
         profile_data.mods_mut().insert(mod_.hash, mod_.clone());
         log::info!(
@@ -381,17 +731,222 @@ impl Manager {
         Ok(mod_)
     }
 
+    /// Updates an installed mod from a delta package, which only has the files
+    /// changed since the installed version. The new version takes the old
+    /// one's place in the load order and keeps its state and, as far as it
+    /// still has them, its options. Returns the new version and the files of
+    /// both versions, which all need remerging.
+    pub fn apply_delta(
+        &self,
+        delta_path: &Path,
+        profile: Option<&String>,
+    ) -> Result<(Mod, Manifest)> {
+        self.ensure_unlocked()?;
+        let meta = ModReader::open_peek(delta_path, vec![])?.meta;
+        let delta = meta.delta.as_ref().context("Mod is not an update package")?;
+        let profile_name = profile.unwrap_or(&self.current_profile);
+        let profile_data = self.get_profile(profile);
+        let old = profile_data
+            .mods()
+            .values()
+            .find(|m| m.meta.name == meta.name)
+            .cloned()
+            .with_context(|| format!("{} must be installed to apply this update", meta.name))?;
+        anyhow_ext::ensure!(
+            old.meta.version == delta.base_version,
+            "This update is for version {} of {}, but version {} is installed",
+            delta.base_version,
+            meta.name,
+            old.meta.version
+        );
+        anyhow_ext::ensure!(
+            old.path.is_file(),
+            "{} is stored unpacked, so it cannot be updated this way",
+            meta.name
+        );
+        profile_data.check_dependencies(&meta)?;
+        self.check_game(&meta)?;
+        let mut manifest = old.manifest()?.as_ref().clone();
+        // Replace the old package only if nothing else is using it
+        let in_use = self.is_shared(&old.path)
+            || self
+                .profiles
+                .iter()
+                .any(|p| p.key() != profile_name && p.value().mods().contains_key(&old.hash));
+        let mods_dir = self
+            .settings
+            .upgrade()
+            .expect("YIKES, the settings manager is gone")
+            .read()
+            .mods_dir();
+        let stored_path = if in_use {
+            mods_dir.join(storage_name(&format!("{} {}", meta.name, meta.version)) + ".zip")
+        } else {
+            old.path.clone()
+        };
+        let tmp_path = stored_path.with_extension("tmp");
+        fs::create_dir_all(&mods_dir)?;
+        uk_mod::delta::apply(&old.path, delta_path, &tmp_path)
+            .with_context(|| format!("Failed to apply update to {}", meta.name))?;
+        let verified = match uk_mod::integrity::verify(&tmp_path) {
+            Ok(verified) => verified,
+            Err(e) => {
+                fs::remove_file(&tmp_path)?;
+                return Err(e).with_context(|| format!("Failed to verify update to {}", meta.name));
+            }
+        };
+        log_verified(&meta.name, &verified);
+        let signer = match verified {
+            Verified::Signed(key) => Some(String::from(key)),
+            _ => None,
+        };
+        if old.signer.is_some() && signer != old.signer {
+            fs::remove_file(&tmp_path)?;
+            anyhow_ext::bail!(
+                "The installed version of {} is signed, but this update is not signed with the \
+                 same key",
+                meta.name
+            );
+        }
+        fs::rename(&tmp_path, &stored_path)?;
+        let mut mod_ = Mod::from_reader(ModReader::open_peek(&stored_path, vec![])?);
+        mod_.enabled = old.enabled;
+        mod_.loose = old.loose;
+        mod_.signer = signer;
+        let option_paths = old
+            .enabled_options
+            .iter()
+            .map(|opt| opt.path.clone())
+            .collect::<Vec<_>>();
+        mod_.enabled_options = mod_.options_at(&option_paths);
+        mod_.excluded_files = old.excluded_files.clone();
+        manifest.extend(mod_.manifest()?.as_ref());
+        profile_data.mods_mut().remove(&old.hash);
+        profile_data.mods_mut().insert(mod_.hash, mod_.clone());
+        profile_data
+            .load_order_mut()
+            .iter_mut()
+            .filter(|hash| **hash == old.hash)
+            .for_each(|hash| *hash = mod_.hash);
+        log::info!(
+            "Updated mod {} from version {} to {} in profile {}",
+            mod_.meta.name,
+            old.meta.version,
+            mod_.meta.version,
+            profile_name.as_str()
+        );
+        Ok((mod_, manifest))
+    }
+
+    /// Replaces the meta of an installed mod, like its name, description, or
+    /// version, in its stored package. The mod keeps its place in the load
+    /// order and its settings. Signed packages lose their signature, which
+    /// only covers the original meta.
+    pub fn edit_meta(
+        &self,
+        mod_: impl LookupMod,
+        meta: Meta,
+        profile: Option<&String>,
+    ) -> Result<Mod> {
+        self.ensure_unlocked()?;
+        let hash = mod_.as_hash_id();
+        let profile_name = profile.unwrap_or(&self.current_profile);
+        let profile_data = self.get_profile(profile);
+        let old = profile_data
+            .mods()
+            .get(&hash)
+            .cloned()
+            .with_context(|| format!("Mod with ID {hash} does not exist"))?;
+        anyhow_ext::ensure!(
+            !meta.name.trim().is_empty() && !meta.version.trim().is_empty(),
+            "A mod needs a name and a version"
+        );
+        anyhow_ext::ensure!(
+            meta.name == old.meta.name
+                || !profile_data
+                    .mods()
+                    .values()
+                    .any(|m| m.meta.name == meta.name),
+            "A mod named {} is already installed",
+            meta.name
+        );
+        let stored_path = if old.path.is_dir() {
+            fs::write(old.path.join("meta.yml"), serde_yaml::to_string(&meta)?)?;
+            old.path.clone()
+        } else {
+            // Edit a copy if anything else is using the package
+            let in_use = self.is_shared(&old.path)
+                || self
+                    .profiles
+                    .iter()
+                    .any(|p| p.key() != profile_name && p.value().mods().contains_key(&hash));
+            let stored_path = if in_use {
+                let mods_dir = self
+                    .settings
+                    .upgrade()
+                    .expect("YIKES, the settings manager is gone")
+                    .read()
+                    .mods_dir();
+                mods_dir.join(storage_name(&format!("{} {}", meta.name, meta.version)) + ".zip")
+            } else {
+                old.path.clone()
+            };
+            let tmp_path = stored_path.with_extension("tmp");
+            uk_mod::edit::replace_meta(&old.path, &meta, &tmp_path)
+                .with_context(|| format!("Failed to edit the meta of {}", old.meta.name))?;
+            fs::rename(&tmp_path, &stored_path)?;
+            stored_path
+        };
+        let mut mod_ = Mod::from_reader(ModReader::open_peek(&stored_path, vec![])?);
+        mod_.enabled = old.enabled;
+        mod_.loose = old.loose;
+        mod_.enabled_options = old.enabled_options.clone();
+        mod_.excluded_files = old.excluded_files.clone();
+        if old.signer.is_some() {
+            log::warn!(
+                "{} is no longer signed after editing its meta",
+                mod_.meta.name
+            );
+        }
+        if mod_.meta.name != old.meta.name {
+            let mut choices = profile_data.option_choices.write();
+            if let Some(paths) = choices.remove(&old.meta.name) {
+                choices.insert(mod_.meta.name.clone(), paths);
+            }
+            let mut resolutions = profile_data.flag_resolutions.write();
+            if let Some(flags) = resolutions.remove(&old.meta.name) {
+                resolutions.insert(mod_.meta.name.clone(), flags);
+            }
+        }
+        profile_data.mods_mut().remove(&old.hash);
+        profile_data.mods_mut().insert(mod_.hash, mod_.clone());
+        profile_data
+            .load_order_mut()
+            .iter_mut()
+            .filter(|hash| **hash == old.hash)
+            .for_each(|hash| *hash = mod_.hash);
+        log::info!(
+            "Edited meta of mod {} in profile {}",
+            mod_.meta.name,
+            profile_name.as_str()
+        );
+        Ok(mod_)
+    }
+
     pub fn del(&self, mod_: impl LookupMod, profile: Option<&String>) -> Result<Arc<Manifest>> {
+        self.ensure_unlocked()?;
         let hash = mod_.as_hash_id();
         let profile_data = self.get_profile(profile);
         let mod_ = profile_data.mods_mut().remove(&hash);
         if let Some(mod_) = mod_ {
             let manifest = mod_.manifest()?;
-            // Only delete the mod file if no other profiles are using it
+            // Only delete the mod file if no other profiles are using it, and
+            // never from shared storage, where other users' profiles may be
             if !self
                 .profiles
                 .iter()
                 .any(|p| p.value().mods().contains_key(&hash))
+                && !self.is_shared(&mod_.path)
             {
                 if mod_.path.is_dir() {
                     util::remove_dir_all(&mod_.path)?;
@@ -418,9 +973,19 @@ impl Manager {
         enabled: bool,
         profile: Option<&String>,
     ) -> Result<Arc<Manifest>> {
+        self.ensure_unlocked()?;
         let hash = mod_.as_hash_id();
         let manifest;
         let profile_data = self.get_profile(profile);
+        let enabling = profile_data
+            .mods()
+            .get(&hash)
+            .filter(|mod_| enabled && !mod_.enabled)
+            .map(|mod_| mod_.meta.clone());
+        if let Some(meta) = enabling {
+            profile_data.check_dependencies(&meta)?;
+            self.check_game(&meta)?;
+        }
         if let Some(mod_) = profile_data.mods_mut().get_mut(&hash) {
             mod_.enabled = enabled;
             manifest = mod_.manifest()?;
@@ -437,15 +1002,45 @@ impl Manager {
         Ok(manifest)
     }
 
+    pub fn set_loose(&self, mod_: impl LookupMod, loose: bool) -> Result<Arc<Manifest>> {
+        self.ensure_unlocked()?;
+        let hash = mod_.as_hash_id();
+        let manifest;
+        if let Some(mod_) = self.profile().mods_mut().get_mut(&hash) {
+            mod_.loose = loose;
+            manifest = mod_.manifest()?;
+            log::info!(
+                "Mod {} now {}",
+                mod_.meta.name,
+                if loose {
+                    "deploys loose"
+                } else {
+                    "merges"
+                }
+            );
+        } else {
+            log::warn!("Mod with ID {} does not exist, doing nothing", hash);
+            return Ok(Default::default());
+        }
+        Ok(manifest)
+    }
+
     pub fn set_enabled_options(
         &self,
         mod_: impl LookupMod,
         options: Vec<ModOption>,
+        profile: Option<&String>,
     ) -> Result<Arc<Manifest>> {
+        self.ensure_unlocked()?;
         let hash = mod_.as_hash_id();
         let manifest;
-        if let Some(mod_) = self.profile().mods_mut().get_mut(&hash) {
+        let profile = self.get_profile(profile);
+        if let Some(mod_) = profile.mods_mut().get_mut(&hash) {
             manifest = mod_.manifest_with_options(&options)?;
+            profile.option_choices.write().insert(
+                mod_.meta.name.clone(),
+                options.iter().map(|opt| opt.path.clone()).collect(),
+            );
             mod_.enabled_options = options;
         } else {
             log::warn!("Mod with ID {} does not exist, doing nothing", hash);
@@ -454,8 +1049,80 @@ impl Manager {
         Ok(manifest)
     }
 
-    pub fn set_order(&self, order: Vec<usize>) {
-        *self.profile().load_order_mut() = order;
+    /// Sets which of a mod's selectable files to leave out. Returns the files
+    /// which were left out before or are now, which need remerging.
+    pub fn set_excluded_files(
+        &self,
+        mod_: impl LookupMod,
+        excluded: Manifest,
+        profile: Option<&String>,
+    ) -> Result<Manifest> {
+        self.ensure_unlocked()?;
+        let hash = mod_.as_hash_id();
+        let profile = self.get_profile(profile);
+        let mut mods = profile.mods_mut();
+        let Some(mod_) = mods.get_mut(&hash) else {
+            log::warn!("Mod with ID {} does not exist, doing nothing", hash);
+            return Ok(Default::default());
+        };
+        let mut changed = excluded.clone();
+        changed.extend(&mod_.excluded_files);
+        log::info!(
+            "Leaving out {} files of mod {}",
+            excluded.content_files.len() + excluded.aoc_files.len(),
+            mod_.meta.name
+        );
+        mod_.excluded_files = excluded;
+        Ok(changed)
+    }
+
+    pub fn set_order(&self, order: Vec<usize>) -> Result<()> {
+        let profile = self.profile();
+        if *profile.load_order() != order {
+            self.ensure_unlocked()?;
+            *profile.load_order_mut() = order;
+        }
+        Ok(())
+    }
+
+    /// Sets how to resolve a gamedata flag conflict for a mod in the current
+    /// profile, or clears it with `None`.
+    pub fn set_flag_resolution(
+        &self,
+        mod_name: &str,
+        flag: &str,
+        resolution: Option<FlagResolution>,
+    ) {
+        let profile = self.profile();
+        let mut resolutions = profile.flag_resolutions.write();
+        match resolution {
+            Some(resolution) => {
+                resolutions
+                    .entry(mod_name.into())
+                    .or_default()
+                    .insert(flag.into(), resolution);
+            }
+            None => {
+                if let Some(flags) = resolutions.get_mut(mod_name) {
+                    flags.remove(flag);
+                    if flags.is_empty() {
+                        resolutions.remove(mod_name);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sets how prices are merged for the named profile and saves it.
+    pub fn set_economy_merge(&self, profile: &str, policy: EconomyMergePolicy) -> Result<()> {
+        let data = self
+            .profiles
+            .get(profile)
+            .with_context(|| format!("Profile {profile} does not exist"))?;
+        *data.economy_merge.write() = policy;
+        self.write_profile(profile, data.deref())?;
+        log::info!("Set price merging for profile {profile} to {}", policy.name());
+        Ok(())
     }
 
     pub fn get_mod(&self, hash: usize) -> Option<Mod> {
@@ -463,40 +1130,368 @@ impl Manager {
     }
 }
 
+/// What kind of leftover an [`Orphan`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrphanKind {
+    /// A stored mod which no profile uses
+    Mod,
+    /// Merged output for a profile with no enabled mods
+    Merged,
+    /// BNPs extracted for conversions that never finished
+    BnpCache,
+}
+
+impl std::fmt::Display for OrphanKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mod => "Unused mod",
+            Self::Merged => "Stale merged files",
+            Self::BnpCache => "BNP extraction cache",
+        }
+        .fmt(f)
+    }
+}
+
+/// Something in the storage folder which nothing refers to anymore, usually
+/// left behind by a crash or an interrupted install.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Orphan {
+    pub path: PathBuf,
+    pub kind: OrphanKind,
+    /// Size on disk in bytes
+    pub size: u64,
+}
+
+impl std::fmt::Display for Orphan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} ({:.1} MB)",
+            self.kind,
+            self.path.display(),
+            self.size as f64 / 1024.0 / 1024.0
+        )
+    }
+}
+
+fn disk_size(path: &Path) -> u64 {
+    if path.is_file() {
+        fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+    } else {
+        jwalk::WalkDir::new(path)
+            .into_iter()
+            .filter_map(|e| e.ok()?.metadata().ok())
+            .filter(|m| m.is_file())
+            .map(|m| m.len())
+            .sum()
+    }
+}
+
+impl Manager {
+    fn is_orphan(&self, path: &Path, kind: OrphanKind) -> bool {
+        match kind {
+            OrphanKind::Mod => {
+                !self
+                    .profiles
+                    .iter()
+                    .any(|p| p.value().mods().values().any(|m| m.path == path))
+            }
+            OrphanKind::Merged => {
+                path.parent()
+                    .and_then(|p| p.file_name())
+                    .and_then(|n| n.to_str())
+                    .map(|profile| {
+                        profile != self.current_profile.as_str()
+                            && self
+                                .profiles
+                                .get(profile)
+                                .map(|p| !p.mods().values().any(|m| m.enabled))
+                                .unwrap_or(true)
+                    })
+                    .unwrap_or(false)
+            }
+            OrphanKind::BnpCache => true,
+        }
+    }
+
+    /// Checks the packages of the current profile's mods against the files
+    /// they were packaged with, and against the key they were signed with when
+    /// installed. Returns each mod which no longer matches, with the reason.
+    /// Mods without integrity data can't be checked and are skipped.
+    pub fn check_integrity(&self) -> Vec<(Mod, std::string::String)> {
+        let mods = self.mods().collect::<Vec<_>>();
+        mods.into_par_iter()
+            .filter_map(|mod_| {
+                if !mod_.path.exists() {
+                    log::warn!("The package for {} is missing", mod_.meta.name);
+                    return Some((mod_, "The mod package is missing".into()));
+                }
+                let problem = match uk_mod::integrity::verify(&mod_.path) {
+                    Err(e) => format!("{e:?}"),
+                    Ok(Verified::Signed(key)) if mod_.signer.as_deref() == Some(key.as_str()) => {
+                        return None;
+                    }
+                    Ok(_) if mod_.signer.is_some() => {
+                        "The mod package is no longer signed with the key it was installed with"
+                            .into()
+                    }
+                    Ok(_) => return None,
+                };
+                log::warn!("{} failed its integrity check: {problem}", mod_.meta.name);
+                Some((mod_, problem))
+            })
+            .collect()
+    }
+
+    /// Cross-references the stored mods and merged folders with every
+    /// profile for the current platform, and lists whatever is unused. The
+    /// current profile's merged folder is never included, since it may be
+    /// what is deployed.
+    pub fn find_orphans(&self) -> Result<Vec<Orphan>> {
+        let mods_dir = self
+            .settings
+            .upgrade()
+            .expect("YIKES, the settings manager is gone")
+            .read()
+            .mods_dir();
+        let mut candidates = vec![];
+        if mods_dir.exists() {
+            candidates.extend(
+                fs::read_dir(&mods_dir)?
+                    .filter_map(|e| e.ok().map(|e| (e.path(), OrphanKind::Mod))),
+            );
+        }
+        if self.dir.exists() {
+            candidates.extend(fs::read_dir(&self.dir)?.filter_map(|e| {
+                let merged = e.ok()?.path().join("merged");
+                merged.exists().then_some((merged, OrphanKind::Merged))
+            }));
+        }
+        let bnp_cache = crate::bnp::bnp_cache_dir();
+        if bnp_cache.exists() {
+            candidates.push((bnp_cache, OrphanKind::BnpCache));
+        }
+        Ok(candidates
+            .into_iter()
+            .filter(|(path, kind)| self.is_orphan(path, *kind))
+            .map(|(path, kind)| {
+                Orphan {
+                    size: disk_size(&path),
+                    path,
+                    kind,
+                }
+            })
+            .collect())
+    }
+
+    /// Deletes orphans found by [`Self::find_orphans`]. Each one is checked
+    /// again first in case something started using it in the meantime.
+    /// Returns how many bytes were reclaimed.
+    pub fn remove_orphans(&self, orphans: &[Orphan]) -> Result<u64> {
+        let mut reclaimed = 0;
+        for orphan in orphans {
+            if !orphan.path.exists() || !self.is_orphan(&orphan.path, orphan.kind) {
+                log::warn!("{} is no longer orphaned, skipping", orphan.path.display());
+                continue;
+            }
+            log::info!("Removing {orphan}");
+            if orphan.path.is_dir() {
+                util::remove_dir_all(&orphan.path)?;
+            } else {
+                fs::remove_file(&orphan.path)?;
+            }
+            reclaimed += orphan.size;
+        }
+        Ok(reclaimed)
+    }
+}
+
+fn log_verified(name: &str, verified: &Verified) {
+    match verified {
+        Verified::Unchecked => log::debug!("{name} has no integrity data to check"),
+        Verified::Intact => log::info!("Verified files of {name}"),
+        Verified::Signed(key) => {
+            log::info!(
+                "Verified files and signature of {name}, signed by {}",
+                uk_mod::integrity::fingerprint(key)
+            )
+        }
+    }
+}
+
+/// Joins a mod package split into parts, given any of them, into a temp
+/// file which can be installed like any other.
+pub fn join_split(path: &Path) -> Result<PathBuf> {
+    let name = path.with_extension("");
+    let out = util::get_temp_folder().join(name.file_name().unwrap_or_default());
+    uk_mod::split::join(path, &out).context("Failed to join split mod")?;
+    Ok(out)
+}
+
+/// Converts a mod package made for the other platform into a temp file for
+/// the platform with byte order `endian`, which can be installed like any
+/// other. Also returns the files which could not be converted.
+pub fn convert_platform(
+    path: &Path,
+    endian: Endian,
+) -> Result<(PathBuf, Vec<std::string::String>)> {
+    let out = util::get_temp_folder().join(path.file_name().unwrap_or_default());
+    let (_, unconverted) =
+        uk_mod::convert::convert(path, endian, &out).context("Failed to convert mod")?;
+    Ok((out, unconverted))
+}
+
+/// Folder names which only hold or lead to game files, so say nothing about
+/// which mod they belong to.
+const GAME_FOLDERS: &[&str] = &[
+    "content",
+    "aoc",
+    "0010",
+    "romfs",
+    "atmosphere",
+    "contents",
+    "titles",
+    "01007ef00011e000",
+    "01007ef00011f001",
+    "breathofthewild",
+];
+
+/// Where the game files of a mod with no UKMM metadata are, which may be
+/// nested some way into the archive or folder it came in.
+#[derive(Debug)]
+struct LooseFiles {
+    content: Option<PathBuf>,
+    aoc:     Option<PathBuf>,
+    endian:  Endian,
+}
+
+impl LooseFiles {
+    /// Finds the shallowest base game and DLC folders in either platform's
+    /// layout: `content` and `aoc/0010` for Wii U, and `romfs` for Switch,
+    /// which is DLC under the DLC title ID and base game otherwise, including
+    /// when it is on its own.
+    fn find(dir: &Path) -> Option<Self> {
+        let mut found: [Option<(usize, PathBuf, Endian)>; 2] = [None, None];
+        let lower = |path: &Path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.to_lowercase())
+                .unwrap_or_default()
+        };
+        for entry in jwalk::WalkDir::new(dir)
+            .sort(true)
+            .into_iter()
+            .filter_map(std::result::Result::ok)
+            .filter(|e| e.file_type().is_dir())
+        {
+            let path = entry.path();
+            let parent = path.parent().map(lower).unwrap_or_default();
+            let (slot, endian) = match lower(&path).as_str() {
+                "content" => (0, Endian::Big),
+                "0010" if parent == "aoc" => (1, Endian::Big),
+                "romfs" if parent == "01007ef00011f001" => (1, Endian::Little),
+                "romfs" => (0, Endian::Little),
+                _ => continue,
+            };
+            if found[slot]
+                .as_ref()
+                .map(|(depth, ..)| entry.depth < *depth)
+                .unwrap_or(true)
+            {
+                found[slot] = Some((entry.depth, path, endian));
+            }
+        }
+        let [content, aoc] = found;
+        let endian = content.as_ref().or(aoc.as_ref())?.2;
+        Some(Self {
+            content: content.filter(|c| c.2 == endian).map(|c| c.1),
+            aoc: aoc.filter(|a| a.2 == endian).map(|a| a.1),
+            endian,
+        })
+    }
+
+    /// A folder with the files in the layout the mod packer expects. When
+    /// they already are, that is just the folder they share. Otherwise they
+    /// are moved, if `movable`, or copied into a new temp folder.
+    fn into_source(self, movable: bool) -> Result<PathBuf> {
+        let (content_prefix, aoc_prefix) = platform_prefixes(self.endian);
+        let root_of = |path: &Option<PathBuf>, prefix: &str| {
+            path.as_ref().map(|path| {
+                path.ends_with(prefix)
+                    .then(|| path.ancestors().nth(Path::new(prefix).components().count()))
+                    .flatten()
+                    .map(Path::to_path_buf)
+            })
+        };
+        match (
+            root_of(&self.content, content_prefix),
+            root_of(&self.aoc, aoc_prefix),
+        ) {
+            (Some(Some(root)), None) | (None, Some(Some(root))) => return Ok(root),
+            (Some(Some(root)), Some(Some(aoc_root))) if root == aoc_root => return Ok(root),
+            _ => (),
+        }
+        let staging = util::get_temp_folder().to_path_buf();
+        for (files, prefix) in [(self.content, content_prefix), (self.aoc, aoc_prefix)] {
+            let Some(files) = files else { continue };
+            let dest = staging.join(prefix);
+            dest.parent().map(fs::create_dir_all).transpose()?;
+            if !(movable && fs::rename(&files, &dest).is_ok()) {
+                dircpy::copy_dir(&files, &dest)
+                    .with_context(|| format!("Failed to copy {}", files.display()))?;
+            }
+        }
+        Ok(staging)
+    }
+}
+
+/// Makes up basic metadata for a mod which has none, named after the file or
+/// folder it came in.
+pub fn loose_mod_meta(path: &Path, endian: Endian) -> Meta {
+    let name = path
+        .ancestors()
+        .filter_map(|p| p.file_stem()?.to_str())
+        .find(|name| {
+            let name = name.to_lowercase();
+            name != "rules" && !GAME_FOLDERS.contains(&name.as_str())
+        })
+        .unwrap_or("Converted Mod");
+    Meta {
+        name: name.into(),
+        version: "1.0.0".into(),
+        category: "Other".into(),
+        description: format!(
+            "Converted from {}",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        )
+        .into(),
+        platform: ModPlatform::Specific(endian),
+        ..Default::default()
+    }
+}
+
+/// Whether a folder has game files in a layout UKMM can convert to a mod.
+pub fn has_loose_files(dir: &Path) -> bool {
+    LooseFiles::find(dir).is_some()
+}
+
+/// Converts a mod without UKMM metadata, such as a Cemu graphic pack or a
+/// plain `content`/`aoc` or `romfs` folder, to a UKMM mod by diffing its
+/// files against the dump. The metadata comes from `meta` if given, then from
+/// a `rules.txt` or `info.json` in the mod, and otherwise is made up from the
+/// file name.
 pub fn convert_gfx(
     core: &crate::core::Manager,
     path: &Path,
     meta: Option<Meta>,
 ) -> Result<PathBuf> {
     log::info!("Attempting to convert mod at {}", path.display());
-    let path = if path.is_file() {
+    let (dir, extracted) = if path.is_file() {
         let ext = path
             .extension()
             .and_then(|e| e.to_str())
             .unwrap_or("")
             .to_uppercase();
-
-        let find_rules = |path: &Path| -> Option<PathBuf> {
-            jwalk::WalkDir::new(path)
-                .into_iter()
-                .filter_map(std::result::Result::ok)
-                .find_map(|f| {
-                    ([Some("rules.txt"), Some("info.json")].contains(&f.file_name().to_str()))
-                        .then(|| f.parent_path().into())
-                })
-        };
-
-        let find_root = |path: &Path| -> Option<PathBuf> {
-            let (content, dlc) = platform_prefixes(core.settings().current_mode.into());
-            jwalk::WalkDir::new(path)
-                .into_iter()
-                .filter_map(std::result::Result::ok)
-                .find_map(|f| {
-                    ([Some(content), Some(dlc)].contains(&f.file_name().to_str()))
-                        .then(|| f.parent_path().into())
-                })
-        };
-
         if ext == "ZIP" {
             log::info!("Extracting ZIP file...");
             let tmpdir = util::get_temp_folder();
@@ -504,36 +1499,47 @@ pub fn convert_gfx(
                 .context("Failed to open ZIP")?
                 .extract(&*tmpdir)
                 .context("Failed to extract ZIP")?;
-            if meta.is_none() {
-                find_rules(&tmpdir).context("Could not find rules.txt in extracted mod")?
-            } else {
-                find_root(&tmpdir)
-                    .context("Could not find base or DLC content folder in extracted mod")?
-            }
+            (tmpdir.to_path_buf(), true)
         } else if ext == "7Z" {
             log::info!("Extracting 7Z file...");
             let tmpdir = util::get_temp_folder();
             extract_7z(path, &tmpdir).context("Failed to extract 7Z file")?;
-            if meta.is_none() {
-                find_rules(&tmpdir).context("Could not find rules.txt in extracted mod")?
-            } else {
-                find_root(&tmpdir)
-                    .context("Could not find base or DLC content folder in extracted mod")?
-            }
+            (tmpdir.to_path_buf(), true)
         } else if path.file_name().context("No file name")?.to_str() == Some("rules.txt") {
-            path.parent().unwrap().to_owned()
+            (path.parent().unwrap().to_owned(), false)
         } else {
             log::error!("{} is not a supported mod archive", path.display());
             anyhow_ext::bail!("{} files are not supported", ext)
         }
     } else {
         log::info!("Unpacked mod, that's easy");
-        path.to_path_buf()
+        (path.to_path_buf(), false)
+    };
+    let rules_root = meta.is_none().then(|| {
+        jwalk::WalkDir::new(&dir)
+            .into_iter()
+            .filter_map(std::result::Result::ok)
+            .find_map(|f| {
+                ([Some("rules.txt"), Some("info.json")].contains(&f.file_name().to_str()))
+                    .then(|| f.parent_path().to_path_buf())
+            })
+    });
+    let (source, meta) = match rules_root.flatten() {
+        Some(root) => (root, None),
+        None => {
+            let files = LooseFiles::find(&dir)
+                .context("Could not find base or DLC content folder in mod")?;
+            let meta = meta.unwrap_or_else(|| {
+                log::info!("Mod has no meta info, generating it from the file name");
+                loose_mod_meta(path, files.endian)
+            });
+            (files.into_source(extracted)?, Some(meta))
+        }
     };
     let temp = util::get_temp_folder();
     log::debug!("Temp folder: {}", temp.display());
     log::info!("Attempting to convert mod...");
-    let packer = ModPacker::new(path, &*temp, meta, vec![
+    let packer = ModPacker::new(source, &*temp, meta, vec![
         core.settings()
             .dump()
             .context("No dump available for current platform")?,
@@ -1,17 +1,29 @@
 use std::{
+    collections::BTreeMap,
     path::{Path, PathBuf},
     sync::{Arc, LazyLock},
 };
 
 use anyhow_ext::{Context, Result};
 use fs_err as fs;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DefaultOnError};
 use smartstring::alias::String;
 use uk_content::constants::Language;
+use uk_mod::unpack::RstbConfig;
 use uk_reader::ResourceReader;
 
+pub use self::{bcml::BcmlSettings, migrate::SETTINGS_VERSION};
+use crate::{
+    backup::{self, Backup},
+    deploy::emulators::{Emulator, EmulatorInstall},
+};
+
+mod bcml;
+mod migrate;
+
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Platform {
     #[default]
@@ -94,31 +106,215 @@ impl From<Platform> for rstb::Endian {
         }
     }
 }
+
+#[inline]
+fn default_target_name() -> String {
+    "Default".into()
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DeployConfig {
+    /// A name to tell deploy targets apart, e.g. "Cemu" or "SD card"
+    #[serde(default = "default_target_name")]
+    pub name: String,
     pub output: PathBuf,
     pub method: DeployMethod,
     pub auto: bool,
     #[serde(default)]
     pub cemu_rules: bool,
+    /// Connection details used by [`DeployMethod::Ftp`]. With FTP, `output`
+    /// is the folder on the server to deploy into.
+    #[serde(default)]
+    pub ftp: FtpConfig,
+    #[serde(default)]
+    pub layout: DeployLayout,
+    /// How many files to copy or link at once. 0 picks automatically.
+    #[serde(default)]
+    pub workers: usize,
+    /// Re-hash copied files after deploying to catch bad writes, which are
+    /// common with flaky SD card readers and network drives.
+    #[serde(default)]
+    pub verify: bool,
+    /// Shell command to run before deploying, e.g. to close the emulator.
+    /// `{output}` and `{platform}` are replaced with the deploy target and
+    /// current platform.
+    #[serde(default)]
+    pub pre_deploy: String,
+    /// Shell command to run after deploying, e.g. to launch the game. Takes
+    /// the same variables as `pre_deploy`.
+    #[serde(default)]
+    pub post_deploy: String,
+    /// Glob patterns for files to leave out of deployment, matched against
+    /// paths inside the content and DLC folders, e.g. `Terrain/**`
+    #[serde(default)]
+    pub exclude: Vec<String>,
 }
 
 impl Default for DeployConfig {
     fn default() -> Self {
         DeployConfig {
+            name: default_target_name(),
             output: "".into(),
             method: DeployMethod::Copy,
             auto: false,
             cemu_rules: false,
+            ftp: Default::default(),
+            layout: Default::default(),
+            workers: 0,
+            verify: false,
+            pre_deploy: "".into(),
+            post_deploy: "".into(),
+            exclude: vec![],
+        }
+    }
+}
+
+impl DeployConfig {
+    /// The folder the merged files actually go into, after applying the
+    /// deploy layout. SDCafiine picks mod folders by title ID, so the game
+    /// language decides the region.
+    pub fn target(&self, platform: Platform, lang: Language) -> PathBuf {
+        match (self.layout, platform) {
+            (DeployLayout::Atmosphere, Platform::Switch) => {
+                self.output.join("atmosphere").join("contents")
+            }
+            (DeployLayout::SdCafiine, Platform::WiiU) => {
+                self.output
+                    .join("sdcafiine")
+                    .join(crate::deploy::sdcard::wiiu_title_id(lang))
+                    .join("UKMM")
+            }
+            _ => self.output.clone(),
+        }
+    }
+
+    /// Matches files against the exclusion patterns. Invalid patterns are
+    /// skipped with a warning rather than failing the deployment.
+    pub fn exclusions(&self) -> GlobSet {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in self.exclude.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            match Glob::new(pattern) {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => log::warn!("Ignoring invalid deploy exclusion {pattern}: {e}"),
+            }
+        }
+        builder.build().unwrap_or_else(|e| {
+            log::warn!("Could not build deploy exclusions: {e}");
+            GlobSet::empty()
+        })
+    }
+
+    /// A config pre-filled to deploy into a detected emulator install.
+    pub fn for_emulator(install: &EmulatorInstall) -> Self {
+        DeployConfig {
+            name: install.emulator.to_string().into(),
+            output: install.deploy_output(),
+            auto: true,
+            cemu_rules: install.emulator == Emulator::Cemu,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+}
+
+impl Default for FtpConfig {
+    fn default() -> Self {
+        FtpConfig {
+            host: "".into(),
+            port: 21,
+            username: "anonymous".into(),
+            password: "".into(),
         }
     }
 }
 
+/// The kind of proxy to send online requests, like update checks and
+/// downloads, through.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProxyKind {
+    /// Connect directly
+    #[default]
+    Disabled,
+    /// An HTTP proxy which supports `CONNECT` tunnels
+    Http,
+    Socks5,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    /// Leave empty if the proxy does not need a login
+    pub username: String,
+    pub password: String,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        ProxyConfig {
+            kind: ProxyKind::Disabled,
+            host: "".into(),
+            port: 8080,
+            username: "".into(),
+            password: "".into(),
+        }
+    }
+}
+
+impl ProxyConfig {
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.kind != ProxyKind::Disabled && !self.host.is_empty()
+    }
+}
+
+/// How the merged folder is laid out under the deploy output.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeployLayout {
+    /// Deploy the merged folder as is
+    #[default]
+    Merged,
+    /// Treat the output as the root of an SD card and deploy Switch files
+    /// under `atmosphere/contents/<title ID>/romfs`
+    Atmosphere,
+    /// Treat the output as the root of an SD card and deploy Wii U files
+    /// under `sdcafiine/<title ID>/UKMM`
+    SdCafiine,
+    /// Treat the output as a Wii U title folder for Loadiine or a WUP
+    /// installer, with `code`, `content`, and `meta` filled out from the
+    /// game dump
+    Title,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DeployMethod {
     Copy,
     HardLink,
     Symlink,
+    /// Copy-on-write clones of each file on filesystems which support them
+    /// (Btrfs, XFS, APFS, ReFS), falling back to a plain copy elsewhere
+    Reflink,
+    /// Link the whole output folder like [`DeployMethod::Symlink`], but with
+    /// an NTFS junction, which needs no special permissions. Other systems
+    /// get a symlink.
+    Junction,
+    /// Upload to a console running an FTP server, such as FTPiiU or ftpd
+    Ftp,
+    /// Copy to a removable SD card for use on real hardware
+    SdCard,
+    /// Copy to an SMB or NFS share, retrying failed writes and resuming
+    /// large files instead of stopping at the first error
+    Network,
 }
 
 impl DeployMethod {
@@ -128,27 +324,175 @@ impl DeployMethod {
             DeployMethod::Copy => "Copy",
             DeployMethod::HardLink => "Hard Links",
             DeployMethod::Symlink => "Symlink",
+            DeployMethod::Reflink => "Reflinks",
+            DeployMethod::Junction => "Junction",
+            DeployMethod::Ftp => "FTP",
+            DeployMethod::SdCard => "SD Card",
+            DeployMethod::Network => "Network Share",
         }
     }
+
+    /// Whether the method links the whole output folder to the merged folder
+    /// instead of deploying files one by one.
+    #[inline(always)]
+    pub fn links_folder(&self) -> bool {
+        matches!(self, DeployMethod::Symlink | DeployMethod::Junction)
+    }
+}
+
+/// Folders one profile uses instead of the shared ones, so switching
+/// profiles leaves the other profiles' merged and deployed files alone.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfilePaths {
+    /// Merge into this folder instead of the one in the profile's storage
+    pub merged_dir: Option<PathBuf>,
+    /// Output folders to use instead of the deploy targets' own, by target
+    /// name
+    pub outputs: BTreeMap<String, PathBuf>,
+}
+
+impl ProfilePaths {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.merged_dir.is_none() && self.outputs.is_empty()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlatformSettings {
     pub language: Language,
+    /// Languages to take a mod's text changes from, in order, when it has
+    /// none for `language`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_languages: Vec<Language>,
     pub profile: String,
     pub dump: Arc<ResourceReader>,
-    pub deploy_config: Option<DeployConfig>,
+    #[serde(default)]
+    pub deploy_targets: Vec<DeployConfig>,
+    /// Folder overrides by profile name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profile_paths: BTreeMap<String, ProfilePaths>,
+    /// Extra room to add to the RSTB sizes of merged resources
+    #[serde(default, skip_serializing_if = "RstbConfig::is_default")]
+    pub rstb: RstbConfig,
+}
+
+impl PlatformSettings {
+    /// The folder overrides of the current profile, if it has any.
+    #[inline]
+    pub fn current_paths(&self) -> Option<&ProfilePaths> {
+        self.profile_paths.get(&self.profile)
+    }
+
+    /// A deploy target as the current profile uses it, with its output
+    /// folder replaced if the profile overrides it.
+    fn for_profile(&self, config: &DeployConfig) -> DeployConfig {
+        let mut config = config.clone();
+        if let Some(output) = self
+            .current_paths()
+            .and_then(|paths| paths.outputs.get(&config.name))
+        {
+            config.output = output.clone();
+        }
+        config
+    }
+
+    /// Every deploy target as the current profile uses it.
+    pub fn targets(&self) -> Vec<DeployConfig> {
+        self.deploy_targets
+            .iter()
+            .map(|config| self.for_profile(config))
+            .collect()
+    }
+
+    /// The first deploy target, used wherever only one is needed, such as
+    /// for the Cemu graphic pack folder.
+    #[inline]
+    pub fn deploy_config(&self) -> Option<DeployConfig> {
+        self.deploy_targets
+            .first()
+            .map(|config| self.for_profile(config))
+    }
+
+    #[inline]
+    pub fn deploy_target(&self, name: &str) -> Option<DeployConfig> {
+        self.deploy_targets
+            .iter()
+            .find(|t| t.name == name)
+            .map(|config| self.for_profile(config))
+    }
+}
+
+/// A folder to keep settings, storage, and logs in, given by the
+/// `--config-dir` flag or else the `UKMM_CONFIG_DIR` environment variable.
+fn config_override() -> Option<&'static Path> {
+    static DIR: LazyLock<Option<PathBuf>> = LazyLock::new(|| {
+        let mut args = std::env::args();
+        let mut dir = None;
+        while let Some(arg) = args.next() {
+            if arg == "--config-dir" {
+                dir = args.next();
+                break;
+            } else if let Some(value) = arg.strip_prefix("--config-dir=") {
+                dir = Some(value.to_owned());
+                break;
+            }
+        }
+        dir.map(PathBuf::from)
+            .or_else(|| std::env::var_os("UKMM_CONFIG_DIR").map(PathBuf::from))
+            .filter(|dir| !dir.as_os_str().is_empty())
+    });
+    DIR.as_deref()
 }
 
 #[inline]
 fn default_storage() -> PathBuf {
-    if std::env::args().any(|a| a == "--portable") {
+    if let Some(dir) = config_override() {
+        dir.join("data")
+    } else if std::env::args().any(|a| a == "--portable") {
         std::env::current_exe().unwrap().with_file_name("data")
     } else {
         dirs2::data_local_dir().unwrap().join("ukmm")
     }
 }
 
+/// How much detail to write to the log.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    #[inline]
+    pub fn iter() -> std::slice::Iter<'static, LogLevel> {
+        static LEVELS: [LogLevel; 5] = [
+            LogLevel::Error,
+            LogLevel::Warn,
+            LogLevel::Info,
+            LogLevel::Debug,
+            LogLevel::Trace,
+        ];
+        LEVELS.iter()
+    }
+
+    #[inline]
+    pub fn filter(self) -> log::LevelFilter {
+        match self {
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum UpdatePreference {
     None,
@@ -161,14 +505,50 @@ pub enum UpdatePreference {
 #[serde(default)]
 #[serde_as]
 pub struct Settings {
+    /// The settings file format, for migrating older files
+    pub version: u32,
     pub current_mode: Platform,
     pub system_7z: bool,
     #[serde(default = "default_storage")]
     pub storage_dir: PathBuf,
+    /// Another storage folder, e.g. on a network share, whose stored mods
+    /// are used in place instead of being copied into this one. UKMM never
+    /// writes to or deletes from it.
+    pub shared_storage_dir: Option<PathBuf>,
+    /// Folder for temporary files, like extracted BNPs, instead of the
+    /// system temp folder
+    pub temp_dir: Option<PathBuf>,
     #[serde(deserialize_with = "serde_with::As::<DefaultOnError>::deserialize")]
     pub check_updates: UpdatePreference,
     pub show_changelog: bool,
     pub last_version: Option<String>,
+    /// Skip resources a mod changes which are missing from the game dump
+    /// (e.g. DLC files without the DLC installed) instead of failing the
+    /// whole merge.
+    pub skip_missing_vanilla: bool,
+    /// Yaz0 compression level for merged files, from 6 (fastest) to 9
+    /// (smallest).
+    pub compression_level: u8,
+    /// Keep merged files whose contents are unchanged instead of compressing
+    /// them again.
+    pub skip_unchanged: bool,
+    /// Keep a copy of the merged folder from before each merge, so a bad
+    /// merge can be undone without merging again.
+    pub backup_merged: bool,
+    /// Roughly how much memory, in MB, to use for keeping resources from the
+    /// game dump which have already been read
+    pub cache_mb: u32,
+    /// Keep parsed resources from the game dump on disk, so they don't need
+    /// to be parsed again each time UKMM runs
+    pub disk_cache: bool,
+    /// Managed mode: mods cannot be installed, removed, toggled, or
+    /// reordered, but the current setup can still be applied and deployed.
+    pub locked: bool,
+    /// Proxy for the update checker and other online features
+    pub proxy: ProxyConfig,
+    pub log_level: LogLevel,
+    /// Where to keep the log file instead of `log.txt` in the config folder
+    pub log_file: Option<PathBuf>,
     pub wiiu_config: Option<PlatformSettings>,
     pub switch_config: Option<PlatformSettings>,
 }
@@ -176,14 +556,27 @@ pub struct Settings {
 impl Default for Settings {
     fn default() -> Self {
         Self {
+            version: SETTINGS_VERSION,
             current_mode: Platform::WiiU,
             system_7z: true,
             storage_dir: default_storage(),
+            shared_storage_dir: None,
+            temp_dir: None,
             wiiu_config: None,
             switch_config: None,
             check_updates: UpdatePreference::Stable,
             show_changelog: true,
             last_version: None,
+            skip_missing_vanilla: false,
+            compression_level: uk_mod::unpack::DEFAULT_COMPRESSION_LEVEL,
+            skip_unchanged: false,
+            backup_merged: false,
+            cache_mb: uk_reader::DEFAULT_CACHE_MB,
+            disk_cache: false,
+            locked: false,
+            proxy: Default::default(),
+            log_level: LogLevel::Info,
+            log_file: None,
         }
     }
 }
@@ -197,7 +590,9 @@ impl Settings {
 
     pub fn config_dir() -> &'static Path {
         static PATH: LazyLock<PathBuf> = LazyLock::new(|| {
-            if std::env::args().any(|a| a == "--portable") {
+            if let Some(dir) = config_override() {
+                dir.to_path_buf()
+            } else if std::env::args().any(|a| a == "--portable") {
                 std::env::current_exe()
                     .expect("No current executable???")
                     .parent()
@@ -215,6 +610,9 @@ impl Settings {
             Ok(settings) => {
                 log::debug!("{:#?}", settings);
                 crate::util::USE_SZ.store(settings.system_7z, std::sync::atomic::Ordering::Release);
+                crate::util::set_temp_root(settings.temp_dir.clone());
+                uk_reader::set_disk_cache(settings.disk_cache_dir());
+                uk_reader::set_remote_cache(settings.remote_cache_dir());
                 settings
             }
             Err(e) => {
@@ -229,6 +627,9 @@ impl Settings {
         *self = match Settings::read(Self::path()) {
             Ok(settings) => {
                 log::debug!("{:#?}", settings);
+                crate::util::set_temp_root(settings.temp_dir.clone());
+                uk_reader::set_disk_cache(settings.disk_cache_dir());
+                uk_reader::set_remote_cache(settings.remote_cache_dir());
                 settings
             }
             Err(e) => {
@@ -239,8 +640,16 @@ impl Settings {
         }
     }
 
+    /// Reads a settings file, migrating it first if it is from an older
+    /// version of UKMM.
     pub fn read(path: &Path) -> Result<Self> {
-        Ok(serde_yaml::from_str(&fs::read_to_string(path)?)?)
+        let settings = migrate::migrate(serde_yaml::from_str(&fs::read_to_string(path)?)?)?;
+        // The game dumps are opened as the settings are read, so their caches
+        // need the budget first
+        if let Some(mb) = settings.get("cache_mb").and_then(|mb| mb.as_u64()) {
+            uk_reader::set_cache_budget(mb as u32);
+        }
+        Ok(serde_yaml::from_value(settings)?)
     }
 
     pub fn apply(&mut self, apply_fn: impl Fn(&mut Self)) -> Result<()> {
@@ -260,11 +669,35 @@ impl Settings {
             std::sync::atomic::Ordering::Relaxed,
             std::sync::atomic::Ordering::Relaxed,
         );
+        crate::util::set_temp_root(self.temp_dir.clone());
+        uk_reader::set_cache_budget(self.cache_mb);
+        uk_reader::set_disk_cache(self.disk_cache_dir());
+        uk_reader::set_remote_cache(self.remote_cache_dir());
+        if let Err(e) = backup::backup(Self::path(), &Self::backups_dir()) {
+            log::warn!("Failed to back up settings: {e}");
+        }
         fs::write(Self::path(), serde_yaml::to_string(self)?)?;
         log::info!("Settings saved");
         Ok(())
     }
 
+    #[inline]
+    pub fn backups_dir() -> PathBuf {
+        Self::config_dir().join("backups")
+    }
+
+    /// Backups of the settings file taken before each save, newest first.
+    pub fn backups() -> Result<Vec<Backup>> {
+        backup::list(Self::path(), &Self::backups_dir())
+    }
+
+    /// Replaces the settings file with a backup. The settings need to be
+    /// reloaded afterwards to take effect.
+    pub fn restore_backup(backup: &Backup) -> Result<()> {
+        Settings::read(&backup.path).context("Backup is not a valid settings file")?;
+        backup::restore(backup, Self::path(), &Self::backups_dir())
+    }
+
     #[inline]
     pub fn platform_dir(&self) -> PathBuf {
         self.get_platform_dir(self.current_mode)
@@ -313,6 +746,19 @@ impl Settings {
         self.platform_dir().join("mods")
     }
 
+    /// The stored mods folder of the shared storage for the current platform,
+    /// if one is set.
+    #[inline]
+    pub fn shared_mods_dir(&self) -> Option<PathBuf> {
+        self.shared_storage_dir.as_ref().map(|dir| {
+            match self.current_mode {
+                Platform::Switch => dir.join("nx"),
+                Platform::WiiU => dir.join("wiiu"),
+            }
+            .join("mods")
+        })
+    }
+
     #[inline]
     pub fn dump(&self) -> Option<Arc<ResourceReader>> {
         match self.current_mode {
@@ -321,6 +767,32 @@ impl Settings {
         }
     }
 
+    /// The current platform's game language, which mod names and
+    /// descriptions are shown in if they have translations.
+    #[inline]
+    pub fn language(&self) -> Language {
+        self.platform_config()
+            .map(|c| c.language)
+            .unwrap_or_default()
+    }
+
+    /// The current platform's fallbacks for mods without text changes in
+    /// the game's language.
+    #[inline]
+    pub fn fallback_languages(&self) -> Vec<Language> {
+        self.platform_config()
+            .map(|c| c.fallback_languages.clone())
+            .unwrap_or_default()
+    }
+
+    /// The current platform's RSTB padding.
+    #[inline]
+    pub fn rstb_config(&self) -> RstbConfig {
+        self.platform_config()
+            .map(|c| c.rstb.clone())
+            .unwrap_or_default()
+    }
+
     #[inline(always)]
     pub fn platform_config(&self) -> Option<&PlatformSettings> {
         match self.current_mode {
@@ -337,17 +809,33 @@ impl Settings {
         }
     }
 
+    /// Where A/B snapshot builds of profiles are merged, one folder per
+    /// profile.
+    #[inline]
+    pub fn snapshots_dir(&self) -> PathBuf {
+        self.platform_dir().join("snapshots")
+    }
+
+    /// Where mods deployed loose are unpacked, one folder per mod.
+    #[inline]
+    pub fn loose_dir(&self) -> PathBuf {
+        self.platform_dir().join("loose")
+    }
+
+    /// Where the current profile's mods are merged: the folder it overrides
+    /// this with, or else the one in its profile folder.
     #[inline]
     pub fn merged_dir(&self) -> PathBuf {
-        self.profile_dir().join("merged")
+        self.platform_config()
+            .and_then(|c| c.current_paths())
+            .and_then(|paths| paths.merged_dir.clone())
+            .unwrap_or_else(|| self.profile_dir().join("merged"))
     }
 
     #[inline]
-    pub fn deploy_dir(&self) -> Option<&Path> {
+    pub fn deploy_dir(&self) -> Option<PathBuf> {
         let config = self.platform_config();
-        config
-            .and_then(|c| c.deploy_config.as_ref())
-            .map(|c| c.output.as_ref())
+        config.and_then(|c| c.deploy_config()).map(|c| c.output)
     }
 
     #[inline]
@@ -355,8 +843,32 @@ impl Settings {
         Self::config_dir().join("ui.json")
     }
 
+    #[inline]
+    pub fn log_file(&self) -> PathBuf {
+        self.log_file
+            .clone()
+            .unwrap_or_else(|| Self::config_dir().join("log.txt"))
+    }
+
     #[inline]
     pub fn projects_dir(&self) -> PathBuf {
         self.storage_dir.join("projects")
     }
+
+    #[inline]
+    pub fn resource_cache_dir(&self) -> PathBuf {
+        self.storage_dir.join("resource_cache")
+    }
+
+    /// Where parsed game files are cached, if the disk cache is on.
+    #[inline]
+    pub fn disk_cache_dir(&self) -> Option<PathBuf> {
+        self.disk_cache.then(|| self.resource_cache_dir())
+    }
+
+    /// Where files downloaded from remote game dumps are kept.
+    #[inline]
+    pub fn remote_cache_dir(&self) -> PathBuf {
+        self.storage_dir.join("remote_cache")
+    }
 }
@@ -0,0 +1,158 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use serde::Deserialize;
+use uk_content::constants::Language;
+use uk_reader::ResourceReader;
+
+use super::{DeployConfig, Platform, PlatformSettings, Settings};
+
+/// The parts of BCML's `settings.json` which UKMM has a use for.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct BcmlSettings {
+    pub lang: Language,
+    pub cemu_dir: Option<PathBuf>,
+    pub export_dir: Option<PathBuf>,
+    pub export_dir_nx: Option<PathBuf>,
+    pub game_dir: Option<PathBuf>,
+    pub game_dir_nx: Option<PathBuf>,
+    pub update_dir: Option<PathBuf>,
+    pub dlc_dir: Option<PathBuf>,
+    pub dlc_dir_nx: Option<PathBuf>,
+    pub store_dir: PathBuf,
+}
+
+/// BCML leaves unset folders as empty strings rather than leaving them out.
+fn set(dir: &Option<PathBuf>) -> Option<PathBuf> {
+    dir.as_ref()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .cloned()
+}
+
+impl BcmlSettings {
+    pub fn path() -> PathBuf {
+        if cfg!(windows) {
+            dirs2::data_local_dir()
+        } else {
+            dirs2::config_dir()
+        }
+        .unwrap()
+        .join("bcml/settings.json")
+    }
+
+    pub fn load() -> Result<Self> {
+        serde_json::from_str(
+            &fs::read_to_string(Self::path()).context("Failed to read BCML settings file")?,
+        )
+        .context("Failed to parse BCML settings file")
+    }
+
+    /// Wii U settings built from BCML's, if it has a Wii U game dump set.
+    /// BCML only has one export folder, so that becomes the only deploy
+    /// target, or else a graphic pack in the Cemu folder.
+    pub fn wiiu_config(&self) -> Result<Option<PlatformSettings>> {
+        let (Some(game_dir), Some(update_dir)) = (set(&self.game_dir), set(&self.update_dir))
+        else {
+            return Ok(None);
+        };
+        let cemu_dir = set(&self.cemu_dir);
+        let target = set(&self.export_dir)
+            .map(|export_dir| {
+                DeployConfig {
+                    output: export_dir,
+                    cemu_rules: cemu_dir.is_some(),
+                    ..Default::default()
+                }
+            })
+            .or_else(|| {
+                cemu_dir.map(|cemu_dir| {
+                    DeployConfig {
+                        name: "Cemu".into(),
+                        output: cemu_dir.join("graphicPacks/BreathOfTheWild_UKMM"),
+                        cemu_rules: true,
+                        ..Default::default()
+                    }
+                })
+            });
+        Ok(Some(PlatformSettings {
+            language: self.lang,
+            profile: "Default".into(),
+            deploy_targets: target.into_iter().collect(),
+            dump: Arc::new(
+                ResourceReader::from_unpacked_dirs(
+                    Some(game_dir),
+                    Some(update_dir),
+                    set(&self.dlc_dir),
+                )
+                .context("Failed to load the Wii U game dump set in BCML")?,
+            ),
+            profile_paths: Default::default(),
+            fallback_languages: vec![],
+            rstb: Default::default(),
+        }))
+    }
+
+    /// Switch settings built from BCML's, if it has a Switch game dump set.
+    pub fn switch_config(&self) -> Result<Option<PlatformSettings>> {
+        let Some(game_dir) = set(&self.game_dir_nx) else {
+            return Ok(None);
+        };
+        Ok(Some(PlatformSettings {
+            language: self.lang,
+            profile: "Default".into(),
+            deploy_targets: set(&self.export_dir_nx)
+                .map(|export_dir| {
+                    DeployConfig {
+                        output: export_dir,
+                        ..Default::default()
+                    }
+                })
+                .into_iter()
+                .collect(),
+            dump: Arc::new(
+                ResourceReader::from_unpacked_dirs(
+                    Some(game_dir),
+                    None::<PathBuf>,
+                    set(&self.dlc_dir_nx),
+                )
+                .context("Failed to load the Switch game dump set in BCML")?,
+            ),
+            profile_paths: Default::default(),
+            fallback_languages: vec![],
+            rstb: Default::default(),
+        }))
+    }
+}
+
+impl Settings {
+    /// Fills in the game dump, language, and deploy folder of each platform
+    /// which BCML has a game dump set for. Profiles, language fallbacks, RSTB
+    /// padding, and any per-profile folders are kept, and so are existing deploy targets
+    /// if BCML has no export folder. Returns the platforms which were imported. Nothing is
+    /// saved until [`Settings::save`] is called.
+    pub fn import_bcml(&mut self, bcml: &BcmlSettings) -> Result<Vec<Platform>> {
+        let mut imported = vec![];
+        for (platform, config, existing) in [
+            (Platform::WiiU, bcml.wiiu_config()?, &mut self.wiiu_config),
+            (Platform::Switch, bcml.switch_config()?, &mut self.switch_config),
+        ] {
+            let Some(mut config) = config else {
+                continue;
+            };
+            if let Some(existing) = existing.take() {
+                config.profile = existing.profile;
+                config.fallback_languages = existing.fallback_languages;
+                config.profile_paths = existing.profile_paths;
+                config.rstb = existing.rstb;
+                if config.deploy_targets.is_empty() {
+                    config.deploy_targets = existing.deploy_targets;
+                }
+            }
+            *existing = Some(config);
+            imported.push(platform);
+        }
+        Ok(imported)
+    }
+}
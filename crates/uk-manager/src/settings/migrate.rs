@@ -0,0 +1,82 @@
+use anyhow_ext::{Context, Result};
+use serde_yaml::{Mapping, Value};
+
+/// The version of the settings file format. Bump it and add a migration to
+/// [`MIGRATIONS`] for any change which older settings files would not load
+/// into as is, like renaming or restructuring a field.
+pub const SETTINGS_VERSION: u32 = 1;
+
+type Migration = fn(&mut Mapping) -> Result<()>;
+
+/// The migration at index `n` upgrades a settings file from version `n` to
+/// version `n + 1`. Files from before versioning count as version 0.
+static MIGRATIONS: [Migration; SETTINGS_VERSION as usize] = [deploy_targets];
+
+/// Brings a settings file from any older version up to [`SETTINGS_VERSION`]
+/// before it is deserialized.
+pub(super) fn migrate(mut settings: Value) -> Result<Value> {
+    let Value::Mapping(map) = &mut settings else {
+        anyhow_ext::bail!("Settings file is not a mapping");
+    };
+    let version = map.get("version").and_then(Value::as_u64).unwrap_or(0) as usize;
+    if version > SETTINGS_VERSION as usize {
+        log::warn!(
+            "Settings file is from a newer version of UKMM (format {version}), some settings may \
+             not load"
+        );
+        return Ok(settings);
+    }
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        migration(map)
+            .with_context(|| format!("Failed to migrate settings from format {from}"))?;
+        log::info!("Migrated settings from format {from} to {}", from + 1);
+    }
+    map.insert("version".into(), SETTINGS_VERSION.into());
+    Ok(settings)
+}
+
+/// Version 1: platforms have a list of deploy targets instead of a single
+/// optional `deploy_config`.
+fn deploy_targets(settings: &mut Mapping) -> Result<()> {
+    for platform in ["wiiu_config", "switch_config"] {
+        let Some(Value::Mapping(config)) = settings.get_mut(platform) else {
+            continue;
+        };
+        if let Some(target) = config.remove("deploy_config") {
+            let targets = match target {
+                Value::Null => vec![],
+                Value::Sequence(targets) => targets,
+                target => vec![target],
+            };
+            config.insert("deploy_targets".into(), Value::Sequence(targets));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_yaml::Value;
+
+    use super::{migrate, SETTINGS_VERSION};
+
+    #[test]
+    fn migrate_deploy_config() {
+        let settings: Value = serde_yaml::from_str(
+            "wiiu_config:\n  deploy_config:\n    output: /tmp/out\nswitch_config:\n  \
+             deploy_config: null\n",
+        )
+        .unwrap();
+        let settings = migrate(settings).unwrap();
+        assert_eq!(settings["version"].as_u64(), Some(SETTINGS_VERSION as u64));
+        assert_eq!(
+            settings["wiiu_config"]["deploy_targets"][0]["output"].as_str(),
+            Some("/tmp/out")
+        );
+        assert!(settings["wiiu_config"].get("deploy_config").is_none());
+        assert_eq!(
+            settings["switch_config"]["deploy_targets"].as_sequence().map(Vec::len),
+            Some(0)
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow_ext::{Context, Result};
+use serde::Deserialize;
+use smartstring::alias::String;
+
+use crate::mods::Mod;
+
+/// Where GameBanana publishes details about each mod page.
+pub const GAMEBANANA_API: &str = "https://gamebanana.com/apiv11/Mod";
+
+/// Where a mod's author publishes its new versions, from its `update_url`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateSource {
+    /// A static JSON file describing the latest [`Release`]
+    Feed(String),
+    /// The ID of a GameBanana mod page
+    GameBanana(u64),
+}
+
+impl UpdateSource {
+    /// Reads a mod's `update_url`: a bare number is a GameBanana ID, and
+    /// anything else must be an HTTP(S) URL.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Ok(id) = value.parse() {
+            Some(Self::GameBanana(id))
+        } else if value.starts_with("https://") || value.starts_with("http://") {
+            Some(Self::Feed(value.into()))
+        } else {
+            None
+        }
+    }
+
+    /// The URL to download to check for a new release.
+    pub fn request_url(&self) -> std::string::String {
+        match self {
+            Self::Feed(url) => url.to_string(),
+            Self::GameBanana(id) => format!("{GAMEBANANA_API}/{id}/ProfilePage"),
+        }
+    }
+
+    /// Reads the latest release from the downloaded feed or mod page.
+    pub fn read(&self, data: &[u8]) -> Result<Release> {
+        match self {
+            Self::Feed(_) => serde_json::from_slice(data).context("Invalid update feed"),
+            Self::GameBanana(id) => {
+                #[derive(Deserialize)]
+                struct ProfilePage {
+                    #[serde(rename = "_sVersion", default)]
+                    version: String,
+                    #[serde(rename = "_sProfileUrl")]
+                    profile_url: Option<String>,
+                }
+                let page: ProfilePage =
+                    serde_json::from_slice(data).context("Invalid GameBanana response")?;
+                anyhow_ext::ensure!(
+                    !page.version.is_empty(),
+                    "GameBanana mod {id} has no version"
+                );
+                Ok(Release {
+                    version: page.version,
+                    url:     Some(
+                        page.profile_url
+                            .unwrap_or_else(|| format!("https://gamebanana.com/mods/{id}").into()),
+                    ),
+                    changes: None,
+                })
+            }
+        }
+    }
+}
+
+/// The newest version of a mod, as described by its update feed.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Release {
+    pub version: String,
+    /// Where to download the new version, or the page to find it on
+    #[serde(default)]
+    pub url:     Option<String>,
+    /// What changed in the new version, in Markdown
+    #[serde(default)]
+    pub changes: Option<String>,
+}
+
+/// An installed mod which has a newer release available.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModUpdate {
+    pub hash:    usize,
+    pub name:    String,
+    pub current: String,
+    pub release: Release,
+}
+
+impl ModUpdate {
+    fn check(mod_: &Mod, fetch: &impl Fn(&str) -> Result<Vec<u8>>) -> Result<Option<Self>> {
+        let source = mod_.meta.update_url.as_deref().and_then(UpdateSource::parse);
+        let Some(source) = source else {
+            return Ok(None);
+        };
+        let data = fetch(&source.request_url())?;
+        let release = source.read(&data)?;
+        let (Ok(latest), Ok(current)) = (
+            lenient_semver::parse(release.version.as_str()),
+            lenient_semver::parse(mod_.meta.version.as_str()),
+        ) else {
+            log::debug!(
+                "Cannot compare version {} of {} to installed version {}",
+                release.version,
+                mod_.meta.name,
+                mod_.meta.version
+            );
+            return Ok(None);
+        };
+        Ok((latest > current).then(|| {
+            Self {
+                hash: mod_.hash(),
+                name: mod_.meta.name.clone(),
+                current: mod_.meta.version.clone(),
+                release,
+            }
+        }))
+    }
+}
+
+/// Checks each mod which declares an update feed for a newer release,
+/// downloading feeds with `fetch`. Feeds which can't be read are logged and
+/// skipped rather than failing the whole check.
+pub fn check_updates<'a>(
+    mods: impl IntoIterator<Item = &'a Mod>,
+    fetch: impl Fn(&str) -> Result<Vec<u8>>,
+) -> Vec<ModUpdate> {
+    mods.into_iter()
+        .filter_map(|mod_| {
+            ModUpdate::check(mod_, &fetch)
+                .inspect_err(|e| {
+                    log::warn!("Failed to check for updates to {}: {e:?}", mod_.meta.name)
+                })
+                .ok()
+                .flatten()
+        })
+        .inspect(|update| {
+            log::info!(
+                "{} has an update from version {} to {}",
+                update.name,
+                update.current,
+                update.release.version
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UpdateSource;
+
+    #[test]
+    fn parse_sources() {
+        assert_eq!(
+            UpdateSource::parse("123456"),
+            Some(UpdateSource::GameBanana(123456))
+        );
+        assert_eq!(
+            UpdateSource::parse("https://example.com/mod.json"),
+            Some(UpdateSource::Feed("https://example.com/mod.json".into()))
+        );
+        assert_eq!(UpdateSource::parse("example.com"), None);
+
+        let release = UpdateSource::Feed("".into())
+            .read(br#"{"version": "1.2.0", "url": "https://example.com/mod.zip"}"#)
+            .unwrap();
+        assert_eq!(release.version, "1.2.0");
+        assert_eq!(release.changes, None);
+        let release = UpdateSource::GameBanana(1)
+            .read(br#"{"_sName": "Test", "_sVersion": "2.0"}"#)
+            .unwrap();
+        assert_eq!(release.version, "2.0");
+        assert_eq!(
+            release.url.as_deref(),
+            Some("https://gamebanana.com/mods/1")
+        );
+        assert!(UpdateSource::GameBanana(1).read(b"{}").is_err());
+    }
+}
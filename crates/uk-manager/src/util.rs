@@ -19,11 +19,48 @@ pub fn remove_dir_all(dir: impl AsRef<std::path::Path>) -> anyhow_ext::Result<()
     inner(dir.as_ref())
 }
 
+/// Hashes a file's contents and size without reading it all into memory at
+/// once.
+pub fn hash_file(path: &Path) -> anyhow_ext::Result<u64> {
+    use std::{hash::Hasher, io::Read};
+    let mut file = fs_err::File::open(path)?;
+    let mut hasher = rustc_hash::FxHasher::default();
+    let mut buf = vec![0; 1 << 20];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.write(&buf[..read]);
+    }
+    hasher.write_u64(fs_err::metadata(path)?.len());
+    Ok(hasher.finish())
+}
+
 static TEMP_FS: LazyLock<RwLock<HashSet<PathBuf>>> =
     LazyLock::new(|| RwLock::new(HashSet::default()));
 
+static TEMP_ROOT: LazyLock<RwLock<Option<PathBuf>>> = LazyLock::new(|| RwLock::new(None));
+
+/// Puts temporary files in `dir` instead of the system temp folder, or back
+/// in the system temp folder with `None`.
+pub fn set_temp_root(dir: Option<PathBuf>) {
+    *TEMP_ROOT.write() = dir.filter(|dir| !dir.as_os_str().is_empty());
+}
+
+/// The folder temporary files go in, created if it is missing.
+pub fn temp_root() -> PathBuf {
+    let root = TEMP_ROOT.read().clone().unwrap_or_else(std::env::temp_dir);
+    if !root.exists()
+        && let Err(e) = fs_err::create_dir_all(&root)
+    {
+        log::warn!("Could not create temp folder: {e}");
+    }
+    root
+}
+
 pub fn get_temp_folder() -> MappedRwLockReadGuard<'static, PathBuf> {
-    let temp = tempfile::tempdir().unwrap().into_path();
+    let temp = tempfile::tempdir_in(temp_root()).unwrap().into_path();
     TEMP_FS.write().insert(temp.clone());
     RwLockReadGuard::map(TEMP_FS.read(), |tmps| unsafe {
         tmps.get(&temp).unwrap_unchecked()
@@ -31,7 +68,11 @@ pub fn get_temp_folder() -> MappedRwLockReadGuard<'static, PathBuf> {
 }
 
 pub fn get_temp_file() -> MappedRwLockReadGuard<'static, PathBuf> {
-    let temp = tempfile::NamedTempFile::new().unwrap().keep().unwrap().1;
+    let temp = tempfile::NamedTempFile::new_in(temp_root())
+        .unwrap()
+        .keep()
+        .unwrap()
+        .1;
     TEMP_FS.write().insert(temp.clone());
     RwLockReadGuard::map(TEMP_FS.read(), |tmps| unsafe {
         tmps.get(&temp).unwrap_unchecked()
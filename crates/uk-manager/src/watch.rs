@@ -0,0 +1,210 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::Duration,
+};
+
+use anyhow_ext::{Context, Result};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use path_slash::PathExt;
+use smartstring::alias::String;
+use uk_mod::{unpack::ModReader, Manifest};
+
+use crate::core;
+
+/// How long to wait after the last change before redeploying, so a burst of
+/// writes (like an editor saving several files) only redeploys once.
+const SETTLE_TIME: Duration = Duration::from_millis(750);
+
+/// What a [`Watcher`] keeps an eye on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchMode {
+    /// The merged folder. Any change there is deployed as is.
+    Merged,
+    /// The folders of enabled mods which are stored unpacked, for mod
+    /// developers. Changed files are merged again and then deployed.
+    ModSources,
+}
+
+impl WatchMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WatchMode::Merged => "merged folder",
+            WatchMode::ModSources => "mod sources",
+        }
+    }
+}
+
+/// Redeploys automatically whenever the watched files change. Watching
+/// stops when this is dropped.
+#[derive(Debug)]
+pub struct Watcher {
+    mode: WatchMode,
+    _watcher: RecommendedWatcher,
+}
+
+impl Watcher {
+    pub fn start(core: &core::Manager, mode: WatchMode) -> Result<Self> {
+        let roots: Vec<PathBuf> = match mode {
+            WatchMode::Merged => vec![core.settings().merged_dir()],
+            WatchMode::ModSources => {
+                core.mod_manager()
+                    .mods()
+                    .map(|m| m.path)
+                    .filter(|path| path.is_dir())
+                    .collect()
+            }
+        };
+        if roots.is_empty() {
+            anyhow_ext::bail!("No unpacked mods are enabled, so there are no mod sources to watch");
+        }
+        let (send, recv) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(send).context("Failed to start watcher")?;
+        for root in &roots {
+            std::fs::create_dir_all(root)?;
+            watcher
+                .watch(root, RecursiveMode::Recursive)
+                .with_context(|| format!("Failed to watch {}", root.display()))?;
+            log::info!("Watching {} for changes", root.display());
+        }
+        let core = core.clone();
+        std::thread::spawn(move || {
+            // The channel closes once the watcher is dropped, which ends the
+            // loop.
+            while let Ok(event) = recv.recv() {
+                let mut changed: Vec<PathBuf> = vec![];
+                let mut removed: Vec<PathBuf> = vec![];
+                let mut collect = |event: notify::Result<notify::Event>| {
+                    match event {
+                        Ok(event) if matches!(event.kind, EventKind::Remove(_)) => {
+                            removed.extend(event.paths)
+                        }
+                        Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                            changed.extend(event.paths)
+                        }
+                        Ok(_) => (),
+                        Err(e) => log::warn!("Error watching for changes: {e}"),
+                    }
+                };
+                collect(event);
+                while let Ok(event) = recv.recv_timeout(SETTLE_TIME) {
+                    collect(event);
+                }
+                changed.retain(|path| path.is_file());
+                if changed.is_empty() && removed.is_empty() {
+                    continue;
+                }
+                let result = match mode {
+                    WatchMode::Merged => redeploy_merged(&core, changed, removed),
+                    WatchMode::ModSources => {
+                        changed.extend(removed);
+                        redeploy_sources(&core, &roots, changed)
+                    }
+                };
+                if let Err(e) = result {
+                    log::error!("Automatic redeploy failed: {e:?}");
+                }
+            }
+            log::info!("Stopped watching the {}", mode.name());
+        });
+        Ok(Self {
+            mode,
+            _watcher: watcher,
+        })
+    }
+
+    #[inline(always)]
+    pub fn mode(&self) -> WatchMode {
+        self.mode
+    }
+}
+
+/// Splits changed paths in the merged folder into base game and DLC files.
+fn merged_manifest(merged: &Path, content: &str, aoc: &str, paths: Vec<PathBuf>) -> Manifest {
+    let mut manifest = Manifest::default();
+    for path in paths {
+        if let Ok(rel) = path.strip_prefix(merged.join(content)) {
+            manifest
+                .content_files
+                .insert(rel.to_slash_lossy().into());
+        } else if let Ok(rel) = path.strip_prefix(merged.join(aoc)) {
+            manifest.aoc_files.insert(rel.to_slash_lossy().into());
+        }
+    }
+    manifest
+}
+
+fn redeploy_merged(
+    core: &core::Manager,
+    changed: Vec<PathBuf>,
+    removed: Vec<PathBuf>,
+) -> Result<()> {
+    let (merged, (content, aoc)) = {
+        let settings = core.settings();
+        (
+            settings.merged_dir(),
+            uk_content::platform_prefixes(settings.current_mode.into()),
+        )
+    };
+    let changed = merged_manifest(&merged, content, aoc, changed);
+    let removed = merged_manifest(&merged, content, aoc, removed);
+    log::info!(
+        "Merged folder changed, redeploying {} files",
+        changed.content_files.len()
+            + changed.aoc_files.len()
+            + removed.content_files.len()
+            + removed.aoc_files.len()
+    );
+    let deploy_manager = core.deploy_manager();
+    deploy_manager.queue_changes(&changed, &removed)?;
+    deploy_manager.deploy()
+}
+
+/// Maps files changed in unpacked mods back to the entries in their
+/// manifests, merges just those files again, and deploys the result.
+fn redeploy_sources(core: &core::Manager, roots: &[PathBuf], paths: Vec<PathBuf>) -> Result<()> {
+    let mut manifest = Manifest::default();
+    for root in roots {
+        let rels: Vec<String> = paths
+            .iter()
+            .filter_map(|path| path.strip_prefix(root).ok())
+            .map(|rel| rel.to_slash_lossy().into())
+            .collect();
+        if rels.is_empty() {
+            continue;
+        }
+        let reader = ModReader::open_peek(root, vec![])
+            .with_context(|| format!("Failed to open mod at {}", root.display()))?;
+        // Option files live under `options/<folder>/`, otherwise at the same
+        // canonical paths as the main files.
+        let canon = |rel: &str| -> std::string::String {
+            match rel.strip_prefix("options/") {
+                Some(rest) => rest.split_once('/').map(|(_, f)| f).unwrap_or(rest).into(),
+                None => rel.into(),
+            }
+        };
+        let rels: Vec<std::string::String> = rels.iter().map(|rel| canon(rel)).collect();
+        for file in &reader.manifest.content_files {
+            if rels.iter().any(|rel| *rel == file.replace(".s", ".")) {
+                manifest.content_files.insert(file.clone());
+            }
+        }
+        for file in &reader.manifest.aoc_files {
+            let canon = ["Aoc/0010/", &file.replace(".s", ".")].concat();
+            if rels.contains(&canon) {
+                manifest.aoc_files.insert(file.clone());
+            }
+        }
+    }
+    if manifest.is_empty() {
+        log::debug!("Changed files are not part of any mod's manifest, nothing to redeploy");
+        return Ok(());
+    }
+    log::info!(
+        "Mod sources changed, remerging and redeploying {} files",
+        manifest.content_files.len() + manifest.aoc_files.len()
+    );
+    let deploy_manager = core.deploy_manager();
+    deploy_manager.apply(Some(manifest))?;
+    deploy_manager.deploy()
+}
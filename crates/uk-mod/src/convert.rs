@@ -0,0 +1,257 @@
+use std::{collections::BTreeMap, io::Read, path::Path};
+
+use anyhow_ext::{ensure, Context, Result};
+use roead::{
+    byml::Byml,
+    sarc::{Sarc, SarcWriter},
+    yaz0::{compress, decompress_if},
+};
+use uk_content::{prelude::Endian, resource::ResourceData};
+
+use crate::{
+    delta::{entries, open},
+    dual::Writer,
+    integrity::INTEGRITY_FILE,
+    lint::{endian_name, file_endian},
+    pack::CompressionConfig,
+    Manifest, Meta, ModPlatform,
+};
+
+/// Swaps a file to the byte order of another platform. BYML files and SARCs,
+/// along with everything in the SARC, are rewritten; files which are the same
+/// on both, like AAMP parameter files, are returned as they are. Returns
+/// `None` for formats which differ in more than byte order between platforms,
+/// like models, textures, and audio.
+fn convert_binary(data: &[u8], endian: Endian) -> Option<Vec<u8>> {
+    let raw = decompress_if(data);
+    match file_endian(&raw) {
+        Some(file_endian) if file_endian != endian => (),
+        _ => return Some(data.to_vec()),
+    }
+    let converted = match &raw[..4] {
+        [b'B', b'Y', ..] | [b'Y', b'B', ..] => {
+            Byml::from_binary(&raw).ok()?.to_binary(endian.into())
+        }
+        b"SARC" => {
+            let sarc = Sarc::new(raw.as_ref()).ok()?;
+            let mut writer =
+                SarcWriter::new(endian.into()).with_min_alignment(sarc.guess_min_alignment());
+            for file in sarc.files() {
+                writer.add_file(file.name?, convert_binary(file.data, endian)?);
+            }
+            writer.to_binary()
+        }
+        _ => return None,
+    };
+    Some(if data.starts_with(b"Yaz0") {
+        compress(converted)
+    } else {
+        converted
+    })
+}
+
+/// Writes a copy of a package made for one platform, converted for the
+/// platform with byte order `endian`, to `out`. Nearly everything UKMM
+/// merges is stored the same way for both platforms already, so only the
+/// BYML files and SARCs kept as they are need rewriting, along with the RSTB
+/// sizes the manifests record for them. Files which can't be converted, like
+/// models and textures, are kept unchanged, and their names returned along
+/// with the new meta, since they may not work. Their RSTB sizes are dropped,
+/// to be estimated when mods are merged.
+pub fn convert(
+    path: &Path,
+    endian: Endian,
+    out: &Path,
+) -> Result<(Meta, Vec<std::string::String>)> {
+    let (mut zip, mut meta) = open(path)?;
+    let from = match endian {
+        Endian::Big => Endian::Little,
+        Endian::Little => Endian::Big,
+    };
+    ensure!(
+        meta.platform == ModPlatform::Specific(from),
+        "{} is not a {} package",
+        meta.name,
+        endian_name(from)
+    );
+    let compression = CompressionConfig::default();
+    let mut writer = Writer::create(out)?;
+    let mut converted = 0;
+    let mut unconverted = vec![];
+    let mut manifests = vec![];
+    // RSTB sizes for the target platform, by file name in the package
+    let mut sizes = BTreeMap::new();
+    let mut data = vec![];
+    for (name, (index, ..)) in entries(&mut zip)? {
+        if name == INTEGRITY_FILE {
+            continue;
+        }
+        // Manifests wait until the sizes of all their files are known
+        if name.ends_with("manifest.yml") {
+            manifests.push((name, index));
+            continue;
+        }
+        data.clear();
+        zip.by_index(index)?.read_to_end(&mut data)?;
+        let binary = zstd::decode_all(data.as_slice())
+            .ok()
+            .and_then(|data| minicbor_ser::from_slice(&data).ok())
+            .and_then(|resource| {
+                match resource {
+                    ResourceData::Binary(binary) => Some(binary),
+                    _ => None,
+                }
+            });
+        let Some(binary) = binary else {
+            writer.copy(&mut zip, index, &name)?;
+            continue;
+        };
+        if file_endian(&binary).is_none() {
+            // The same on both platforms, apart from its RSTB size
+            sizes.insert(
+                name.clone(),
+                rstb::calc::estimate_from_slice_and_name(&binary, &name, endian.into()),
+            );
+            writer.copy(&mut zip, index, &name)?;
+            continue;
+        }
+        match convert_binary(&binary, endian) {
+            Some(binary) => {
+                sizes.insert(
+                    name.clone(),
+                    rstb::calc::estimate_from_slice_and_name(&binary, &name, endian.into()),
+                );
+                let resource = minicbor_ser::to_vec(&ResourceData::Binary(binary))
+                    .map_err(|e| anyhow_ext::anyhow!("{e:?}"))
+                    .with_context(|| format!("Failed to serialize {name}"))?;
+                writer.write(&name, &compression.compress(&name, &resource)?)?;
+                converted += 1;
+            }
+            None => {
+                writer.copy(&mut zip, index, &name)?;
+                unconverted.push(name);
+            }
+        }
+    }
+    for (name, index) in manifests {
+        data.clear();
+        zip.by_index(index)?.read_to_end(&mut data)?;
+        let mut manifest = Manifest::from_yaml(std::str::from_utf8(&data)?)
+            .with_context(|| format!("Failed to parse {name}"))?;
+        let root = name.trim_end_matches("manifest.yml");
+        manifest.rstb = std::mem::take(&mut manifest.rstb)
+            .into_keys()
+            .filter_map(|canon| {
+                let size = *sizes.get(&format!("{root}{canon}"))?;
+                Some((canon, size))
+            })
+            .collect();
+        writer.write(&name, serde_yaml::to_string(&manifest)?.as_bytes())?;
+    }
+    meta.platform = ModPlatform::Specific(endian);
+    writer
+        .finish(&meta, None)
+        .with_context(|| format!("Failed to write {}", out.display()))?;
+    log::info!(
+        "Converted {} for {} with {} files rewritten and {} left as they were",
+        meta.name,
+        endian_name(endian),
+        converted,
+        unconverted.len()
+    );
+    Ok((meta, unconverted))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use roead::byml::Byml;
+    use uk_content::{prelude::Endian, resource::ResourceData};
+
+    use crate::{test_util, Manifest, Meta, ModPlatform};
+
+    #[test]
+    fn convert_binary() {
+        let byml = Byml::Map(
+            [("Name".into(), Byml::String("Test".into()))]
+                .into_iter()
+                .collect(),
+        );
+        let big = byml.to_binary(roead::Endian::Big);
+        let little = super::convert_binary(&big, Endian::Little).unwrap();
+        assert_eq!(super::file_endian(&little), Some(Endian::Little));
+        assert_eq!(Byml::from_binary(&little).unwrap(), byml);
+        assert_eq!(
+            super::convert_binary(&little, Endian::Little).unwrap(),
+            little
+        );
+
+        let yaz0 = roead::yaz0::compress(&big);
+        let converted = super::convert_binary(&yaz0, Endian::Little).unwrap();
+        assert!(converted.starts_with(b"Yaz0"));
+        assert_eq!(roead::yaz0::decompress(&converted).unwrap(), little);
+
+        assert_eq!(super::convert_binary(b"FRES    model", Endian::Big), None);
+        assert_eq!(
+            super::convert_binary(b"AAMP data", Endian::Big).unwrap(),
+            b"AAMP data"
+        );
+    }
+
+    #[test]
+    fn convert_rstb() {
+        let resource = |data: &[u8]| {
+            let cbor = minicbor_ser::to_vec(&ResourceData::Binary(data.to_vec())).unwrap();
+            zstd::encode_all(cbor.as_slice(), 0).unwrap()
+        };
+        let byml = Byml::Map(Default::default());
+        let (big, fres) = (
+            resource(&byml.to_binary(roead::Endian::Big)),
+            resource(b"FRES\x00\x00\x00\x00 model"),
+        );
+        let meta = serde_yaml::to_string(&Meta {
+            platform: ModPlatform::Specific(Endian::Big),
+            ..test_util::meta()
+        })
+        .unwrap();
+        let manifest = serde_yaml::to_string(&Manifest {
+            rstb: [
+                ("Actor/Test.byml".into(), Some(100)),
+                ("Model/Test.bfres".into(), Some(200)),
+            ]
+            .into_iter()
+            .collect(),
+            format: crate::FORMAT_VERSION,
+            ..Default::default()
+        })
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let (path, out) = (dir.path().join("wiiu.zip"), dir.path().join("nx.zip"));
+        test_util::write_package(&path, &[
+            ("meta.yml", meta.as_bytes()),
+            ("manifest.yml", manifest.as_bytes()),
+            ("Actor/Test.byml", big.as_slice()),
+            ("Model/Test.bfres", fres.as_slice()),
+        ]);
+
+        let (_, unconverted) = super::convert(&path, Endian::Little, &out).unwrap();
+        assert_eq!(unconverted, ["Model/Test.bfres"]);
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&out).unwrap()).unwrap();
+        let mut text = String::new();
+        zip.by_name("manifest.yml")
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        let manifest = Manifest::from_yaml(&text).unwrap();
+        assert_eq!(manifest.rstb.len(), 1);
+        assert_eq!(
+            manifest.rstb["Actor/Test.byml"],
+            rstb::calc::estimate_from_slice_and_name(
+                &byml.to_binary(roead::Endian::Little),
+                "Actor/Test.byml",
+                Endian::Little.into(),
+            )
+        );
+    }
+}
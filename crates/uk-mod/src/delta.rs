@@ -0,0 +1,202 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufReader, Read, Write},
+    path::Path,
+};
+
+use anyhow_ext::{ensure, Context, Result};
+use fs_err as fs;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::{pack::zip_options, Delta, Meta, ModPlatform};
+
+pub(crate) type Archive = ZipArchive<BufReader<fs::File>>;
+
+/// Index, CRC, and size of each file in a package, by name
+pub(crate) type Entries = BTreeMap<std::string::String, (usize, u32, u64)>;
+
+pub(crate) fn open(path: &Path) -> Result<(Archive, Meta)> {
+    let mut zip = ZipArchive::new(BufReader::new(fs::File::open(path)?))
+        .with_context(|| format!("Failed to open mod package {}", path.display()))?;
+    let mut text = std::string::String::new();
+    zip.by_name("meta.yml")
+        .context("Mod missing meta file")?
+        .read_to_string(&mut text)?;
+    let meta = serde_yaml::from_str(&text).context("Failed to parse meta file")?;
+    Ok((zip, meta))
+}
+
+/// Every file in a package except the meta, which is always rewritten.
+pub(crate) fn entries(zip: &mut Archive) -> Result<Entries> {
+    let mut entries = Entries::new();
+    for i in 0..zip.len() {
+        let file = zip.by_index_raw(i)?;
+        if file.is_file() && file.name() != "meta.yml" {
+            entries.insert(file.name().to_owned(), (i, file.crc32(), file.size()));
+        }
+    }
+    Ok(entries)
+}
+
+fn write_meta(writer: &mut ZipWriter<fs::File>, meta: &Meta) -> Result<()> {
+    writer.start_file("meta.yml", zip_options())?;
+    writer.write_all(serde_yaml::to_string(meta)?.as_bytes())?;
+    Ok(())
+}
+
+/// Makes a delta package at `out` with only the files of the package at
+/// `new` which differ from the package of an earlier version at `base`. It
+/// can be applied on top of the earlier version with [`apply`].
+pub fn create(base: &Path, new: &Path, out: &Path) -> Result<Meta> {
+    let (mut base_zip, base_meta) = open(base)?;
+    let (mut new_zip, mut meta) = open(new)?;
+    ensure!(
+        base_meta.name == meta.name,
+        "Cannot make an update for {} from {}",
+        meta.name,
+        base_meta.name
+    );
+    ensure!(
+        base_meta.delta.is_none() && meta.delta.is_none(),
+        "Updates can only be made between full mod packages"
+    );
+    ensure!(
+        base_meta.platform != ModPlatform::Dual && meta.platform != ModPlatform::Dual,
+        "Updates cannot be made for dual-platform packages"
+    );
+    ensure!(
+        base_meta.version != meta.version,
+        "Both packages are version {}",
+        meta.version
+    );
+    let base_files = entries(&mut base_zip)?;
+    let new_files = entries(&mut new_zip)?;
+    let mut writer = ZipWriter::new(fs::File::create(out)?);
+    let mut changed = 0;
+    for (name, (index, crc, size)) in &new_files {
+        let same = base_files
+            .get(name)
+            .map(|(_, base_crc, base_size)| base_crc == crc && base_size == size)
+            .unwrap_or(false);
+        if !same {
+            writer.raw_copy_file(new_zip.by_index_raw(*index)?)?;
+            changed += 1;
+        }
+    }
+    meta.delta = Some(Delta {
+        base_version: base_meta.version,
+        removed:      base_files
+            .keys()
+            .filter(|name| !new_files.contains_key(*name))
+            .map(|name| name.as_str().into())
+            .collect(),
+    });
+    write_meta(&mut writer, &meta)?;
+    writer.finish()?;
+    log::info!(
+        "Created update for {} with {} changed and {} removed files",
+        meta.name,
+        changed,
+        meta.delta.as_ref().map(|d| d.removed.len()).unwrap_or_default()
+    );
+    Ok(meta)
+}
+
+/// Writes the full package for a new version of a mod to `out`, made from
+/// the package of the version it updates at `base` and the delta package at
+/// `delta`. Returns the meta of the new version.
+pub fn apply(base: &Path, delta: &Path, out: &Path) -> Result<Meta> {
+    let (mut base_zip, base_meta) = open(base)?;
+    let (mut delta_zip, mut meta) = open(delta)?;
+    let info = meta.delta.take().context("Mod package is not an update")?;
+    ensure!(
+        base_meta.name == meta.name,
+        "This update is for {}, not {}",
+        meta.name,
+        base_meta.name
+    );
+    ensure!(
+        base_meta.version == info.base_version,
+        "This update is for version {} of {}, but version {} is installed",
+        info.base_version,
+        meta.name,
+        base_meta.version
+    );
+    let updated = entries(&mut delta_zip)?;
+    let mut writer = ZipWriter::new(fs::File::create(out)?);
+    for (name, (index, ..)) in entries(&mut base_zip)? {
+        if !updated.contains_key(&name) && !info.removed.contains(name.as_str()) {
+            writer.raw_copy_file(base_zip.by_index_raw(index)?)?;
+        }
+    }
+    for (index, ..) in updated.into_values() {
+        writer.raw_copy_file(delta_zip.by_index_raw(index)?)?;
+    }
+    write_meta(&mut writer, &meta)?;
+    writer.finish()?;
+    log::info!(
+        "Updated {} from version {} to {}",
+        meta.name,
+        info.base_version,
+        meta.version
+    );
+    Ok(meta)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_util::{self, read_files, write_package},
+        Meta,
+    };
+
+    fn meta(version: &str) -> String {
+        serde_yaml::to_string(&Meta {
+            version: version.into(),
+            ..test_util::meta()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn create_and_apply() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, new, delta, updated) = (
+            dir.path().join("old.zip"),
+            dir.path().join("new.zip"),
+            dir.path().join("delta.zip"),
+            dir.path().join("updated.zip"),
+        );
+        write_package(&old, &[
+            ("meta.yml", meta("1.0.0").as_str()),
+            ("Actor/A.bxml", "a"),
+            ("Actor/B.bxml", "b"),
+            ("Actor/C.bxml", "c"),
+        ]);
+        write_package(&new, &[
+            ("meta.yml", meta("1.1.0").as_str()),
+            ("Actor/A.bxml", "a"),
+            ("Actor/C.bxml", "c2"),
+            ("Actor/D.bxml", "d"),
+        ]);
+        let meta = super::create(&old, &new, &delta).unwrap();
+        let info = meta.delta.unwrap();
+        assert_eq!(info.base_version, "1.0.0");
+        assert_eq!(
+            info.removed.iter().map(|f| f.as_str()).collect::<Vec<_>>(),
+            ["Actor/B.bxml"]
+        );
+        assert_eq!(
+            read_files(&delta),
+            [("Actor/C.bxml", "c2"), ("Actor/D.bxml", "d")]
+                .into_iter()
+                .map(|(n, d)| (n.to_owned(), d.to_owned()))
+                .collect()
+        );
+        let meta = super::apply(&old, &delta, &updated).unwrap();
+        assert_eq!(meta.version, "1.1.0");
+        assert!(meta.delta.is_none());
+        assert_eq!(read_files(&updated), read_files(&new));
+        assert!(super::apply(&new, &delta, &dir.path().join("bad.zip")).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use std::{io::Read, path::Path};
+
+use anyhow_ext::{ensure, Context, Result};
+use fs_err as fs;
+use uk_content::prelude::Endian;
+use zip::ZipWriter;
+
+use crate::{
+    delta::{entries, open, Archive},
+    integrity::{Integrity, SigningKey, INTEGRITY_FILE},
+    pack::zip_options,
+    Meta, ModPlatform,
+};
+
+/// The folder in a dual-platform package for the files which only apply to
+/// one platform, which take the place of the shared file of the same name.
+pub fn variant_folder(endian: Endian) -> &'static str {
+    match endian {
+        Endian::Big => "wiiu",
+        Endian::Little => "nx",
+    }
+}
+
+/// Writes packages with hashes of every file, like the mod packer does.
+pub(crate) struct Writer {
+    zip: ZipWriter<fs::File>,
+    integrity: Integrity,
+}
+
+impl Writer {
+    pub(crate) fn create(path: &Path) -> Result<Self> {
+        Ok(Self {
+            zip: ZipWriter::new(fs::File::create(path)?),
+            integrity: Integrity::default(),
+        })
+    }
+
+    pub(crate) fn copy(&mut self, zip: &mut Archive, index: usize, name: &str) -> Result<()> {
+        let mut data = vec![];
+        zip.by_index(index)?.read_to_end(&mut data)?;
+        self.integrity.add(name, &data);
+        self.zip
+            .raw_copy_file_rename(zip.by_index_raw(index)?, name)?;
+        Ok(())
+    }
+
+    pub(crate) fn write(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        self.integrity.add(name, data);
+        self.zip.start_file(name, zip_options())?;
+        std::io::Write::write_all(&mut self.zip, data)?;
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self, meta: &Meta, key: Option<&SigningKey>) -> Result<()> {
+        let opts = zip_options();
+        let text = serde_yaml::to_string(meta)?;
+        self.zip.start_file("meta.yml", opts)?;
+        std::io::Write::write_all(&mut self.zip, text.as_bytes())?;
+        self.integrity.add("meta.yml", text.as_bytes());
+        if let Some(key) = key {
+            self.integrity.sign(key);
+        }
+        self.zip.start_file(INTEGRITY_FILE, opts)?;
+        std::io::Write::write_all(
+            &mut self.zip,
+            serde_yaml::to_string(&self.integrity)?.as_bytes(),
+        )?;
+        self.zip.finish()?;
+        Ok(())
+    }
+}
+
+/// Combines a Wii U and a Switch package of the same mod into one package
+/// for both at `out`. Files which are the same for both are only stored once;
+/// the rest go in each platform's [`variant_folder`]. The combined package is
+/// signed with `key` if given, since the originals' signatures don't cover it.
+pub fn combine(wiiu: &Path, switch: &Path, out: &Path, key: Option<&SigningKey>) -> Result<Meta> {
+    let (mut wiiu_zip, wiiu_meta) = open(wiiu)?;
+    let (mut nx_zip, mut meta) = open(switch)?;
+    ensure!(
+        wiiu_meta.platform == ModPlatform::Specific(Endian::Big)
+            && meta.platform == ModPlatform::Specific(Endian::Little),
+        "A dual-platform package needs one Wii U and one Switch package"
+    );
+    ensure!(
+        wiiu_meta.name == meta.name && wiiu_meta.version == meta.version,
+        "Cannot combine {} {} with {} {}",
+        wiiu_meta.name,
+        wiiu_meta.version,
+        meta.name,
+        meta.version
+    );
+    let mut wiiu_files = entries(&mut wiiu_zip)?;
+    let mut nx_files = entries(&mut nx_zip)?;
+    wiiu_files.remove(INTEGRITY_FILE);
+    nx_files.remove(INTEGRITY_FILE);
+    let mut writer = Writer::create(out)?;
+    let mut shared = 0;
+    for (name, (index, crc, size)) in &wiiu_files {
+        let same = nx_files
+            .get(name)
+            .map(|(_, nx_crc, nx_size)| nx_crc == crc && nx_size == size)
+            .unwrap_or(false);
+        if same {
+            writer.copy(&mut wiiu_zip, *index, name)?;
+            nx_files.remove(name);
+            shared += 1;
+        } else {
+            let name = format!("{}/{name}", variant_folder(Endian::Big));
+            writer.copy(&mut wiiu_zip, *index, &name)?;
+        }
+    }
+    for (name, (index, ..)) in &nx_files {
+        let name = format!("{}/{name}", variant_folder(Endian::Little));
+        writer.copy(&mut nx_zip, *index, &name)?;
+    }
+    meta.platform = ModPlatform::Dual;
+    writer.finish(&meta, key)?;
+    log::info!(
+        "Combined {} with {} shared files, {} Wii U only, and {} Switch only",
+        meta.name,
+        shared,
+        wiiu_files.len() - shared,
+        nx_files.len()
+    );
+    Ok(meta)
+}
+
+/// Writes a package with only the files of a dual-platform package which
+/// apply to one platform to `out`, laid out like any other package for that
+/// platform. Returns its meta.
+pub fn extract(path: &Path, endian: Endian, out: &Path) -> Result<Meta> {
+    let (mut zip, mut meta) = open(path)?;
+    ensure!(
+        meta.platform == ModPlatform::Dual,
+        "{} is not a dual-platform package",
+        meta.name
+    );
+    let own = format!("{}/", variant_folder(endian));
+    let other = format!(
+        "{}/",
+        variant_folder(match endian {
+            Endian::Big => Endian::Little,
+            Endian::Little => Endian::Big,
+        })
+    );
+    let files = entries(&mut zip)?;
+    let mut writer = Writer::create(out)?;
+    for (name, (index, ..)) in &files {
+        if name == INTEGRITY_FILE || name.starts_with(&other) {
+            continue;
+        }
+        if let Some(name) = name.strip_prefix(&own) {
+            writer.copy(&mut zip, *index, name)?;
+        } else if !files.contains_key(&format!("{own}{name}")) {
+            writer.copy(&mut zip, *index, name)?;
+        }
+    }
+    meta.platform = ModPlatform::Specific(endian);
+    writer
+        .finish(&meta, None)
+        .with_context(|| format!("Failed to write {}", out.display()))?;
+    log::info!("Extracted {:?} files of {}", endian, meta.name);
+    Ok(meta)
+}
+
+#[cfg(test)]
+mod tests {
+    use uk_content::prelude::Endian;
+
+    use crate::{
+        test_util::{self, read_files, write_package},
+        Meta, ModPlatform,
+    };
+
+    fn meta(endian: Endian) -> String {
+        serde_yaml::to_string(&Meta {
+            platform: ModPlatform::Specific(endian),
+            ..test_util::meta()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn combine_and_extract() {
+        let dir = tempfile::tempdir().unwrap();
+        let (wiiu, nx, dual, extracted) = (
+            dir.path().join("wiiu.zip"),
+            dir.path().join("nx.zip"),
+            dir.path().join("dual.zip"),
+            dir.path().join("extracted.zip"),
+        );
+        write_package(&wiiu, &[
+            ("meta.yml", meta(Endian::Big).as_str()),
+            ("manifest.yml", "content: []"),
+            ("Actor/A.bxml", "a"),
+            ("Model/B.bfres", "wiiu model"),
+            ("Sound/C.bfstm", "wiiu only"),
+        ]);
+        write_package(&nx, &[
+            ("meta.yml", meta(Endian::Little).as_str()),
+            ("manifest.yml", "content: []"),
+            ("Actor/A.bxml", "a"),
+            ("Model/B.bfres", "nx model"),
+        ]);
+        let meta = super::combine(&wiiu, &nx, &dual, None).unwrap();
+        assert_eq!(meta.platform, ModPlatform::Dual);
+        assert_eq!(
+            read_files(&dual)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            [
+                "Actor/A.bxml",
+                "manifest.yml",
+                "nx/Model/B.bfres",
+                "wiiu/Model/B.bfres",
+                "wiiu/Sound/C.bfstm"
+            ]
+        );
+        for (endian, original) in [(Endian::Big, &wiiu), (Endian::Little, &nx)] {
+            let meta = super::extract(&dual, endian, &extracted).unwrap();
+            assert_eq!(meta.platform, ModPlatform::Specific(endian));
+            assert_eq!(read_files(&extracted), read_files(original));
+            assert_eq!(
+                crate::integrity::verify(&extracted).unwrap(),
+                crate::integrity::Verified::Intact
+            );
+        }
+        assert!(super::combine(&nx, &wiiu, &dual, None).is_err());
+    }
+}
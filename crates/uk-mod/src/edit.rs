@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use anyhow_ext::{ensure, Context, Result};
+
+use crate::{
+    delta::{entries, open},
+    dual::Writer,
+    integrity::INTEGRITY_FILE,
+    Meta,
+};
+
+/// Writes a copy of the package at `path` to `out` with its meta replaced,
+/// without unpacking anything else. The hashes are updated to match, but any
+/// signature is dropped, since it only covers the original meta. The options
+/// and platform can't be changed this way, since the files depend on them.
+pub fn replace_meta(path: &Path, meta: &Meta, out: &Path) -> Result<()> {
+    let (mut zip, old) = open(path)?;
+    ensure!(
+        meta.options == old.options && meta.platform == old.platform,
+        "The options and platform of {} can only be changed by packaging it again",
+        old.name
+    );
+    ensure!(
+        meta.delta.is_none() && old.delta.is_none(),
+        "The meta of update packages cannot be edited"
+    );
+    let mut writer = Writer::create(out)?;
+    for (name, (index, ..)) in entries(&mut zip)? {
+        if name != INTEGRITY_FILE {
+            writer.copy(&mut zip, index, &name)?;
+        }
+    }
+    writer
+        .finish(meta, None)
+        .with_context(|| format!("Failed to write {}", out.display()))?;
+    log::info!("Replaced meta of {}", meta.name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{integrity::Verified, test_util, Meta};
+
+    #[test]
+    fn replace_meta() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, out) = (dir.path().join("mod.zip"), dir.path().join("edited.zip"));
+        let meta = serde_yaml::to_string(&test_util::meta()).unwrap();
+        test_util::write_package(&path, &[
+            ("meta.yml", meta.as_str()),
+            ("Actor/A.bxml", "a"),
+        ]);
+
+        let mut meta = Meta::from_mod(&path).unwrap();
+        meta.name = "Renamed Mod".into();
+        meta.version = "1.0.1".into();
+        super::replace_meta(&path, &meta, &out).unwrap();
+        assert_eq!(Meta::from_mod(&out).unwrap(), meta);
+        assert_eq!(crate::integrity::verify(&out).unwrap(), Verified::Intact);
+
+        meta.platform = crate::ModPlatform::Dual;
+        assert!(super::replace_meta(&path, &meta, &out).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufReader, Read, Write},
+    path::Path,
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use ring::{
+    digest::{digest, Context as DigestContext, SHA256},
+    rand::SystemRandom,
+    signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519},
+};
+use serde::{Deserialize, Serialize};
+use zip::ZipArchive;
+
+/// The name of the file in a package which holds its hashes and signature
+pub const INTEGRITY_FILE: &str = "integrity.yml";
+
+fn to_hex(bytes: &[u8]) -> std::string::String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(text: &str) -> Result<Vec<u8>> {
+    anyhow_ext::ensure!(text.len() % 2 == 0, "Invalid hex string");
+    (0..text.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(text.get(i..i + 2).context("Invalid hex string")?, 16)
+                .context("Invalid hex string")
+        })
+        .collect()
+}
+
+/// A short, readable form of a public key for showing to users.
+pub fn fingerprint(public_key: &str) -> std::string::String {
+    public_key
+        .as_bytes()
+        .chunks(4)
+        .take(4)
+        .map(|c| std::str::from_utf8(c).unwrap_or_default().to_uppercase())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// An ed25519 key for signing mod packages.
+pub struct SigningKey(Ed25519KeyPair);
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SigningKey")
+            .field(&self.public_key())
+            .finish()
+    }
+}
+
+impl SigningKey {
+    /// Generates a new key and saves it to `path` in PKCS#8 format.
+    pub fn generate(path: &Path) -> Result<Self> {
+        let doc = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map_err(|_| anyhow_ext::anyhow!("Failed to generate signing key"))?;
+        fs::write(path, doc.as_ref())?;
+        Self::from_pkcs8(doc.as_ref())
+    }
+
+    pub fn from_pkcs8(data: &[u8]) -> Result<Self> {
+        Ed25519KeyPair::from_pkcs8(data)
+            .map(Self)
+            .map_err(|e| anyhow_ext::anyhow!("Invalid signing key: {e}"))
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::from_pkcs8(&fs::read(path)?)
+            .with_context(|| format!("Failed to load signing key {}", path.display()))
+    }
+
+    /// The public half of the key, in hex.
+    pub fn public_key(&self) -> std::string::String {
+        to_hex(self.0.public_key().as_ref())
+    }
+}
+
+/// The SHA-256 hash of every file in a package, and optionally an ed25519
+/// signature over them, so changes to the package after it was made can be
+/// detected.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Integrity {
+    pub files: BTreeMap<std::string::String, std::string::String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<std::string::String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<std::string::String>,
+}
+
+/// Hashes data on its way to another writer, so files too large to keep in
+/// memory can be hashed as they are written.
+pub(crate) struct HashingWriter<W> {
+    inner:   W,
+    context: DigestContext,
+}
+
+impl<W> HashingWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            context: DigestContext::new(&SHA256),
+        }
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.context.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// What checking a package found, if it was not broken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verified {
+    /// The package has no hashes to check, as with older or unpacked mods
+    Unchecked,
+    /// The files match their hashes, but the package is not signed
+    Intact,
+    /// The files match their hashes, which are signed by this public key
+    Signed(std::string::String),
+}
+
+impl Integrity {
+    pub fn add(&mut self, name: &str, data: &[u8]) {
+        self.files
+            .insert(name.into(), to_hex(digest(&SHA256, data).as_ref()));
+    }
+
+    /// Adds the hash of a file written through a [`HashingWriter`].
+    pub(crate) fn add_hashed<W>(&mut self, name: &str, writer: HashingWriter<W>) {
+        self.files
+            .insert(name.into(), to_hex(writer.context.finish().as_ref()));
+    }
+
+    /// The data covered by the signature: each file and its hash, one per
+    /// line, in order.
+    fn signed_data(&self) -> Vec<u8> {
+        self.files
+            .iter()
+            .flat_map(|(name, hash)| [name.as_bytes(), b"\0", hash.as_bytes(), b"\n"])
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    pub fn sign(&mut self, key: &SigningKey) {
+        self.public_key = Some(key.public_key());
+        self.signature = Some(to_hex(key.0.sign(&self.signed_data()).as_ref()));
+    }
+
+    fn check_signature(&self) -> Result<Verified> {
+        match (self.public_key.as_ref(), self.signature.as_ref()) {
+            (Some(public_key), Some(signature)) => {
+                UnparsedPublicKey::new(&ED25519, from_hex(public_key)?)
+                    .verify(&self.signed_data(), &from_hex(signature)?)
+                    .map_err(|_| anyhow_ext::anyhow!("Mod package has an invalid signature"))?;
+                Ok(Verified::Signed(public_key.clone()))
+            }
+            (None, None) => Ok(Verified::Intact),
+            _ => anyhow_ext::bail!("Mod package has an incomplete signature"),
+        }
+    }
+}
+
+/// Checks the files of a mod package against the hashes it was made with,
+/// and its signature if it has one. Fails if anything was changed, added, or
+/// removed since, listing what. Update packages can't be checked until they
+/// have been applied, since they only hold some of the files.
+pub fn verify(path: &Path) -> Result<Verified> {
+    if path.is_dir() {
+        return Ok(Verified::Unchecked);
+    }
+    let mut zip = ZipArchive::new(BufReader::new(fs::File::open(path)?))
+        .with_context(|| format!("Failed to open mod package {}", path.display()))?;
+    let integrity: Integrity = match zip.by_name(INTEGRITY_FILE) {
+        Ok(file) => serde_yaml::from_reader(file).context("Failed to parse integrity file")?,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(Verified::Unchecked),
+        Err(e) => return Err(e.into()),
+    };
+    let mut actual = Integrity::default();
+    let mut data = vec![];
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i)?;
+        if file.is_file() && file.name() != INTEGRITY_FILE {
+            data.clear();
+            file.read_to_end(&mut data)?;
+            actual.add(file.name(), &data);
+        }
+    }
+    let mut problems = vec![];
+    for (name, hash) in &integrity.files {
+        match actual.files.get(name) {
+            None => problems.push(format!("{name} is missing")),
+            Some(actual) if actual != hash => problems.push(format!("{name} has been modified")),
+            Some(_) => (),
+        }
+    }
+    problems.extend(
+        actual
+            .files
+            .keys()
+            .filter(|name| !integrity.files.contains_key(*name))
+            .map(|name| format!("{name} was added")),
+    );
+    if !problems.is_empty() {
+        anyhow_ext::bail!(
+            "{} does not match the files it was packaged with:\n{}",
+            path.display(),
+            problems
+                .iter()
+                .map(|p| format!("• {p}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+    integrity.check_signature()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn write_package(path: &Path, files: &[(&str, &str)], integrity: &Integrity) {
+        let integrity = serde_yaml::to_string(integrity).unwrap();
+        let mut files = files.to_vec();
+        files.push((INTEGRITY_FILE, integrity.as_str()));
+        crate::test_util::write_package(path, &files);
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = SigningKey::generate(&dir.path().join("key.pk8")).unwrap();
+        assert_eq!(
+            SigningKey::open(&dir.path().join("key.pk8"))
+                .unwrap()
+                .public_key(),
+            key.public_key()
+        );
+        let files = [("meta.yml", "name: Test"), ("Actor/A.bxml", "a")];
+        let mut integrity = Integrity::default();
+        for (name, data) in files {
+            integrity.add(name, data.as_bytes());
+        }
+        let path = dir.path().join("intact.zip");
+        write_package(&path, &files, &integrity);
+        assert_eq!(verify(&path).unwrap(), Verified::Intact);
+
+        integrity.sign(&key);
+        write_package(&path, &files, &integrity);
+        assert_eq!(verify(&path).unwrap(), Verified::Signed(key.public_key()));
+
+        let path = dir.path().join("modified.zip");
+        write_package(
+            &path,
+            &[("meta.yml", "name: Test"), ("Actor/A.bxml", "b")],
+            &integrity,
+        );
+        assert!(verify(&path).is_err());
+
+        let other = SigningKey::generate(&dir.path().join("other.pk8")).unwrap();
+        integrity.public_key = Some(other.public_key());
+        let path = dir.path().join("forged.zip");
+        write_package(&path, &files, &integrity);
+        assert!(verify(&path).is_err());
+    }
+
+    #[test]
+    fn hashing_writer() {
+        let mut writer = HashingWriter::new(vec![]);
+        writer.write_all(b"some ").unwrap();
+        writer.write_all(b"data").unwrap();
+        let mut integrity = Integrity::default();
+        integrity.add_hashed("streamed", writer);
+        integrity.add("whole", b"some data");
+        assert_eq!(integrity.files["streamed"], integrity.files["whole"]);
+    }
+}
@@ -1,6 +1,6 @@
 #![feature(result_option_inspect, seek_stream_len, let_chains, once_cell)]
 use std::{
-    collections::BTreeSet,
+    collections::{BTreeMap, BTreeSet},
     path::{Path, PathBuf},
 };
 
@@ -8,22 +8,75 @@ use anyhow_ext::Context;
 use serde::{Deserialize, Serialize};
 use smartstring::alias::String;
 use uk_content::{
+    canonicalize,
+    constants::Language,
     prelude::Endian,
     util::{HashSet, IndexMap},
 };
+pub mod convert;
+pub mod delta;
+pub mod dual;
+pub mod edit;
+pub mod integrity;
+pub mod lint;
 pub mod pack;
+pub mod split;
+#[cfg(test)]
+mod test_util;
 pub mod unpack;
 pub use zstd;
 
+/// The package format written by this version of UKMM. Bump it whenever
+/// packages change in a way older versions can't read, and keep reading the
+/// older formats in [`Manifest::from_yaml`] and the resource decoders.
+///
+/// - 0: packages from before the format was recorded, whose `BarslistInfo`
+///   entries may be bare file lists
+/// - 1: the format is recorded in the manifest
+/// - 2: `BarslistInfo` entries are always tagged as file lists or track tables
+pub const FORMAT_VERSION: u32 = 2;
+
+#[inline(always)]
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Manifest {
     #[serde(rename = "content")]
     pub content_files: BTreeSet<String>,
     #[serde(rename = "aoc")]
     pub aoc_files:     BTreeSet<String>,
+    /// RSTB sizes of the unmergeable resources, by canonical path, worked out
+    /// when the mod was packaged so they needn't be estimated on every merge
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rstb:          BTreeMap<String, Option<u32>>,
+    /// The package format version, for manifests in packages
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub format:        u32,
 }
 
 impl Manifest {
+    /// Reads a package manifest of any format version up to
+    /// [`FORMAT_VERSION`].
+    pub fn from_yaml(text: &str) -> anyhow_ext::Result<Self> {
+        let manifest: Self = serde_yaml::from_str(text)?;
+        match manifest.format {
+            FORMAT_VERSION => Ok(manifest),
+            // Older manifests only lack the format and optional fields like
+            // `rstb`. Their resources differ in encoding alone, and the
+            // resource decoders still accept the old encodings, as
+            // `BarslistEntry` does for bare file lists.
+            format if format < FORMAT_VERSION => Ok(manifest),
+            format => {
+                anyhow_ext::bail!(
+                    "This mod uses package format {format}, but this version of UKMM only reads \
+                     up to format {FORMAT_VERSION}. Update UKMM to install it."
+                )
+            }
+        }
+    }
+
     pub fn resources(&self) -> impl Iterator<Item = String> + '_ {
         self.content_files
             .iter()
@@ -41,9 +94,17 @@ impl Manifest {
         self.aoc_files.extend(other.aoc_files.iter().cloned());
     }
 
+    /// Removes every file which is also in `other`.
+    pub fn remove(&mut self, other: &Manifest) {
+        self.content_files
+            .retain(|f| !other.content_files.contains(f));
+        self.aoc_files.retain(|f| !other.aoc_files.contains(f));
+    }
+
     pub fn clear(&mut self) {
         self.content_files.clear();
         self.aoc_files.clear();
+        self.rstb.clear();
     }
 
     pub fn is_empty(&self) -> bool {
@@ -56,7 +117,12 @@ pub struct ModOption {
     pub name: String,
     pub description: String,
     pub path: PathBuf,
+    /// Options which must also be enabled to use this one
+    #[serde(default)]
     pub requires: Vec<PathBuf>,
+    /// Options which cannot be enabled together with this one
+    #[serde(default)]
+    pub excludes: Vec<PathBuf>,
 }
 
 impl ModOption {
@@ -212,6 +278,145 @@ pub static CATEGORIES: &[&str] = &[
 pub enum ModPlatform {
     Specific(Endian),
     Universal,
+    /// Carries separate files for Wii U and Switch where they differ. Only
+    /// the current platform's are kept when installing.
+    Dual,
+}
+
+/// An emulator setting a mod needs to work properly, such as extra memory
+/// in Cemu or FPS++ being turned off.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EmulatorFlag {
+    /// The emulator this applies to, or empty for any
+    #[serde(default)]
+    pub emulator: String,
+    /// The setting, e.g. `FPS++`
+    pub setting: String,
+    /// The value it needs, e.g. `Disabled`
+    pub value: String,
+}
+
+impl std::fmt::Display for EmulatorFlag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.emulator.is_empty() {
+            write!(f, "{}: ", self.emulator)?;
+        }
+        write!(f, "{} = {}", self.setting, self.value)
+    }
+}
+
+/// Another mod which must be installed and enabled for this one to work.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Dependency {
+    /// The name of the mod
+    pub name: String,
+    /// The versions which work, as a semver requirement like `>=1.2, <2`.
+    /// Empty or `*` means any version.
+    #[serde(default)]
+    pub version: String,
+}
+
+impl Dependency {
+    fn requirement(&self) -> anyhow_ext::Result<semver::VersionReq> {
+        match self.version.trim() {
+            "" => Ok(semver::VersionReq::STAR),
+            req => semver::VersionReq::parse(req).with_context(|| {
+                format!("Invalid version requirement for {}: {}", self.name, req)
+            }),
+        }
+    }
+
+    /// Whether a version of the mod satisfies this dependency. Versions are
+    /// parsed leniently, so `1.2` counts as `1.2.0`.
+    pub fn matches(&self, version: &str) -> anyhow_ext::Result<bool> {
+        let req = self.requirement()?;
+        let version = lenient_semver::parse(version)
+            .map_err(|e| anyhow_ext::anyhow!("Invalid version {version} of {}: {e}", self.name))?;
+        Ok(req.matches(&version))
+    }
+}
+
+impl std::fmt::Display for Dependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.version.trim() {
+            "" | "*" => write!(f, "{}", self.name),
+            req => write!(f, "{} ({})", self.name, req),
+        }
+    }
+}
+
+/// Marks a package as an update with only the files changed since an earlier
+/// version of the mod, to be applied on top of that version.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Delta {
+    /// The version this package updates from
+    pub base_version: String,
+    /// Files in the earlier version's package which this version dropped
+    #[serde(default)]
+    pub removed: BTreeSet<String>,
+}
+
+/// What changed in one version of a mod, so users can see what an update
+/// brings.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ChangelogEntry {
+    pub version: String,
+    /// The changes, in Markdown
+    pub changes: String,
+}
+
+/// Someone who worked on a mod besides its author.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Credit {
+    pub name: String,
+    /// What they did, e.g. `Models` or `Testing`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub role: String,
+    /// A page about them, like their profile on a mod site
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url:  Option<String>,
+}
+
+/// What others may do with a mod's files. Anything left unset means to ask
+/// the author.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Permissions {
+    /// Whether the mod may be uploaded elsewhere
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redistribute: Option<bool>,
+    /// Whether others may release changed versions of the mod
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modify:       Option<bool>,
+    /// Whether the mod's assets may be used in other mods
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reuse_assets: Option<bool>,
+}
+
+impl Permissions {
+    #[inline]
+    pub fn is_unset(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Each permission with a short label, for display.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, Option<bool>)> {
+        [
+            ("Redistribution", self.redistribute),
+            ("Modification", self.modify),
+            ("Asset reuse", self.reuse_assets),
+        ]
+        .into_iter()
+    }
+}
+
+/// A mod's name and description in another language. Either may be left
+/// out to keep the original.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetaTranslation {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
 }
 
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -227,6 +432,89 @@ pub struct Meta {
     #[serde(rename = "option_groups")]
     pub options: Vec<OptionGroup>,
     pub masters: IndexMap<usize, (String, String)>,
+    #[serde(default)]
+    pub emulator_flags: Vec<EmulatorFlag>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<Dependency>,
+    /// The file name of the preview image in the package, e.g. `thumb.png`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>,
+    /// A page about the author, like their profile on a mod site
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author_url: Option<String>,
+    /// The mod's home page, if it has one besides the download URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    /// A full write-up for the mod page in Markdown, where `description` is
+    /// the summary
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub long_description: String,
+    /// What changed in each version released so far, newest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changelog: Vec<ChangelogEntry>,
+    /// The earliest game version the mod works with, e.g. `1.5.0`, on the
+    /// platform it is for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_version: Option<String>,
+    /// Whether the mod needs the DLC
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub requires_dlc: bool,
+    /// Glob patterns for standalone files users may choose to leave out when
+    /// installing, like single music tracks or textures, matched without case
+    /// against their canonical paths, e.g. `Sound/Resource/Stream/*.bfstm`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub selectable: Vec<String>,
+    /// Where to check for newer versions: the URL of a JSON feed naming the
+    /// latest `version`, or a GameBanana mod ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_url: Option<String>,
+    /// The name and description in other languages, shown instead of the
+    /// originals to users playing in that language
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub translations: IndexMap<Language, MetaTranslation>,
+    /// The license the mod is shared under, ideally as an SPDX identifier
+    /// like `CC-BY-4.0`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// Everyone else who worked on the mod
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub credits: Vec<Credit>,
+    #[serde(default, skip_serializing_if = "Permissions::is_unset")]
+    pub permissions: Permissions,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta: Option<Delta>,
+}
+
+impl Default for Meta {
+    fn default() -> Self {
+        Self {
+            name: Default::default(),
+            version: Default::default(),
+            author: Default::default(),
+            category: Default::default(),
+            description: Default::default(),
+            platform: ModPlatform::Universal,
+            url: None,
+            options: vec![],
+            masters: Default::default(),
+            emulator_flags: Default::default(),
+            dependencies: Default::default(),
+            thumbnail: None,
+            author_url: None,
+            homepage: None,
+            long_description: Default::default(),
+            changelog: Default::default(),
+            game_version: None,
+            requires_dlc: false,
+            selectable: Default::default(),
+            update_url: None,
+            translations: Default::default(),
+            license: None,
+            credits: Default::default(),
+            permissions: Default::default(),
+            delta: None,
+        }
+    }
 }
 
 #[allow(clippy::derived_hash_with_manual_eq)]
@@ -255,17 +543,239 @@ impl Meta {
         }
     }
 
+    /// The translation for `lang`, or else for the same language in another
+    /// region, like `EUfr` for `USfr`.
+    pub fn translation(&self, lang: Language) -> Option<&MetaTranslation> {
+        self.translations.get(&lang).or_else(|| {
+            self.translations
+                .iter()
+                .find(|(l, _)| l.short() == lang.short())
+                .map(|(_, t)| t)
+        })
+    }
+
+    /// The name to show to users playing in `lang`. The original name still
+    /// identifies the mod everywhere else.
+    pub fn display_name(&self, lang: Language) -> &str {
+        self.translation(lang)
+            .map(|t| t.name.as_str())
+            .filter(|name| !name.is_empty())
+            .unwrap_or(self.name.as_str())
+    }
+
+    /// The description to show to users playing in `lang`.
+    pub fn display_description(&self, lang: Language) -> &str {
+        self.translation(lang)
+            .map(|t| t.description.as_str())
+            .filter(|desc| !desc.is_empty())
+            .unwrap_or(self.description.as_str())
+    }
+
     #[inline(always)]
     pub fn parse(file: impl AsRef<Path>) -> anyhow_ext::Result<Self> {
         fs_err::read_to_string(file.as_ref())
             .context("Failed to read meta file")
             .and_then(|s| serde_yaml::from_str(&s).context("Failed to parse meta file"))
     }
+
+    /// What the mod needs which a game dump of version `game_version`, if
+    /// known, and with or without the DLC, lacks. Empty if nothing.
+    pub fn missing_requirements(
+        &self,
+        game_version: Option<&str>,
+        has_dlc: bool,
+    ) -> Vec<std::string::String> {
+        let mut missing = vec![];
+        if let Some(required) = self.game_version.as_deref()
+            && let Some(version) = game_version
+            && let Ok(required_semver) = lenient_semver::parse(required)
+            && let Ok(semver) = lenient_semver::parse(version)
+            && semver < required_semver
+        {
+            missing.push(format!(
+                "game version {required} or later, but the game dump is version {version}"
+            ));
+        }
+        if self.requires_dlc && !has_dlc {
+            missing.push("the DLC, but the game dump does not include it".into());
+        }
+        missing
+    }
+
+    /// The files in `manifest` which users may leave out when installing, as
+    /// matched by the `selectable` patterns.
+    pub fn selectable_files(&self, manifest: &Manifest) -> anyhow_ext::Result<Manifest> {
+        let mut files = Manifest::default();
+        if self.selectable.is_empty() {
+            return Ok(files);
+        }
+        let patterns =
+            pack::Excludes::new(&self.selectable).context("Invalid selectable file pattern")?;
+        files.content_files = manifest
+            .content_files
+            .iter()
+            .filter(|f| patterns.is_excluded(&canonicalize(f.as_str())))
+            .cloned()
+            .collect();
+        files.aoc_files = manifest
+            .aoc_files
+            .iter()
+            .filter(|f| patterns.is_excluded(&canonicalize(format!("Aoc/0010/{f}"))))
+            .cloned()
+            .collect();
+        Ok(files)
+    }
+
+    /// The changelog entry for this version, if it has one.
+    pub fn current_changes(&self) -> Option<&ChangelogEntry> {
+        self.changelog.iter().find(|e| e.version == self.version)
+    }
+
+    /// Sets what changed in this version, replacing any entry it already
+    /// had. Blank changes remove the entry.
+    pub fn set_changes(&mut self, changes: &str) {
+        self.changelog.retain(|e| e.version != self.version);
+        if !changes.trim().is_empty() {
+            self.changelog.push(ChangelogEntry {
+                version: self.version.clone(),
+                changes: changes.trim().into(),
+            });
+        }
+        self.sort_changelog();
+    }
+
+    /// Adds the entries from the meta of an earlier version which this one
+    /// doesn't have yet, so the changelog builds up from release to release.
+    pub fn merge_changelog(&mut self, earlier: &Meta) {
+        for entry in &earlier.changelog {
+            if !self.changelog.iter().any(|e| e.version == entry.version) {
+                self.changelog.push(entry.clone());
+            }
+        }
+        self.sort_changelog();
+    }
+
+    /// Keeps the changelog newest first. Versions which are not semver go
+    /// last, in the order they were.
+    fn sort_changelog(&mut self) {
+        self.changelog.sort_by_key(|e| {
+            std::cmp::Reverse(lenient_semver::parse(e.version.as_str()).ok())
+        });
+    }
+
+    /// The changelog entries for the versions after `version`, newest first,
+    /// to show what updating from it changed.
+    pub fn changes_since(&self, version: &str) -> Vec<&ChangelogEntry> {
+        match lenient_semver::parse(version) {
+            Ok(since) => {
+                self.changelog
+                    .iter()
+                    .filter(|e| {
+                        lenient_semver::parse(e.version.as_str())
+                            .map(|v| v > since)
+                            .unwrap_or(false)
+                    })
+                    .collect()
+            }
+            Err(_) => {
+                self.changelog
+                    .iter()
+                    .take_while(|e| e.version != version)
+                    .collect()
+            }
+        }
+    }
+
+    pub fn option(&self, path: impl AsRef<Path>) -> Option<&ModOption> {
+        let path = path.as_ref();
+        self.options
+            .iter()
+            .flat_map(|group| group.options().iter())
+            .find(|opt| opt.path == path)
+    }
+
+    /// Check that option requires/excludes rules only refer to options which
+    /// exist and do not contradict themselves.
+    pub fn validate_options(&self) -> anyhow_ext::Result<()> {
+        for group in &self.options {
+            for opt in group.options() {
+                for (rule, paths) in [("require", &opt.requires), ("exclude", &opt.excludes)] {
+                    for path in paths {
+                        if *path == opt.path {
+                            anyhow_ext::bail!("Option \"{}\" cannot {} itself", opt.name, rule);
+                        }
+                        if self.option(path).is_none() {
+                            anyhow_ext::bail!(
+                                "Option \"{}\" tries to {} unknown option {}",
+                                opt.name,
+                                rule,
+                                path.display()
+                            );
+                        }
+                    }
+                }
+                if let Some(path) = opt.requires.iter().find(|p| opt.excludes.contains(p)) {
+                    anyhow_ext::bail!(
+                        "Option \"{}\" both requires and excludes option {}",
+                        opt.name,
+                        path.display()
+                    );
+                }
+                if let OptionGroup::Exclusive(group) = group
+                    && let Some(other) = group
+                        .options
+                        .iter()
+                        .find(|other| opt.requires.contains(&other.path))
+                {
+                    anyhow_ext::bail!(
+                        "Option \"{}\" requires \"{}\", but they are in the same exclusive \
+                         group",
+                        opt.name,
+                        other.name
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that every dependency has a valid version requirement.
+    pub fn validate_dependencies(&self) -> anyhow_ext::Result<()> {
+        self.dependencies
+            .iter()
+            .try_for_each(|dep| dep.requirement().map(|_| ()))
+    }
+
+    /// List the option requires/excludes rules broken by a set of enabled
+    /// options, as messages suitable to show the user.
+    pub fn option_rule_violations(&self, enabled: &[ModOption]) -> Vec<std::string::String> {
+        let name = |path: &PathBuf| {
+            self.option(path)
+                .map(|opt| opt.name.to_string())
+                .unwrap_or_else(|| path.display().to_string())
+        };
+        let is_enabled = |path: &PathBuf| enabled.iter().any(|opt| opt.path == *path);
+        let mut violations = vec![];
+        for opt in enabled {
+            for path in opt.requires.iter().filter(|p| !is_enabled(p)) {
+                violations.push(format!("\"{}\" requires \"{}\"", opt.name, name(path)));
+            }
+            for path in opt.excludes.iter().filter(|p| is_enabled(p)) {
+                violations.push(format!(
+                    "\"{}\" cannot be used with \"{}\"",
+                    opt.name,
+                    name(path)
+                ));
+            }
+        }
+        violations
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util;
 
     #[test]
     fn read_meta() {
@@ -285,9 +795,169 @@ mod tests {
                 url: None,
                 version: "1.0.0".into(),
                 masters: Default::default(),
+                emulator_flags: Default::default(),
+                dependencies: Default::default(),
+                thumbnail: None,
+                author_url: None,
+                homepage: None,
+                long_description: Default::default(),
+                changelog: Default::default(),
+                game_version: None,
+                requires_dlc: false,
+                selectable: Default::default(),
+                update_url: None,
+                translations: Default::default(),
+                license: None,
+                credits: Default::default(),
+                permissions: Default::default(),
+                delta: None,
                 options: Default::default(),
             })
             .unwrap()
         );
     }
+
+    #[test]
+    fn changelog() {
+        let mut meta = test_util::meta();
+        meta.set_changes("- First release");
+        let earlier = meta.clone();
+        meta.version = "1.2".into();
+        meta.set_changes("- Fixed Link's hair");
+        meta.merge_changelog(&earlier);
+        meta.version = "1.10.0".into();
+        meta.set_changes("- New armor\n");
+        meta.merge_changelog(&earlier);
+        assert_eq!(
+            meta.changelog
+                .iter()
+                .map(|e| e.version.as_str())
+                .collect::<Vec<_>>(),
+            ["1.10.0", "1.2", "1.0.0"]
+        );
+        assert_eq!(meta.current_changes().unwrap().changes, "- New armor");
+        assert_eq!(meta.changes_since("1.0.0").len(), 2);
+        assert_eq!(meta.changes_since("1.2.0")[0].version, "1.10.0");
+        assert!(meta.changes_since("1.10").is_empty());
+        meta.set_changes(" ");
+        assert!(meta.current_changes().is_none());
+    }
+
+    #[test]
+    fn game_requirements() {
+        let mut meta = Meta {
+            game_version: Some("1.5.0".into()),
+            requires_dlc: true,
+            ..test_util::meta()
+        };
+        assert!(meta.missing_requirements(Some("1.5.0"), true).is_empty());
+        assert!(meta.missing_requirements(None, true).is_empty());
+        assert_eq!(meta.missing_requirements(Some("1.4.1"), false).len(), 2);
+        meta.game_version = None;
+        meta.requires_dlc = false;
+        assert!(meta.missing_requirements(Some("1.0.0"), false).is_empty());
+        assert!(!serde_yaml::to_string(&meta).unwrap().contains("requires_dlc"));
+    }
+
+    #[test]
+    fn dependency_versions() {
+        let dep = |version: &str| {
+            Dependency {
+                name: "Second Wind".into(),
+                version: version.into(),
+            }
+        };
+        assert!(dep("").matches("1.0").unwrap());
+        assert!(dep(">=1.2, <2").matches("1.2").unwrap());
+        assert!(dep(">=1.2, <2").matches("1.10.3").unwrap());
+        assert!(!dep(">=1.2, <2").matches("2.0.0").unwrap());
+        assert!(!dep("^1.2").matches("1.1.9").unwrap());
+        assert!(dep("not a range").matches("1.0.0").is_err());
+    }
+
+    #[test]
+    fn option_rules() {
+        let opt = |path: &str, requires: &[&str], excludes: &[&str]| {
+            ModOption {
+                name: path.into(),
+                description: Default::default(),
+                path: path.into(),
+                requires: requires.iter().map(|p| PathBuf::from(*p)).collect(),
+                excludes: excludes.iter().map(|p| PathBuf::from(*p)).collect(),
+            }
+        };
+        let mut meta = Meta {
+            options: vec![OptionGroup::Multiple(MultipleOptionGroup {
+                name: "Extras".into(),
+                description: Default::default(),
+                required: false,
+                defaults: Default::default(),
+                options: vec![
+                    opt("a", &["b"], &[]),
+                    opt("b", &[], &[]),
+                    opt("c", &[], &["a"]),
+                ],
+            })],
+            ..Default::default()
+        };
+        meta.validate_options().unwrap();
+        let [a, b, c] = [0, 1, 2].map(|i| meta.options[0].options()[i].clone());
+        assert!(meta.option_rule_violations(&[a.clone(), b.clone()]).is_empty());
+        assert_eq!(meta.option_rule_violations(&[a.clone()]).len(), 1);
+        assert_eq!(meta.option_rule_violations(&[a, b, c]).len(), 1);
+        meta.options[0].options_mut()[1].requires.push("d".into());
+        assert!(meta.validate_options().is_err());
+    }
+
+    #[test]
+    fn manifest_rstb() {
+        let mut manifest: Manifest =
+            serde_yaml::from_str("content:\n- Model/Test.sbfres\naoc: []\n").unwrap();
+        assert!(manifest.rstb.is_empty());
+        assert!(!serde_yaml::to_string(&manifest).unwrap().contains("rstb"));
+        manifest.rstb.insert("Model/Test.bfres".into(), Some(1024));
+        let text = serde_yaml::to_string(&manifest).unwrap();
+        assert_eq!(serde_yaml::from_str::<Manifest>(&text).unwrap(), manifest);
+    }
+
+    #[test]
+    fn translations() {
+        let meta = Meta {
+            translations: [
+                (Language::EUfr, MetaTranslation {
+                    name: "Mod de test".into(),
+                    description: "Un mod UKMM".into(),
+                }),
+                (Language::JPja, MetaTranslation {
+                    name: Default::default(),
+                    description: "UKMMのMOD".into(),
+                }),
+            ]
+            .into_iter()
+            .collect(),
+            ..test_util::meta()
+        };
+        assert_eq!(meta.display_name(Language::EUfr), "Mod de test");
+        assert_eq!(meta.display_name(Language::USfr), "Mod de test");
+        assert_eq!(meta.display_description(Language::USfr), "Un mod UKMM");
+        assert_eq!(meta.display_name(Language::JPja), "Test Mod");
+        assert_eq!(meta.display_description(Language::JPja), "UKMMのMOD");
+        assert_eq!(meta.display_name(Language::USen), "Test Mod");
+    }
+
+    #[test]
+    fn manifest_format() {
+        let old =
+            Manifest::from_yaml("content:\n- Actor/ActorInfo.product.sbyml\naoc: []\n").unwrap();
+        assert_eq!(old.format, 0);
+        assert!(Manifest::from_yaml("content: []\naoc: []\nformat: 1\n").is_ok());
+        let current = Manifest {
+            format: FORMAT_VERSION,
+            ..old.clone()
+        };
+        let text = serde_yaml::to_string(&current).unwrap();
+        assert_eq!(Manifest::from_yaml(&text).unwrap(), current);
+        let newer = format!("content: []\naoc: []\nformat: {}\n", FORMAT_VERSION + 1);
+        assert!(Manifest::from_yaml(&newer).is_err());
+    }
 }
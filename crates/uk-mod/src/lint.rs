@@ -0,0 +1,494 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow_ext::{Context, Result};
+use botw_utils::{
+    extensions::{AAMP_EXTS, BYML_EXTS},
+    hashes::StockHashTable,
+};
+use fs_err as fs;
+use jwalk::WalkDir;
+use path_slash::PathExt;
+use roead::{aamp::ParameterIO, byml::Byml, yaz0::decompress_if};
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use uk_content::{
+    platform_prefixes,
+    prelude::Endian,
+    resource::{MergeableResource, ResourceData},
+};
+use uk_reader::ResourceLoader;
+
+use crate::{
+    pack::{FileCategory, NX_HASH_TABLE, WIIU_HASH_TABLE},
+    unpack::ModReader,
+    ModPlatform,
+};
+
+/// Textures bigger than this, uncompressed, are flagged as likely to use too
+/// much memory in game.
+pub const TEXTURE_SIZE_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Folders of files which the game only reads from inside a pack, and where
+/// they belong instead.
+const PACKED_FOLDERS: &[(&str, &str)] = &[
+    ("Actor/ActorLink/", "the actor's pack in Actor/Pack"),
+    ("Actor/AIProgram/", "the actor's pack in Actor/Pack"),
+    ("Actor/AISchedule/", "the actor's pack in Actor/Pack"),
+    ("Actor/AS/", "the actor's pack in Actor/Pack"),
+    ("Actor/ASList/", "the actor's pack in Actor/Pack"),
+    ("Actor/AttClient/", "the actor's pack in Actor/Pack"),
+    ("Actor/AttClientList/", "the actor's pack in Actor/Pack"),
+    ("Actor/Awareness/", "the actor's pack in Actor/Pack"),
+    ("Actor/BoneControl/", "the actor's pack in Actor/Pack"),
+    ("Actor/Chemical/", "the actor's pack in Actor/Pack"),
+    ("Actor/DamageParam/", "the actor's pack in Actor/Pack"),
+    ("Actor/DropTable/", "the actor's pack in Actor/Pack"),
+    ("Actor/GeneralParamList/", "the actor's pack in Actor/Pack"),
+    ("Actor/LifeCondition/", "the actor's pack in Actor/Pack"),
+    ("Actor/LOD/", "the actor's pack in Actor/Pack"),
+    ("Actor/ModelList/", "the actor's pack in Actor/Pack"),
+    ("Actor/Physics/", "the actor's pack in Actor/Pack"),
+    ("Actor/Recipe/", "the actor's pack in Actor/Pack"),
+    ("Actor/ShopData/", "the actor's pack in Actor/Pack"),
+    ("Actor/UMii/", "the actor's pack in Actor/Pack"),
+    ("EventFlow/", "the event's pack in Event"),
+    ("GameData/", "GameData/gamedata.ssarc in Pack/Bootup.pack"),
+    (
+        "Message/",
+        "Message/Msg_<language>.product.ssarc in Pack/Bootup_<language>.pack",
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Severity {
+    /// Probably a mistake, but the mod may still work
+    Warning,
+    /// The mod will not work as intended
+    Error,
+}
+
+/// A problem found in a mod, with what to do about it.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Issue {
+    pub severity: Severity,
+    /// The file with the problem, from the mod root
+    pub file: String,
+    pub message: std::string::String,
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}: {}", self.severity, self.file, self.message)
+    }
+}
+
+pub(crate) fn endian_name(endian: Endian) -> &'static str {
+    match endian {
+        Endian::Big => "Wii U",
+        Endian::Little => "Switch",
+    }
+}
+
+/// The byte order of a binary file by its header, for the formats which
+/// differ between platforms.
+pub(crate) fn file_endian(data: &[u8]) -> Option<Endian> {
+    let bom = |offset: usize| {
+        match data.get(offset..offset + 2)? {
+            [0xFE, 0xFF] => Some(Endian::Big),
+            [0xFF, 0xFE] => Some(Endian::Little),
+            _ => None,
+        }
+    };
+    match data.get(..4)? {
+        [b'B', b'Y', ..] => Some(Endian::Big),
+        [b'Y', b'B', ..] => Some(Endian::Little),
+        b"SARC" => bom(6),
+        b"FRES" if data.get(4..8) == Some(&b"    "[..]) => Some(Endian::Little),
+        b"FRES" => Some(Endian::Big),
+        b"FSTM" | b"FWAV" | b"FLYT" | b"FLAN" | b"FSAR" | b"FSTP" => bom(4),
+        _ => None,
+    }
+}
+
+/// Collects the problems found in a mod's files.
+struct Linter {
+    issues: Vec<Issue>,
+    /// Actors placed by map units, with the file placing them
+    placed: BTreeSet<(std::string::String, String)>,
+    /// Actors the mod adds or edits
+    actors: BTreeSet<std::string::String>,
+}
+
+impl Linter {
+    fn new() -> Self {
+        Self {
+            issues: vec![],
+            placed: BTreeSet::new(),
+            actors: BTreeSet::new(),
+        }
+    }
+
+    fn add(&mut self, severity: Severity, file: &str, message: std::string::String) {
+        self.issues.push(Issue {
+            severity,
+            file: file.into(),
+            message,
+        });
+    }
+
+    /// Notes any actor pack, which other files may refer to.
+    fn note_actor(&mut self, name: &str) {
+        if let Some(actor) = name
+            .rsplit_once('/')
+            .filter(|(dir, _)| dir.ends_with("Actor/Pack"))
+            .and_then(|(_, file)| file.split_once('.'))
+            .map(|(stem, _)| stem)
+        {
+            self.actors.insert(actor.into());
+        }
+    }
+
+    /// Checks things which only depend on the binary contents of a file.
+    fn check_binary(&mut self, file: &str, data: &[u8], endian: Option<Endian>) {
+        if let Some(endian) = endian
+            && let Some(found) = file_endian(data)
+            && found != endian
+        {
+            self.add(
+                Severity::Error,
+                file,
+                format!(
+                    "This is a {} file, but the mod is for {}. Use the {} version of the file \
+                     instead, or convert it.",
+                    endian_name(found),
+                    endian_name(endian),
+                    endian_name(endian)
+                ),
+            );
+        }
+        if FileCategory::of(file) == FileCategory::Textures && data.len() > TEXTURE_SIZE_LIMIT {
+            self.add(
+                Severity::Warning,
+                file,
+                format!(
+                    "This texture is {} MB uncompressed, which can run the game out of memory. \
+                     Consider lowering its resolution or removing mipmaps it doesn't need.",
+                    data.len() / 1024 / 1024
+                ),
+            );
+        }
+    }
+
+    fn check_map_unit(&mut self, file: &str, objs: impl Iterator<Item = &'_ Byml>) {
+        for obj in objs {
+            if let Ok(name) = obj
+                .as_hash()
+                .map_err(|_| ())
+                .and_then(|obj| obj.get("UnitConfigName").ok_or(()))
+                .and_then(|name| name.as_string().map_err(|_| ()))
+            {
+                self.placed.insert((name.to_string(), file.into()));
+            }
+        }
+    }
+
+    /// Checks a file from a mod folder, by its path inside the content or
+    /// DLC folder.
+    fn check_file(&mut self, file: &str, name: &str, data: &[u8], endian: Endian, aoc: bool) {
+        let data = decompress_if(data);
+        self.note_actor(name);
+        if !aoc
+            && let Some((folder, home)) = PACKED_FOLDERS
+                .iter()
+                .find(|(folder, _)| name.starts_with(folder))
+        {
+            self.add(
+                Severity::Error,
+                file,
+                format!(
+                    "The game only reads files in {} from inside a pack, so this one is ignored. \
+                     Move it into {}.",
+                    folder.trim_end_matches('/'),
+                    home
+                ),
+            );
+        }
+        let ext = name.rsplit('.').next().unwrap_or_default();
+        if BYML_EXTS.contains(&ext) {
+            match Byml::from_binary(&data) {
+                Ok(byml) => {
+                    if ext.ends_with("mubin")
+                        && let Ok(objs) = byml
+                            .as_hash()
+                            .map_err(|_| ())
+                            .and_then(|root| root.get("Objs").ok_or(()))
+                            .and_then(|objs| objs.as_array().map_err(|_| ()))
+                    {
+                        self.check_map_unit(file, objs.iter());
+                    }
+                }
+                Err(e) => {
+                    self.add(
+                        Severity::Error,
+                        file,
+                        format!(
+                            "This is not a valid BYML file ({e}). Check that it was saved \
+                             properly by your BYML editor."
+                        ),
+                    );
+                }
+            }
+        } else if AAMP_EXTS.contains(&ext)
+            && let Err(e) = ParameterIO::from_binary(&data)
+        {
+            self.add(
+                Severity::Error,
+                file,
+                format!(
+                    "This is not a valid AAMP file ({e}). Check that it was saved properly by \
+                     your AAMP editor."
+                ),
+            );
+        }
+        self.check_binary(file, &data, Some(endian));
+    }
+
+    /// Reports actors placed by the mod which neither it nor the game has.
+    fn check_actors(&mut self, hash_tables: &[&StockHashTable]) {
+        let exists = |actor: &str| {
+            hash_tables.iter().any(|table| {
+                [
+                    format!("Actor/Pack/{actor}.bactorpack"),
+                    format!("Aoc/0010/Actor/Pack/{actor}.bactorpack"),
+                ]
+                .iter()
+                .any(|canon| !table.is_file_new(canon))
+            })
+        };
+        let missing = std::mem::take(&mut self.placed)
+            .into_iter()
+            .filter(|(actor, _)| !self.actors.contains(actor) && !exists(actor))
+            .collect::<Vec<_>>();
+        for (actor, file) in missing {
+            self.add(
+                Severity::Error,
+                &file,
+                format!(
+                    "This map places the actor {actor}, which is not in the game or this mod. \
+                     Check the name for typos, or add the actor's pack to Actor/Pack."
+                ),
+            );
+        }
+    }
+
+    fn finish(mut self) -> Vec<Issue> {
+        self.issues.sort();
+        self.issues.dedup();
+        self.issues
+    }
+}
+
+/// The roots in a mod folder which hold files: the mod root itself and the
+/// folder for each option.
+fn source_roots(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut roots = vec![path.to_path_buf()];
+    let options = path.join("options");
+    if options.is_dir() {
+        for entry in fs::read_dir(options)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                roots.push(entry.path());
+            }
+        }
+    }
+    Ok(roots)
+}
+
+fn lint_source(path: &Path) -> Result<Vec<Issue>> {
+    let mut linter = Linter::new();
+    let mut platforms = vec![];
+    for root in source_roots(path)? {
+        for endian in [Endian::Big, Endian::Little] {
+            let (content, aoc) = platform_prefixes(endian);
+            for (dir, is_aoc) in [(root.join(content), false), (root.join(aoc), true)] {
+                if !dir.is_dir() {
+                    continue;
+                }
+                if !platforms.contains(&endian) {
+                    platforms.push(endian);
+                }
+                for entry in WalkDir::new(&dir).into_iter().filter_map(|e| e.ok()) {
+                    if !entry.file_type().is_file() {
+                        continue;
+                    }
+                    let file_path = entry.path();
+                    let file = file_path
+                        .strip_prefix(path)
+                        .unwrap_or(file_path.as_path())
+                        .to_slash_lossy()
+                        .into_owned();
+                    let name = file_path
+                        .strip_prefix(&dir)
+                        .unwrap_or(file_path.as_path())
+                        .to_slash_lossy()
+                        .into_owned();
+                    let data = fs::read(&file_path)?;
+                    linter.check_file(&file, &name, &data, endian, is_aoc);
+                }
+            }
+        }
+    }
+    anyhow_ext::ensure!(
+        !platforms.is_empty(),
+        "No content or DLC folder found in {}",
+        path.display()
+    );
+    let tables = platforms
+        .into_iter()
+        .map(|endian| {
+            match endian {
+                Endian::Big => &*WIIU_HASH_TABLE,
+                Endian::Little => &*NX_HASH_TABLE,
+            }
+        })
+        .collect::<Vec<_>>();
+    linter.check_actors(&tables);
+    Ok(linter.finish())
+}
+
+fn lint_package(path: &Path) -> Result<Vec<Issue>> {
+    let meta = ModReader::open_peek(path, vec![])?.meta;
+    let options = meta
+        .options
+        .iter()
+        .flat_map(|group| group.options().iter().cloned())
+        .collect::<Vec<_>>();
+    let reader = ModReader::open(path, options)?;
+    let endian = match meta.platform {
+        ModPlatform::Specific(endian) => Some(endian),
+        ModPlatform::Universal | ModPlatform::Dual => None,
+    };
+    let mut linter = Linter::new();
+    for name in reader
+        .manifest
+        .content_files
+        .iter()
+        .chain(reader.manifest.aoc_files.iter())
+    {
+        linter.note_actor(name);
+    }
+    for canon in reader.manifest.resources() {
+        let data = reader
+            .get_data(Path::new(canon.as_str()))
+            .with_context(|| format!("Failed to read {canon} from mod"))?;
+        let resource: ResourceData = minicbor_ser::from_slice(&data)
+            .map_err(|e| anyhow_ext::anyhow!("{e:?}"))
+            .with_context(|| format!("Failed to parse {canon} from mod"))?;
+        match resource {
+            ResourceData::Binary(data) => {
+                if meta.platform == ModPlatform::Universal && file_endian(&data).is_some() {
+                    linter.add(
+                        Severity::Warning,
+                        &canon,
+                        "This file only works on one platform, but the mod is marked as \
+                         cross-platform. Package the mod for one platform instead."
+                            .into(),
+                    );
+                }
+                linter.check_binary(&canon, &data, endian);
+            }
+            ResourceData::Mergeable(MergeableResource::MapUnit(unit)) => {
+                linter.check_map_unit(&canon, unit.objects.iter().map(|(_, obj)| obj));
+            }
+            _ => (),
+        }
+    }
+    let tables: Vec<&StockHashTable> = match endian {
+        Some(Endian::Big) => vec![&*WIIU_HASH_TABLE],
+        Some(Endian::Little) => vec![&*NX_HASH_TABLE],
+        None => vec![&*WIIU_HASH_TABLE, &*NX_HASH_TABLE],
+    };
+    linter.check_actors(&tables);
+    Ok(linter.finish())
+}
+
+/// Checks a mod, either a package or a folder being worked on, for common
+/// mistakes: files which can't be parsed, files left loose which the game
+/// only reads from packs, files for the wrong platform, huge textures, and
+/// map objects for actors which don't exist. Problems are listed by file, in
+/// order. Files in a package were already parsed when it was made, so only
+/// folders are checked for malformed or misplaced files.
+pub fn lint(path: &Path) -> Result<Vec<Issue>> {
+    log::info!("Checking {} for problems", path.display());
+    let issues = if path.is_dir() {
+        lint_source(path)?
+    } else {
+        lint_package(path)?
+    };
+    log::info!("Found {} problems in {}", issues.len(), path.display());
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_endians() {
+        assert_eq!(file_endian(b"BY\x00\x02"), Some(Endian::Big));
+        assert_eq!(file_endian(b"YB\x02\x00"), Some(Endian::Little));
+        assert_eq!(
+            file_endian(b"SARC\x00\x14\xFF\xFE\x00\x00"),
+            Some(Endian::Little)
+        );
+        assert_eq!(file_endian(b"FRES    \x00\x05"), Some(Endian::Little));
+        assert_eq!(
+            file_endian(b"FRES\x00\x04\x00\x04\xFE\xFF"),
+            Some(Endian::Big)
+        );
+        assert_eq!(file_endian(b"AAMP\x02\x00"), None);
+    }
+
+    #[test]
+    fn lint_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = dir.path().join("content");
+        std::fs::create_dir_all(content.join("Actor/ActorLink")).unwrap();
+        std::fs::create_dir_all(content.join("Map/MainField/A-1")).unwrap();
+        std::fs::write(content.join("Actor/ActorLink/Test.bxml"), b"AAMP").unwrap();
+        let objs = ["Enemy_Bokoblin_Junior", "Enemy_Not_Real"]
+            .into_iter()
+            .map(|name| {
+                Byml::Hash(
+                    [("UnitConfigName".into(), name.into())]
+                        .into_iter()
+                        .collect(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let map = "content/Map/MainField/A-1/A-1_Static.smubin";
+        std::fs::write(
+            dir.path().join(map),
+            Byml::Hash([("Objs".into(), Byml::Array(objs))].into_iter().collect())
+                .to_binary(roead::Endian::Little),
+        )
+        .unwrap();
+        let issues = lint(dir.path()).unwrap();
+        assert_eq!(issues.len(), 4, "{issues:#?}");
+        assert!(issues.iter().all(|i| i.severity == Severity::Error));
+        let for_file = |file: &str| {
+            issues
+                .iter()
+                .filter(|i| i.file == file)
+                .map(|i| i.message.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(for_file("content/Actor/ActorLink/Test.bxml").len(), 2);
+        let map_issues = for_file(map);
+        assert!(map_issues.iter().any(|msg| msg.contains("Switch file")));
+        assert!(map_issues.iter().any(|msg| msg.contains("Enemy_Not_Real")));
+    }
+}
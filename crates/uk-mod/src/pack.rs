@@ -1,13 +1,16 @@
+mod stream;
+
 use std::{
-    collections::BTreeSet,
-    io::Write,
+    collections::{BTreeMap, BTreeSet},
+    io::{Read, Write},
     path::{Path, PathBuf},
     sync::{atomic::AtomicUsize, Arc, LazyLock},
 };
 
 use anyhow_ext::{Context, Result};
-use botw_utils::hashes::StockHashTable;
+use botw_utils::hashes::{HashTable, StockHashTable};
 use fs_err as fs;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use join_str::jstr;
 use jwalk::WalkDir;
 use parking_lot::{Mutex, RwLock};
@@ -15,7 +18,7 @@ use path_slash::PathExt;
 use rayon::prelude::*;
 use roead::{sarc::Sarc, yaz0::decompress_if};
 pub use sanitise_file_name::sanitise;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use smartstring::alias::String;
 use uk_content::{
     canonicalize,
@@ -27,16 +30,216 @@ use uk_content::{
 use zip::{write::FileOptions, ZipWriter as ZipW};
 
 use crate::{
+    integrity::{HashingWriter, Integrity, SigningKey, INTEGRITY_FILE},
+    unpack::has_rstb_entry,
     ExclusiveOptionGroup, Manifest, Meta, ModOption, ModOptionGroup, ModPlatform,
-    MultipleOptionGroup, OptionGroup,
+    MultipleOptionGroup, OptionGroup, FORMAT_VERSION,
 };
 
 pub type ZipWriter = Arc<Mutex<ZipW<fs::File>>>;
 
-static NX_HASH_TABLE: LazyLock<StockHashTable> =
+/// Textures, models, and audio larger than this, like 4K textures, are packed
+/// straight from disk, so they are never held in memory whole. Other files
+/// this large are packed one at a time, so packing many of them at once
+/// doesn't need several copies of each in memory.
+const LARGE_FILE_SIZE: u64 = 32 * 1024 * 1024;
+
+/// Options for files written to a package: stored, since resources are
+/// compressed already, and with a fixed timestamp, so packaging the same
+/// files always makes the same package.
+pub(crate) fn zip_options() -> FileOptions {
+    FileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored)
+        .last_modified_time(zip::DateTime::default())
+}
+
+/// Rewrites a package with its files in order by name. Resources are packed
+/// in parallel, so they are written in whatever order they finish.
+fn sort_package(path: &Path) -> Result<()> {
+    let mut zip = zip::ZipArchive::new(std::io::BufReader::new(fs::File::open(path)?))?;
+    let mut names = zip.file_names().map(|n| n.to_owned()).collect::<Vec<_>>();
+    names.sort();
+    let sorted_path = path.with_extension("sorting");
+    let mut sorted = ZipW::new(fs::File::create(&sorted_path)?);
+    for name in names {
+        sorted.raw_copy_file(zip.by_name(&name)?)?;
+    }
+    sorted.finish()?;
+    drop(zip);
+    fs::rename(sorted_path, path)?;
+    Ok(())
+}
+
+/// Kinds of file which can be compressed differently when packaging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum FileCategory {
+    Textures,
+    Models,
+    Audio,
+    Other,
+}
+
+impl FileCategory {
+    pub fn iter() -> std::slice::Iter<'static, Self> {
+        [Self::Textures, Self::Models, Self::Audio, Self::Other].iter()
+    }
+
+    /// The category of a file by its name.
+    pub fn of(name: &str) -> Self {
+        let ext = name.rsplit('.').next().unwrap_or_default();
+        let ext = ext.strip_prefix('s').filter(|e| e.starts_with('b')).unwrap_or(ext);
+        match ext {
+            "bntx" | "bflim" => Self::Textures,
+            "bfres" if name.contains(".Tex") => Self::Textures,
+            "bfres" => Self::Models,
+            "bfstm" | "bfwav" | "bars" | "bfsar" => Self::Audio,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl std::fmt::Display for FileCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// How hard to compress a kind of file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compression {
+    /// Use zstd's fastest mode, which barely touches data that is already
+    /// compressed, like most textures and audio. The file is still zstd
+    /// compressed, just with little effort.
+    #[serde(alias = "Store")]
+    Fastest,
+    /// A zstd compression level
+    Level(i32),
+}
+
+/// Compression settings for packaging a mod. Higher levels make smaller
+/// packages but take longer to pack; unpacking speed barely changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressionConfig {
+    /// The zstd level for files with no setting for their category
+    pub level: i32,
+    /// Settings for particular categories of file
+    pub categories: BTreeMap<FileCategory, Compression>,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            level: 3,
+            categories: BTreeMap::new(),
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// The range of zstd levels which compress, from fastest to smallest.
+    pub const LEVELS: std::ops::RangeInclusive<i32> = 1..=22;
+
+    /// The zstd level to use for a file.
+    pub fn level_for(&self, name: &str) -> i32 {
+        match self.categories.get(&FileCategory::of(name)) {
+            Some(Compression::Fastest) => *zstd::compression_level_range().start(),
+            Some(Compression::Level(level)) => *level,
+            None => self.level,
+        }
+    }
+
+    pub fn compress(&self, name: &str, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(zstd::encode_all(data, self.level_for(name))?)
+    }
+}
+
+/// A file in a mod's source folder listing more files to leave out of its
+/// package, one glob pattern per line, like a `.gitignore`.
+pub const IGNORE_FILE: &str = ".ukmmignore";
+
+/// Files which never belong in a package: image editor sources, and files
+/// left behind by the OS or version control.
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    "**/*.psd",
+    "**/*.xcf",
+    "**/*.kra",
+    "**/Thumbs.db",
+    "**/desktop.ini",
+    "**/.DS_Store",
+    "**/.git/**",
+    "**/.svn/**",
+];
+
+/// Glob patterns for files to leave out when packaging, matched without case
+/// against paths from the mod root or option folder, e.g.
+/// `content/Model/*.blend` or `**/*.bak`.
+#[derive(Debug, Clone)]
+pub struct Excludes {
+    patterns: Vec<std::string::String>,
+    set: GlobSet,
+}
+
+impl Default for Excludes {
+    fn default() -> Self {
+        Self::new(DEFAULT_EXCLUDES).expect("Default excludes should be valid")
+    }
+}
+
+impl Excludes {
+    pub fn new(patterns: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self> {
+        let mut excludes = Self {
+            patterns: vec![],
+            set: GlobSet::empty(),
+        };
+        excludes.extend(patterns)?;
+        Ok(excludes)
+    }
+
+    /// Adds more patterns. Blank lines and lines starting with `#` are
+    /// skipped, so the lines of an ignore file can be passed as they are.
+    pub fn extend(&mut self, patterns: impl IntoIterator<Item = impl AsRef<str>>) -> Result<()> {
+        let mut all = self.patterns.clone();
+        all.extend(
+            patterns
+                .into_iter()
+                .map(|p| p.as_ref().trim().to_owned())
+                .filter(|p| !p.is_empty() && !p.starts_with('#')),
+        );
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &all {
+            builder.add(
+                GlobBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .with_context(|| format!("Invalid exclude pattern {pattern}"))?,
+            );
+        }
+        self.set = builder.build()?;
+        self.patterns = all;
+        Ok(())
+    }
+
+    pub fn patterns(&self) -> &[std::string::String] {
+        &self.patterns
+    }
+
+    /// Whether a file, by its path from the mod root, should be left out.
+    pub fn is_excluded(&self, path: &str) -> bool {
+        self.set.is_match(path)
+    }
+}
+
+pub(crate) static NX_HASH_TABLE: LazyLock<StockHashTable> =
     LazyLock::new(|| StockHashTable::new(&botw_utils::hashes::Platform::Switch));
-static WIIU_HASH_TABLE: LazyLock<StockHashTable> =
+pub(crate) static WIIU_HASH_TABLE: LazyLock<StockHashTable> =
     LazyLock::new(|| StockHashTable::new(&botw_utils::hashes::Platform::WiiU));
+/// The stock hashes themselves, for checking large files hashed as they are
+/// read.
+static NX_HASHES: LazyLock<HashTable> =
+    LazyLock::new(|| botw_utils::hashes::get_hash_table(&botw_utils::hashes::Platform::Switch));
+static WIIU_HASHES: LazyLock<HashTable> =
+    LazyLock::new(|| botw_utils::hashes::get_hash_table(&botw_utils::hashes::Platform::WiiU));
 
 pub struct ModPacker {
     source_dir: PathBuf,
@@ -45,8 +248,14 @@ pub struct ModPacker {
     zip: ZipWriter,
     endian: Endian,
     built_resources: Arc<RwLock<BTreeSet<String>>>,
+    rstb: Mutex<BTreeMap<String, Option<u32>>>,
     masters: Vec<Arc<uk_reader::ResourceReader>>,
     hash_table: &'static StockHashTable,
+    compression: CompressionConfig,
+    excludes: Excludes,
+    integrity: Mutex<Integrity>,
+    signing_key: Option<SigningKey>,
+    large_file: Mutex<()>,
     _zip_opts: FileOptions,
     _out_file: PathBuf,
 }
@@ -64,6 +273,9 @@ impl std::fmt::Debug for ModPacker {
                 &jstr!("zip::ZipWriter at {&self._out_file.to_string_lossy()}"),
             )
             .field("built_resources", &self.built_resources)
+            .field("compression", &self.compression)
+            .field("excludes", &self.excludes.patterns())
+            .field("signing_key", &self.signing_key)
             .finish()
     }
 }
@@ -101,6 +313,7 @@ impl From<BnpOption> for ModOption {
             description: opt.desc,
             path: opt.folder,
             requires: vec![],
+            excludes: vec![],
         }
     }
 }
@@ -174,6 +387,22 @@ impl ModPacker {
             category: Default::default(),
             author: Default::default(),
             masters: Default::default(),
+            emulator_flags: Default::default(),
+            dependencies: Default::default(),
+            thumbnail: None,
+            author_url: None,
+            homepage: None,
+            long_description: Default::default(),
+            changelog: Default::default(),
+            game_version: None,
+            requires_dlc: false,
+            selectable: Default::default(),
+            update_url: None,
+            translations: Default::default(),
+            license: None,
+            credits: Default::default(),
+            permissions: Default::default(),
+            delta: None,
             options: vec![],
             platform: if parent.join("content").exists() || parent.join("aoc").exists() {
                 ModPlatform::Specific(Endian::Big)
@@ -193,6 +422,22 @@ impl ModPacker {
             category: Default::default(),
             author: Default::default(),
             masters: Default::default(),
+            emulator_flags: Default::default(),
+            dependencies: Default::default(),
+            thumbnail: None,
+            author_url: None,
+            homepage: None,
+            long_description: Default::default(),
+            changelog: Default::default(),
+            game_version: None,
+            requires_dlc: false,
+            selectable: Default::default(),
+            update_url: None,
+            translations: Default::default(),
+            license: None,
+            credits: Default::default(),
+            permissions: Default::default(),
+            delta: None,
             options: (!info.options.multi.is_empty())
                 .then(|| multi_from_bnp_multi(info.options.multi))
                 .into_iter()
@@ -244,6 +489,10 @@ impl ModPacker {
             } else {
                 anyhow_ext::bail!("No meta info provided or meta file available");
             };
+            meta.validate_options()
+                .context("Mod has invalid option dependency rules")?;
+            meta.validate_dependencies()
+                .context("Mod has invalid dependencies")?;
             let ((content_u, dlc_u), (content_nx, dlc_nx)) = (
                 platform_prefixes(Endian::Big),
                 platform_prefixes(Endian::Little),
@@ -267,6 +516,14 @@ impl ModPacker {
             if dest_file.exists() {
                 fs::remove_file(&dest_file)?;
             }
+            let mut excludes = Excludes::default();
+            let ignore_file = source.join(IGNORE_FILE);
+            if ignore_file.exists() {
+                log::debug!("Reading exclude patterns from {}", ignore_file.display());
+                excludes
+                    .extend(fs::read_to_string(&ignore_file)?.lines())
+                    .with_context(|| format!("Invalid pattern in {}", ignore_file.display()))?;
+            }
             log::debug!("Creating ZIP file");
             let zip = Arc::new(Mutex::new(ZipW::new(fs::File::create(&dest_file)?)));
             Ok(ModPacker {
@@ -281,14 +538,51 @@ impl ModPacker {
                 },
                 meta,
                 built_resources: Arc::new(RwLock::new(BTreeSet::new())),
-                _zip_opts: FileOptions::default()
-                    .compression_method(zip::CompressionMethod::Stored),
+                rstb: Mutex::new(BTreeMap::new()),
+                compression: Default::default(),
+                excludes,
+                integrity: Default::default(),
+                signing_key: None,
+                large_file: Mutex::new(()),
+                _zip_opts: zip_options(),
                 _out_file: dest_file,
             })
         }
         inner(source.as_ref(), dest.as_ref(), meta, masters)
     }
 
+    /// Packs the files for one platform, for sources which have both.
+    pub fn with_endian(mut self, endian: Endian) -> Self {
+        self.endian = endian;
+        self.hash_table = match endian {
+            Endian::Little => &NX_HASH_TABLE,
+            Endian::Big => &WIIU_HASH_TABLE,
+        };
+        self
+    }
+
+    pub fn with_compression(mut self, compression: CompressionConfig) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Also leaves out files matching these glob patterns, on top of the
+    /// defaults and any in the source's ignore file.
+    pub fn with_excludes(
+        mut self,
+        patterns: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<Self> {
+        self.excludes.extend(patterns)?;
+        Ok(self)
+    }
+
+    /// Signs the package with an ed25519 key, so users can tell it comes
+    /// from whoever holds the key and hasn't been changed since.
+    pub fn with_signing_key(mut self, key: SigningKey) -> Self {
+        self.signing_key = Some(key);
+        self
+    }
+
     fn collect_resources(&self, root: PathBuf) -> Result<BTreeSet<String>> {
         let files = WalkDir::new(&root)
             .into_iter()
@@ -296,9 +590,33 @@ impl ModPacker {
                 f.ok()
                     .and_then(|f| f.file_type().is_file().then(|| f.path()))
             })
+            .filter(|path| {
+                let name = path
+                    .strip_prefix(&self.current_root)
+                    .unwrap_or(path)
+                    .to_slash_lossy();
+                let excluded = self.excludes.is_excluded(&name);
+                if excluded {
+                    log::debug!("Excluding {name} from package");
+                }
+                !excluded
+            })
             .collect::<Vec<PathBuf>>();
         let total_files = files.len();
         let current_file = AtomicUsize::new(0);
+        let report_progress = || {
+            let progress = current_file.load(std::sync::atomic::Ordering::Relaxed) + 1;
+            current_file.store(progress, std::sync::atomic::Ordering::Relaxed);
+            let percent = (progress as f64 / total_files as f64) * 100.0;
+            let fract = percent.fract();
+            if fract <= 0.1 || fract >= 0.95 {
+                log::info!(
+                    "PROGRESSBuilding {} files: {}%",
+                    total_files,
+                    percent as usize
+                );
+            }
+        };
         log::debug!("Resources found in root {}:\n{:#?}", root.display(), &files);
         Ok(files
             .into_par_iter()
@@ -311,6 +629,25 @@ impl ModPacker {
                     .into();
                 // We know this is sound because we got `path` by iterating the contents of `root`.
                 let canon = canonicalize(name.as_str());
+                let in_option = self.current_root != self.source_dir;
+                let large = fs::metadata(&path)?.len() > LARGE_FILE_SIZE;
+                if large && FileCategory::of(name.as_str()) != FileCategory::Other {
+                    let Some(changed) = self
+                        .process_large_file(&path, &name, &canon)
+                        .with_context(|| jstr!("Failed to process resource {&canon}"))?
+                    else {
+                        return Ok(None);
+                    };
+                    report_progress();
+                    if in_option && !changed {
+                        log::trace!("Resource {} has no changes from base mod, ignoring", &canon);
+                        return Ok(None);
+                    }
+                    return Ok(Some(
+                        path.strip_prefix(&root).unwrap().to_slash_lossy().into(),
+                    ));
+                }
+                let _large_file = large.then(|| self.large_file.lock());
                 let file_data = fs::read(&path)?;
                 let file_data = decompress_if(&file_data);
 
@@ -323,36 +660,55 @@ impl ModPacker {
                     return Ok(None);
                 }
 
+                // Options only store what they change from the base mod, so
+                // toggling them only remerges the files which differ
+                if in_option {
+                    let base = self
+                        .source_dir
+                        .join(path.strip_prefix(&self.current_root).unwrap());
+                    if base.exists() && *decompress_if(&fs::read(&base)?) == *file_data {
+                        log::trace!("Resource {} same as in base mod, ignoring", &canon);
+                        return Ok(None);
+                    }
+                }
+
                 let resource = ResourceData::from_binary(name.as_str(), &*file_data)
                     .with_context(|| jstr!("Failed to parse resource {&name}"))?;
-                self.process_resource(name.clone(), canon.clone(), resource, false)
+                let is_binary = resource.as_binary().is_some();
+                let mut changed = self
+                    .process_resource(name.clone(), canon.clone(), resource, false)
                     .with_context(|| jstr!("Failed to process resource {&canon}"))?;
+                // Unmergeable files are used as they are, so their RSTB sizes
+                // can be worked out now instead of on every merge
+                if changed && is_binary && has_rstb_entry(canon.as_str()) {
+                    let size = rstb::calc::estimate_from_slice_and_name(
+                        &file_data,
+                        name.as_str(),
+                        self.endian.into(),
+                    );
+                    self.rstb.lock().insert(canon.clone(), size);
+                }
                 if is_mergeable_sarc(canon.as_str(), file_data.as_ref()) {
                     log::trace!(
                         "Resource {} is a mergeable SARC, processing contents",
                         &canon
                     );
-                    self.process_sarc(
-                        Sarc::new(file_data.as_ref())?,
-                        name.as_str().as_ref(),
-                        self.hash_table.is_file_new(&canon),
-                        canon.starts_with("Aoc"),
-                    )
-                    .with_context(|| jstr!("Failed to process SARC file {&canon}"))?;
+                    changed |= self
+                        .process_sarc(
+                            Sarc::new(file_data.as_ref())?,
+                            name.as_str().as_ref(),
+                            self.hash_table.is_file_new(&canon),
+                            canon.starts_with("Aoc"),
+                        )
+                        .with_context(|| jstr!("Failed to process SARC file {&canon}"))?;
                 }
 
-                let progress = current_file.load(std::sync::atomic::Ordering::Relaxed) + 1;
-                current_file.store(progress, std::sync::atomic::Ordering::Relaxed);
-                let percent = (progress as f64 / total_files as f64) * 100.0;
-                let fract = percent.fract();
-                if fract <= 0.1 || fract >= 0.95 {
-                    log::info!(
-                        "PROGRESSBuilding {} files: {}%",
-                        total_files,
-                        percent as usize
-                    );
-                }
+                report_progress();
 
+                if in_option && !changed {
+                    log::trace!("Resource {} has no changes from base mod, ignoring", &canon);
+                    return Ok(None);
+                }
                 Ok(Some(
                     path.strip_prefix(&root).unwrap().to_slash_lossy().into(),
                 ))
@@ -363,18 +719,9 @@ impl ModPacker {
             .collect())
     }
 
-    fn process_resource(
-        &self,
-        name: String,
-        canon: String,
-        mut resource: ResourceData,
-        in_new_sarc: bool,
-    ) -> Result<()> {
-        if self.built_resources.read().contains(&canon) {
-            log::trace!("Already processed {}, skipping", &canon);
-            return Ok(());
-        }
-        if resource.as_binary().is_some() && self.meta.platform == ModPlatform::Universal {
+    /// Fails for cross-platform mods, which can't include unmergeable files.
+    fn check_unmergeable(&self, canon: &str) -> Result<()> {
+        if self.meta.platform == ModPlatform::Universal {
             anyhow_ext::bail!(
                 "The resource {} is not a mergeable asset. Cross-platform mods must consist only \
                  of mergeable assets. While there is no ready-made comprehensive list of \
@@ -383,17 +730,22 @@ impl ModPacker {
                 canon
             );
         }
+        Ok(())
+    }
+
+    /// Finds the game's or the base mod's version of a resource to diff
+    /// against, if it has one.
+    fn find_reference(&self, name: &str, canon: &str) -> Option<Arc<ResourceData>> {
         let prefixes = platform_prefixes(self.endian);
         let ref_name = name
             .trim_start_matches(prefixes.0)
             .trim_start_matches(prefixes.1)
             .trim_start_matches('/');
-        let reference = self
-            .masters
+        self.masters
             .iter()
             .filter_map(|master| {
                 master
-                    .get_resource(canon.as_str())
+                    .get_resource(canon)
                     .or_else(|err| {
                         log::trace!("{err}");
                         master.get_data(ref_name)
@@ -414,7 +766,37 @@ impl ModPacker {
                     })
                     .ok()
             })
-            .last();
+            .last()
+    }
+
+    /// The path of a resource in the package.
+    fn zip_name(&self, canon: &str) -> std::string::String {
+        self.current_root
+            .strip_prefix(&self.source_dir)
+            .unwrap()
+            .join(canon)
+            .to_slash_lossy()
+            .into_owned()
+    }
+
+    /// Writes a resource to the package, as a diff against the game's or,
+    /// for options, the base mod's version where possible. Returns whether
+    /// anything was written, which it isn't if the resource is the same.
+    fn process_resource(
+        &self,
+        name: String,
+        canon: String,
+        mut resource: ResourceData,
+        in_new_sarc: bool,
+    ) -> Result<bool> {
+        if self.built_resources.read().contains(&canon) {
+            log::trace!("Already processed {}, skipping", &canon);
+            return Ok(true);
+        }
+        if resource.as_binary().is_some() {
+            self.check_unmergeable(&canon)?;
+        }
+        let reference = self.find_reference(&name, &canon);
         log::trace!("Resource {} has a master: {}", &canon, reference.is_some());
         if let Some(ref_res_data) = reference.as_ref()
             && let Some(ref_res) = ref_res_data.as_mergeable()
@@ -422,7 +804,7 @@ impl ModPacker {
         {
             if ref_res == res {
                 log::trace!("{} not modded, skipping", &canon);
-                return Ok(());
+                return Ok(false);
             }
             log::trace!("Diffing {}", &canon);
             resource = ResourceData::Mergeable(ref_res.diff(res));
@@ -432,32 +814,115 @@ impl ModPacker {
         {
             if ref_sarc == sarc && !in_new_sarc {
                 log::trace!("{} not modded, skipping", &canon);
-                return Ok(());
+                return Ok(false);
             }
             log::trace!("Diffing {}", &canon);
             resource = ResourceData::Sarc(ref_sarc.diff(sarc));
+        } else if let Some(ref_res_data) = reference.as_ref()
+            && resource.as_binary().is_some()
+            && ref_res_data.as_ref() == &resource
+            && !in_new_sarc
+        {
+            log::trace!("{} not modded, skipping", &canon);
+            return Ok(false);
         }
 
         let data = minicbor_ser::to_vec(&resource)
             .map_err(|e| anyhow::format_err!("{:?}", e))
             .with_context(|| jstr!("Failed to serialize {&name}"))?;
-        let zip_path = self
-            .current_root
-            .strip_prefix(&self.source_dir)
-            .unwrap()
-            .join(canon.as_str());
-        {
+        drop(resource);
+        if data.len() as u64 > LARGE_FILE_SIZE {
+            self.write_streamed(&canon, data.as_slice())?;
+        } else {
+            let name = self.zip_name(&canon);
+            let data = self.compression.compress(&canon, &data)?;
             log::trace!("Writing {} to ZIP", &canon);
             let mut zip = self.zip.lock();
-            zip.start_file(zip_path.to_slash_lossy(), self._zip_opts)?;
-            zip.write_all(&zstd::encode_all(&*data, 3)?)?;
+            zip.start_file(name.as_str(), self._zip_opts)?;
+            zip.write_all(&data)?;
+            self.integrity.lock().add(&name, &data);
         }
         self.built_resources.write().insert(canon);
 
+        Ok(true)
+    }
+
+    /// Compresses a serialized resource into the package as it is read.
+    fn write_streamed(&self, canon: &str, data: impl Read) -> Result<()> {
+        log::trace!("Streaming {} to ZIP", canon);
+        let name = self.zip_name(canon);
+        let mut zip = self.zip.lock();
+        zip.start_file(name.as_str(), self._zip_opts)?;
+        let mut writer = HashingWriter::new(&mut *zip);
+        zstd::stream::copy_encode(data, &mut writer, self.compression.level_for(canon))?;
+        self.integrity.lock().add_hashed(&name, writer);
         Ok(())
     }
 
-    fn process_sarc(&self, sarc: Sarc, path: &Path, is_new_sarc: bool, is_aoc: bool) -> Result<()> {
+    /// Packs a large unmergeable file, like a 4K texture, straight from disk.
+    /// It is read once to hash it and once more to compress it into the
+    /// package, so it is never held in memory whole. Returns `None` if the
+    /// file is unmodded or, in an option, the same as in the base mod, and
+    /// otherwise whether anything was written, like
+    /// [`Self::process_resource`].
+    fn process_large_file(&self, path: &Path, name: &str, canon: &str) -> Result<Option<bool>> {
+        let (hash, size) = stream::hash_file(path)?;
+        let stock_hashes = match self.endian {
+            Endian::Big => &WIIU_HASHES,
+            Endian::Little => &NX_HASHES,
+        };
+        if stock_hashes
+            .get(canon)
+            .map_or(false, |hashes| hashes.contains(&hash))
+        {
+            log::trace!("Resource {} not modded, ignoring", canon);
+            return Ok(None);
+        }
+        if self.current_root != self.source_dir {
+            let base = self
+                .source_dir
+                .join(path.strip_prefix(&self.current_root).unwrap());
+            if base.exists() && stream::hash_file(&base)? == (hash, size) {
+                log::trace!("Resource {} same as in base mod, ignoring", canon);
+                return Ok(None);
+            }
+        }
+        if self.built_resources.read().contains(canon) {
+            log::trace!("Already processed {}, skipping", canon);
+            return Ok(Some(true));
+        }
+        self.check_unmergeable(canon)?;
+        if let Some(reference) = self.find_reference(name, canon)
+            && let Some(data) = reference.as_binary()
+            && data.len() as u64 == size
+            && stream::hash_data(data) == hash
+        {
+            log::trace!("{} not modded, skipping", canon);
+            return Ok(Some(false));
+        }
+        self.write_streamed(
+            canon,
+            stream::BinaryResource::new(stream::open(path)?, size)?,
+        )?;
+        if has_rstb_entry(canon) {
+            let rstb_size =
+                rstb::calc::estimate_from_size_and_name(size as usize, name, self.endian.into());
+            self.rstb.lock().insert(canon.into(), rstb_size);
+        }
+        self.built_resources.write().insert(canon.into());
+        Ok(Some(true))
+    }
+
+    /// Processes the modded files in a SARC. Returns whether any of them
+    /// were written.
+    fn process_sarc(
+        &self,
+        sarc: Sarc,
+        path: &Path,
+        is_new_sarc: bool,
+        is_aoc: bool,
+    ) -> Result<bool> {
+        let mut changed = false;
         for file in sarc.files() {
             if file.data.is_empty() {
                 continue;
@@ -479,25 +944,26 @@ impl ModPacker {
             let resource = ResourceData::from_binary(name, &*file_data).with_context(|| {
                 jstr!("Failed to parse resource {&canon} in SARC {&path.display().to_string()}")
             })?;
-            self.process_resource(name.into(), canon.clone(), resource, is_new_sarc)?;
+            changed |= self.process_resource(name.into(), canon.clone(), resource, is_new_sarc)?;
             if is_mergeable_sarc(canon.as_str(), file_data.as_ref()) {
                 log::trace!(
                     "Resource {} in SARC {} is a mergeable SARC, processing contents",
                     &canon,
                     path.display()
                 );
-                self.process_sarc(
-                    Sarc::new(file_data.as_ref())?,
-                    name.as_ref(),
-                    is_new_sarc,
-                    is_aoc,
-                )
-                .with_context(|| {
-                    jstr!("Failed to process {&canon} in SARC {&path.display().to_string()}")
-                })?;
+                changed |= self
+                    .process_sarc(
+                        Sarc::new(file_data.as_ref())?,
+                        name.as_ref(),
+                        is_new_sarc,
+                        is_aoc,
+                    )
+                    .with_context(|| {
+                        jstr!("Failed to process {&canon} in SARC {&path.display().to_string()}")
+                    })?;
             }
         }
-        Ok(())
+        Ok(changed)
     }
 
     #[allow(irrefutable_let_patterns)]
@@ -505,6 +971,7 @@ impl ModPacker {
         fn inner(self_: &ModPacker, root: &Path) -> Result<()> {
             log::debug!("Packing from root of {}", root.display());
             self_.built_resources.write().clear();
+            self_.rstb.lock().clear();
             let (content, aoc) = platform_prefixes(self_.endian);
             let content_dir = root.join(content);
             log::debug!("Checking for content folder at {}", content_dir.display());
@@ -537,17 +1004,20 @@ impl ModPacker {
                     })
                     .transpose()?
                     .unwrap_or_default(),
+                rstb:          std::mem::take(&mut *self_.rstb.lock()),
+                format:        FORMAT_VERSION,
             })?;
             log::info!("Writing manifest");
+            let name = root
+                .strip_prefix(&self_.source_dir)
+                .unwrap()
+                .join("manifest.yml")
+                .to_slash_lossy()
+                .into_owned();
             let mut zip = self_.zip.lock();
-            zip.start_file(
-                root.strip_prefix(&self_.source_dir)
-                    .unwrap()
-                    .join("manifest.yml")
-                    .to_slash_lossy(),
-                self_._zip_opts,
-            )?;
+            zip.start_file(&name, self_._zip_opts)?;
             zip.write_all(manifest.as_bytes())?;
+            self_.integrity.lock().add(&name, manifest.as_bytes());
             Ok(())
         }
         inner(self, root.as_ref())
@@ -563,14 +1033,18 @@ impl ModPacker {
         roots
     }
 
-    fn pack_thumbnail(&self) -> Result<()> {
+    fn pack_thumbnail(&mut self) -> Result<()> {
         for name in ["thumb", "thumbnail", "preview"] {
             for ext in ["jpg", "jpeg", "png", "svg"] {
                 let path = self.source_dir.join(name).with_extension(ext);
                 if path.exists() {
+                    let file_name = format!("thumb.{}", ext);
+                    let data = fs::read(path)?;
                     let mut zip = self.zip.lock();
-                    zip.start_file(format!("thumb.{}", ext), self._zip_opts)?;
-                    zip.write_all(&fs::read(path)?)?;
+                    zip.start_file(&file_name, self._zip_opts)?;
+                    zip.write_all(&data)?;
+                    self.integrity.lock().add(&file_name, &data);
+                    self.meta.thumbnail = Some(file_name.into());
                     return Ok(());
                 }
             }
@@ -578,6 +1052,22 @@ impl ModPacker {
         Ok(())
     }
 
+    /// Uses a `README.md` in the mod root as the long description, unless
+    /// the meta already has one.
+    fn pack_readme(&mut self) -> Result<()> {
+        if !self.meta.long_description.is_empty() {
+            return Ok(());
+        }
+        for name in ["README.md", "readme.md", "Readme.md"] {
+            let path = self.source_dir.join(name);
+            if path.exists() {
+                self.meta.long_description = fs::read_to_string(path)?.into();
+                break;
+            }
+        }
+        Ok(())
+    }
+
     pub fn pack(mut self) -> Result<PathBuf> {
         self.pack_root(&self.source_dir)?;
         if self.source_dir.join("options").exists() {
@@ -593,17 +1083,28 @@ impl ModPacker {
             }
         }
         self.pack_thumbnail()?;
+        self.pack_readme()?;
         match Arc::try_unwrap(self.zip).map(|z| z.into_inner()) {
             Ok(mut zip) => {
                 log::info!("Writing meta");
+                let meta = serde_yaml::to_string(&self.meta)?;
                 zip.start_file("meta.yml", self._zip_opts)?;
-                zip.write_all(serde_yaml::to_string(&self.meta)?.as_bytes())?;
+                zip.write_all(meta.as_bytes())?;
+                let mut integrity = self.integrity.into_inner();
+                integrity.add("meta.yml", meta.as_bytes());
+                if let Some(key) = self.signing_key.as_ref() {
+                    log::info!("Signing package with key {}", key.public_key());
+                    integrity.sign(key);
+                }
+                zip.start_file(INTEGRITY_FILE, self._zip_opts)?;
+                zip.write_all(serde_yaml::to_string(&integrity)?.as_bytes())?;
                 zip.finish()?
             }
             Err(_) => {
                 anyhow_ext::bail!("Failed to finish writing zip, this is probably a big deal")
             }
         };
+        sort_package(&self._out_file).context("Failed to sort package contents")?;
         log::info!("Completed packaging mod");
         Ok(self._out_file)
     }
@@ -616,6 +1117,68 @@ mod tests {
 
     use super::*;
     use crate::{ModOption, MultipleOptionGroup, OptionGroup};
+    #[test]
+    fn compression_categories() {
+        assert_eq!(FileCategory::of("Model/Link.Tex1.sbfres"), FileCategory::Textures);
+        assert_eq!(FileCategory::of("Model/Link.sbfres"), FileCategory::Models);
+        assert_eq!(FileCategory::of("Layout/Common.sblarc"), FileCategory::Other);
+        assert_eq!(FileCategory::of("Sound/Resource/Stream/BGM.bfstm"), FileCategory::Audio);
+        assert_eq!(FileCategory::of("Actor/ActorInfo.product.sbyml"), FileCategory::Other);
+        let config = CompressionConfig {
+            level: 19,
+            categories: [(FileCategory::Textures, Compression::Fastest)].into_iter().collect(),
+        };
+        assert_eq!(config.level_for("Model/Link.sbfres"), 19);
+        assert!(config.level_for("Model/Link.Tex2.sbfres") < 1);
+        let data = vec![7u8; 4096];
+        let packed = config.compress("Model/Link.Tex2.sbfres", &data).unwrap();
+        assert_eq!(zstd::decode_all(packed.as_slice()).unwrap(), data);
+    }
+
+    #[test]
+    fn excludes() {
+        let mut excludes = Excludes::default();
+        assert!(excludes.is_excluded("content/UI/StockItem/Icon.psd"));
+        assert!(excludes.is_excluded("content/Model/thumbs.db"));
+        assert!(excludes.is_excluded("content/.git/objects/ab/cdef"));
+        assert!(!excludes.is_excluded("content/Model/Link.sbfres"));
+        excludes
+            .extend(["# Blender files", "", "content/Model/*.blend"])
+            .unwrap();
+        assert_eq!(excludes.patterns().len(), DEFAULT_EXCLUDES.len() + 1);
+        assert!(excludes.is_excluded("content/Model/Link.blend"));
+        assert!(!excludes.is_excluded("aoc/0010/Model/Link.blend"));
+        assert!(excludes.extend(["content/[Model"]).is_err());
+    }
+
+    #[test]
+    fn sort_package() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &Path, names: &[&str]| {
+            let mut zip = ZipW::new(std::fs::File::create(path).unwrap());
+            for name in names {
+                zip.start_file(*name, zip_options()).unwrap();
+                zip.write_all(name.as_bytes()).unwrap();
+            }
+            zip.finish().unwrap();
+            super::sort_package(path).unwrap();
+        };
+        let (first, second) = (dir.path().join("first.zip"), dir.path().join("second.zip"));
+        write(&first, &["meta.yml", "content/B.bxml", "content/A.bxml"]);
+        write(&second, &["content/A.bxml", "meta.yml", "content/B.bxml"]);
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&first).unwrap()).unwrap();
+        assert_eq!(
+            (0..zip.len())
+                .map(|i| zip.by_index(i).unwrap().name().to_owned())
+                .collect::<Vec<_>>(),
+            ["content/A.bxml", "content/B.bxml", "meta.yml"]
+        );
+        assert_eq!(
+            std::fs::read(&first).unwrap(),
+            std::fs::read(&second).unwrap()
+        );
+    }
+
     #[test]
     fn pack_mod() {
         env_logger::init();
@@ -636,6 +1199,22 @@ mod tests {
                 author: "Lord Caleb".into(),
                 description: "A test mod".into(),
                 masters: IndexMap::default(),
+                emulator_flags: Default::default(),
+                dependencies: Default::default(),
+                thumbnail: None,
+                author_url: None,
+                homepage: None,
+                long_description: Default::default(),
+                changelog: Default::default(),
+                game_version: None,
+                requires_dlc: false,
+                selectable: Default::default(),
+                update_url: None,
+                translations: Default::default(),
+                license: None,
+                credits: Default::default(),
+                permissions: Default::default(),
+                delta: None,
                 url: None,
                 options: vec![OptionGroup::Multiple(MultipleOptionGroup {
                     name: "Test Option Group".into(),
@@ -646,6 +1225,7 @@ mod tests {
                         description: "An option".into(),
                         path: "option1".into(),
                         requires: vec![],
+                        excludes: vec![],
                     }]
                     .into_iter()
                     .collect(),
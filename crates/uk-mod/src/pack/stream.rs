@@ -0,0 +1,220 @@
+//! Reading large files for packaging a piece at a time, so they never have to
+//! be held in memory whole.
+use std::{
+    hash::Hasher,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use anyhow_ext::Result;
+use fs_err as fs;
+use twox_hash::XxHash64;
+use uk_content::resource::ResourceData;
+
+const WINDOW: usize = 0x1000;
+
+/// Decompresses yaz0 data as it is read.
+pub(crate) struct Yaz0Reader<R> {
+    inner:     R,
+    remaining: usize,
+    window:    Box<[u8; WINDOW]>,
+    pos:       usize,
+    code:      u8,
+    bits:      u8,
+    copy_dist: usize,
+    copy_left: usize,
+}
+
+impl<R: Read> Yaz0Reader<R> {
+    /// Starts reading after the 16-byte header, which gives the decompressed
+    /// size.
+    pub(crate) fn new(inner: R, size: usize) -> Self {
+        Self {
+            inner,
+            remaining: size,
+            window: Box::new([0; WINDOW]),
+            pos: 0,
+            code: 0,
+            bits: 0,
+            copy_dist: 0,
+            copy_left: 0,
+        }
+    }
+
+    fn read_u8(&mut self) -> std::io::Result<u8> {
+        let mut byte = [0; 1];
+        self.inner.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+}
+
+impl<R: Read> Read for Yaz0Reader<R> {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        let mut written = 0;
+        while written < out.len() && self.remaining > 0 {
+            let byte = if self.copy_left > 0 {
+                self.copy_left -= 1;
+                self.window[(self.pos + WINDOW - self.copy_dist) % WINDOW]
+            } else {
+                if self.bits == 0 {
+                    self.code = self.read_u8()?;
+                    self.bits = 8;
+                }
+                self.bits -= 1;
+                if self.code & (1 << self.bits) != 0 {
+                    self.read_u8()?
+                } else {
+                    let (high, low) = (self.read_u8()?, self.read_u8()?);
+                    self.copy_dist = (((high & 0xf) as usize) << 8 | low as usize) + 1;
+                    self.copy_left = match high >> 4 {
+                        0 => self.read_u8()? as usize + 0x12,
+                        len => len as usize + 2,
+                    };
+                    continue;
+                }
+            };
+            self.window[self.pos] = byte;
+            self.pos = (self.pos + 1) % WINDOW;
+            out[written] = byte;
+            written += 1;
+            self.remaining -= 1;
+        }
+        Ok(written)
+    }
+}
+
+/// Opens a file for reading its contents, decompressing them on the way if
+/// they are yaz0 compressed.
+pub(crate) fn open(path: &Path) -> Result<Box<dyn Read + Send>> {
+    let mut file = BufReader::new(fs::File::open(path)?);
+    let mut header = [0; 16];
+    if file.read_exact(&mut header).is_ok() && &header[..4] == b"Yaz0" {
+        let size = u32::from_be_bytes(header[4..8].try_into().unwrap()) as usize;
+        Ok(Box::new(Yaz0Reader::new(file, size)))
+    } else {
+        file.seek(SeekFrom::Start(0))?;
+        Ok(Box::new(file))
+    }
+}
+
+/// Hashes the contents of a file the way the stock hash table does, returning
+/// the hash and the decompressed size.
+pub(crate) fn hash_file(path: &Path) -> Result<(u64, u64)> {
+    let mut reader = open(path)?;
+    let mut hasher = XxHash64::with_seed(0);
+    let mut buf = vec![0; 0x10000];
+    let mut size = 0;
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.write(&buf[..read]);
+        size += read as u64;
+    }
+    Ok((hasher.finish(), size))
+}
+
+/// Hashes data the same way as [`hash_file`].
+pub(crate) fn hash_data(data: &[u8]) -> u64 {
+    let mut hasher = XxHash64::with_seed(0);
+    hasher.write(data);
+    hasher.finish()
+}
+
+/// Encodes a binary resource as it is read, giving the same bytes as
+/// serializing [`ResourceData::Binary`] with the data read from `inner`.
+pub(crate) struct BinaryResource<R> {
+    inner: R,
+    buf:   Vec<u8>,
+    pos:   usize,
+}
+
+impl<R: Read> BinaryResource<R> {
+    /// `size` must be the number of bytes `inner` will give.
+    pub(crate) fn new(inner: R, size: u64) -> Result<Self> {
+        // A `Vec<u8>` is serialized as an array of integers, so the prefix
+        // for an empty one only needs its array length replacing.
+        let mut buf = minicbor_ser::to_vec(&ResourceData::Binary(vec![]))
+            .map_err(|e| anyhow::format_err!("{:?}", e))?;
+        debug_assert_eq!(buf.last(), Some(&0x80));
+        buf.pop();
+        match size {
+            0..=23 => buf.push(0x80 | size as u8),
+            24..=0xff => buf.extend([0x98, size as u8]),
+            0x100..=0xffff => {
+                buf.push(0x99);
+                buf.extend((size as u16).to_be_bytes());
+            }
+            0x1_0000..=0xffff_ffff => {
+                buf.push(0x9a);
+                buf.extend((size as u32).to_be_bytes());
+            }
+            _ => {
+                buf.push(0x9b);
+                buf.extend(size.to_be_bytes());
+            }
+        }
+        Ok(Self { inner, buf, pos: 0 })
+    }
+}
+
+impl<R: Read> Read for BinaryResource<R> {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos == self.buf.len() {
+            let mut chunk = [0; 0x2000];
+            let read = self.inner.read(&mut chunk)?;
+            self.buf.clear();
+            self.pos = 0;
+            for byte in &chunk[..read] {
+                // Integers from 24 up take an extra byte for their type
+                if *byte >= 24 {
+                    self.buf.push(0x18);
+                }
+                self.buf.push(*byte);
+            }
+        }
+        let len = out.len().min(self.buf.len() - self.pos);
+        out[..len].copy_from_slice(&self.buf[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use uk_content::resource::ResourceData;
+
+    #[test]
+    fn yaz0_reader() {
+        let data = (0..0x30000u32)
+            .map(|i| (i % 251) as u8 ^ (i / 0x1000) as u8)
+            .collect::<Vec<_>>();
+        let compressed = roead::yaz0::compress(&data);
+        let mut reader = super::Yaz0Reader::new(
+            &compressed[16..],
+            u32::from_be_bytes(compressed[4..8].try_into().unwrap()) as usize,
+        );
+        let mut decompressed = vec![];
+        reader.read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn binary_resource() {
+        for size in [0, 5, 24, 300, 0x12345] {
+            let data = (0..size).map(|i| (i % 256) as u8).collect::<Vec<_>>();
+            let mut encoded = vec![];
+            super::BinaryResource::new(data.as_slice(), size as u64)
+                .unwrap()
+                .read_to_end(&mut encoded)
+                .unwrap();
+            assert_eq!(
+                encoded,
+                minicbor_ser::to_vec(&ResourceData::Binary(data)).unwrap()
+            );
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+
+/// The path of one part of a split package, numbered from 1, e.g.
+/// `My Mod.zip.001`.
+fn part_path(base: &Path, number: usize) -> PathBuf {
+    let mut name = base.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{number:03}"));
+    base.with_file_name(name)
+}
+
+/// Whether a file is a part of a split mod package, going by its name.
+pub fn is_split(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.len() == 3 && ext.bytes().all(|b| b.is_ascii_digit()))
+        .unwrap_or(false)
+}
+
+/// All the parts of the split package that a part belongs to, in order.
+pub fn parts(path: &Path) -> Result<Vec<PathBuf>> {
+    anyhow_ext::ensure!(is_split(path), "{} is not part of a split mod", path.display());
+    let base = path.with_extension("");
+    let parts = (1..)
+        .map(|number| part_path(&base, number))
+        .take_while(|part| part.exists())
+        .collect::<Vec<_>>();
+    anyhow_ext::ensure!(
+        parts.iter().any(|part| part == path),
+        "Missing parts of split mod {} before {}",
+        base.display(),
+        path.display()
+    );
+    Ok(parts)
+}
+
+/// Splits a package into numbered parts of at most `part_size` bytes next to
+/// it, removing the original. A package which already fits is left alone.
+/// Returns the paths of the parts.
+pub fn split(path: &Path, part_size: u64) -> Result<Vec<PathBuf>> {
+    anyhow_ext::ensure!(part_size > 0, "Split size must be more than 0");
+    let size = fs::metadata(path)?.len();
+    if size <= part_size {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut file = fs::File::open(path)?;
+    let mut parts = vec![];
+    for number in 1..=((size + part_size - 1) / part_size) as usize {
+        let part = part_path(path, number);
+        let mut out = fs::File::create(&part)?;
+        std::io::copy(&mut (&mut file).take(part_size), &mut out)
+            .with_context(|| format!("Failed to write {}", part.display()))?;
+        out.flush()?;
+        parts.push(part);
+    }
+    drop(file);
+    fs::remove_file(path)?;
+    log::info!("Split {} into {} parts", path.display(), parts.len());
+    Ok(parts)
+}
+
+/// Joins the parts of a split package, given any of them, back into one
+/// package at `out`.
+pub fn join(path: &Path, out: &Path) -> Result<()> {
+    let parts = parts(path)?;
+    let mut writer = fs::File::create(out)?;
+    for part in &parts {
+        std::io::copy(&mut fs::File::open(part)?, &mut writer)
+            .with_context(|| format!("Failed to read {}", part.display()))?;
+    }
+    writer.flush()?;
+    log::info!("Joined {} parts into {}", parts.len(), out.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn split_and_join() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Test Mod.zip");
+        let data = (0..2500u32).map(|i| i as u8).collect::<Vec<_>>();
+        std::fs::write(&path, &data).unwrap();
+        let parts = super::split(&path, 1000).unwrap();
+        assert_eq!(parts.len(), 3);
+        assert!(!path.exists());
+        assert!(parts.iter().all(|part| super::is_split(part)));
+        assert_eq!(std::fs::metadata(&parts[2]).unwrap().len(), 500);
+        let joined = dir.path().join("joined.zip");
+        super::join(&parts[1], &joined).unwrap();
+        assert_eq!(std::fs::read(joined).unwrap(), data);
+    }
+}
@@ -0,0 +1,43 @@
+//! Fixtures for tests which write and inspect whole mod packages.
+use std::{collections::BTreeSet, io::Write, path::Path};
+
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+use crate::Meta;
+
+/// The metadata of a sample mod, to adjust with struct update syntax.
+pub fn meta() -> Meta {
+    Meta {
+        name: "Test Mod".into(),
+        version: "1.0.0".into(),
+        author: "Nicene Nerd".into(),
+        category: "Other".into(),
+        description: "A sample UKMM mod".into(),
+        ..Default::default()
+    }
+}
+
+/// Writes a package holding exactly the given files.
+pub fn write_package<D: AsRef<[u8]>>(path: &Path, files: &[(&str, D)]) {
+    let mut zip = ZipWriter::new(std::fs::File::create(path).unwrap());
+    for (name, data) in files {
+        zip.start_file(*name, FileOptions::default()).unwrap();
+        zip.write_all(data.as_ref()).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+/// The names and contents of a package's files, except the metadata and
+/// integrity record, which are written fresh each time.
+pub fn read_files(path: &Path) -> BTreeSet<(String, String)> {
+    let mut zip = ZipArchive::new(std::fs::File::open(path).unwrap()).unwrap();
+    (0..zip.len())
+        .map(|i| {
+            let mut file = zip.by_index(i).unwrap();
+            let mut data = String::new();
+            std::io::Read::read_to_string(&mut file, &mut data).unwrap();
+            (file.name().to_owned(), data)
+        })
+        .filter(|(name, _)| name != "meta.yml" && name != "integrity.yml")
+        .collect()
+}
@@ -1,11 +1,12 @@
 mod de;
 use std::{
-    collections::BTreeSet,
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
     io::{BufReader, Read, Write},
     ops::Deref,
     path::{Path, PathBuf},
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
 };
@@ -18,20 +19,24 @@ use join_str::jstr;
 use jwalk::WalkDir;
 use mmap_rs::{Mmap, MmapOptions};
 use ouroboros::self_referencing;
+use parking_lot::{Condvar, Mutex};
 use path_slash::PathExt;
 use rayon::prelude::*;
 use roead::{
     sarc::SarcWriter,
-    yaz0::{compress, compress_if},
+    yaz0::{compress_with_options, decompress_if, CompressOptions},
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use smartstring::alias::String;
 use uk_content::{
+    actor::{info::ActorInfo, params::general::EconomyMergePolicy},
     canonicalize,
     constants::Language,
+    data::gamedata::{FlagConflict, FlagResolution, GameDataPack},
     platform_content, platform_prefixes,
     prelude::{Endian, Mergeable, Resource},
-    resource::{MergeableResource, ResourceData, SarcMap},
+    quest::product::QuestProduct,
+    resource::{MergeableResource, ResidentActors, ResidentEvents, ResourceData, SarcMap},
     util::{HashMap, IndexSet},
 };
 use uk_reader::{ResourceLoader, ResourceReader};
@@ -155,6 +160,9 @@ pub struct ModReader {
     options: Vec<ModOption>,
     pub meta: Meta,
     pub manifest: Manifest,
+    /// Canonical paths of the files the user chose to leave out
+    #[serde(skip_serializing)]
+    excluded: BTreeSet<String>,
     #[serde(skip_serializing)]
     zip: Option<ParallelZipReader>,
 }
@@ -170,6 +178,9 @@ impl ResourceLoader for ModReader {
     #[allow(irrefutable_let_patterns)]
     fn get_data(&self, name: &Path) -> uk_reader::Result<Vec<u8>> {
         let canon = canonicalize(name);
+        if self.excluded.contains(&canon) {
+            return self.get_aoc_file_data(name);
+        }
         if let Some(zip) = self.zip.as_ref() {
             if let Ok(data) =  zip.get_file(canon.as_str()) {
                 return Ok(zstd::decode_all(data.as_slice()).with_context(|| jstr!("Failed to decompress file {&canon} from mod"))?);
@@ -200,6 +211,9 @@ impl ResourceLoader for ModReader {
     #[allow(irrefutable_let_patterns)]
     fn get_aoc_file_data(&self, name: &Path) -> uk_reader::Result<Vec<u8>> {
         let canon = canonicalize(jstr!("Aoc/0010/{name.to_str().unwrap_or_default()}"));
+        if self.excluded.contains(&canon) {
+            return Err(anyhow_ext::anyhow!("{} was left out of {}", canon, self.meta.name).into());
+        }
         if let Some(zip) = self.zip.as_ref() {
             if let Ok(data) =  zip.get_file(canon.as_str()) {
                 return Ok(zstd::decode_all(data.as_slice()).with_context(|| jstr!("Failed to decompress file {&canon} from mod"))?);
@@ -257,19 +271,20 @@ impl ModReader {
 
     fn open_unzipped(path: PathBuf, options: Vec<ModOption>) -> Result<Self> {
         let meta: Meta = serde_yaml::from_str(&fs::read_to_string(path.join("meta.yml"))?)?;
-        let mut manifest: Manifest =
-            serde_yaml::from_str(&fs::read_to_string(path.join("manifest.yml"))?)?;
+        let mut manifest = Manifest::from_yaml(&fs::read_to_string(path.join("manifest.yml"))?)?;
         for option in &options {
-            let opt_manifest: Manifest =
-                serde_yaml::from_str(&fs::read_to_string(path.join(option.manifest_path()))?)?;
+            let opt_manifest =
+                Manifest::from_yaml(&fs::read_to_string(path.join(option.manifest_path()))?)?;
             manifest.content_files.extend(opt_manifest.content_files);
             manifest.aoc_files.extend(opt_manifest.aoc_files);
+            manifest.rstb.extend(opt_manifest.rstb);
         }
         Ok(Self {
             path,
             options,
             meta,
             manifest,
+            excluded: Default::default(),
             zip: None,
         })
     }
@@ -301,12 +316,16 @@ impl ModReader {
                 .get(Path::new("manifest.yml"))
                 .context("Mod missing manifest file")?;
             size = manifest.size;
+            // Manifests with RSTB sizes can outgrow the buffer in big mods
+            if size > buffer.len() {
+                buffer.resize(size, 0);
+            }
             let mut reader = zip.borrow_zip().read(manifest)?;
             read = reader.read(&mut buffer)?;
             if read != size {
                 anyhow_ext::bail!("Failed to read manifest file from mod")
             }
-            serde_yaml::from_str::<Manifest>(std::str::from_utf8(&buffer[..read])?)
+            Manifest::from_yaml(std::str::from_utf8(&buffer[..read])?)
                 .context("Failed to parse manifest file")?
         };
         for opt in &options {
@@ -315,22 +334,26 @@ impl ModReader {
                 .get(opt.manifest_path().as_path())
                 .context("Mod missing option manifest file")?;
             size = opt_manifest.size;
+            if size > buffer.len() {
+                buffer.resize(size, 0);
+            }
             let mut reader = zip.borrow_zip().read(opt_manifest)?;
             read = reader.read(&mut buffer)?;
             if read != size {
                 anyhow_ext::bail!("Failed to read option manifest file from mod")
             }
-            let opt_manifest =
-                serde_yaml::from_str::<Manifest>(std::str::from_utf8(&buffer[..read])?)
-                    .context("Failed to parse option manifest file")?;
+            let opt_manifest = Manifest::from_yaml(std::str::from_utf8(&buffer[..read])?)
+                .context("Failed to parse option manifest file")?;
             manifest.content_files.extend(opt_manifest.content_files);
             manifest.aoc_files.extend(opt_manifest.aoc_files);
+            manifest.rstb.extend(opt_manifest.rstb);
         }
         Ok(Self {
             path,
             options,
             meta,
             manifest,
+            excluded: Default::default(),
             zip: Some(zip),
         })
     }
@@ -349,18 +372,70 @@ impl ModReader {
         &self.manifest
     }
 
+    /// Leaves out files the user chose not to install, as listed in the
+    /// manifest. They are dropped from the manifest and never read from the
+    /// mod, so other mods' versions of them, or the game's, are used instead.
+    pub fn with_excluded(mut self, excluded: &Manifest) -> Self {
+        self.manifest.remove(excluded);
+        self.excluded = excluded
+            .content_files
+            .iter()
+            .map(|f| canonicalize(f.as_str()))
+            .chain(
+                excluded
+                    .aoc_files
+                    .iter()
+                    .map(|f| canonicalize(jstr!("Aoc/0010/{f}"))),
+            )
+            .collect();
+        self
+    }
+
+    /// The mod's preview image, with its file name to tell the format by.
+    /// Packages from before the meta recorded the thumbnail are searched for
+    /// one under the usual names.
+    pub fn thumbnail(&self) -> Result<Option<(String, Vec<u8>)>> {
+        let names = match self.meta.thumbnail.as_ref() {
+            Some(name) => vec![name.clone()],
+            None => {
+                ["jpg", "jpeg", "png", "svg"]
+                    .into_iter()
+                    .map(|ext| format!("thumb.{ext}").into())
+                    .collect()
+            }
+        };
+        for name in names {
+            if let Some(zip) = self.zip.as_ref() {
+                if zip.borrow_files().contains_key(Path::new(name.as_str())) {
+                    let data = zip.get_file(name.as_str())?;
+                    return Ok(Some((name, data)));
+                }
+            } else {
+                let path = self.path.join(name.as_str());
+                if path.exists() {
+                    let data = fs::read(path)?;
+                    return Ok(Some((name, data)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
     #[allow(irrefutable_let_patterns)]
     pub fn get_versions(&self, name: &Path) -> Result<Vec<Vec<u8>>> {
         let canon = canonicalize(name);
         let mut versions = Vec::with_capacity(1);
-        if let Some(zip) = self.zip.as_ref() {
+        let excluded = self.excluded.contains(&canon);
+        if excluded {
+            log::trace!("{} left out of {}", canon, self.meta.name);
+        } else if let Some(zip) = self.zip.as_ref() {
             if let Ok(data) =  zip.get_file(canon.as_str()) {
                 versions.push(zstd::decode_all(data.as_slice()).with_context(|| jstr!("Failed to decompress file {&canon} from mod"))?);
             }
         } else if let path = self.path.join(canon.as_str()) && path.exists() {
             versions.push(fs::read(path)?);
         }
-        for opt in &self.options {
+        for opt in self.options.iter().filter(|_| !excluded) {
             let path = Path::new("options").join(&opt.path).join(canon.as_str());
             if let Some(zip) = self.zip.as_ref() {
                 if let Ok(data) =  zip.get_file(path) {
@@ -382,6 +457,21 @@ impl ModReader {
         }
         Ok(versions)
     }
+
+    /// Reads one resource from the mod by its canonical path, without
+    /// unpacking anything else; open the mod with [`ModReader::open_peek`] to
+    /// avoid loading the whole package as well. If the mod and its enabled
+    /// options have more than one version, the last option's is returned.
+    pub fn extract(&self, name: impl AsRef<Path>) -> Result<ResourceData> {
+        let name = name.as_ref();
+        let data = self
+            .get_versions(name)?
+            .pop()
+            .expect("Versions should not be empty");
+        minicbor_ser::from_slice(&data)
+            .map_err(|e| anyhow_ext::anyhow!("{e:?}"))
+            .with_context(|| format!("Failed to parse {} from mod", name.display()))
+    }
 }
 
 static RSTB_EXCLUDE_EXTS: &[&str] = &[
@@ -389,6 +479,168 @@ static RSTB_EXCLUDE_EXTS: &[&str] = &[
 ];
 static RSTB_EXCLUDE_NAMES: &[&str] = &["ActorInfo.product.byml"];
 
+/// Whether a resource, by its canonical name, belongs in the RSTB.
+pub(crate) fn has_rstb_entry(canon: &str) -> bool {
+    let filename = Path::new(canon);
+    !RSTB_EXCLUDE_EXTS.contains(
+        &filename
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default(),
+    ) && !RSTB_EXCLUDE_NAMES.contains(
+        &filename
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default(),
+    )
+}
+
+/// Extra room added to a resource size for the RSTB: a percentage of the
+/// size, then a number of bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RstbPadding {
+    pub percent: u32,
+    pub bytes:   u32,
+}
+
+impl RstbPadding {
+    #[inline]
+    pub fn is_zero(&self) -> bool {
+        self.percent == 0 && self.bytes == 0
+    }
+
+    #[inline]
+    pub fn apply(&self, size: u32) -> u32 {
+        let extra = (size as u64 * self.percent as u64 / 100).min(u32::MAX as u64) as u32;
+        size.saturating_add(extra).saturating_add(self.bytes)
+    }
+}
+
+/// RSTB padding for every resource, which can be overridden for a class of
+/// resource by its file extension, like `bfres` or `baiprog`. A leading `s`
+/// for compressed files is optional, so `bfres` covers `sbfres` as well.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RstbConfig {
+    pub padding: RstbPadding,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub classes: BTreeMap<String, RstbPadding>,
+}
+
+impl RstbConfig {
+    #[inline]
+    pub fn is_default(&self) -> bool {
+        self.padding.is_zero() && self.classes.is_empty()
+    }
+
+    /// The padding to use for a resource, by its canonical name.
+    pub fn padding_for(&self, name: &str) -> RstbPadding {
+        let ext = Path::new(name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        self.classes
+            .get(ext)
+            .or_else(|| ext.strip_prefix('s').and_then(|ext| self.classes.get(ext)))
+            .copied()
+            .unwrap_or(self.padding)
+    }
+
+    #[inline]
+    pub fn apply(&self, name: &str, size: u32) -> u32 {
+        self.padding_for(name).apply(size)
+    }
+}
+
+/// Runtime controls for a running merge, so the user can pause it or limit
+/// how many files are built at once and keep their machine usable.
+#[derive(Debug)]
+pub struct Throttle {
+    paused:   AtomicBool,
+    max_jobs: AtomicUsize,
+    active:   Mutex<usize>,
+    cond:     Condvar,
+}
+
+pub static MERGE_THROTTLE: Throttle = Throttle::new();
+
+/// The yaz0 compression level used unless another is set, which is the same
+/// one the game's own files use.
+pub const DEFAULT_COMPRESSION_LEVEL: u8 = 7;
+
+struct ThrottleGuard<'a>(&'a Throttle);
+
+impl Drop for ThrottleGuard<'_> {
+    fn drop(&mut self) {
+        *self.0.active.lock() -= 1;
+        self.0.cond.notify_all();
+    }
+}
+
+impl Throttle {
+    const fn new() -> Self {
+        Self {
+            paused:   AtomicBool::new(false),
+            max_jobs: AtomicUsize::new(0),
+            active:   parking_lot::const_mutex(0),
+            cond:     Condvar::new(),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+        self.cond.notify_all();
+    }
+
+    /// The maximum number of files merged at once. 0 means no limit beyond
+    /// the size of the thread pool.
+    pub fn max_jobs(&self) -> usize {
+        self.max_jobs.load(Ordering::Relaxed)
+    }
+
+    pub fn set_max_jobs(&self, jobs: usize) {
+        self.max_jobs.store(jobs, Ordering::Relaxed);
+        self.cond.notify_all();
+    }
+
+    fn acquire(&self) -> ThrottleGuard<'_> {
+        let mut active = self.active.lock();
+        loop {
+            let max = self.max_jobs();
+            if !self.is_paused() && (max == 0 || *active < max) {
+                break;
+            }
+            self.cond.wait(&mut active);
+        }
+        *active += 1;
+        ThrottleGuard(self)
+    }
+}
+
+/// Gamedata flag conflict resolutions, by mod name and then flag name.
+pub type FlagResolutions = HashMap<String, HashMap<String, FlagResolution>>;
+
+/// A gamedata flag conflict found while merging, with the name of the mod
+/// whose definition clashed with the ones before it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModFlagConflict {
+    pub mod_name: String,
+    pub conflict: FlagConflict,
+}
+
+/// A resource a mod changes which was skipped because it is missing from the
+/// game dump.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModMissingResource {
+    pub mod_name: String,
+    pub file: String,
+}
+
 #[derive(Debug)]
 pub struct ModUnpacker {
     dump:     Arc<ResourceReader>,
@@ -399,6 +651,17 @@ pub struct ModUnpacker {
     rstb:     DashMap<String, Option<u32>>,
     hashes:   StockHashTable,
     out_dir:  PathBuf,
+    flag_resolutions: FlagResolutions,
+    flag_conflicts: Arc<Mutex<Vec<ModFlagConflict>>>,
+    skip_missing: bool,
+    missing: Arc<Mutex<Vec<ModMissingResource>>>,
+    economy_merge: EconomyMergePolicy,
+    merged_quests: Mutex<Option<QuestProduct>>,
+    merged_actor_info: Mutex<Option<ActorInfo>>,
+    compression_level: u8,
+    skip_unchanged: bool,
+    fallback_langs: Vec<Language>,
+    rstb_config: RstbConfig,
 }
 
 impl ModUnpacker {
@@ -421,6 +684,17 @@ impl ModUnpacker {
                 Endian::Big => botw_utils::hashes::Platform::WiiU,
             }),
             out_dir,
+            flag_resolutions: Default::default(),
+            flag_conflicts: Default::default(),
+            skip_missing: false,
+            missing: Default::default(),
+            economy_merge: Default::default(),
+            merged_quests: Default::default(),
+            merged_actor_info: Default::default(),
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+            skip_unchanged: false,
+            fallback_langs: vec![],
+            rstb_config: Default::default(),
         }
     }
 
@@ -429,6 +703,120 @@ impl ModUnpacker {
         self
     }
 
+    pub fn with_flag_resolutions(mut self, resolutions: FlagResolutions) -> Self {
+        self.flag_resolutions = resolutions;
+        self
+    }
+
+    /// Skip stock resources which are missing from the dump instead of
+    /// failing, recording which mods changed them.
+    pub fn with_skip_missing(mut self, skip: bool) -> Self {
+        self.skip_missing = skip;
+        self
+    }
+
+    /// How to combine prices and other economy fields in general parameter
+    /// lists changed by more than one mod.
+    pub fn with_economy_merge(mut self, policy: EconomyMergePolicy) -> Self {
+        self.economy_merge = policy;
+        self
+    }
+
+    /// The yaz0 compression level for merged files, from 6 (fastest) to 9
+    /// (smallest).
+    pub fn with_compression_level(mut self, level: u8) -> Self {
+        self.compression_level = level.clamp(6, 9);
+        self
+    }
+
+    /// Leave merged files which already exist with the same contents alone
+    /// instead of compressing and writing them again.
+    pub fn with_skip_unchanged(mut self, skip: bool) -> Self {
+        self.skip_unchanged = skip;
+        self
+    }
+
+    /// Other languages to take a mod's text changes from, in order, when it
+    /// has none for the game's language. Versions of the game's language
+    /// for other regions, like EUen for USen, are always tried first.
+    pub fn with_language_fallbacks(mut self, langs: Vec<Language>) -> Self {
+        self.fallback_langs = langs;
+        self
+    }
+
+    /// Extra room to add to the RSTB sizes of merged resources, for mods
+    /// whose files need more memory than the estimates allow.
+    pub fn with_rstb_config(mut self, config: RstbConfig) -> Self {
+        self.rstb_config = config;
+        self
+    }
+
+    /// Every language whose text changes can be merged, most preferred
+    /// first.
+    fn lang_chain(&self) -> IndexSet<Language> {
+        std::iter::once(self.lang)
+            .chain(
+                Language::iter()
+                    .copied()
+                    .filter(|l| l.short() == self.lang.short()),
+            )
+            .chain(self.fallback_langs.iter().copied())
+            .collect()
+    }
+
+    /// The resources skipped so far because they are missing from the dump.
+    /// Shared with the unpacker, like [`Self::flag_conflicts`].
+    pub fn missing_resources(&self) -> Arc<Mutex<Vec<ModMissingResource>>> {
+        self.missing.clone()
+    }
+
+    /// The gamedata flag conflicts found so far. This is shared with the
+    /// unpacker, so it can be taken before calling [`Self::unpack`] and read
+    /// once it has finished.
+    pub fn flag_conflicts(&self) -> Arc<Mutex<Vec<ModFlagConflict>>> {
+        self.flag_conflicts.clone()
+    }
+
+    /// Merges a mod's gamedata, applying any saved flag conflict resolutions
+    /// for it. Unresolved conflicts are recorded, and the later mod's
+    /// definition wins, the same as for any other conflicting edit.
+    fn merge_gamedata(
+        &self,
+        base: &GameDataPack,
+        diff: &GameDataPack,
+        mod_name: &String,
+    ) -> GameDataPack {
+        let mut diff = std::borrow::Cow::Borrowed(diff);
+        if let Some(resolutions) = self.flag_resolutions.get(mod_name) {
+            for (flag, resolution) in resolutions {
+                diff.to_mut().resolve_flag(flag, resolution);
+            }
+        }
+        let conflicts = base.flag_conflicts(&diff);
+        if conflicts.is_empty() {
+            return base.merge(&diff);
+        }
+        let mut base = base.clone();
+        let mut found = self.flag_conflicts.lock();
+        for conflict in conflicts {
+            log::warn!(
+                "Flag {} in {mod_name} is already defined as {} with hash {}, but {mod_name} uses \
+                 {} with hash {}",
+                conflict.name,
+                conflict.existing.0,
+                conflict.existing.1,
+                conflict.incoming.0,
+                conflict.incoming.1,
+            );
+            base.remove_flag(&conflict.name);
+            found.push(ModFlagConflict {
+                mod_name: mod_name.clone(),
+                conflict,
+            });
+        }
+        base.merge(&diff)
+    }
+
     pub fn unpack(self) -> Result<DashMap<String, Option<u32>>> {
         if !self.out_dir.exists() {
             fs::create_dir_all(&self.out_dir)?;
@@ -451,9 +839,9 @@ impl ModUnpacker {
                 .collect();
         }
         let mut modded_langs: IndexSet<Language> = Default::default();
-        for lang in Language::iter().filter(|l| l.short() == self.lang.short()) {
+        for lang in self.lang_chain() {
             if content_files.remove(&lang.bootup_path()) {
-                modded_langs.insert(*lang);
+                modded_langs.insert(lang);
             }
         }
         let (content, aoc) = platform_prefixes(self.endian);
@@ -498,10 +886,13 @@ impl ModUnpacker {
             }
             Ok(())
         })?;
+        self.check_quest_actors();
         Ok(self.rstb)
     }
 
-    fn unpack_texts(&self, mut langs: IndexSet<Language>) -> Result<()> {
+    /// Merges each mod's text changes into the game's language pack, taking
+    /// them from the first language in `langs` the mod has.
+    fn unpack_texts(&self, langs: IndexSet<Language>) -> Result<()> {
         if !langs.is_empty() {
             log::info!("Unpacking game texts");
             let Some(MergeableResource::MessagePack(mut base)) =
@@ -511,11 +902,6 @@ impl ModUnpacker {
             {
                 bail!("Broken stock language pack for {}", self.lang);
             };
-            langs.sort_unstable_by(|l1, l2| {
-                (*l1 == self.lang).cmp(&(*l2 == self.lang)).then_with(|| {
-                    (l1.short() == self.lang.short()).cmp(&(l2.short() == self.lang.short()))
-                })
-            });
             for mod_ in self.mods.iter() {
                 for lang in langs.iter() {
                     if let Ok(packs) = mod_.get_versions(lang.message_path().as_str().as_ref()) {
@@ -537,12 +923,13 @@ impl ModUnpacker {
                 .join(self.lang.bootup_path().as_str());
             out.parent().map(fs::create_dir_all).transpose()?;
             let data = base.into_binary(self.endian);
-            self.rstb.insert(
-                format!("Message/Msg_{}.product.sarc", self.lang).into(),
-                rstb::calc::calc_from_size_and_name(data.len(), "Msg.sarc", self.endian.into()),
-            );
+            let name: String = format!("Message/Msg_{}.product.sarc", self.lang).into();
+            let size =
+                rstb::calc::calc_from_size_and_name(data.len(), "Msg.sarc", self.endian.into())
+                    .map(|size| self.rstb_config.apply(&name, size));
+            self.rstb.insert(name, size);
             let mut sarc = SarcWriter::new(self.endian.into())
-                .with_file(self.lang.message_path(), compress(data));
+                .with_file(self.lang.message_path(), self.compress(data));
             fs::write(out, sarc.to_binary())?;
         }
         Ok(())
@@ -558,13 +945,19 @@ impl ModUnpacker {
         aoc: bool,
     ) -> Result<()> {
         files.into_par_iter().try_for_each(|file| -> Result<()> {
-            let data = self.build_file(file.as_str(), aoc)?;
-            let out_file = dir.join(file.as_str());
-            if let parent = out_file.parent().unwrap() && !parent.exists() {
-                fs::create_dir_all(parent)?;
+            let _permit = MERGE_THROTTLE.acquire();
+            if let Some(data) = self.build_file(file.as_str(), aoc)? {
+                let out_file = dir.join(file.as_str());
+                if let parent = out_file.parent().unwrap() && !parent.exists() {
+                    fs::create_dir_all(parent)?;
+                }
+                if self.skip_unchanged && Self::unchanged(&out_file, &data) {
+                    log::trace!("Skipping unchanged {}", out_file.display());
+                } else {
+                    let mut writer = std::io::BufWriter::new(fs::File::create(&out_file)?);
+                    writer.write_all(&self.compress_if(data.as_ref(), &out_file))?;
+                }
             }
-            let mut writer = std::io::BufWriter::new(fs::File::create(&out_file)?);
-            writer.write_all(&compress_if(data.as_ref(), &out_file))?;
             let progress = 1 + current_file.load(Ordering::Relaxed);
             current_file.store(progress, Ordering::Relaxed);
             let percent = (progress as f64 / total_files as f64) * 100.0;
@@ -580,37 +973,46 @@ impl ModUnpacker {
         })
     }
 
-    fn build_file(&self, file: &str, aoc: bool) -> Result<Vec<u8>> {
+    /// Merges every version of a file. Returns `None` if the file was skipped
+    /// because it is missing from the dump.
+    fn build_file(&self, file: &str, aoc: bool) -> Result<Option<Vec<u8>>> {
         let mut versions = std::collections::VecDeque::with_capacity(
             (self.mods.len() as f32 / 2.).ceil() as usize,
         );
+        let mut owners = std::collections::VecDeque::with_capacity(versions.capacity());
         let canon = if aoc {
             canonicalize(jstr!("Aoc/0010/{file}"))
         } else {
             canonicalize(file)
         };
-        let filename = Path::new(canon.as_str());
         let mut rstb_val = None;
-        let can_rstb = !RSTB_EXCLUDE_EXTS.contains(
-            &filename
-                .extension()
-                .and_then(|e| e.to_str())
-                .unwrap_or_default(),
-        ) && !RSTB_EXCLUDE_NAMES.contains(
-            &filename
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or_default(),
-        );
+        let can_rstb = has_rstb_entry(canon.as_str());
         match self.dump.get_data(file).or_else(|e| {
             log::trace!("{e}");
             self.dump
                 .get_data(canon.as_str())
                 .or_else(|_| self.dump.get_resource(canon.as_str()))
         }) {
-            Ok(ref_res) => versions.push_back(ref_res),
+            Ok(ref_res) => {
+                versions.push_back(ref_res);
+                owners.push_back(None);
+            }
             Err(e) => {
                 log::trace!("{e}");
+                if self.skip_missing && !self.hashes.is_file_new(&canon) {
+                    let mut missing = self.missing.lock();
+                    for mod_ in self
+                        .mods
+                        .iter()
+                        .filter(|mod_| mod_.get_versions(file.as_ref()).is_ok())
+                    {
+                        missing.push(ModMissingResource {
+                            mod_name: mod_.meta.name.clone(),
+                            file: file.into(),
+                        });
+                    }
+                    return Ok(None);
+                }
             }
         }
         for (data, mod_) in self
@@ -619,27 +1021,38 @@ impl ModUnpacker {
             .filter_map(|mod_| {
                 mod_.get_versions(file.as_ref())
                     .ok()
-                    .map(|d| d.into_iter().map(|d| (d, &mod_.meta.name)))
+                    .map(|d| d.into_iter().map(|d| (d, mod_)))
             })
             .flatten()
         {
             versions.push_back(Arc::new(minicbor_ser::from_slice(&data).with_context(
-                || jstr!(r#"Failed to parse mod resource {&file} in mod '{mod_}'"#),
+                || jstr!(r#"Failed to parse mod resource {&file} in mod '{&mod_.meta.name}'"#),
             )?));
+            owners.push_back(Some(mod_));
         }
         let base_version = versions
             .pop_front()
             .with_context(|| format!("No base version for file {}", &file))?;
+        let base_owner = owners.pop_front().flatten();
         let is_modded = !versions.is_empty() || self.hashes.is_file_new(&canon);
         let data = match base_version.as_ref() {
             ResourceData::Binary(_) => {
+                let owner = owners.pop_back().unwrap_or(base_owner);
                 let res = versions.pop_back().unwrap_or(base_version);
                 if can_rstb && is_modded {
-                    rstb_val = Some(rstb::calc::estimate_from_slice_and_name(
-                        res.as_binary().expect("Binary"),
-                        file,
-                        self.endian.into(),
-                    ));
+                    // Packages record the sizes of their unmergeable files,
+                    // so they only need estimating for older ones
+                    rstb_val = Some(
+                        owner
+                            .and_then(|mod_| mod_.manifest.rstb.get(&canon).copied())
+                            .unwrap_or_else(|| {
+                                rstb::calc::estimate_from_slice_and_name(
+                                    res.as_binary().expect("Binary"),
+                                    file,
+                                    self.endian.into(),
+                                )
+                            }),
+                    );
                 }
                 match Arc::try_unwrap(res) {
                     Ok(res) => res.take_binary().unwrap(),
@@ -647,14 +1060,93 @@ impl ModUnpacker {
                 }
             }
             ResourceData::Mergeable(base_res) => {
-                let merged = versions
-                    .into_iter()
-                    .fold(base_res.clone(), |mut res, version| {
+                let merged = versions.into_iter().zip(owners).fold(
+                    base_res.clone(),
+                    |mut res, (version, owner)| {
                         if let Some(mergeable) = version.as_mergeable() {
-                            res = res.merge(mergeable);
+                            res = match (&res, mergeable, owner) {
+                                (
+                                    MergeableResource::GameDataPack(base),
+                                    MergeableResource::GameDataPack(diff),
+                                    Some(mod_),
+                                ) => MergeableResource::GameDataPack(Box::new(
+                                    self.merge_gamedata(base, diff, &mod_.meta.name),
+                                )),
+                                (
+                                    MergeableResource::GeneralParamList(current),
+                                    MergeableResource::GeneralParamList(diff),
+                                    _,
+                                ) if self.economy_merge != EconomyMergePolicy::LastWins => {
+                                    match base_res {
+                                        MergeableResource::GeneralParamList(vanilla) => {
+                                            MergeableResource::GeneralParamList(Box::new(
+                                                current.merge_economy(
+                                                    diff,
+                                                    vanilla,
+                                                    self.economy_merge,
+                                                ),
+                                            ))
+                                        }
+                                        _ => res.merge(mergeable),
+                                    }
+                                }
+                                _ => res.merge(mergeable),
+                            };
                         }
                         res
-                    });
+                    },
+                );
+                if is_modded {
+                    match (base_res, &merged) {
+                        // Quest actors can only be checked once the actor info
+                        // is merged too, so both wait for the end of the build.
+                        (_, MergeableResource::QuestProduct(quests)) => {
+                            *self.merged_quests.lock() = Some(QuestProduct::clone(quests));
+                        }
+                        (_, MergeableResource::ActorInfo(info)) => {
+                            *self.merged_actor_info.lock() = Some(ActorInfo::clone(info));
+                        }
+                        (
+                            MergeableResource::ResidentActors(base),
+                            MergeableResource::ResidentActors(merged),
+                        ) => {
+                            let added = merged.added_since(base);
+                            if added.len() > ResidentActors::ADDED_BUDGET {
+                                log::warn!(
+                                    "Mods add {} resident actors to {file}, which stay loaded \
+                                     for the whole game and may use too much memory on \
+                                     console: {}",
+                                    added.len(),
+                                    added
+                                        .iter()
+                                        .map(|name| name.as_str())
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                );
+                            }
+                        }
+                        (
+                            MergeableResource::ResidentEvents(base),
+                            MergeableResource::ResidentEvents(merged),
+                        ) => {
+                            let added = merged.added_since(base);
+                            if added.len() > ResidentEvents::ADDED_BUDGET {
+                                log::warn!(
+                                    "Mods add {} resident events to {file}, which stay loaded \
+                                     for the whole game and may use too much memory on \
+                                     console: {}",
+                                    added.len(),
+                                    added
+                                        .iter()
+                                        .map(|(_, file)| file.as_str())
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                );
+                            }
+                        }
+                        _ => (),
+                    }
+                }
                 let data = merged.into_binary(self.endian);
                 if can_rstb && (is_modded || self.hashes.is_file_modded(&canon, &data, true)) {
                     rstb_val = Some(rstb::calc::estimate_from_slice_and_name(
@@ -688,20 +1180,86 @@ impl ModUnpacker {
             }
         };
         if let Some(val) = rstb_val {
+            let val = val.map(|size| self.rstb_config.apply(&canon, size));
             self.rstb.insert(canon, val);
         }
-        Ok(data)
+        Ok(Some(data))
+    }
+
+    /// Warns about quest steps bound to actors which won't exist once
+    /// everything is merged. Runs after the build, against the merged actor
+    /// info if mods changed it or the stock one if not.
+    fn check_quest_actors(&self) {
+        let Some(quests) = self.merged_quests.lock().take() else {
+            return;
+        };
+        let info = match self.merged_actor_info.lock().take() {
+            Some(info) => Ok(info),
+            None => {
+                self.dump
+                    .get_data("Actor/ActorInfo.product.sbyml")
+                    .map_err(anyhow_ext::Error::from)
+                    .and_then(|res| {
+                        match ResourceData::clone(&res).take_mergeable() {
+                            Some(MergeableResource::ActorInfo(info)) => Ok(*info),
+                            _ => bail!("Broken stock actor info"),
+                        }
+                    })
+            }
+        };
+        match info {
+            Ok(info) => {
+                for missing in quests.missing_actors(&info) {
+                    log::warn!("{missing}");
+                }
+            }
+            Err(e) => log::warn!("Could not check quest actors: {e:?}"),
+        }
+    }
+
+    fn compress(&self, data: impl AsRef<[u8]>) -> Vec<u8> {
+        compress_with_options(data, CompressOptions {
+            compression_level: self.compression_level.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Compresses files with yaz0 extensions (`.sbactorpack`, `.sbyml`, and
+    /// so on, but not `.sarc`), like [`roead::yaz0::compress_if`], at the
+    /// configured level.
+    fn compress_if<'a>(&self, data: &'a [u8], path: impl AsRef<Path>) -> Cow<'a, [u8]> {
+        let compressed = path
+            .as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map_or(false, |ext| ext.starts_with('s') && ext != "sarc");
+        if compressed {
+            Cow::Owned(self.compress(data))
+        } else {
+            Cow::Borrowed(data)
+        }
+    }
+
+    /// Whether a merged file already exists with exactly this content, once
+    /// decompressed. Decompressing is far cheaper than compressing again.
+    fn unchanged(out_file: &Path, data: &[u8]) -> bool {
+        fs::read(out_file)
+            .map(|existing| decompress_if(&existing).as_ref() == data)
+            .unwrap_or(false)
     }
 
     fn build_sarc(&self, sarc: SarcMap, aoc: bool) -> Result<Vec<u8>> {
         let mut writer = SarcWriter::new(self.endian.into()).with_min_alignment(sarc.alignment);
         for file in sarc.files.into_iter() {
-            let data = self
+            let Some(data) = self
                 .build_file(&file, aoc)
-                .with_context(|| jstr!("Failed to build file {&file} for SARC"))?;
+                .with_context(|| jstr!("Failed to build file {&file} for SARC"))?
+            else {
+                continue;
+            };
             writer.add_file(
                 file.as_str(),
-                compress_if(data.as_ref(), file.as_str()).as_ref(),
+                self.compress_if(data.as_ref(), file.as_str()).as_ref(),
             );
         }
         Ok(writer.to_binary())
@@ -751,6 +1309,14 @@ mod tests {
         dbg!(&mod_reader.manifest);
     }
 
+    #[test]
+    fn extract_file() {
+        let mod_reader = ModReader::open_peek("test/wiiu.zip", vec![]).unwrap();
+        let name = mod_reader.manifest.resources().next().unwrap();
+        dbg!(mod_reader.extract(name.as_str()).unwrap());
+        assert!(mod_reader.extract("Actor/Missing.bxml").is_err());
+    }
+
     #[test]
     fn unpack_mod() {
         let mod_reader = ModReader::open("test/wiiu.zip", vec![]).unwrap();
@@ -775,6 +1341,24 @@ mod tests {
         let out_path = "test/wiiu_unzip";
         super::unzip_mod(mod_path.as_ref(), out_path.as_ref()).unwrap();
     }
+
+    #[test]
+    fn rstb_padding() {
+        let mut config = RstbConfig {
+            padding: RstbPadding {
+                percent: 10,
+                bytes:   100,
+            },
+            ..Default::default()
+        };
+        config.classes.insert("bfres".into(), RstbPadding {
+            percent: 50,
+            bytes:   0,
+        });
+        assert_eq!(config.apply("Actor/Pack/Foo.sbactorpack", 1000), 1200);
+        assert_eq!(config.apply("Model/Foo.sbfres", 1000), 1500);
+        assert_eq!(RstbConfig::default().apply("Model/Foo.sbfres", 1000), 1000);
+    }
 }
 
 #[cfg(test)]
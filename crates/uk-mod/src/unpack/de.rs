@@ -91,6 +91,7 @@ impl<'de> Deserialize<'de> for ModReader {
                     meta,
                     manifest,
                     options,
+                    excluded: Default::default(),
                     zip: Some(
                         super::ParallelZipReader::open(&path, false)
                             .map_err(serde::de::Error::custom)?,
@@ -0,0 +1,93 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ROMError, ResourceLoader, Result};
+
+/// Several game dumps read in priority order, so a file missing from one,
+/// like an update folder on its own, is read from the next.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Chain {
+    sources: Vec<Box<dyn ResourceLoader>>,
+}
+
+impl Chain {
+    pub(crate) fn new(sources: Vec<Box<dyn ResourceLoader>>) -> Result<Self> {
+        if sources.is_empty() {
+            return Err(ROMError::OtherMessage("No game dumps to read from"));
+        }
+        log::info!(
+            "Using {} game dumps: {}",
+            sources.len(),
+            sources
+                .iter()
+                .map(|source| source.host_path().display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Ok(Self { sources })
+    }
+
+    /// Tries each source in turn, returning the first file found or else the
+    /// first source's error.
+    fn find<'a>(
+        &self,
+        name: &Path,
+        sources: impl Iterator<Item = &'a Box<dyn ResourceLoader>>,
+        read: impl Fn(&dyn ResourceLoader) -> Result<Vec<u8>>,
+    ) -> Result<Vec<u8>> {
+        let mut error = None;
+        for source in sources {
+            match read(source.as_ref()) {
+                Ok(data) => return Ok(data),
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        Err(error.unwrap_or_else(|| {
+            ROMError::FileNotFound(
+                name.to_string_lossy().into(),
+                self.host_path().to_path_buf(),
+            )
+        }))
+    }
+}
+
+#[typetag::serde]
+impl ResourceLoader for Chain {
+    fn get_data(&self, name: &Path) -> Result<Vec<u8>> {
+        self.find(name, self.sources.iter(), |source| source.get_data(name))
+    }
+
+    fn get_aoc_file_data(&self, name: &Path) -> Result<Vec<u8>> {
+        self.find(
+            name,
+            self.sources.iter().filter(|source| source.has_aoc()),
+            |source| source.get_aoc_file_data(name),
+        )
+    }
+
+    fn file_exists(&self, name: &Path) -> bool {
+        self.sources.iter().any(|source| source.file_exists(name))
+    }
+
+    fn host_path(&self) -> &Path {
+        self.sources
+            .first()
+            .map(|source| source.host_path())
+            .unwrap_or_else(|| Path::new(""))
+    }
+
+    fn content_dirs(&self) -> Vec<&Path> {
+        self.sources
+            .iter()
+            .map(|source| source.content_dirs())
+            .find(|dirs| !dirs.is_empty())
+            .unwrap_or_default()
+    }
+
+    fn has_aoc(&self) -> bool {
+        self.sources.iter().any(|source| source.has_aoc())
+    }
+}
@@ -0,0 +1,356 @@
+//! Looks for BOTW game dumps in the places they are usually kept, so they can
+//! be offered to the user instead of having to find every folder by hand.
+use std::path::{Path, PathBuf};
+
+use fs_err as fs;
+use uk_content::prelude::Endian;
+
+use crate::{zarchive::ZArchive, ResourceReader, Result, AOC_TEST, CONTENT_TEST, UPDATE_TEST};
+
+/// How deep to look inside each folder. This is enough to reach the DLC in
+/// an MLC folder from the MLC folder itself, e.g.
+/// `usr/title/0005000c/101c9400/content/0010`.
+const MAX_DEPTH: usize = 6;
+
+/// A game dump found by [`detect_dumps`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DumpCandidate {
+    /// Unpacked base game, update, and DLC folders. For Switch, the base game
+    /// folder includes the update files.
+    Unpacked {
+        endian:      Endian,
+        content_dir: Option<PathBuf>,
+        update_dir:  Option<PathBuf>,
+        aoc_dir:     Option<PathBuf>,
+    },
+    /// A WUA file with the whole Wii U game
+    ZArchive(PathBuf),
+}
+
+impl DumpCandidate {
+    /// Which platform the dump is for.
+    pub fn endian(&self) -> Endian {
+        match self {
+            Self::Unpacked { endian, .. } => *endian,
+            Self::ZArchive(_) => Endian::Big,
+        }
+    }
+
+    /// Whether the dump includes the DLC.
+    pub fn has_aoc(&self) -> bool {
+        match self {
+            Self::Unpacked { aoc_dir, .. } => aoc_dir.is_some(),
+            // Only WUAs with the base game and update are listed, and the DLC
+            // is all that could be missing
+            Self::ZArchive(path) => {
+                ZArchive::new(path)
+                    .map(|archive| crate::ResourceLoader::has_aoc(&archive))
+                    .unwrap_or(false)
+            }
+        }
+    }
+
+    /// Opens the dump for use.
+    pub fn open(&self) -> Result<ResourceReader> {
+        match self {
+            Self::Unpacked {
+                content_dir,
+                update_dir,
+                aoc_dir,
+                ..
+            } => {
+                ResourceReader::from_unpacked_dirs(
+                    content_dir.as_ref(),
+                    update_dir.as_ref(),
+                    aoc_dir.as_ref(),
+                )
+            }
+            Self::ZArchive(path) => ResourceReader::from_zarchive(path),
+        }
+    }
+}
+
+impl std::fmt::Display for DumpCandidate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unpacked {
+                endian,
+                content_dir,
+                update_dir,
+                aoc_dir,
+            } => {
+                let dirs = [content_dir, update_dir, aoc_dir]
+                    .into_iter()
+                    .flatten()
+                    .map(|dir| dir.display().to_string())
+                    .collect::<Vec<_>>();
+                write!(
+                    f,
+                    "Unpacked {} dump ({})",
+                    match endian {
+                        Endian::Big => "Wii U",
+                        Endian::Little => "Switch",
+                    },
+                    dirs.join(", ")
+                )
+            }
+            Self::ZArchive(path) => write!(f, "WUA ({})", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Part {
+    Base,
+    Update,
+    Aoc,
+}
+
+/// Works out whether a folder is the root of an unpacked base game, update,
+/// or DLC. Wii U dumps end in `content`, or `content/0010` for the DLC, and
+/// Switch dumps end in `romfs`. A Switch base game with the update files
+/// merged in counts as the base game.
+fn classify(dir: &Path) -> Option<(Endian, Part)> {
+    let name = dir.file_name()?.to_str()?.to_ascii_lowercase();
+    let endian = match name.as_str() {
+        "content" | "0010" => Endian::Big,
+        "romfs" => Endian::Little,
+        _ => return None,
+    };
+    if dir.join(AOC_TEST).exists() {
+        return Some((endian, Part::Aoc));
+    }
+    let (base, update) = (
+        dir.join(CONTENT_TEST).exists(),
+        dir.join(UPDATE_TEST).exists(),
+    );
+    match endian {
+        Endian::Little if base => Some((endian, Part::Base)),
+        // The title type in an MLC path is more reliable than the test files
+        // when both are present
+        Endian::Big if base || update => {
+            let title_type = dir
+                .ancestors()
+                .nth(2)
+                .and_then(|dir| dir.file_name()?.to_str())
+                .map(|t| t.to_ascii_lowercase());
+            match title_type.as_deref() {
+                Some("0005000e") => Some((endian, Part::Update)),
+                Some("00050000") => Some((endian, Part::Base)),
+                _ if update && !base => Some((endian, Part::Update)),
+                _ => Some((endian, Part::Base)),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Everything found inside one of the searched folders.
+#[derive(Debug, Default)]
+struct Found {
+    wiiu:   [Option<PathBuf>; 3],
+    switch: [Option<PathBuf>; 3],
+    wuas:   Vec<PathBuf>,
+}
+
+impl Found {
+    fn scan(&mut self, dir: &Path, depth: usize) {
+        if let Some((endian, part)) = classify(dir) {
+            log::debug!("Found {:?} {:?} files at {}", endian, part, dir.display());
+            let parts = match endian {
+                Endian::Big => &mut self.wiiu,
+                Endian::Little => &mut self.switch,
+            };
+            parts[part as usize].get_or_insert_with(|| dir.to_path_buf());
+            return;
+        }
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for path in entries.filter_map(|e| e.ok().map(|e| e.path())) {
+            let hidden = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.starts_with('.') || n.starts_with('$'))
+                .unwrap_or(true);
+            if hidden {
+                continue;
+            }
+            if path.is_dir() {
+                if depth < MAX_DEPTH {
+                    self.scan(&path, depth + 1);
+                }
+            } else if path
+                .extension()
+                .map(|ext| ext.eq_ignore_ascii_case("wua"))
+                .unwrap_or(false)
+                && ZArchive::new(&path).is_ok()
+            {
+                log::debug!("Found WUA at {}", path.display());
+                self.wuas.push(path);
+            }
+        }
+    }
+
+    /// The usable dumps among what was found: the Wii U needs the base game
+    /// and the update, and Switch needs the base game with the update.
+    fn candidates(self) -> impl Iterator<Item = DumpCandidate> {
+        let [wiiu_base, wiiu_update, wiiu_aoc] = self.wiiu;
+        let [switch_base, _, switch_aoc] = self.switch;
+        let wiiu = (wiiu_base.is_some() && wiiu_update.is_some()).then_some(
+            DumpCandidate::Unpacked {
+                endian:      Endian::Big,
+                content_dir: wiiu_base,
+                update_dir:  wiiu_update,
+                aoc_dir:     wiiu_aoc,
+            },
+        );
+        let switch = switch_base.is_some().then_some(DumpCandidate::Unpacked {
+            endian:      Endian::Little,
+            content_dir: switch_base,
+            update_dir:  None,
+            aoc_dir:     switch_aoc,
+        });
+        wiiu.into_iter()
+            .chain(switch)
+            .chain(self.wuas.into_iter().map(DumpCandidate::ZArchive))
+    }
+}
+
+/// Reads the values of every `tag` element in a simple XML file, like Cemu's
+/// settings.
+fn xml_values<'a>(text: &'a str, tag: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{tag}>"), format!("</{tag}>"));
+    text.split(open.as_str())
+        .skip(1)
+        .filter_map(|s| s.split_once(close.as_str()).map(|(value, _)| value.trim()))
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+/// The folders emulators keep game dumps in: Cemu's MLC folder and game
+/// paths, Yuzu's RomFS dump folder, and Ryujinx's user data folder.
+fn emulator_dirs() -> Vec<PathBuf> {
+    let mut roots = vec![];
+    for (name, flatpak) in [
+        ("Cemu", "info.cemu.Cemu"),
+        ("yuzu", "org.yuzu_emu.yuzu"),
+        ("Ryujinx", "org.ryujinx.Ryujinx"),
+    ] {
+        roots.extend(
+            [dirs2::data_dir(), dirs2::data_local_dir(), dirs2::config_dir()]
+                .into_iter()
+                .flatten()
+                .map(|dir| (name, dir.join(name))),
+        );
+        if let Some(home) = dirs2::home_dir() {
+            let flatpak = home.join(".var/app").join(flatpak);
+            roots.push((name, flatpak.join("data").join(name)));
+            roots.push((name, flatpak.join("config").join(name)));
+        }
+    }
+    let mut dirs = vec![];
+    for (name, root) in roots.into_iter().filter(|(_, root)| root.is_dir()) {
+        match name {
+            "Cemu" => {
+                dirs.push(root.join("mlc01"));
+                if let Ok(text) = fs::read_to_string(root.join("settings.xml")) {
+                    dirs.extend(
+                        xml_values(&text, "mlc_path")
+                            .into_iter()
+                            .chain(xml_values(&text, "Entry"))
+                            .map(PathBuf::from),
+                    );
+                }
+            }
+            "yuzu" => dirs.push(root.join("dump")),
+            _ => dirs.push(root),
+        }
+    }
+    dirs
+}
+
+fn scan(dirs: impl IntoIterator<Item = PathBuf>) -> Vec<DumpCandidate> {
+    let mut candidates: Vec<DumpCandidate> = vec![];
+    for dir in dirs.into_iter().filter(|dir| dir.is_dir()) {
+        log::debug!("Looking for game dumps in {}", dir.display());
+        let mut found = Found::default();
+        found.scan(&dir, 0);
+        for candidate in found.candidates() {
+            if !candidates.contains(&candidate) {
+                log::info!("Found game dump: {candidate}");
+                candidates.push(candidate);
+            }
+        }
+    }
+    candidates
+}
+
+/// Looks for BOTW dumps in the usual places for Cemu, Yuzu, and Ryujinx, as
+/// well as in `extra_dirs`, such as drives or folders chosen by the user.
+/// Only dumps with at least the base game and update are returned.
+pub fn detect_dumps(
+    extra_dirs: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Vec<DumpCandidate> {
+    scan(
+        emulator_dirs()
+            .into_iter()
+            .chain(extra_dirs.into_iter().map(|dir| dir.as_ref().to_path_buf())),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use uk_content::prelude::Endian;
+
+    use super::DumpCandidate;
+
+    fn touch(path: &Path) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, b"").unwrap();
+    }
+
+    #[test]
+    fn scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let mlc = dir.path().join("mlc01");
+        let title = mlc.join("usr/title");
+        let content = title.join("00050000/101c9400/content");
+        let update = title.join("0005000E/101C9400/content");
+        let aoc = title.join("0005000c/101c9400/content/0010");
+        touch(&content.join(super::CONTENT_TEST));
+        touch(&update.join(super::UPDATE_TEST));
+        touch(&aoc.join(super::AOC_TEST));
+        let switch = dir.path().join("dumps/01007EF00011E800/romfs");
+        touch(&switch.join(super::CONTENT_TEST));
+        touch(&switch.join(super::UPDATE_TEST));
+        touch(&dir.path().join("dumps/empty/romfs/Pack/Dungeon000.pack"));
+
+        let candidates = super::scan([mlc, dir.path().join("dumps")]);
+        assert_eq!(candidates, vec![
+            DumpCandidate::Unpacked {
+                endian:      Endian::Big,
+                content_dir: Some(content),
+                update_dir:  Some(update),
+                aoc_dir:     Some(aoc),
+            },
+            DumpCandidate::Unpacked {
+                endian:      Endian::Little,
+                content_dir: Some(switch),
+                update_dir:  None,
+                aoc_dir:     None,
+            }
+        ]);
+        assert!(super::scan([dir.path().join("mlc01/usr/title/0005000c")]).is_empty());
+    }
+
+    #[test]
+    fn xml_values() {
+        let text = "<content><mlc_path>C:\\mlc01</mlc_path><GamePaths><Entry>D:\\Games</Entry>\
+                    <Entry></Entry></GamePaths></content>";
+        assert_eq!(super::xml_values(text, "mlc_path"), ["C:\\mlc01"]);
+        assert_eq!(super::xml_values(text, "Entry"), ["D:\\Games"]);
+    }
+}
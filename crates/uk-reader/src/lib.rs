@@ -1,19 +1,30 @@
 #![feature(let_chains, once_cell)]
-// mod nsp;
+mod chain;
+pub mod detect;
+mod nsp;
+mod remote;
 mod unpacked;
+mod wud;
 mod zarchive;
 
 use std::{
+    hash::Hasher,
     path::{Path, PathBuf},
-    sync::{Arc, LazyLock, Once},
+    sync::{
+        atomic::{AtomicU32, AtomicUsize, Ordering},
+        Arc, LazyLock, Once, OnceLock,
+    },
     time::Duration,
 };
 
 use anyhow_ext::Context;
+use botw_utils::hashes::{Platform, StockHashTable};
 use dashmap::DashMap;
+use fs_err as fs;
 use include_flate::flate;
 use join_str::jstr;
 use moka::sync::Cache;
+use parking_lot::RwLock;
 // use once_cell::sync::Lazy;
 use roead::sarc::Sarc;
 use serde::{Deserialize, Serialize};
@@ -23,7 +34,9 @@ use uk_content::{
     util::HashMap,
 };
 
-use self::{unpacked::Unpacked, zarchive::ZArchive};
+use self::{
+    chain::Chain, nsp::Nsp, remote::Remote, unpacked::Unpacked, wud::Wud, zarchive::ZArchive,
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ROMError {
@@ -52,11 +65,126 @@ impl From<ROMError> for uk_content::UKError {
 }
 
 flate!(static NEST_MAP: str from "data/nest_map.json");
-type ResourceCache = Cache<String, Arc<ResourceData>>;
+/// Files which are only found in the base game, update, and DLC
+/// respectively, used to check that a dump is what it claims to be.
+static CONTENT_TEST: &str = "Map/MainField/A-1/A-1.00_Clustering.sblwp";
+static UPDATE_TEST: &str = "Actor/Pack/Enemy_Lynel_Dark.sbactorpack";
+static AOC_TEST: &str = "Pack/AocMainField.pack";
+/// Cached resources along with the size of the file each was read from,
+/// which stands in for how much memory it takes up.
+type ResourceCache = Cache<String, (Arc<ResourceData>, u32)>;
 type SarcCache = Cache<String, Arc<Sarc<'static>>>;
-const CACHE_SIZE: usize = 10000;
+/// The default memory budget for cached resources, in MB.
+pub const DEFAULT_CACHE_MB: u32 = 1024;
+static CACHE_MB: AtomicU32 = AtomicU32::new(DEFAULT_CACHE_MB);
+
+/// Sets roughly how much memory, in MB, each [`ResourceReader`] created
+/// afterwards may use to keep resources it has already read. The least
+/// recently used resources are dropped to stay within it.
+pub fn set_cache_budget(mb: u32) {
+    CACHE_MB.store(mb.max(1), Ordering::Relaxed);
+}
+
+/// Bump this whenever the layout of [`ResourceData`] changes, so resources
+/// cached on disk by older versions are not read.
+const DISK_CACHE_VERSION: u32 = 2;
+static DISK_CACHE: LazyLock<RwLock<Option<PathBuf>>> = LazyLock::new(|| RwLock::new(None));
+
+/// Hashes names for files kept on disk. Unlike the standard library's hasher,
+/// the result is the same from one build to the next, so the files are found
+/// again after updating.
+pub(crate) fn stable_hash(parts: &[&[u8]]) -> String {
+    let mut hasher = twox_hash::XxHash64::with_seed(0);
+    for part in parts {
+        hasher.write(part);
+        hasher.write_u8(0);
+    }
+    format!("{:016x}", hasher.finish()).into()
+}
+
+/// Keeps parsed resources from game dumps in `dir`, so they don't need to be
+/// decompressed and parsed again the next time UKMM runs, or stops doing so
+/// with `None`. Dumps already in use keep the setting they started with.
+pub fn set_disk_cache(dir: Option<PathBuf>) {
+    *DISK_CACHE.write() = dir.filter(|dir| !dir.as_os_str().is_empty());
+}
+
+static REMOTE_CACHE: LazyLock<RwLock<Option<PathBuf>>> = LazyLock::new(|| RwLock::new(None));
+
+/// Keeps files downloaded from remote game dumps in `dir`. Remote dumps set
+/// up afterwards use it; otherwise they fall back to the system temp folder.
+pub fn set_remote_cache(dir: PathBuf) {
+    *REMOTE_CACHE.write() = Some(dir).filter(|dir| !dir.as_os_str().is_empty());
+}
+
 pub type Result<T> = std::result::Result<T, ROMError>;
 
+static WIIU_HASH_TABLE: LazyLock<StockHashTable> =
+    LazyLock::new(|| StockHashTable::new(&Platform::WiiU));
+static NX_HASH_TABLE: LazyLock<StockHashTable> =
+    LazyLock::new(|| StockHashTable::new(&Platform::Switch));
+
+/// The files checked by [`ResourceReader::validate`]: every pack in the
+/// nest map except the language packs, which only some regions have, and
+/// every 8th actor and event pack.
+static VALIDATION_FILES: LazyLock<Vec<std::string::String>> = LazyLock::new(|| {
+    let nest_map: HashMap<String, std::string::String> =
+        serde_json::from_str(NEST_MAP.as_ref()).unwrap();
+    let parents = nest_map
+        .into_values()
+        .filter_map(|path| path.split("//").next().map(|p| p.to_owned()))
+        .collect::<std::collections::BTreeSet<_>>();
+    let (packs, rest): (Vec<_>, Vec<_>) = parents
+        .into_iter()
+        .partition(|path| !path.starts_with("Actor/") && !path.starts_with("Event/"));
+    packs
+        .into_iter()
+        .filter(|path| !path.starts_with("Pack/Bootup_") || path == "Pack/Bootup_Graphics.pack")
+        .chain(rest.into_iter().step_by(8))
+        .collect()
+});
+
+/// The versions of the game and DLC in a dump, as read by
+/// [`ResourceReader::version`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DumpVersion {
+    /// The game version, e.g. `1.5.0` for the latest Wii U update or `1.6.0`
+    /// for Switch
+    pub game: Option<String>,
+    /// The DLC version, if the dump has the DLC
+    pub aoc:  Option<String>,
+}
+
+/// What [`ResourceReader::validate`] found in a game dump.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DumpReport {
+    /// How many files were checked
+    pub checked: usize,
+    /// Files which are not in the dump at all
+    pub missing: Vec<String>,
+    /// Files which are in the dump but differ from the game's own copies
+    pub corrupt: Vec<String>,
+}
+
+impl DumpReport {
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty()
+    }
+}
+
+impl std::fmt::Display for DumpReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Checked {} files: {} missing, {} corrupt",
+            self.checked,
+            self.missing.len(),
+            self.corrupt.len()
+        )
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BinType {
     Nintendo,
@@ -95,16 +223,24 @@ pub trait ResourceLoader: std::fmt::Debug + Send + Sync {
     fn get_aoc_file_data(&self, name: &Path) -> Result<Vec<u8>>;
     fn file_exists(&self, name: &Path) -> bool;
     fn host_path(&self) -> &Path;
+    /// The unpacked base game and update `content` folders, in that order,
+    /// for dumps which have them on disk.
+    fn content_dirs(&self) -> Vec<&Path> {
+        vec![]
+    }
+    /// Whether the dump includes the DLC.
+    fn has_aoc(&self) -> bool {
+        false
+    }
 }
 
 fn construct_res_cache() -> ResourceCache {
-    log::debug!(
-        "Initializing resource cache (up to {} resources)",
-        CACHE_SIZE
-    );
+    let budget = CACHE_MB.load(Ordering::Relaxed);
+    log::debug!("Initializing resource cache (up to {} MB)", budget);
     ResourceCache::builder()
-        .max_capacity(CACHE_SIZE as u64)
-        .initial_capacity(CACHE_SIZE / 10)
+        .max_capacity(budget as u64 * 1024 * 1024)
+        .weigher(|_, (_, size)| *size)
+        .initial_capacity(1000)
         .time_to_idle(Duration::from_secs(30))
         .build()
 }
@@ -123,6 +259,11 @@ pub struct ResourceReader {
     sarc_cache: SarcCache,
     #[serde(skip)]
     nest_map: Arc<DashMap<String, Arc<str>>>,
+    /// This dump's folder in the disk cache, worked out on first use
+    #[serde(skip)]
+    disk_cache: OnceLock<Option<PathBuf>>,
+    #[serde(skip)]
+    version: OnceLock<DumpVersion>,
 }
 
 impl PartialEq for ResourceReader {
@@ -146,6 +287,122 @@ impl ResourceReader {
         self.cache.invalidate_all();
     }
 
+    /// The folder for this dump's resources in the disk cache, if it is on.
+    /// It is named for the dump and its version, so resources from a dump
+    /// which has since changed are not used.
+    fn disk_cache_dir(&self) -> Option<&Path> {
+        if self.bin_type != BinType::Nintendo {
+            return None;
+        }
+        self.disk_cache
+            .get_or_init(|| {
+                let root = DISK_CACHE.read().clone()?;
+                let version = self.version();
+                let hash = stable_hash(&[
+                    self.source_ser().as_bytes(),
+                    version.game.as_deref().unwrap_or_default().as_bytes(),
+                    version.aoc.as_deref().unwrap_or_default().as_bytes(),
+                    &DISK_CACHE_VERSION.to_le_bytes(),
+                    env!("CARGO_PKG_VERSION").as_bytes(),
+                ]);
+                let dir = root.join(hash.as_str());
+                log::debug!("Using resource cache at {}", dir.display());
+                Some(dir)
+            })
+            .as_deref()
+    }
+
+    fn disk_cache_path(&self, canon: &str) -> Option<PathBuf> {
+        let hash = stable_hash(&[canon.as_bytes()]);
+        Some(self.disk_cache_dir()?.join(&hash[..2]).join(&hash[2..]))
+    }
+
+    /// Reads a resource from the disk cache, along with its size there.
+    fn read_disk_cache(&self, canon: &str) -> Option<(ResourceData, usize)> {
+        let data = std::fs::read(self.disk_cache_path(canon)?).ok()?;
+        let data = zstd::decode_all(data.as_slice()).ok()?;
+        let resource = minicbor_ser::from_slice(&data).ok()?;
+        log::trace!("Resource {} found in disk cache", canon);
+        Some((resource, data.len()))
+    }
+
+    fn write_disk_cache(&self, canon: &str, resource: &ResourceData) {
+        static TEMP_ID: AtomicUsize = AtomicUsize::new(0);
+        // Reading a pack from the dump also caches the files inside it, which
+        // a cached copy of its file list would skip, so packs are not cached
+        if resource.as_sarc().is_some() {
+            return;
+        }
+        let Some(path) = self.disk_cache_path(canon) else {
+            return;
+        };
+        if path.exists() {
+            return;
+        }
+        // Written under a unique name first, since other threads may be
+        // reading or writing the same resource
+        let temp = path.with_extension(format!("{}.tmp", TEMP_ID.fetch_add(1, Ordering::Relaxed)));
+        let result = minicbor_ser::to_vec(resource)
+            .map_err(|e| anyhow_ext::anyhow!("{e:?}"))
+            .and_then(|data| Ok(zstd::encode_all(data.as_slice(), 3)?))
+            .and_then(|data| {
+                fs::create_dir_all(path.parent().expect("Cache files have a parent"))?;
+                fs::write(&temp, data)?;
+                fs::rename(&temp, &path)?;
+                Ok(())
+            });
+        if let Err(e) = result {
+            log::debug!("Failed to cache {} on disk: {:?}", canon, e);
+        }
+    }
+
+    /// Checks a sample of the dump's files against hashes of the game's own
+    /// copies, listing any which are missing or don't match. The DLC is
+    /// checked too if the dump has it. Every file checked is read in full, so
+    /// this takes a little while.
+    pub fn validate(&self) -> DumpReport {
+        let tables = [&*WIIU_HASH_TABLE, &*NX_HASH_TABLE];
+        let mut report = DumpReport::default();
+        // Corrupt files for each platform, since the dump could be for either
+        let mut corrupt: [Vec<String>; 2] = Default::default();
+        for path in VALIDATION_FILES.iter() {
+            let canon = canonicalize(path);
+            let aoc_canon = jstr!("Aoc/0010/{&canon}");
+            let (canon, data) = if tables.iter().any(|t| !t.is_file_new(&canon)) {
+                (canon, self.source.get_data(path.as_ref()))
+            } else if self.source.has_aoc() && tables.iter().any(|t| !t.is_file_new(&aoc_canon)) {
+                (
+                    aoc_canon.into(),
+                    self.source.get_aoc_file_data(path.as_ref()),
+                )
+            } else {
+                continue;
+            };
+            report.checked += 1;
+            match data {
+                Ok(data) => {
+                    let data = roead::yaz0::decompress_if(&data);
+                    for (table, corrupt) in tables.iter().zip(corrupt.iter_mut()) {
+                        if table.is_file_modded(&canon, &*data, true) {
+                            corrupt.push(canon.clone());
+                        }
+                    }
+                }
+                Err(ROMError::FileNotFound(..)) => report.missing.push(canon),
+                Err(e) => {
+                    log::warn!("Failed to read {} from game dump: {}", path, e);
+                    report.corrupt.push(canon);
+                }
+            }
+        }
+        let [wiiu, nx] = corrupt;
+        report
+            .corrupt
+            .extend(if wiiu.len() <= nx.len() { wiiu } else { nx });
+        log::info!("Validated game dump. {}", report);
+        report
+    }
+
     pub fn source(&self) -> &dyn ResourceLoader {
         self.source.as_ref()
     }
@@ -161,6 +418,66 @@ impl ResourceReader {
             sarc_cache: construct_sarc_cache(),
             bin_type: BinType::Nintendo,
             nest_map: Default::default(),
+            disk_cache: Default::default(),
+            version: Default::default(),
+        })
+    }
+
+    /// Reads a Switch dump from the NSP or XCI files of the base game and
+    /// optionally the update and DLC, using the keys in `key_path`.
+    pub fn from_nsp(
+        key_path: impl AsRef<Path>,
+        base_path: impl AsRef<Path>,
+        update_path: Option<impl AsRef<Path>>,
+        aoc_path: Option<impl AsRef<Path>>,
+    ) -> Result<Self> {
+        Ok(Self {
+            source: Box::new(Nsp::new(key_path, base_path, update_path, aoc_path)?),
+            cache: construct_res_cache(),
+            sarc_cache: construct_sarc_cache(),
+            bin_type: BinType::Nintendo,
+            nest_map: Default::default(),
+            disk_cache: Default::default(),
+            version: Default::default(),
+        })
+    }
+
+    /// Reads a Wii U dump from a WUD or WUX disc image, using the disc key
+    /// and common key in `key_path`. The disc only has the base game, so the
+    /// update and DLC are read from unpacked folders.
+    pub fn from_wud(
+        key_path: impl AsRef<Path>,
+        image_path: impl AsRef<Path>,
+        update_dir: Option<impl AsRef<Path>>,
+        aoc_dir: Option<impl AsRef<Path>>,
+    ) -> Result<Self> {
+        Ok(Self {
+            source: Box::new(Wud::new(key_path, image_path, update_dir, aoc_dir)?),
+            cache: construct_res_cache(),
+            sarc_cache: construct_sarc_cache(),
+            bin_type: BinType::Nintendo,
+            nest_map: Default::default(),
+            disk_cache: Default::default(),
+            version: Default::default(),
+        })
+    }
+
+    /// Reads a dump served over FTP or HTTP(S), given the URLs of the
+    /// unpacked base game, update, and DLC folders. Files are downloaded only
+    /// when needed and then kept on disk.
+    pub fn from_remote(
+        content_url: Option<impl AsRef<str>>,
+        update_url: Option<impl AsRef<str>>,
+        aoc_url: Option<impl AsRef<str>>,
+    ) -> Result<Self> {
+        Ok(Self {
+            source: Box::new(Remote::new(content_url, update_url, aoc_url)?),
+            cache: construct_res_cache(),
+            sarc_cache: construct_sarc_cache(),
+            bin_type: BinType::Nintendo,
+            nest_map: Default::default(),
+            disk_cache: Default::default(),
+            version: Default::default(),
         })
     }
 
@@ -175,6 +492,25 @@ impl ResourceReader {
             sarc_cache: construct_sarc_cache(),
             bin_type: BinType::Nintendo,
             nest_map: Default::default(),
+            disk_cache: Default::default(),
+            version: Default::default(),
+        })
+    }
+
+    /// Reads from several game dumps in priority order. Any file missing from
+    /// one dump is read from the next, so e.g. an unpacked update folder can
+    /// be backed by a WUA with the base game.
+    pub fn from_chain(readers: impl IntoIterator<Item = ResourceReader>) -> Result<Self> {
+        Ok(Self {
+            source: Box::new(Chain::new(
+                readers.into_iter().map(|reader| reader.source).collect(),
+            )?),
+            cache: construct_res_cache(),
+            sarc_cache: construct_sarc_cache(),
+            bin_type: BinType::Nintendo,
+            nest_map: Default::default(),
+            disk_cache: Default::default(),
+            version: Default::default(),
         })
     }
 
@@ -203,11 +539,58 @@ impl ResourceReader {
                 sarc_cache: construct_sarc_cache(),
                 bin_type: BinType::Nintendo,
                 nest_map: Default::default(),
+                // Mods are not vanilla resources
+                disk_cache: OnceLock::from(None),
+                version: Default::default(),
             })
         }
         inner(mod_dir.as_ref())
     }
 
+    /// The versions of the game and DLC in the dump, as given in
+    /// `System/Version.txt` and the DLC's `System/AocVersion.txt`. The
+    /// update's copy of the game version takes priority over the base game's.
+    /// They are read once and remembered.
+    pub fn version(&self) -> &DumpVersion {
+        fn parse(data: Result<Vec<u8>>) -> Option<String> {
+            let data = data.ok()?;
+            let version = std::str::from_utf8(&data).ok()?.trim();
+            (!version.is_empty()).then(|| version.into())
+        }
+        self.version.get_or_init(|| {
+            let version = DumpVersion {
+                game: parse(self.source.get_data(Path::new("System/Version.txt"))),
+                aoc:  self
+                    .source
+                    .has_aoc()
+                    .then(|| {
+                        parse(
+                            self.source
+                                .get_aoc_file_data(Path::new("System/AocVersion.txt")),
+                        )
+                    })
+                    .flatten(),
+            };
+            log::debug!("Game dump version: {:?}", version);
+            version
+        })
+    }
+
+    /// The game version of the dump, e.g. `1.5.0`.
+    pub fn game_version(&self) -> Option<std::string::String> {
+        self.version().game.as_ref().map(|v| v.to_string())
+    }
+
+    /// The DLC version of the dump, if it has the DLC.
+    pub fn aoc_version(&self) -> Option<std::string::String> {
+        self.version().aoc.as_ref().map(|v| v.to_string())
+    }
+
+    /// Whether the dump includes the DLC.
+    pub fn has_dlc(&self) -> bool {
+        self.source.has_aoc()
+    }
+
     pub fn get_resource(&self, name: impl AsRef<Path>) -> Result<Arc<ResourceData>> {
         let name = name
             .as_ref()
@@ -216,6 +599,7 @@ impl ResourceReader {
             .into();
         self.cache
             .get(&name)
+            .map(|(resource, _)| resource)
             .ok_or_else(|| ROMError::FileNotFound(name, self.source.host_path().to_path_buf()))
     }
 
@@ -280,6 +664,7 @@ impl ResourceReader {
             .with_context(|| format!("Failed to read {} from SARC at path {}", canon, nest_path))?;
         let resource = ResourceData::from_binary(canon, &data)
             .with_context(|| jstr!("Failed to parse resource {canon}"))?;
+        self.write_disk_cache(canon, &resource);
         if is_mergeable_sarc(canon, &data) {
             self.process_sarc(
                 Sarc::new(&data)
@@ -287,7 +672,10 @@ impl ResourceReader {
                 nest_path.split("//").last().unwrap_or_default(),
             )?;
         }
-        Ok(self.cache.get_with(canon.into(), || Arc::new(resource)))
+        Ok(self
+            .cache
+            .get_with(canon.into(), || (Arc::new(resource), data.len() as u32))
+            .0)
     }
 
     fn get_or_add_resource(
@@ -300,36 +688,46 @@ impl ResourceReader {
             .cache
             .try_get_with(canon.clone(), || -> uk_content::Result<_> {
                 log::trace!("Resource {} not in cache, pulling", &canon);
+                if let Some((resource, size)) = self.read_disk_cache(&canon) {
+                    return Ok((Arc::new(resource), size as u32));
+                }
                 let data = self
                     .source
                     .get_data(path)
                     .with_context(|| jstr!("File {&canon} not found in dump"))?;
-                let resource = match self.bin_type {
+                let (resource, size) = match self.bin_type {
                     BinType::Nintendo => {
                         let data = roead::yaz0::decompress_if(data.as_slice());
                         let res = ResourceData::from_binary(canon.as_str(), data.as_ref())?;
+                        self.write_disk_cache(&canon, &res);
                         if is_mergeable_sarc(canon.as_str(), data.as_ref()) {
                             self.process_sarc(
                                 Sarc::new(data.as_ref())?,
                                 path.display().to_string().as_str(),
                             )?;
                         }
-                        res
+                        (res, data.len())
                     }
                     BinType::MiniCbor => {
-                        minicbor_ser::from_slice(data.as_slice())
-                            .map_err(anyhow_ext::Error::from)?
+                        (
+                            minicbor_ser::from_slice(data.as_slice())
+                                .map_err(anyhow_ext::Error::from)?,
+                            data.len(),
+                        )
                     }
                 };
-                Ok(Arc::new(resource))
+                Ok((Arc::new(resource), size as u32))
             }) {
-            Ok(res) => Ok(res),
+            Ok((res, _)) => Ok(res),
             Err(e) => {
                 log::trace!("Failed to get file from dump: {e}. Performing parent lookup...");
                 static NEST_INIT: Once = Once::new();
                 NEST_INIT.call_once(|| {
                     log::trace!("Initializing nest map...");
-                    if self.source.typetag_name() == "Unpacked" {
+                    if matches!(
+                        self.source.typetag_name(),
+                        "Unpacked" | "Remote" | "Wud" | "Chain"
+                    ) {
                         let stock: HashMap<String, Arc<str>> =
                             serde_json::from_str(NEST_MAP.as_ref()).unwrap();
                         for (k, v) in stock {
@@ -371,10 +769,12 @@ impl ResourceReader {
                 let data = file.data;
                 let data = roead::yaz0::decompress_if(data);
                 let resource = ResourceData::from_binary(&name, data.as_ref())?;
+                self.write_disk_cache(&canon, &resource);
                 if is_mergeable_sarc(canon.as_str(), data.as_ref()) {
                     self.process_sarc(Sarc::new(data.as_ref())?, &name)?;
                 }
-                self.cache.insert(canon.clone(), Arc::new(resource));
+                self.cache
+                    .insert(canon.clone(), (Arc::new(resource), data.len() as u32));
             }
             // if !self.nest_map.contains_key(&canon) {
             //     self.nest_map.insert(canon, sarc_path.into());
@@ -401,3 +801,32 @@ impl ResourceReader {
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn validate() {
+        assert!(super::VALIDATION_FILES.contains(&"Pack/Bootup.pack".into()));
+        assert!(!super::VALIDATION_FILES.contains(&"Pack/Bootup_USen.pack".into()));
+
+        let dir = tempfile::tempdir().unwrap();
+        let test = dir.path().join(super::CONTENT_TEST);
+        std::fs::create_dir_all(test.parent().unwrap()).unwrap();
+        std::fs::write(test, b"").unwrap();
+        std::fs::create_dir_all(dir.path().join("Pack")).unwrap();
+        std::fs::write(dir.path().join("Pack/Bootup.pack"), b"SARC").unwrap();
+        let reader =
+            super::ResourceReader::from_unpacked_dirs(Some(dir.path()), None::<&str>, None::<&str>)
+                .unwrap();
+        let report = reader.validate();
+        assert!(!report.is_ok());
+        assert_eq!(report.corrupt, ["Pack/Bootup.pack"]);
+        assert_eq!(report.missing.len(), report.checked - 1);
+    }
+
+    #[test]
+    fn stable_hash() {
+        let hash = super::stable_hash(&[b"Actor/Pack/Player_Link.bactorpack".as_slice()]);
+        assert_eq!(hash.as_str(), "a13626eadd49fd94");
+    }
+}
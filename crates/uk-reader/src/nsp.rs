@@ -1,76 +1,348 @@
-use crate::Result;
-use fs_err as fs;
 use std::{
-    io::Cursor,
-    path::Path,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+
+use crate::{ROMError, Result, AOC_TEST, CONTENT_TEST, UPDATE_TEST};
+
+/// Where the offset of the root HFS0 partition is stored in an XCI header.
+const XCI_ROOT_OFFSET: u64 = 0x130;
+
+fn nx_error(err: impl std::fmt::Debug) -> ROMError {
+    ROMError::Any(anyhow_ext::anyhow!("{:?}", err))
+}
+
+/// A window into part of a file, so a file inside an NSP or XCI can be read
+/// as though it were on its own.
 #[derive(Debug)]
-pub(crate) struct Nsp {
-    keys: cntx::key::Keyset,
+struct Section {
+    file:  fs::File,
+    start: u64,
+    len:   u64,
+    pos:   u64,
 }
 
-impl Nsp {
-    pub(crate) fn new(key_path: impl AsRef<Path>, nsp_path: impl AsRef<Path>) -> Result<Self> {
-        let key_file = fs::File::open(key_path.as_ref())?;
-        let keys = cntx::key::Keyset::from(&key_file)?;
-        let nsp_file = fs::File::open(nsp_path.as_ref())?;
-        let mut pfs0 = cntx::pfs0::PFS0::new(Arc::new(Mutex::new(nsp_file)))?;
-        let files = pfs0.list_files()?;
-        for (i, file) in files.into_iter().enumerate() {
-            let size = pfs0.get_file_size(i)?;
-            println!("{} is {} bytes", file, size);
-            if file == "861707001401bbce36c7e421efac76d4.nca" {
-                let nca_reader = pfs0.get_file_reader(i)?;
-                let mut nca = cntx::nca::NCA::new(Arc::new(Mutex::new(nca_reader)), &keys, None)?;
-                let fs_count = nca.get_filesystem_count();
-                println!("NCA has {} filesystems", fs_count);
-                for i in 0..fs_count {
-                    if let Ok(mut romfs) = nca.open_romfs_filesystem(i) {
-                        let mut dir_iterator = romfs.open_dir_iterator("".into())?;
-                        let file_count = dir_iterator.get_file_count();
-                        for _ in 0..file_count {
-                            let (name, subsize) = dir_iterator.next_file()?;
-                            println!("Subfile {} is {} bytes", name, subsize);
-                        }
-                    } else if let Ok(psf) = nca.open_pfs0_filesystem(i) {
-                        println!("PFS0 has these files:\n{:?}", psf.list_files());
-                    }
+impl Read for Section {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let max = (self.len.saturating_sub(self.pos) as usize).min(buf.len());
+        self.file.seek(SeekFrom::Start(self.start + self.pos))?;
+        let read = self.file.read(&mut buf[..max])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for Section {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        self.pos = pos.ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "Seek before start")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+/// Reads the file table of a PFS0 (NSP) or HFS0 (XCI) partition starting at
+/// `offset`, returning the name, absolute offset, and size of each file.
+fn read_partition(
+    reader: &mut (impl Read + Seek),
+    offset: u64,
+) -> Result<Vec<(std::string::String, u64, u64)>> {
+    fn u32_at(buf: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(buf[at..at + 4].try_into().unwrap())
+    }
+    fn u64_at(buf: &[u8], at: usize) -> u64 {
+        u64::from_le_bytes(buf[at..at + 8].try_into().unwrap())
+    }
+    let mut header = [0; 0x10];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut header)?;
+    let entry_size = match &header[..4] {
+        b"PFS0" => 0x18,
+        b"HFS0" => 0x40,
+        _ => return Err(ROMError::OtherMessage("Not a valid NSP or XCI partition")),
+    };
+    let count = u32_at(&header, 4) as usize;
+    let strings_size = u32_at(&header, 8) as usize;
+    let mut table = vec![0; count * entry_size + strings_size];
+    reader.read_exact(&mut table)?;
+    let (entries, strings) = table.split_at(count * entry_size);
+    let data_start = offset + 0x10 + table.len() as u64;
+    entries
+        .chunks_exact(entry_size)
+        .map(|entry| {
+            let name = strings
+                .get(u32_at(entry, 0x10) as usize..)
+                .and_then(|name| name.split(|c| *c == 0).next())
+                .ok_or(ROMError::OtherMessage("Invalid file name in partition"))?;
+            Ok((
+                std::string::String::from_utf8_lossy(name).into_owned(),
+                data_start + u64_at(entry, 0),
+                u64_at(entry, 8),
+            ))
+        })
+        .collect()
+}
+
+/// Lists the NCAs in an NSP, or in the secure partition of an XCI.
+fn list_ncas(path: &Path) -> Result<Vec<Section>> {
+    let mut file = fs::File::open(path)?;
+    let files = if path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("xci"))
+        .unwrap_or(false)
+    {
+        let mut offset = [0; 8];
+        file.seek(SeekFrom::Start(XCI_ROOT_OFFSET))?;
+        file.read_exact(&mut offset)?;
+        let (_, secure, _) = read_partition(&mut file, u64::from_le_bytes(offset))?
+            .into_iter()
+            .find(|(name, ..)| name == "secure")
+            .ok_or(ROMError::OtherMessage("XCI has no secure partition"))?;
+        read_partition(&mut file, secure)?
+    } else {
+        read_partition(&mut file, 0)?
+    };
+    files
+        .into_iter()
+        .filter(|(name, ..)| name.ends_with(".nca") && !name.ends_with(".cnmt.nca"))
+        .map(|(_, start, len)| {
+            Ok(Section {
+                file: fs::File::open(path)?,
+                start,
+                len,
+                pos: 0,
+            })
+        })
+        .collect()
+}
+
+/// The RomFS of the program NCA for one title in an NSP or XCI.
+struct RomFs {
+    nca:   Mutex<cntx::nca::NCA>,
+    index: usize,
+}
+
+impl std::fmt::Debug for RomFs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RomFs").field("index", &self.index).finish()
+    }
+}
+
+impl RomFs {
+    /// Finds the NCA in the NSP or XCI at `path` with a RomFS containing
+    /// `test`. Updates are patches over the base game's RomFS, so they need
+    /// the base to be read at all.
+    fn open(
+        path: &Path,
+        keys: &cntx::key::Keyset,
+        test: &str,
+        base: Option<&RomFs>,
+        kind: &'static str,
+    ) -> Result<Self> {
+        log::info!("Opening {} NCA from {}", kind, path.display());
+        for section in list_ncas(path)? {
+            let base = base.map(|base| base.nca.lock().unwrap());
+            let Ok(mut nca) =
+                cntx::nca::NCA::new(Arc::new(Mutex::new(section)), keys, base.as_deref())
+            else {
+                continue;
+            };
+            for index in 0..nca.get_filesystem_count() {
+                let has_test = nca
+                    .open_romfs_filesystem(index)
+                    .map(|mut romfs| romfs.get_file_size(test.into()).is_ok())
+                    .unwrap_or(false);
+                if has_test {
+                    return Ok(Self {
+                        nca: Mutex::new(nca),
+                        index,
+                    });
                 }
             }
         }
-        Ok(Self { keys })
+        log::error!("Test file {} not found in {}", test, path.display());
+        Err(ROMError::MissingDumpDir(kind, path.to_path_buf()))
+    }
+
+    fn read(&self, name: &Path) -> Option<Vec<u8>> {
+        let name = name.to_string_lossy().replace('\\', "/");
+        let mut nca = self.nca.lock().unwrap();
+        let mut romfs = nca.open_romfs_filesystem(self.index).ok()?;
+        let size = romfs.get_file_size(name.clone()).ok()?;
+        let mut data = vec![0; size];
+        romfs.read_file(name, 0, &mut data).ok()?;
+        Some(data)
+    }
+
+    fn exists(&self, name: &Path) -> bool {
+        let name = name.to_string_lossy().replace('\\', "/");
+        let mut nca = self.nca.lock().unwrap();
+        nca.open_romfs_filesystem(self.index)
+            .map(|mut romfs| romfs.get_file_size(name).is_ok())
+            .unwrap_or(false)
     }
 }
 
+/// The files which make up an NSP or XCI dump, which is all that needs to be
+/// stored to open it again.
+#[derive(Deserialize)]
+struct NspPaths {
+    key_path:    PathBuf,
+    base_path:   PathBuf,
+    update_path: Option<PathBuf>,
+    aoc_path:    Option<PathBuf>,
+}
+
+impl TryFrom<NspPaths> for Nsp {
+    type Error = ROMError;
+
+    fn try_from(paths: NspPaths) -> Result<Self> {
+        Self::new(
+            paths.key_path,
+            paths.base_path,
+            paths.update_path,
+            paths.aoc_path,
+        )
+    }
+}
+
+/// A Switch dump read straight from the NSP or XCI files of the base game,
+/// update, and DLC, decrypted with the user's own `prod.keys`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(try_from = "NspPaths")]
+pub(crate) struct Nsp {
+    key_path:    PathBuf,
+    base_path:   PathBuf,
+    update_path: Option<PathBuf>,
+    aoc_path:    Option<PathBuf>,
+    host_path:   PathBuf,
+    #[serde(skip_serializing)]
+    base:        RomFs,
+    #[serde(skip_serializing)]
+    update:      Option<RomFs>,
+    #[serde(skip_serializing)]
+    aoc:         Option<RomFs>,
+}
+
+impl Nsp {
+    pub(crate) fn new(
+        key_path: impl AsRef<Path>,
+        base_path: impl AsRef<Path>,
+        update_path: Option<impl AsRef<Path>>,
+        aoc_path: Option<impl AsRef<Path>>,
+    ) -> Result<Self> {
+        let key_path = key_path.as_ref();
+        let base_path = base_path.as_ref();
+        let update_path = update_path.map(|p| p.as_ref().to_path_buf());
+        let aoc_path = aoc_path.map(|p| p.as_ref().to_path_buf());
+        log::info!("Loading Switch keys from {}", key_path.display());
+        let keys = cntx::key::Keyset::from(&fs::File::open(key_path)?).map_err(nx_error)?;
+        let base = RomFs::open(base_path, &keys, CONTENT_TEST, None, "base game")?;
+        let update = update_path
+            .as_deref()
+            .map(|path| RomFs::open(path, &keys, UPDATE_TEST, Some(&base), "update"))
+            .transpose()?;
+        let aoc = aoc_path
+            .as_deref()
+            .map(|path| RomFs::open(path, &keys, AOC_TEST, None, "DLC"))
+            .transpose()?;
+        Ok(Self {
+            key_path: key_path.to_path_buf(),
+            base_path: base_path.to_path_buf(),
+            update_path,
+            aoc_path,
+            host_path: base_path.to_path_buf(),
+            base,
+            update,
+            aoc,
+        })
+    }
+}
+
+#[typetag::serde]
 impl super::ResourceLoader for Nsp {
     fn get_data(&self, name: &Path) -> Result<Vec<u8>> {
-        todo!()
+        self.update
+            .as_ref()
+            .and_then(|update| update.read(name))
+            .or_else(|| self.base.read(name))
+            .ok_or_else(|| {
+                ROMError::FileNotFound(name.to_string_lossy().into(), self.host_path.clone())
+            })
     }
 
     fn get_aoc_file_data(&self, name: &Path) -> Result<Vec<u8>> {
-        todo!()
+        self.aoc
+            .as_ref()
+            .ok_or_else(|| ROMError::MissingDumpDir("DLC", self.host_path.clone()))?
+            .read(name)
+            .ok_or_else(|| {
+                ROMError::FileNotFound(name.to_string_lossy().into(), self.host_path.clone())
+            })
     }
 
     fn file_exists(&self, name: &Path) -> bool {
-        todo!()
+        self.update
+            .as_ref()
+            .map(|update| update.exists(name))
+            .unwrap_or(false)
+            || self.base.exists(name)
+    }
+
+    fn host_path(&self) -> &Path {
+        &self.host_path
     }
 
-    fn host_path(&self) -> &std::path::Path {
-        todo!()
+    fn has_aoc(&self) -> bool {
+        self.aoc.is_some()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use std::io::{Cursor, Read, Seek, SeekFrom};
+
     #[test]
-    fn read_nsp() {
-        let nsp = Nsp::new(
-            "/data/GameDuo/NX/prod.keys",
-            "/data/Downloads/The_Legend_of_Zelda_BotW/The Legend of Zelda Breath of the Wild.nsp",
-        )
-        .unwrap();
+    fn read_partition() {
+        let names = b"A.nca\0B.cnmt.nca\0\0\0\0";
+        let mut pfs0 = b"PFS0".to_vec();
+        pfs0.extend(2u32.to_le_bytes());
+        pfs0.extend((names.len() as u32).to_le_bytes());
+        pfs0.extend(0u32.to_le_bytes());
+        for (offset, size, name) in [(0u64, 3u64, 0u32), (3, 2, 6)] {
+            pfs0.extend(offset.to_le_bytes());
+            pfs0.extend(size.to_le_bytes());
+            pfs0.extend(name.to_le_bytes());
+            pfs0.extend(0u32.to_le_bytes());
+        }
+        pfs0.extend(names);
+        let data_start = pfs0.len() as u64;
+        pfs0.extend(b"abcde");
+        let mut prefixed = vec![0; 0x20];
+        prefixed.extend(pfs0);
+
+        let files = super::read_partition(&mut Cursor::new(&prefixed), 0x20).unwrap();
+        assert_eq!(files, vec![
+            ("A.nca".into(), 0x20 + data_start, 3),
+            ("B.cnmt.nca".into(), 0x20 + data_start + 3, 2)
+        ]);
+        let (_, start, len) = &files[1];
+        let mut data = [0; 2];
+        let mut cursor = Cursor::new(&prefixed);
+        cursor.seek(SeekFrom::Start(*start)).unwrap();
+        cursor.read_exact(&mut data).unwrap();
+        assert_eq!(&data, b"de");
+        assert_eq!(*len, 2);
+
+        assert!(super::read_partition(&mut Cursor::new(&prefixed), 0).is_err());
     }
 }
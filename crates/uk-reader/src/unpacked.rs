@@ -3,7 +3,7 @@ use std::path::{Path, PathBuf};
 use fs_err as fs;
 use serde::{Deserialize, Serialize};
 
-use crate::{ROMError, Result};
+use crate::{ROMError, Result, AOC_TEST, CONTENT_TEST, UPDATE_TEST};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct Unpacked {
@@ -40,9 +40,6 @@ impl Unpacked {
             aoc_dir.map(|p| p.display())
         );
         if test_valid {
-            static CONTENT_TEST: &str = "Map/MainField/A-1/A-1.00_Clustering.sblwp";
-            static UPDATE_TEST: &str = "Actor/Pack/Enemy_Lynel_Dark.sbactorpack";
-            static AOC_TEST: &str = "Pack/AocMainField.pack";
             if let Some(content_dir) = content_dir.as_ref()
                 && !content_dir
                     .join(CONTENT_TEST)
@@ -164,4 +161,16 @@ impl super::ResourceLoader for Unpacked {
     fn host_path(&self) -> &std::path::Path {
         &self.host_path
     }
+
+    fn has_aoc(&self) -> bool {
+        self.aoc_dir.is_some()
+    }
+
+    fn content_dirs(&self) -> Vec<&Path> {
+        self.content_dir
+            .iter()
+            .chain(self.update_dir.iter())
+            .map(|dir| dir.as_path())
+            .collect()
+    }
 }
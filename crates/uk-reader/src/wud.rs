@@ -0,0 +1,577 @@
+use std::{
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use aes::Aes128;
+use cbc::{
+    cipher::{block_padding::NoPadding, BlockDecryptMut, KeyIvInit},
+    Decryptor,
+};
+use fs_err as fs;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use uk_content::util::HashMap;
+
+use crate::{ROMError, Result, AOC_TEST, CONTENT_TEST, UPDATE_TEST};
+
+type Key = [u8; 16];
+
+/// The first magic values of a WUX header, `WUX0` and `0x1099D02E`.
+const WUX_MAGIC: &[u8; 8] = b"WUX0\x2e\xd0\x99\x10";
+const WUX_HEADER_SIZE: u64 = 0x20;
+/// Disc images are laid out, and partitions addressed, in sectors of this
+/// size.
+const SECTOR_SIZE: u64 = 0x8000;
+const PARTITION_TABLE_OFFSET: u64 = 0x18000;
+const PARTITION_TABLE_MAGIC: u32 = 0xCCA6E67B;
+const PARTITION_HEADER_MAGIC: u32 = 0xCC93A4F5;
+/// Hashed clusters are stored in blocks of hashes followed by data, with
+/// each block's data encrypted using its own hash as the IV.
+const HASHED_BLOCK_SIZE: u64 = 0x10000;
+const HASHED_BLOCK_HASHES: u64 = 0x400;
+const HASHED_BLOCK_DATA: u64 = HASHED_BLOCK_SIZE - HASHED_BLOCK_HASHES;
+
+fn u16_at(buf: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes(buf[at..at + 2].try_into().unwrap())
+}
+
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(buf[at..at + 4].try_into().unwrap())
+}
+
+fn decrypt(key: &Key, iv: &Key, data: &mut [u8]) {
+    let _ = Decryptor::<Aes128>::new(key.into(), iv.into()).decrypt_padded_mut::<NoPadding>(data);
+}
+
+/// Reads the keys in a text file like Cemu's `keys.txt`: one key per line in
+/// hex, with anything after a `#` ignored.
+fn parse_keys(text: &str) -> Vec<Key> {
+    text.lines()
+        .filter_map(|line| line.split('#').next()?.split_whitespace().next())
+        .filter(|key| key.len() == 32)
+        .filter_map(|key| {
+            let mut bytes = [0; 16];
+            for (i, byte) in bytes.iter_mut().enumerate() {
+                *byte = u8::from_str_radix(key.get(i * 2..i * 2 + 2)?, 16).ok()?;
+            }
+            Some(bytes)
+        })
+        .collect()
+}
+
+/// A plain WUD disc image, or a WUX image, which stores each distinct sector
+/// only once with a table of where to find each one.
+#[derive(Debug)]
+enum Image {
+    Wud(fs::File),
+    Wux {
+        file:        fs::File,
+        sector_size: u64,
+        index:       Vec<u32>,
+        data_start:  u64,
+    },
+}
+
+impl Image {
+    fn open(path: &Path) -> Result<Self> {
+        let mut file = fs::File::open(path)?;
+        let mut header = [0; WUX_HEADER_SIZE as usize];
+        file.read_exact(&mut header)?;
+        if &header[..8] != WUX_MAGIC {
+            return Ok(Self::Wud(file));
+        }
+        let sector_size = u32::from_le_bytes(header[0x8..0xC].try_into().unwrap()) as u64;
+        let size = u64::from_le_bytes(header[0x10..0x18].try_into().unwrap());
+        if sector_size == 0 || sector_size % 16 != 0 {
+            return Err(ROMError::OtherMessage("Invalid sector size in WUX image"));
+        }
+        let count = ((size + sector_size - 1) / sector_size) as usize;
+        let mut table = vec![0; count * 4];
+        file.read_exact(&mut table)?;
+        let index = table
+            .chunks_exact(4)
+            .map(|entry| u32::from_le_bytes(entry.try_into().unwrap()))
+            .collect();
+        // The sectors start at the next sector boundary after the index
+        let data_start =
+            (WUX_HEADER_SIZE + table.len() as u64 + sector_size - 1) / sector_size * sector_size;
+        Ok(Self::Wux {
+            file,
+            sector_size,
+            index,
+            data_start,
+        })
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        match self {
+            Self::Wud(file) => {
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_exact(buf)?;
+            }
+            Self::Wux {
+                file,
+                sector_size,
+                index,
+                data_start,
+            } => {
+                let mut done = 0;
+                while done < buf.len() {
+                    let pos = offset + done as u64;
+                    let sector = *index
+                        .get((pos / *sector_size) as usize)
+                        .ok_or(ROMError::OtherMessage("Read past the end of WUX image"))?;
+                    let within = pos % *sector_size;
+                    let len = ((*sector_size - within) as usize).min(buf.len() - done);
+                    file.seek(SeekFrom::Start(
+                        *data_start + sector as u64 * *sector_size + within,
+                    ))?;
+                    file.read_exact(&mut buf[done..done + len])?;
+                    done += len;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Cluster {
+    offset: u64,
+    hashed: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FileEntry {
+    cluster: usize,
+    offset:  u64,
+    size:    u64,
+}
+
+/// The files in one partition of a disc, found through its FST.
+struct Volume {
+    key:      Key,
+    clusters: Vec<Cluster>,
+    files:    HashMap<std::string::String, FileEntry>,
+}
+
+impl std::fmt::Debug for Volume {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Volume")
+            .field("clusters", &self.clusters.len())
+            .field("files", &self.files.len())
+            .finish()
+    }
+}
+
+impl Volume {
+    /// Reads `len` bytes at `offset` in a cluster. Plain clusters are one
+    /// long encrypted stream, and hashed ones are made of blocks which are
+    /// each decrypted on their own.
+    fn read_cluster(
+        image: &mut Image,
+        key: &Key,
+        index: usize,
+        cluster: Cluster,
+        offset: u64,
+        len: u64,
+    ) -> Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(len as usize);
+        if cluster.hashed {
+            let mut block = vec![0; HASHED_BLOCK_SIZE as usize];
+            let mut pos = offset;
+            while pos < offset + len {
+                let (number, within) = (pos / HASHED_BLOCK_DATA, pos % HASHED_BLOCK_DATA);
+                image.read_at(cluster.offset + number * HASHED_BLOCK_SIZE, &mut block)?;
+                let (hashes, data) = block.split_at_mut(HASHED_BLOCK_HASHES as usize);
+                decrypt(key, &[0; 16], hashes);
+                let at = (number % 16) as usize * 20;
+                decrypt(key, &hashes[at..at + 16].try_into().unwrap(), data);
+                let take = (HASHED_BLOCK_DATA - within).min(offset + len - pos) as usize;
+                out.extend_from_slice(&data[within as usize..within as usize + take]);
+                pos += take as u64;
+            }
+        } else {
+            let start = offset & !0xF;
+            let end = (offset + len + 0xF) & !0xF;
+            let mut iv = [0; 16];
+            if start == 0 {
+                iv[..2].copy_from_slice(&(index as u16).to_be_bytes());
+            } else {
+                image.read_at(cluster.offset + start - 16, &mut iv)?;
+            }
+            let mut data = vec![0; (end - start) as usize];
+            image.read_at(cluster.offset + start, &mut data)?;
+            decrypt(key, &iv, &mut data);
+            let skip = (offset - start) as usize;
+            out.extend_from_slice(&data[skip..skip + len as usize]);
+        }
+        Ok(out)
+    }
+
+    /// Opens the partition at `offset`, returning `None` if `key` is not the
+    /// one it is encrypted with.
+    fn open(image: &mut Image, offset: u64, key: &Key) -> Result<Option<Self>> {
+        let mut header = [0; 0x28];
+        image.read_at(offset, &mut header)?;
+        if u32_at(&header, 0) != PARTITION_HEADER_MAGIC {
+            return Err(ROMError::OtherMessage("Invalid disc partition header"));
+        }
+        let fst_size = u32_at(&header, 0x14) as u64;
+        let fst = Cluster {
+            offset: offset + u32_at(&header, 0x18) as u64 * SECTOR_SIZE,
+            hashed: header[0x24] == 2,
+        };
+        let fst = Self::read_cluster(image, key, 0, fst, 0, fst_size)?;
+        if fst.len() < 0x20 || &fst[..4] != b"FST\0" {
+            return Ok(None);
+        }
+        let offset_factor = u32_at(&fst, 0x4) as u64;
+        let cluster_count = u32_at(&fst, 0x8) as usize;
+        let invalid = || ROMError::OtherMessage("Invalid FST in disc partition");
+        let clusters = fst
+            .get(0x20..0x20 + cluster_count * 0x20)
+            .ok_or_else(invalid)?
+            .chunks_exact(0x20)
+            .map(|entry| {
+                Cluster {
+                    offset: offset + u32_at(entry, 0) as u64 * SECTOR_SIZE,
+                    hashed: entry[0x14] == 2,
+                }
+            })
+            .collect();
+        let entries = &fst[0x20 + cluster_count * 0x20..];
+        let count = entries
+            .get(8..12)
+            .map(|size| u32_at(size, 0))
+            .ok_or_else(invalid)? as usize;
+        let names = entries.get(count * 0x10..).ok_or_else(invalid)?;
+        let mut files = HashMap::default();
+        let mut dirs: Vec<(usize, std::string::String)> = vec![];
+        for i in 1..count {
+            let entry = &entries[i * 0x10..i * 0x10 + 0x10];
+            while dirs.last().map(|(end, _)| *end <= i).unwrap_or(false) {
+                dirs.pop();
+            }
+            let name = names
+                .get(u32_at(entry, 0) as usize & 0xFFFFFF..)
+                .and_then(|name| name.split(|c| *c == 0).next())
+                .ok_or_else(invalid)?;
+            let path = match dirs.last() {
+                Some((_, parent)) => {
+                    format!("{}/{}", parent, std::string::String::from_utf8_lossy(name))
+                }
+                None => std::string::String::from_utf8_lossy(name).into_owned(),
+            };
+            let kind = entry[0];
+            if kind & 1 != 0 {
+                dirs.push((u32_at(entry, 8) as usize, path));
+            } else if kind & 0x80 == 0 {
+                // Offsets are in units of the offset factor unless flagged
+                // as being in bytes
+                let offset = u32_at(entry, 4) as u64;
+                files.insert(path, FileEntry {
+                    cluster: u16_at(entry, 0xE) as usize,
+                    offset:  if u16_at(entry, 0xC) & 4 == 0 {
+                        offset * offset_factor
+                    } else {
+                        offset
+                    },
+                    size:    u32_at(entry, 8) as u64,
+                });
+            }
+        }
+        Ok(Some(Self {
+            key: *key,
+            clusters,
+            files,
+        }))
+    }
+
+    fn read(&self, image: &mut Image, path: &str) -> Option<Vec<u8>> {
+        let file = self.files.get(path)?;
+        let cluster = *self.clusters.get(file.cluster)?;
+        Self::read_cluster(
+            image,
+            &self.key,
+            file.cluster,
+            cluster,
+            file.offset,
+            file.size,
+        )
+        .map_err(|e| log::warn!("Failed to read {} from disc image: {:?}", path, e))
+        .ok()
+    }
+}
+
+/// The game partition of a Wii U disc image, which holds the base game.
+#[derive(Debug)]
+struct Disc {
+    image:  Mutex<Image>,
+    volume: Volume,
+}
+
+impl Disc {
+    /// Opens the disc image at `path`, trying each of `keys` as the disc key
+    /// and the Wii U common key.
+    fn open(path: &Path, keys: &[Key]) -> Result<Self> {
+        log::info!("Opening disc image at {}", path.display());
+        let mut image = Image::open(path)?;
+        let mut encrypted = vec![0; SECTOR_SIZE as usize];
+        image.read_at(PARTITION_TABLE_OFFSET, &mut encrypted)?;
+        let (disc_key, table) = keys
+            .iter()
+            .find_map(|key| {
+                let mut table = encrypted.clone();
+                decrypt(key, &[0; 16], &mut table);
+                (u32_at(&table, 0) == PARTITION_TABLE_MAGIC).then_some((key, table))
+            })
+            .ok_or(ROMError::OtherMessage(
+                "The disc key for this image was not found in the keys file",
+            ))?;
+        let partitions = (0..u32_at(&table, 0x1C) as usize)
+            .filter_map(|i| {
+                let entry = table.get(0x800 + i * 0x80..0x800 + (i + 1) * 0x80)?;
+                let name = entry[..0x1F].split(|c| *c == 0).next()?;
+                Some((
+                    std::string::String::from_utf8_lossy(name).into_owned(),
+                    u32_at(entry, 0x20) as u64 * SECTOR_SIZE,
+                ))
+            })
+            .collect::<Vec<_>>();
+        log::debug!("Disc partitions: {:?}", partitions);
+        let (_, si_offset) = partitions
+            .iter()
+            .find(|(name, _)| name.starts_with("SI"))
+            .ok_or(ROMError::OtherMessage("Disc image has no SI partition"))?;
+        let si = Volume::open(&mut image, *si_offset, disc_key)?.ok_or(ROMError::OtherMessage(
+            "Could not read SI partition of disc image",
+        ))?;
+        // The SI partition has the ticket for each title on the disc, which
+        // holds the title key encrypted with the common key
+        let tickets = si
+            .files
+            .keys()
+            .filter(|path| path.ends_with("title.tik"))
+            .cloned()
+            .collect::<Vec<_>>();
+        for path in tickets {
+            let Some(ticket) = si.read(&mut image, &path).filter(|t| t.len() >= 0x1E4) else {
+                continue;
+            };
+            let title_id = &ticket[0x1DC..0x1E4];
+            let title_hex = title_id
+                .iter()
+                .map(|byte| format!("{byte:02X}"))
+                .collect::<std::string::String>();
+            let Some((_, offset)) = partitions.iter().find(|(name, _)| {
+                name.starts_with("GM") && name[2..].to_ascii_uppercase().starts_with(&title_hex)
+            }) else {
+                continue;
+            };
+            let mut iv = [0; 16];
+            iv[..8].copy_from_slice(title_id);
+            for common_key in keys {
+                let mut title_key: Key = ticket[0x1BF..0x1CF].try_into().unwrap();
+                decrypt(common_key, &iv, &mut title_key);
+                if let Some(volume) = Volume::open(&mut image, *offset, &title_key)?
+                    && volume
+                        .files
+                        .contains_key(&format!("content/{CONTENT_TEST}"))
+                {
+                    return Ok(Self {
+                        image: Mutex::new(image),
+                        volume,
+                    });
+                }
+            }
+        }
+        log::error!("Test file {} not found in {}", CONTENT_TEST, path.display());
+        Err(ROMError::MissingDumpDir("base game", path.to_path_buf()))
+    }
+
+    fn read(&self, name: &Path) -> Option<Vec<u8>> {
+        let path = format!("content/{}", name.to_string_lossy().replace('\\', "/"));
+        self.volume.read(&mut self.image.lock(), &path)
+    }
+
+    fn exists(&self, name: &Path) -> bool {
+        let path = format!("content/{}", name.to_string_lossy().replace('\\', "/"));
+        self.volume.files.contains_key(&path)
+    }
+}
+
+/// The files which make up a disc image dump, which is all that needs to be
+/// stored to open it again.
+#[derive(Deserialize)]
+struct WudPaths {
+    key_path:   PathBuf,
+    image_path: PathBuf,
+    update_dir: Option<PathBuf>,
+    aoc_dir:    Option<PathBuf>,
+}
+
+impl TryFrom<WudPaths> for Wud {
+    type Error = ROMError;
+
+    fn try_from(paths: WudPaths) -> Result<Self> {
+        Self::new(
+            paths.key_path,
+            paths.image_path,
+            paths.update_dir,
+            paths.aoc_dir,
+        )
+    }
+}
+
+/// A Wii U dump read straight from a WUD or WUX disc image, decrypted with
+/// the user's own keys. The disc only has the base game, so the update and
+/// DLC come from unpacked folders.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(try_from = "WudPaths")]
+pub(crate) struct Wud {
+    key_path:   PathBuf,
+    image_path: PathBuf,
+    update_dir: Option<PathBuf>,
+    aoc_dir:    Option<PathBuf>,
+    host_path:  PathBuf,
+    #[serde(skip_serializing)]
+    disc:       Disc,
+}
+
+impl Wud {
+    pub(crate) fn new(
+        key_path: impl AsRef<Path>,
+        image_path: impl AsRef<Path>,
+        update_dir: Option<impl AsRef<Path>>,
+        aoc_dir: Option<impl AsRef<Path>>,
+    ) -> Result<Self> {
+        let key_path = key_path.as_ref();
+        let image_path = image_path.as_ref();
+        let update_dir = update_dir
+            .map(|d| d.as_ref().to_path_buf())
+            .filter(|d| !d.as_os_str().is_empty());
+        let aoc_dir = aoc_dir
+            .map(|d| d.as_ref().to_path_buf())
+            .filter(|d| !d.as_os_str().is_empty());
+        log::info!("Loading Wii U keys from {}", key_path.display());
+        let keys = parse_keys(&fs::read_to_string(key_path)?);
+        let disc = Disc::open(image_path, &keys)?;
+        if let Some(update_dir) = update_dir.as_ref()
+            && !update_dir.join(UPDATE_TEST).exists()
+        {
+            log::error!("Test file {} not found in update folder", UPDATE_TEST);
+            return Err(ROMError::MissingDumpDir("update", update_dir.clone()));
+        }
+        if let Some(aoc_dir) = aoc_dir.as_ref()
+            && !aoc_dir.join(AOC_TEST).exists()
+        {
+            log::error!("Test file {} not found in DLC folder", AOC_TEST);
+            return Err(ROMError::MissingDumpDir("DLC", aoc_dir.clone()));
+        }
+        Ok(Self {
+            key_path: key_path.to_path_buf(),
+            image_path: image_path.to_path_buf(),
+            update_dir,
+            aoc_dir,
+            host_path: image_path.to_path_buf(),
+            disc,
+        })
+    }
+}
+
+#[typetag::serde]
+impl super::ResourceLoader for Wud {
+    fn get_data(&self, name: &Path) -> Result<Vec<u8>> {
+        if let Some(update_dir) = self.update_dir.as_ref()
+            && update_dir.join(name).exists()
+        {
+            return Ok(fs::read(update_dir.join(name))?);
+        }
+        if let Some(data) = self.disc.read(name) {
+            return Ok(data);
+        }
+        self.get_aoc_file_data(name).map_err(|_| {
+            ROMError::FileNotFound(name.to_string_lossy().into(), self.host_path.clone())
+        })
+    }
+
+    fn get_aoc_file_data(&self, name: &Path) -> Result<Vec<u8>> {
+        let aoc_dir = self
+            .aoc_dir
+            .as_ref()
+            .ok_or_else(|| ROMError::MissingDumpDir("DLC", self.host_path.clone()))?;
+        let file = aoc_dir.join(name);
+        if file.exists() {
+            Ok(fs::read(file)?)
+        } else {
+            Err(ROMError::FileNotFound(
+                name.to_string_lossy().into(),
+                self.host_path.clone(),
+            ))
+        }
+    }
+
+    fn file_exists(&self, name: &Path) -> bool {
+        self.update_dir
+            .iter()
+            .chain(self.aoc_dir.iter())
+            .any(|dir| dir.join(name).exists())
+            || self.disc.exists(name)
+    }
+
+    fn host_path(&self) -> &Path {
+        &self.host_path
+    }
+
+    fn has_aoc(&self) -> bool {
+        self.aoc_dir.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Image;
+
+    #[test]
+    fn parse_keys() {
+        let text = "# Disc keys\n00112233445566778899aabbccddeeff # BOTW (USA)\n\nnot a key\n\
+                    FFEEDDCCBBAA99887766554433221100\n";
+        let mut key = [0; 16];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = i as u8 * 0x11;
+        }
+        let mut reversed = key;
+        reversed.reverse();
+        assert_eq!(super::parse_keys(text), [key, reversed]);
+    }
+
+    #[test]
+    fn read_wux() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("game.wux");
+        // Three 16-byte sectors, where the first and last are the same
+        let mut wux = super::WUX_MAGIC.to_vec();
+        wux.extend(16u32.to_le_bytes());
+        wux.extend(0u32.to_le_bytes());
+        wux.extend(48u64.to_le_bytes());
+        wux.extend([0; 8]);
+        for sector in [0u32, 1, 0] {
+            wux.extend(sector.to_le_bytes());
+        }
+        wux.extend([0; 4]);
+        wux.extend([b'a'; 16]);
+        wux.extend([b'b'; 16]);
+        std::fs::write(&path, wux).unwrap();
+
+        let mut image = Image::open(&path).unwrap();
+        assert!(matches!(image, Image::Wux { .. }));
+        let mut buf = [0; 20];
+        image.read_at(10, &mut buf).unwrap();
+        assert_eq!(&buf, b"aaaaaabbbbbbbbbbbbbb");
+        image.read_at(28, &mut buf).unwrap();
+        assert_eq!(&buf, b"bbbbaaaaaaaaaaaaaaaa");
+        assert!(image.read_at(40, &mut buf).is_err());
+    }
+}
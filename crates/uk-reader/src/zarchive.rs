@@ -14,25 +14,67 @@ pub(crate) struct ZArchive {
     host_path:   PathBuf,
 }
 
+/// The title IDs of BOTW for each region, less the title type.
+static TITLE_IDS: &[&str] = &["101c9300", "101c9400", "101c9500"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Title {
+    Base,
+    Update,
+    Aoc,
+}
+
+/// Reads a title folder name in a WUA, like `0005000e101c9400_v208`, into
+/// which part of the game it holds and its version. Folders for other games
+/// are ignored.
+fn parse_title_dir(name: &str) -> Option<(Title, u32)> {
+    let (id, version) = name.split_once("_v")?;
+    let id = id.to_ascii_lowercase();
+    if id.len() != 16 || !TITLE_IDS.contains(&&id[8..]) {
+        return None;
+    }
+    let title = match &id[..8] {
+        "00050000" => Title::Base,
+        "0005000e" => Title::Update,
+        "0005000c" => Title::Aoc,
+        _ => return None,
+    };
+    Some((title, version.parse().ok()?))
+}
+
 impl ZArchive {
     pub(crate) fn new(path: impl AsRef<Path>) -> Result<Self> {
         log::info!("Opening ZArchive at {}", path.as_ref().display());
         let archive = zarchive::reader::ZArchiveReader::open(path.as_ref())?;
         let mut content_dir: Option<PathBuf> = None;
-        let mut update_dir: Option<PathBuf> = None;
-        let mut aoc_dir: Option<PathBuf> = None;
+        let mut update_dir: Option<(PathBuf, u32)> = None;
+        let mut aoc_dir: Option<(PathBuf, u32)> = None;
+        // Archives can hold more than one version of the update or DLC, so
+        // take the newest of each
         for dir in archive.iter()? {
-            if dir.name().starts_with("0005000") && dir.name().ends_with("v0") {
-                content_dir = Some(Path::new(dir.name()).join("content"));
-                log::debug!("Found content folder in ZArchive at {:?}", &content_dir);
-            } else if dir.name().starts_with("0005000") && dir.name().ends_with("v208") {
-                update_dir = Some(Path::new(dir.name()).join("content"));
-                log::debug!("Found update folder in ZArchive at {:?}", &update_dir);
-            } else if dir.name().starts_with("0005000") && dir.name().ends_with("v80") {
-                aoc_dir = Some(Path::new(dir.name()).join("content/0010"));
-                log::debug!("Found DLC folder in ZArchive at {:?}", &aoc_dir);
+            let Some((title, version)) = parse_title_dir(dir.name()) else {
+                continue;
+            };
+            let root = Path::new(dir.name()).join("content");
+            match title {
+                Title::Base => {
+                    log::debug!("Found content folder in ZArchive at {:?}", &root);
+                    content_dir = Some(root);
+                }
+                Title::Update if update_dir.as_ref().map_or(true, |(_, v)| *v < version) => {
+                    log::debug!("Found update folder in ZArchive at {:?}", &root);
+                    update_dir = Some((root, version));
+                }
+                Title::Aoc if aoc_dir.as_ref().map_or(true, |(_, v)| *v < version) => {
+                    let root = root.join("0010");
+                    log::debug!("Found DLC folder in ZArchive at {:?}", &root);
+                    aoc_dir = Some((root, version));
+                }
+                _ => (),
             }
         }
+        let update_dir = update_dir.map(|(dir, _)| dir);
+        let aoc_dir = aoc_dir.map(|(dir, _)| dir);
         Ok(Self {
             archive,
             content_dir: content_dir.ok_or_else(|| {
@@ -77,12 +119,19 @@ impl super::ResourceLoader for ZArchive {
     }
 
     fn file_exists(&self, name: &Path) -> bool {
-        self.archive.file_size(name).is_some()
+        self.archive
+            .file_size(&self.update_dir.join(name))
+            .or_else(|| self.archive.file_size(&self.content_dir.join(name)))
+            .is_some()
     }
 
     fn host_path(&self) -> &Path {
         &self.host_path
     }
+
+    fn has_aoc(&self) -> bool {
+        self.aoc_dir.is_some()
+    }
 }
 
 mod de {
@@ -208,4 +257,23 @@ mod tests {
             String::from_utf8(arch.get_data("System/Version.txt".as_ref()).unwrap()).unwrap()
         );
     }
+
+    #[test]
+    fn title_dirs() {
+        use super::{parse_title_dir, Title};
+        assert_eq!(
+            parse_title_dir("00050000101C9400_v0"),
+            Some((Title::Base, 0))
+        );
+        assert_eq!(
+            parse_title_dir("0005000e101c9500_v176"),
+            Some((Title::Update, 176))
+        );
+        assert_eq!(
+            parse_title_dir("0005000c101c9300_v80"),
+            Some((Title::Aoc, 80))
+        );
+        assert_eq!(parse_title_dir("00050000101c7b00_v0"), None);
+        assert_eq!(parse_title_dir("0005000e101c9400"), None);
+    }
 }
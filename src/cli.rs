@@ -2,12 +2,29 @@ use std::{
     io::{stdin, stdout, Write},
     option::Option,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use roead::yaz0::{compress_if, decompress_if};
 use smartstring::alias::String;
-use uk_manager::{core, mods::LookupMod, settings::Platform};
-use uk_mod::{unpack::ModReader, Manifest, Meta};
+use uk_content::{
+    prelude::{Endian, Mergeable},
+    resource::MergeableResource,
+};
+use uk_manager::{
+    core,
+    mods::LookupMod,
+    settings::Platform,
+    watch::{WatchMode, Watcher},
+};
+use uk_mod::{
+    integrity::SigningKey,
+    pack::{Compression, CompressionConfig, FileCategory},
+    unpack::ModReader,
+    Manifest, Meta, ModOption, ModOptionGroup, ModPlatform, OptionGroup,
+};
 
 use crate::gui::{package, tasks};
 
@@ -20,6 +37,8 @@ xflags::xflags! {
         optional -d, --debug
         /// Run using settings in same folder as executable
         optional -p, --portable
+        /// Keep settings, storage, and logs in this folder instead (overrides UKMM_CONFIG_DIR)
+        optional --config-dir config_dir: PathBuf
         /// Automatically deploy after running command (redunant with `deploy` command)
         optional -D, --deploy
         /// Install a mod
@@ -28,6 +47,15 @@ xflags::xflags! {
             required path: PathBuf
             /// The profile to install the mod in
             optional profile: String
+            /// Enable this mod option, by name or path (repeatable). Skips the
+            /// option prompt.
+            repeated -o, --option option: String
+            /// Use the options chosen last time, or the mod's defaults,
+            /// without prompting
+            optional --defaults
+            /// Leave out this selectable file of the mod, by path
+            /// (repeatable)
+            repeated --skip skip: String
         }
         /// Package a mod
         cmd package {
@@ -37,6 +65,33 @@ xflags::xflags! {
             required output: PathBuf
             /// Path to the meta file for the mod
             required meta: PathBuf
+            /// Also make an update package from this package of an earlier
+            /// version, with only the files changed since then
+            optional --delta-from delta_from: PathBuf
+            /// zstd compression level, from 1 (fastest) to 22 (smallest)
+            optional -l, --level level: i32
+            /// Compress a category of file (Textures, Models, or Audio) with
+            /// zstd's fastest mode (repeatable)
+            repeated --fastest fastest: String
+            /// Split the package into parts of at most this many megabytes
+            optional --split-mb split_mb: u64
+            /// Sign the package with this ed25519 key (see `keygen`)
+            optional --sign sign: PathBuf
+            /// Leave out files matching this glob pattern, e.g.
+            /// `content/Model/*.blend` (repeatable)
+            repeated -x, --exclude exclude: String
+            /// What changed in this version, in Markdown, for the changelog
+            optional --changes changes: String
+        }
+        /// Generate a key for signing mod packages
+        cmd keygen {
+            /// Where to save the key. Keep it private.
+            required output: PathBuf
+        }
+        /// Check a mod package or folder for common mistakes
+        cmd lint {
+            /// Path to the mod package or root directory
+            required path: PathBuf
         }
         /// Uninstall a mod
         cmd uninstall {
@@ -48,12 +103,94 @@ xflags::xflags! {
         /// Refresh merge
         cmd remerge {}
         /// Deploy mods
-        cmd deploy {}
+        cmd deploy {
+            /// List the files deployment would change without deploying
+            optional -n, --dry-run
+            /// Only deploy to the named target
+            optional -t, --target target: String
+        }
+        /// Merge two profiles side by side and list the merged files that
+        /// differ between them
+        cmd compare {
+            /// The first profile (A)
+            required a: String
+            /// The second profile (B)
+            required b: String
+        }
+        /// Check deployed files against the merged files
+        cmd verify {
+            /// Copy any files which don't match again
+            optional -r, --repair
+            /// Check installed mod packages against the files they were
+            /// packaged with instead
+            optional -m, --mods
+        }
+        /// Measure parse, merge, compress, and deploy speed on this machine
+        /// and print a report to share
+        cmd bench {}
+        /// Put a deploy target back the way it was before the last deployment
+        cmd rollback {
+            /// Only restore the named target instead of the first one
+            optional -t, --target target: String
+        }
+        /// Redeploy automatically whenever the merged folder changes, until
+        /// Enter is pressed
+        cmd watch {
+            /// Watch the folders of enabled unpacked mods instead, remerging
+            /// changed files before deploying
+            optional -s, --sources
+        }
+        /// Manage profiles for the current mode
+        cmd profile {
+            /// List profiles, marking the current one
+            cmd list {}
+            /// Make a profile current, creating it if it doesn't exist
+            cmd switch {
+                required name: String
+            }
+            /// Copy a profile, with its mods and merged files, under a new name
+            cmd duplicate {
+                required name: String
+                required new_name: String
+            }
+            /// Rename a profile
+            cmd rename {
+                required name: String
+                required new_name: String
+            }
+            /// Delete a profile other than the current one
+            cmd delete {
+                required name: String
+            }
+        }
         /// Change current mode (Switch or Wii U)
         cmd mode {
             /// Mode to activate (Switch or Wii U)
             required platform: Platform
         }
+        /// Diff or merge single game files. Files must keep their in-game
+        /// names so their resource type can be identified.
+        cmd file {
+            /// Write the changes between a vanilla and a modded file to YAML
+            cmd diff {
+                /// Path to the vanilla file
+                required vanilla: PathBuf
+                /// Path to the modded file
+                required modded: PathBuf
+                /// Path to the output YAML diff
+                required -o, --output output: PathBuf
+            }
+            /// Merge diffs into a vanilla file, in order. Each diff can be a
+            /// YAML diff or a modded copy of the file.
+            cmd merge {
+                /// Path to the vanilla file
+                required vanilla: PathBuf
+                /// Paths to the diffs to merge
+                repeated diffs: PathBuf
+                /// Path to the output file
+                required -o, --output output: PathBuf
+            }
+        }
     }
 }
 // generated start
@@ -63,6 +200,7 @@ xflags::xflags! {
 pub struct Ukmm {
     pub debug: bool,
     pub portable: bool,
+    pub config_dir: Option<PathBuf>,
     pub deploy: bool,
     pub subcommand: UkmmCmd,
 }
@@ -72,22 +210,51 @@ pub enum UkmmCmd {
     Install(Install),
     Uninstall(Uninstall),
     Package(Package),
+    Keygen(Keygen),
+    Lint(Lint),
     Remerge(Remerge),
     Deploy(Deploy),
+    Compare(Compare),
+    Verify(Verify),
+    Bench(Bench),
+    Rollback(Rollback),
+    Watch(Watch),
+    Profile(Profile),
     Mode(Mode),
+    File(File),
 }
 
 #[derive(Debug)]
 pub struct Install {
-    pub path:    PathBuf,
-    pub profile: Option<String>,
+    pub path:     PathBuf,
+    pub profile:  Option<String>,
+    pub option:   Vec<String>,
+    pub defaults: bool,
+    pub skip:     Vec<String>,
 }
 
 #[derive(Debug)]
 pub struct Package {
-    pub path:   PathBuf,
+    pub path:       PathBuf,
+    pub output:     PathBuf,
+    pub meta:       PathBuf,
+    pub delta_from: Option<PathBuf>,
+    pub level:      Option<i32>,
+    pub fastest:    Vec<String>,
+    pub split_mb:   Option<u64>,
+    pub sign:       Option<PathBuf>,
+    pub exclude:    Vec<String>,
+    pub changes:    Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Keygen {
     pub output: PathBuf,
-    pub meta:   PathBuf,
+}
+
+#[derive(Debug)]
+pub struct Lint {
+    pub path: PathBuf,
 }
 
 #[derive(Debug)]
@@ -100,13 +267,105 @@ pub struct Uninstall {
 pub struct Remerge;
 
 #[derive(Debug)]
-pub struct Deploy;
+pub struct Deploy {
+    pub dry_run: bool,
+    pub target:  Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Compare {
+    pub a: String,
+    pub b: String,
+}
+
+#[derive(Debug)]
+pub struct Verify {
+    pub repair: bool,
+    pub mods:   bool,
+}
+
+#[derive(Debug)]
+pub struct Bench;
+
+#[derive(Debug)]
+pub struct Rollback {
+    pub target: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Watch {
+    pub sources: bool,
+}
+
+#[derive(Debug)]
+pub struct Profile {
+    pub subcommand: ProfileCmd,
+}
+
+#[derive(Debug)]
+pub enum ProfileCmd {
+    List(List),
+    Switch(Switch),
+    Duplicate(Duplicate),
+    Rename(Rename),
+    Delete(Delete),
+}
+
+#[derive(Debug)]
+pub struct List;
+
+#[derive(Debug)]
+pub struct Switch {
+    pub name: String,
+}
+
+#[derive(Debug)]
+pub struct Duplicate {
+    pub name:     String,
+    pub new_name: String,
+}
+
+#[derive(Debug)]
+pub struct Rename {
+    pub name:     String,
+    pub new_name: String,
+}
+
+#[derive(Debug)]
+pub struct Delete {
+    pub name: String,
+}
 
 #[derive(Debug)]
 pub struct Mode {
     pub platform: Platform,
 }
 
+#[derive(Debug)]
+pub struct File {
+    pub subcommand: FileCmd,
+}
+
+#[derive(Debug)]
+pub enum FileCmd {
+    Diff(Diff),
+    Merge(Merge),
+}
+
+#[derive(Debug)]
+pub struct Diff {
+    pub vanilla: PathBuf,
+    pub modded:  PathBuf,
+    pub output:  PathBuf,
+}
+
+#[derive(Debug)]
+pub struct Merge {
+    pub vanilla: PathBuf,
+    pub diffs:   Vec<PathBuf>,
+    pub output:  PathBuf,
+}
+
 impl Ukmm {
     #[allow(dead_code)]
     pub fn from_env_or_exit() -> Self {
@@ -134,6 +393,13 @@ macro_rules! input {
     }};
 }
 
+fn load_resource(path: &Path) -> Result<MergeableResource> {
+    let data = fs::read(path)?;
+    MergeableResource::from_binary(path, &decompress_if(&data))
+        .with_context(|| format!("Failed to parse {}", path.display()))?
+        .with_context(|| format!("{} is not a mergeable resource", path.display()))
+}
+
 #[derive(Debug)]
 pub struct Runner {
     core: core::Manager,
@@ -150,6 +416,36 @@ impl Runner {
 
     fn check_mod(&self, path: &Path) -> Result<Option<PathBuf>> {
         println!("Opening mod at {}...", path.display());
+        let joined;
+        let path = if uk_mod::split::is_split(path) {
+            println!("Joining split mod...");
+            joined = uk_manager::mods::join_split(path)?;
+            joined.as_path()
+        } else {
+            path
+        };
+        let converted;
+        let path = if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.eq_ignore_ascii_case("bnp"))
+            .unwrap_or(false)
+        {
+            println!("Converting BNP...");
+            let (mod_path, report) = uk_manager::bnp::convert_bnp_with_progress(
+                &self.core,
+                path,
+                Arc::new(|progress| println!("{progress}")),
+            )
+            .context("Failed to convert BNP to UKMM mod")?;
+            if !report.is_complete() {
+                println!("Some parts of this BNP could not be converted:\n{report}");
+            }
+            converted = mod_path;
+            converted.as_path()
+        } else {
+            path
+        };
         let (mod_, path) = match ModReader::open(path, vec![]) {
             Ok(mod_) => (mod_, path.to_path_buf()),
             Err(e) => {
@@ -173,27 +469,373 @@ impl Runner {
                 }
             }
         };
-        if !mod_.meta.options.is_empty() {
-            anyhow_ext::bail!(
-                "This mod contains configuration options and should be installed via the GUI."
-            );
+        let endian = Endian::from(self.core.settings().current_mode);
+        let path = match mod_.meta.platform {
+            ModPlatform::Specific(mod_endian) if mod_endian != endian && path.is_file() => {
+                println!(
+                    "Converting {} from {} to {}...",
+                    mod_.meta.name,
+                    Platform::from(mod_endian),
+                    Platform::from(endian)
+                );
+                let (converted, unconverted) = uk_manager::mods::convert_platform(&path, endian)?;
+                if !unconverted.is_empty() {
+                    println!("These files could not be converted, and may not work:");
+                    for file in unconverted {
+                        println!("  {file}");
+                    }
+                }
+                converted
+            }
+            _ => path,
+        };
+        if mod_.meta.delta.is_some() {
+            println!("Updating {}...", mod_.meta.name);
+        } else {
+            println!("Installing {}...", mod_.meta.name);
         }
-        println!("Installing {}...", mod_.meta.name);
         Ok(Some(path))
     }
 
+    /// Picks which options to enable for a newly added mod: the ones named on
+    /// the command line, the remembered or default ones with `--defaults`, or
+    /// otherwise whatever the user chooses when asked about each group.
+    fn choose_options(
+        &self,
+        mod_: &uk_manager::mods::Mod,
+        args: &Install,
+    ) -> Result<Vec<ModOption>> {
+        let meta = &mod_.meta;
+        let options = if !args.option.is_empty() {
+            args.option
+                .iter()
+                .map(|arg| {
+                    meta.options
+                        .iter()
+                        .flat_map(|group| group.options().iter())
+                        .find(|opt| {
+                            opt.path == Path::new(arg.as_str())
+                                || opt.name.eq_ignore_ascii_case(arg)
+                        })
+                        .cloned()
+                        .with_context(|| format!("{} has no option \"{arg}\"", meta.name))
+                })
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            let preset = self
+                .core
+                .mod_manager()
+                .get_profile(args.profile.as_ref())
+                .remembered_options(mod_)
+                .unwrap_or_else(|| {
+                    let mut mod_ = mod_.clone();
+                    mod_.enabled_options.clear();
+                    mod_.enable_default_options();
+                    mod_.enabled_options
+                });
+            if args.defaults {
+                preset
+            } else {
+                let mut options = vec![];
+                for group in &meta.options {
+                    options.extend(Self::prompt_group(group, &preset)?);
+                }
+                options
+            }
+        };
+        for group in &meta.options {
+            let chosen = group
+                .options()
+                .iter()
+                .filter(|opt| options.iter().any(|o| o.path == opt.path))
+                .count();
+            if chosen == 0 && group.required() {
+                anyhow_ext::bail!("Option group \"{}\" needs an option", group.name());
+            }
+            if chosen > 1 && matches!(group, OptionGroup::Exclusive(_)) {
+                anyhow_ext::bail!("Only one option can be chosen from \"{}\"", group.name());
+            }
+        }
+        let violations = meta.option_rule_violations(&options);
+        if !violations.is_empty() {
+            anyhow_ext::bail!("Invalid options: {}", violations.join(", "));
+        }
+        Ok(options)
+    }
+
+    /// Finds the selectable files of a mod named by `--skip`, given by their
+    /// path in the base game, e.g. `Sound/Resource/Stream/Title.bfstm`, or
+    /// with `Aoc/0010/` in front for DLC files.
+    fn choose_skipped(mod_: &uk_manager::mods::Mod, skip: &[String]) -> Result<Manifest> {
+        let selectable = mod_.selectable_files()?;
+        let mut excluded = Manifest::default();
+        for arg in skip {
+            let arg = arg.replace('\\', "/");
+            if let Some(file) = selectable.content_files.get(arg.as_str()) {
+                excluded.content_files.insert(file.clone());
+            } else if let Some(file) = arg
+                .strip_prefix("Aoc/0010/")
+                .and_then(|f| selectable.aoc_files.get(f))
+            {
+                excluded.aoc_files.insert(file.clone());
+            } else {
+                anyhow_ext::bail!("{} has no selectable file \"{arg}\"", mod_.meta.name);
+            }
+        }
+        Ok(excluded)
+    }
+
+    fn prompt_group(group: &OptionGroup, preset: &[ModOption]) -> Result<Vec<ModOption>> {
+        let exclusive = matches!(group, OptionGroup::Exclusive(_));
+        println!("\n{}", group.name());
+        if !group.description().is_empty() {
+            println!("  {}", group.description());
+        }
+        for (i, opt) in group.options().iter().enumerate() {
+            let mark = if preset.iter().any(|o| o.path == opt.path) {
+                "*"
+            } else {
+                " "
+            };
+            println!("  {mark}{}. {}", i + 1, opt.name);
+        }
+        loop {
+            print!(
+                "{} (Enter keeps the marked options{}): ",
+                if exclusive {
+                    "Choose one option by number"
+                } else {
+                    "Choose options by number, separated by spaces"
+                },
+                if group.required() { "" } else { ", 0 for none" }
+            );
+            let input = input!();
+            let input = input.trim();
+            if input.is_empty() {
+                return Ok(group
+                    .options()
+                    .iter()
+                    .filter(|opt| preset.iter().any(|o| o.path == opt.path))
+                    .cloned()
+                    .collect());
+            }
+            if input == "0" && !group.required() {
+                return Ok(vec![]);
+            }
+            let chosen = input
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|n| !n.is_empty())
+                .map(|n| {
+                    n.parse::<usize>()
+                        .ok()
+                        .and_then(|n| group.options().get(n.checked_sub(1)?))
+                        .cloned()
+                })
+                .collect::<Option<Vec<_>>>();
+            match chosen {
+                Some(chosen) if exclusive && chosen.len() > 1 => {
+                    println!("Only one option can be chosen from this group")
+                }
+                Some(chosen) => return Ok(chosen),
+                None => println!("Invalid choice, try again"),
+            }
+        }
+    }
+
     fn deploy(&self) -> Result<()> {
         let deployer = self.core.deploy_manager();
         if deployer.pending() {
             println!("Deploying changes...");
             deployer.deploy()?;
-            println!("Deployment complete");
+            Self::check_failures(&deployer)?;
+            if deployer.queued() {
+                println!(
+                    "Deploy target is not available, deployment queued until the next deploy"
+                );
+            } else {
+                println!("Deployment complete");
+                let required = deployer.required_settings();
+                if !required.is_empty() {
+                    println!("Enabled mods need the following emulator settings:");
+                    for (flag, mods) in required {
+                        println!("  {flag} ({})", mods.join(", "));
+                    }
+                }
+            }
         } else {
             println!("No changes pending deployment");
         };
         Ok(())
     }
 
+    /// Lists any files the last network share deployment could not write.
+    fn check_failures(deployer: &uk_manager::deploy::Manager) -> Result<()> {
+        let failures = deployer.failures();
+        if !failures.is_empty() {
+            for failure in &failures {
+                println!("{failure}");
+            }
+            anyhow_ext::bail!(
+                "{} files could not be written to the network share, deploy again to retry them",
+                failures.len()
+            );
+        }
+        Ok(())
+    }
+
+    fn profile(&self, cmd: &ProfileCmd) -> Result<()> {
+        match cmd {
+            ProfileCmd::List(_) => {
+                let current = self
+                    .core
+                    .settings()
+                    .platform_config()
+                    .map(|c| c.profile.clone())
+                    .unwrap_or_default();
+                for name in self.core.mod_manager().profile_names() {
+                    let marker = if name == current { "*" } else { " " };
+                    println!("{marker} {name}");
+                }
+            }
+            ProfileCmd::Switch(Switch { name }) => {
+                self.core.change_profile(name)?;
+                self.core.settings().save()?;
+                println!("Switched to profile {name}");
+                if self.cli.deploy {
+                    // Each profile has its own merged files, so everything
+                    // has to be checked against the deploy target again.
+                    self.core.deploy_manager().reset_pending()?;
+                    self.deploy()?;
+                }
+            }
+            ProfileCmd::Duplicate(Duplicate { name, new_name }) => {
+                self.core.clone_profile(name, new_name)?;
+                println!("Copied profile {name} to {new_name}");
+            }
+            ProfileCmd::Rename(Rename { name, new_name }) => {
+                self.core.rename_profile(name, new_name)?;
+                println!("Renamed profile {name} to {new_name}");
+            }
+            ProfileCmd::Delete(Delete { name }) => {
+                self.core.delete_profile(name)?;
+                println!("Deleted profile {name}");
+            }
+        }
+        Ok(())
+    }
+
+    fn dry_run(&self, target: Option<&str>) -> Result<()> {
+        let changes = self.core.deploy_manager().dry_run(target)?;
+        if changes.is_empty() {
+            println!("No changes to deploy");
+        } else {
+            for change in &changes {
+                println!("{change}");
+            }
+            let total: u64 = changes.iter().map(|c| c.size).sum();
+            println!(
+                "{} files, {:.1} MB",
+                changes.len(),
+                total as f64 / 1024.0 / 1024.0
+            );
+        }
+        Ok(())
+    }
+
+    fn verify(&self, repair: bool) -> Result<()> {
+        let deployer = self.core.deploy_manager();
+        println!("Verifying deployed files...");
+        let mut mismatches = deployer.verify()?;
+        if !mismatches.is_empty() && repair {
+            println!("Repairing {} files...", mismatches.len());
+            mismatches = deployer.repair()?;
+        }
+        if mismatches.is_empty() {
+            println!("All deployed files verified");
+        } else {
+            for mismatch in &mismatches {
+                println!("{mismatch}");
+            }
+            anyhow_ext::bail!(
+                "{} deployed files do not match the merged files",
+                mismatches.len()
+            );
+        }
+        Ok(())
+    }
+
+    fn verify_mods(&self) -> Result<()> {
+        println!("Verifying mod packages...");
+        let problems = self.core.mod_manager().check_integrity();
+        if problems.is_empty() {
+            println!("All mod packages verified");
+        } else {
+            for (mod_, problem) in &problems {
+                println!("{}: {problem}", mod_.meta.name);
+            }
+            anyhow_ext::bail!(
+                "{} mods do not match the files they were installed with",
+                problems.len()
+            );
+        }
+        Ok(())
+    }
+
+    fn watch(&self, sources: bool) -> Result<()> {
+        let mode = if sources {
+            WatchMode::ModSources
+        } else {
+            WatchMode::Merged
+        };
+        let _watcher = Watcher::start(&self.core, mode)?;
+        println!("Watching {} for changes, press Enter to stop", mode.name());
+        stdin().read_line(&mut std::string::String::new())?;
+        Ok(())
+    }
+
+    fn diff_file(&self, args: &Diff) -> Result<()> {
+        let vanilla = load_resource(&args.vanilla)?;
+        let modded = load_resource(&args.modded)?;
+        if std::mem::discriminant(&vanilla) != std::mem::discriminant(&modded) {
+            anyhow_ext::bail!("Cannot diff a {vanilla} file against a {modded} file");
+        }
+        let diff = vanilla.diff(&modded);
+        fs::write(&args.output, serde_yaml::to_string(&diff)?)?;
+        println!("Wrote diff to {}", args.output.display());
+        Ok(())
+    }
+
+    fn merge_file(&self, args: &Merge) -> Result<()> {
+        let vanilla = load_resource(&args.vanilla)?;
+        let mut merged = vanilla.clone();
+        for path in &args.diffs {
+            println!("Merging {}...", path.display());
+            let is_yaml = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.eq_ignore_ascii_case("yml") || e.eq_ignore_ascii_case("yaml"))
+                .unwrap_or(false);
+            let diff: MergeableResource = if is_yaml {
+                serde_yaml::from_str(&fs::read_to_string(path)?)
+                    .with_context(|| format!("Failed to parse diff {}", path.display()))?
+            } else {
+                let modded = load_resource(path)?;
+                if std::mem::discriminant(&vanilla) != std::mem::discriminant(&modded) {
+                    anyhow_ext::bail!("{} is not a {vanilla} file", path.display());
+                }
+                vanilla.diff(&modded)
+            };
+            if std::mem::discriminant(&merged) != std::mem::discriminant(&diff) {
+                anyhow_ext::bail!("{} is a {diff} diff, not {merged}", path.display());
+            }
+            merged = merged.merge(&diff);
+        }
+        let data = merged.into_binary(self.core.settings().current_mode.into());
+        fs::write(&args.output, compress_if(&data, &args.output))?;
+        println!("Wrote merged file to {}", args.output.display());
+        Ok(())
+    }
+
     pub fn run(self) -> Result<()> {
         match &self.cli.subcommand {
             UkmmCmd::Mode(Mode { platform }) => {
@@ -207,15 +849,49 @@ impl Runner {
                 }
                 println!("Done!");
             }
-            UkmmCmd::Install(Install { path, profile }) => {
+            UkmmCmd::Install(args) => {
+                let Install { path, profile, .. } = args;
                 if let Some(path) = self.check_mod(path)? {
                     let mods = self.core.mod_manager();
-                    let mod_ = mods.add(&path, profile.as_ref())?;
-                    mods.set_enabled(mod_.as_hash_id(), true, profile.as_ref())?;
+                    let delta = Meta::from_mod(&path).ok().and_then(|meta| meta.delta);
+                    let manifest = if let Some(delta) = delta {
+                        let (mod_, manifest) = mods.apply_delta(&path, profile.as_ref())?;
+                        println!("Updated {} to version {}", mod_.meta.name, mod_.meta.version);
+                        for entry in mod_.meta.changes_since(&delta.base_version) {
+                            println!("\nChanges in {}:\n{}", entry.version, entry.changes);
+                        }
+                        manifest
+                    } else {
+                        let mod_ = mods.add(&path, profile.as_ref())?;
+                        if let Some(key) = mod_.signer.as_ref() {
+                            println!("Signed by {}", uk_mod::integrity::fingerprint(key));
+                        }
+                        mods.set_enabled(mod_.as_hash_id(), true, profile.as_ref())?;
+                        let manifest = if mod_.meta.options.is_empty() {
+                            mod_.manifest()?
+                        } else {
+                            let options = self.choose_options(&mod_, args)?;
+                            mods.set_enabled_options(
+                                mod_.as_hash_id(),
+                                options,
+                                profile.as_ref(),
+                            )?
+                        };
+                        let mut manifest = manifest.as_ref().clone();
+                        if !args.skip.is_empty() {
+                            let excluded = Self::choose_skipped(&mod_, &args.skip)?;
+                            manifest.extend(&mods.set_excluded_files(
+                                mod_.as_hash_id(),
+                                excluded,
+                                profile.as_ref(),
+                            )?);
+                        }
+                        manifest
+                    };
                     mods.save()?;
                     println!("Applying mod to load order...");
                     let deployer = self.core.deploy_manager();
-                    deployer.apply(Some(mod_.manifest()?.as_ref().clone()))?;
+                    deployer.apply(Some(manifest))?;
                     if self.cli.deploy {
                         self.deploy()?;
                     }
@@ -224,14 +900,67 @@ impl Runner {
             }
             UkmmCmd::Package(pkg) => {
                 println!("Packaging mod...");
+                let mut compression = CompressionConfig::default();
+                if let Some(level) = pkg.level {
+                    anyhow_ext::ensure!(
+                        CompressionConfig::LEVELS.contains(&level),
+                        "Compression level must be from 1 to 22"
+                    );
+                    compression.level = level;
+                }
+                for name in &pkg.fastest {
+                    let category = FileCategory::iter()
+                        .find(|c| c.to_string().eq_ignore_ascii_case(name))
+                        .with_context(|| format!("Unknown file category {name}"))?;
+                    compression.categories.insert(*category, Compression::Fastest);
+                }
+                let meta = Meta::parse(&pkg.meta)?;
+                let changes = pkg.changes.clone().unwrap_or_else(|| {
+                    meta.current_changes()
+                        .map(|e| e.changes.to_string())
+                        .unwrap_or_default()
+                });
                 let builder = package::ModPackerBuilder {
-                    source: pkg.path.clone(),
-                    dest:   pkg.output.clone(),
-                    meta:   Meta::parse(&pkg.meta)?,
+                    source:      pkg.path.clone(),
+                    dest:        pkg.output.clone(),
+                    meta,
+                    delta_from:  pkg.delta_from.clone(),
+                    compression,
+                    split_mb:    pkg.split_mb,
+                    signing_key: pkg.sign.clone(),
+                    exclude:     pkg.exclude.clone(),
+                    changes,
                 };
                 tasks::package_mod(&self.core, builder)?;
                 println!("Done!");
             }
+            UkmmCmd::Keygen(Keygen { output }) => {
+                let key = SigningKey::generate(output)?;
+                println!("Saved signing key to {}", output.display());
+                println!(
+                    "Public key: {}\nFingerprint: {}",
+                    key.public_key(),
+                    uk_mod::integrity::fingerprint(&key.public_key())
+                );
+            }
+            UkmmCmd::Lint(Lint { path }) => {
+                println!("Checking mod for problems...");
+                let issues = uk_mod::lint::lint(path)?;
+                for issue in &issues {
+                    println!("{issue}");
+                }
+                let errors = issues
+                    .iter()
+                    .filter(|issue| issue.severity == uk_mod::lint::Severity::Error)
+                    .count();
+                if errors > 0 {
+                    anyhow_ext::bail!(
+                        "Found {errors} errors and {} warnings",
+                        issues.len() - errors
+                    );
+                }
+                println!("Found {} warnings", issues.len());
+            }
             UkmmCmd::Remerge(_) => {
                 println!("Remerging...");
                 tasks::apply_changes(&self.core, vec![], None)?;
@@ -281,7 +1010,43 @@ impl Runner {
                 }
                 println!("Done!");
             }
+            UkmmCmd::Deploy(Deploy {
+                dry_run: true,
+                target,
+            }) => self.dry_run(target.as_deref())?,
+            UkmmCmd::Deploy(Deploy { target: Some(target), .. }) => {
+                println!("Deploying to {target}...");
+                let deployer = self.core.deploy_manager();
+                deployer.deploy_target(target)?;
+                Self::check_failures(&deployer)?;
+                println!("Deployment complete");
+            }
             UkmmCmd::Deploy(_) => self.deploy()?,
+            UkmmCmd::Compare(Compare { a, b }) => {
+                println!("Building snapshots of {a} and {b}...");
+                let report = self.core.deploy_manager().compare_profiles(a, b)?;
+                print!("{report}");
+            }
+            UkmmCmd::Verify(Verify { mods: true, .. }) => self.verify_mods()?,
+            UkmmCmd::Verify(Verify { repair, .. }) => self.verify(*repair)?,
+            UkmmCmd::Bench(_) => {
+                println!("Running benchmark...");
+                let report = uk_manager::bench::run(&self.core)?;
+                print!("{report}");
+            }
+            UkmmCmd::Rollback(Rollback { target }) => {
+                println!("Restoring previous deployment...");
+                let restored = self.core.deploy_manager().rollback(target.as_deref())?;
+                println!("Restored {restored} files");
+            }
+            UkmmCmd::Watch(Watch { sources }) => self.watch(*sources)?,
+            UkmmCmd::Profile(Profile { subcommand }) => self.profile(subcommand)?,
+            UkmmCmd::File(File { subcommand }) => {
+                match subcommand {
+                    FileCmd::Diff(args) => self.diff_file(args)?,
+                    FileCmd::Merge(args) => self.merge_file(args)?,
+                }
+            }
         };
         Ok(())
     }
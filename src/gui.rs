@@ -6,6 +6,7 @@ mod options;
 pub(crate) mod package;
 mod picker;
 mod profiles;
+mod proxy;
 mod settings;
 mod tabs;
 pub(crate) mod tasks;
@@ -17,7 +18,7 @@ use std::{
     path::PathBuf,
     sync::Arc,
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow_ext::{Context, Result};
@@ -30,13 +31,20 @@ use parking_lot::{Mutex, RwLock};
 use picker::FilePickerState;
 use rustc_hash::FxHashSet;
 use serde::{Deserialize, Serialize};
-use uk_content::util::HashMap;
+use uk_content::{
+    actor::params::general::EconomyMergePolicy, constants::Language,
+    data::gamedata::FlagResolution, util::HashMap,
+};
 use uk_manager::{
+    backup::Backup,
+    compat::CompatDb,
     core::Manager,
-    mods::{LookupMod, Mod},
-    settings::{Platform, Settings},
+    mods::{LookupMod, Mod, Orphan},
+    settings::{BcmlSettings, DeployMethod, Platform, ProfilePaths, Settings},
+    updates::ModUpdate,
+    watch::{WatchMode, Watcher},
 };
-use uk_mod::{pack::sanitise, Manifest, Meta, ModPlatform};
+use uk_mod::{pack::sanitise, unpack::ModFlagConflict, Manifest, Meta, ModPlatform};
 pub use uk_ui::visuals;
 use uk_ui::{
     egui::{
@@ -88,6 +96,8 @@ pub enum Tabs {
     Info,
     Install,
     Deploy,
+    DeployConfirmed,
+    DeployTarget(smartstring::alias::String),
     Mods,
     Log,
     Settings,
@@ -150,33 +160,43 @@ pub enum Message {
     AddMod(Mod),
     AddProfile,
     Apply,
+    Benchmark,
     ChangeProfile(String),
     ChangeSort(Sort, bool),
     CheckMeta,
+    CheckModUpdates,
     ClearDrag,
     ClearSelect,
     CloseAbout,
     CloseConfirm,
     CloseError,
     CloseChangelog,
+    CloseFlagConflicts,
+    CloseModChanges,
     ClosePackagingOptions,
     ClosePackagingDependencies,
     CloseProfiles,
+    CompareProfile(String),
     Confirm(Box<Message>, String),
     DeleteProfile(String),
     Deploy,
     Deselect(usize),
     DoUpdate,
     DuplicateProfile(String),
+    EditMeta(Mod),
     Error(anyhow_ext::Error),
     FilePickerBack,
     FilePickerSet(Option<PathBuf>),
     FilePickerUp,
+    FindOrphans,
     GetPackagingOptions,
     HandleMod(Mod),
     HandleSettings,
+    ImportBcmlSettings,
     ImportCemu,
     InstallMod(Mod),
+    LintMod(PathBuf),
+    MetaEdited(usize, Mod),
     Log(Entry),
     MigrateBcml,
     MoveSelected(usize),
@@ -185,35 +205,78 @@ pub enum Message {
     OfferUpdate(VersionResponse),
     OpenMod(PathBuf),
     PackageMod,
+    PreviewDeploy,
+    RepairDeploy,
     RefreshModsDisplay,
     Remerge,
     ReloadProfiles,
     RemoveMods(Vec<Mod>),
+    RemoveOrphans(Vec<Orphan>),
     RenameProfile(String, String),
+    ReplaceMod(Mod, Manifest),
     RequestMeta(PathBuf),
     RequestOptions(Mod, bool),
     ResetMods,
+    ResolveFlagConflicts(Vec<(String, String, Option<FlagResolution>)>),
     ResetPacker,
     ResetPending,
     ResetSettings,
     Restart,
+    RestoreMerged,
+    RestoreProfile(String, Backup),
+    RestoreSettings(Backup),
+    RollbackDeploy,
+    SaveMeta(Mod, Meta),
     SaveSettings,
     SelectAlso(usize),
     SelectFile,
     SelectOnly(usize),
     SelectProfileManage(smartstring::alias::String),
     SetChangelog(String),
+    SetCompat(CompatDb),
     SetFocus(FocusedPane),
+    SetEconomyMerge(String, EconomyMergePolicy),
+    SetLoose(Option<Vec<Mod>>, bool),
+    SetModUpdates(Vec<ModUpdate>),
+    SetProfilePaths(String, ProfilePaths),
     SetTheme(uk_ui::visuals::Theme),
     ShowAbout,
+    ShowFlagConflicts(Vec<ModFlagConflict>),
     ShowPackagingOptions(FxHashSet<PathBuf>),
     ShowPackagingDependencies,
     StartDrag(usize),
     Toast(String),
     ToggleMods(Option<Vec<Mod>>, bool),
+    ToggleWatch(Option<WatchMode>),
     UpdatePackageMeta(Meta),
     UninstallMods(Option<Vec<Mod>>),
+    UpdateCompat,
+    UpdateMod(Mod),
     UpdateOptions(Mod),
+    VerifyDeploy,
+    VerifyMods,
+}
+
+impl Message {
+    /// Whether the message installs, removes, toggles, or reorders mods,
+    /// which is not allowed while mods are locked.
+    fn changes_mods(&self) -> bool {
+        matches!(
+            self,
+            Message::InstallMod(_)
+                | Message::MigrateBcml
+                | Message::MoveSelected(_)
+                | Message::OpenMod(_)
+                | Message::RequestOptions(..)
+                | Message::SaveMeta(..)
+                | Message::SelectFile
+                | Message::SetLoose(..)
+                | Message::ToggleMods(..)
+                | Message::UninstallMods(_)
+                | Message::UpdateMod(_)
+                | Message::UpdateOptions(_)
+        )
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -268,9 +331,20 @@ pub struct App {
     theme: uk_ui::visuals::Theme,
     dock_style: uk_ui::egui_dock::Style,
     changelog: Option<String>,
+    /// The title and Markdown of what changed in a mod which was updated
+    mod_changes: Option<(String, String)>,
+    compat: CompatDb,
+    /// Installed mods with newer versions named by their update feeds
+    mod_updates: Vec<ModUpdate>,
+    flag_conflicts: Option<Vec<(ModFlagConflict, Option<FlagResolution>)>>,
     new_version: Option<VersionResponse>,
+    last_queue_check: Instant,
+    sys: RefCell<(sysinfo::System, Instant)>,
+    watcher: Option<Watcher>,
 }
 
+const QUEUE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
 impl App {
     fn new(cc: &eframe::CreationContext) -> Self {
         if option_env!("UPDATE_PLATFORM").unwrap_or_default() == "steamdeck" {
@@ -291,7 +365,7 @@ impl App {
         let mods: Vec<_> = core.mod_manager().all_mods().collect();
         let (send, recv) = flume::unbounded();
         crate::logger::LOGGER.set_sender(send.clone());
-        crate::logger::LOGGER.set_file(Settings::config_dir().join("log.txt"));
+        crate::logger::LOGGER.configure(&core.settings());
         log::info!("Logger initialized");
         let temp_settings = core.settings().clone();
         let platform = core.settings().current_mode;
@@ -320,6 +394,10 @@ impl App {
                     None
                 }
             },
+            mod_changes: None,
+            compat: CompatDb::load(&core.settings()),
+            mod_updates: vec![],
+            flag_conflicts: None,
             channel: (send, recv),
             core,
             logs: Vec::new(),
@@ -342,6 +420,9 @@ impl App {
             dock_style: uk_ui::visuals::style_dock(&cc.egui_ctx.style()),
             install_queue: Default::default(),
             new_version: None,
+            last_queue_check: Instant::now(),
+            sys: RefCell::new((sysinfo::System::new(), Instant::now())),
+            watcher: None,
         }
     }
 
@@ -350,6 +431,11 @@ impl App {
         self.core.settings().current_mode
     }
 
+    #[inline(always)]
+    fn language(&self) -> Language {
+        self.core.settings().language()
+    }
+
     #[inline(always)]
     fn modal_open(&self) -> bool {
         self.error.is_some()
@@ -362,6 +448,8 @@ impl App {
             || self.opt_folders.is_some()
             || self.meta_input.is_open()
             || self.changelog.is_some()
+            || self.mod_changes.is_some()
+            || self.flag_conflicts.is_some()
     }
 
     fn do_update(&self, message: Message) {
@@ -413,9 +501,31 @@ impl App {
         }
     }
 
+    fn check_queued_deploy(&mut self, ctx: &eframe::egui::Context) {
+        if !self.core.deploy_manager().queued() {
+            return;
+        }
+        ctx.request_repaint_after(QUEUE_CHECK_INTERVAL);
+        if !self.modal_open() && self.last_queue_check.elapsed() >= QUEUE_CHECK_INTERVAL {
+            self.last_queue_check = Instant::now();
+            if self.core.deploy_manager().target_available() {
+                self.do_task(|core| {
+                    core.deploy_manager().retry_queued()?;
+                    Ok(Message::ResetMods)
+                });
+            }
+        }
+    }
+
     fn handle_update(&mut self, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
         if let Ok(msg) = self.channel.1.try_recv() {
             match msg {
+                msg if msg.changes_mods() && self.core.settings().locked => {
+                    self.do_update(Message::Toast(
+                        "Mods are locked. Turn off Lock Mods in the settings to change them."
+                            .into(),
+                    ));
+                }
                 Message::Noop => self.busy.set(false),
                 Message::Log(entry) => {
                     if !entry.args.starts_with("PROGRESS") {
@@ -458,6 +568,7 @@ impl App {
                 Message::CloseAbout => self.show_about = false,
                 Message::CloseProfiles => self.profiles_state.borrow_mut().show = false,
                 Message::Confirm(msg, prompt) => {
+                    self.busy.set(false);
                     self.confirm = Some((*msg, prompt));
                 }
                 Message::SelectOnly(i) => {
@@ -571,25 +682,32 @@ impl App {
                 }
                 Message::DeleteProfile(profile) => {
                     self.do_task(move |core| {
-                        let path = core.settings().profiles_dir().join(profile);
-                        fs::remove_dir_all(path)?;
+                        core.delete_profile(&profile)?;
                         Ok(Message::ReloadProfiles)
                     })
                 }
+                Message::CompareProfile(profile) => {
+                    self.do_task(move |core| tasks::compare_profile(&core, &profile));
+                }
+                Message::SetEconomyMerge(profile, policy) => {
+                    self.do_task(move |core| tasks::set_economy_merge(&core, &profile, policy));
+                }
+                Message::SetProfilePaths(profile, paths) => {
+                    self.do_task(move |core| {
+                        core.set_profile_paths(&profile, paths)
+                            .context("Failed to save profile folders")?;
+                        Ok(Message::ResetSettings)
+                    });
+                }
                 Message::DuplicateProfile(profile) => {
                     self.do_task(move |core| {
-                        let profiles_dir = core.settings().profiles_dir();
-                        dircpy::copy_dir(
-                            profiles_dir.join(&profile),
-                            profiles_dir.join(profile + "_copy"),
-                        )?;
+                        core.clone_profile(&profile, &(profile.clone() + "_copy"))?;
                         Ok(Message::ReloadProfiles)
                     });
                 }
                 Message::RenameProfile(profile, rename) => {
                     self.do_task(move |core| {
-                        let profiles_dir = core.settings().profiles_dir();
-                        fs::rename(profiles_dir.join(&profile), profiles_dir.join(rename))?;
+                        core.rename_profile(&profile, &rename)?;
                         Ok(Message::ReloadProfiles)
                     })
                 }
@@ -598,7 +716,9 @@ impl App {
                     self.busy.set(false);
                 }
                 Message::SelectProfileManage(name) => {
-                    self.profiles_state.borrow_mut().selected = Some(name);
+                    let mut state = self.profiles_state.borrow_mut();
+                    state.selected = Some(name);
+                    state.paths = None;
                 }
                 Message::SetFocus(pane) => {
                     self.focused = pane;
@@ -610,10 +730,14 @@ impl App {
                 }
                 Message::SelectFile => {
                     if let Some(mut paths) = rfd::FileDialog::new()
-                            .add_filter("Any mod (*.zip, *.7z, *.bnp)", &["zip", "bnp", "7z"])
+                            .add_filter(
+                                "Any mod (*.zip, *.7z, *.bnp, *.001)",
+                                &["zip", "bnp", "7z", "001"],
+                            )
                             .add_filter("UKMM Mod (*.zip)", &["zip"])
                             .add_filter("BCML Mod (*.bnp)", &["bnp"])
                             .add_filter("Legacy Mod (*.zip, *.7z)", &["zip", "7z"])
+                            .add_filter("Split Mod (*.001)", &["001"])
                             .add_filter("All files (*.*)", &["*"])
                             .pick_files() && !paths.is_empty()
                     {
@@ -648,7 +772,23 @@ impl App {
                             self.platform()
                         )));
                     }
-                    if !mod_.meta.options.is_empty() {
+                    let warnings = self.compat.check_mod(
+                        &mod_,
+                        &self.mods.iter().chain([&mod_]).cloned().collect::<Vec<_>>(),
+                    );
+                    if !warnings.is_empty() {
+                        self.do_update(Message::Error(anyhow_ext::anyhow!(
+                            "Known compatibility issues:\n{}",
+                            warnings
+                                .iter()
+                                .map(|w| format!("• {w}"))
+                                .collect::<Vec<_>>()
+                                .join("\n")
+                        )));
+                    }
+                    if mod_.meta.delta.is_some() {
+                        self.do_update(Message::UpdateMod(mod_));
+                    } else if !mod_.meta.options.is_empty() || !mod_.meta.selectable.is_empty() {
                         self.do_update(Message::RequestOptions(mod_, false));
                     } else {
                         self.do_update(Message::InstallMod(mod_));
@@ -659,8 +799,11 @@ impl App {
                         let mods = core.mod_manager();
                         let mod_ = mods.add(&tmp_mod_.path, None)?;
                         let hash = mod_.as_hash_id();
-                        if !tmp_mod_.enabled_options.is_empty() {
-                            mods.set_enabled_options(hash, tmp_mod_.enabled_options)?;
+                        if !tmp_mod_.meta.options.is_empty() {
+                            mods.set_enabled_options(hash, tmp_mod_.enabled_options, None)?;
+                        }
+                        if !tmp_mod_.excluded_files.is_empty() {
+                            mods.set_excluded_files(hash, tmp_mod_.excluded_files, None)?;
                         }
                         mods.save()?;
                         log::info!("Added mod {} to current profile", mod_.meta.name.as_str());
@@ -668,6 +811,14 @@ impl App {
                         Ok(Message::AddMod(mod_))
                     });
                 }
+                Message::UpdateMod(delta) => {
+                    self.do_task(move |core| {
+                        let mods = core.mod_manager();
+                        let (mod_, manifest) = mods.apply_delta(&delta.path, None)?;
+                        mods.save()?;
+                        Ok(Message::ReplaceMod(mod_, manifest))
+                    });
+                }
                 Message::UninstallMods(mods) => {
                     let mods = mods.unwrap_or_else(|| self.selected.clone());
                     self.do_task(move |core| {
@@ -681,6 +832,19 @@ impl App {
                         Ok(Message::RemoveMods(mods))
                     });
                 }
+                Message::SetLoose(mods, loose) => {
+                    let mods = mods.as_ref().unwrap_or(&self.selected);
+                    match mods.iter().try_for_each(|m| -> Result<()> {
+                        let mod_ =
+                            unsafe { self.mods.iter_mut().find(|m2| m.eq(m2)).unwrap_unchecked() };
+                        mod_.loose = loose;
+                        self.dirty.extend(m.manifest()?.as_ref());
+                        Ok(())
+                    }) {
+                        Ok(()) => self.do_update(Message::RefreshModsDisplay),
+                        Err(e) => self.do_update(Message::Error(e)),
+                    };
+                }
                 Message::ToggleMods(mods, enabled) => {
                     let mods = mods.as_ref().unwrap_or(&self.selected);
                     match mods.iter().try_for_each(|m| -> Result<()> {
@@ -705,6 +869,27 @@ impl App {
                         self.do_task(move |core| tasks::open_mod(&core, &path, None));
                     }
                 }
+                Message::ReplaceMod(mod_, manifest) => {
+                    self.dirty.extend(&manifest);
+                    self.selected.retain(|m| m.meta.name != mod_.meta.name);
+                    if let Some(old) =
+                        self.mods.iter_mut().find(|m| m.meta.name == mod_.meta.name)
+                    {
+                        let changes = mod_.meta.changes_since(&old.meta.version);
+                        if !changes.is_empty() {
+                            self.mod_changes = Some((
+                                format!("What's New in {} {}", mod_.meta.name, mod_.meta.version),
+                                info::changelog_markdown(changes),
+                            ));
+                        }
+                        *old = mod_;
+                    }
+                    self.do_update(Message::RefreshModsDisplay);
+                    self.busy.set(false);
+                    if let Some(path) = self.install_queue.pop_front() {
+                        self.do_task(move |core| tasks::open_mod(&core, &path, None));
+                    }
+                }
                 Message::RemoveMods(mods) => {
                     self.mods.retain(|m| !mods.contains(m));
                     self.selected.retain(|m| !mods.contains(m));
@@ -722,11 +907,33 @@ impl App {
                     self.do_task(move |core| tasks::apply_changes(&core, mods, Some(dirty)));
                 }
                 Message::Deploy => {
-                    self.do_task(move |core| {
-                        log::info!("Deploying current mod configuration");
-                        core.deploy_manager().deploy()?;
-                        Ok(Message::ResetMods)
-                    })
+                    match self
+                        .core
+                        .settings()
+                        .platform_config()
+                        .and_then(|c| {
+                            c.deploy_targets
+                                .iter()
+                                .find(|t| t.method == DeployMethod::SdCard)
+                        })
+                    {
+                        Some(config) => {
+                            self.do_update(Message::Confirm(
+                                Box::new(Message::DeployConfirmed),
+                                format!(
+                                    "This will copy the merged files to the SD card at {}. Make \
+                                     sure the right card is inserted and do not remove it until \
+                                     deployment finishes. Continue?",
+                                    config.output.display()
+                                ),
+                            ))
+                        }
+                        None => self.do_update(Message::DeployConfirmed),
+                    }
+                }
+                Message::DeployConfirmed => self.do_task(move |core| tasks::deploy(&core, None)),
+                Message::DeployTarget(name) => {
+                    self.do_task(move |core| tasks::deploy(&core, Some(&name)))
                 }
                 Message::ResetPending => {
                     self.do_task(|core| {
@@ -738,6 +945,108 @@ impl App {
                 Message::Remerge => {
                     self.do_task(|core| tasks::apply_changes(&core, vec![], None));
                 }
+                Message::UpdateCompat => {
+                    self.do_task(tasks::update_compat);
+                }
+                Message::SetCompat(compat) => {
+                    self.busy.set(false);
+                    self.toasts.add({
+                        let mut toast = Toast::success(format!(
+                            "Compatibility rules updated to version {}",
+                            compat.version
+                        ));
+                        toast.set_duration(Some(Duration::new(2, 0)));
+                        toast
+                    });
+                    self.compat = compat;
+                }
+                Message::CheckModUpdates => {
+                    self.do_task(tasks::check_mod_updates);
+                }
+                Message::SetModUpdates(updates) => {
+                    self.busy.set(false);
+                    self.toasts.add({
+                        let mut toast = Toast::success(match updates.len() {
+                            0 => "No mod updates found".into(),
+                            1 => format!("An update is available for {}", updates[0].name),
+                            n => format!("Updates are available for {n} mods"),
+                        });
+                        toast.set_duration(Some(Duration::new(2, 0)));
+                        toast
+                    });
+                    self.mod_updates = updates;
+                }
+                Message::PreviewDeploy => {
+                    self.do_task(|core| tasks::preview_deploy(&core));
+                }
+                Message::VerifyDeploy => {
+                    self.do_task(|core| tasks::verify_deploy(&core));
+                }
+                Message::VerifyMods => {
+                    self.do_task(|core| tasks::verify_mods(&core));
+                }
+                Message::LintMod(path) => {
+                    self.do_task(move |_| tasks::lint_mod(&path));
+                }
+                Message::RepairDeploy => {
+                    self.do_task(|core| tasks::repair_deploy(&core));
+                }
+                Message::Benchmark => {
+                    self.do_task(|core| tasks::benchmark(&core));
+                }
+                Message::RollbackDeploy => {
+                    self.do_task(|core| tasks::rollback_deploy(&core));
+                }
+                Message::RestoreMerged => {
+                    self.do_task(|core| tasks::restore_merged(&core));
+                }
+                Message::ToggleWatch(mode) => {
+                    self.watcher = None;
+                    if let Some(mode) = mode {
+                        match Watcher::start(&self.core, mode) {
+                            Ok(watcher) => {
+                                self.watcher = Some(watcher);
+                                self.do_update(Message::Toast(format!(
+                                    "Watching {} for changes",
+                                    mode.name()
+                                )));
+                            }
+                            Err(e) => self.do_update(Message::Error(e)),
+                        }
+                    }
+                }
+                Message::FindOrphans => {
+                    self.do_task(|core| tasks::find_orphans(&core));
+                }
+                Message::RemoveOrphans(orphans) => {
+                    self.do_task(move |core| tasks::remove_orphans(&core, orphans));
+                }
+                Message::ShowFlagConflicts(conflicts) => {
+                    self.flag_conflicts = Some(conflicts.into_iter().map(|c| (c, None)).collect());
+                    self.do_update(Message::ResetMods);
+                }
+                Message::CloseFlagConflicts => self.flag_conflicts = None,
+                Message::CloseModChanges => self.mod_changes = None,
+                Message::ResolveFlagConflicts(choices) => {
+                    self.flag_conflicts = None;
+                    self.do_task(move |core| tasks::resolve_flag_conflicts(&core, choices));
+                }
+                Message::RestoreSettings(backup) => {
+                    match self.core.restore_settings(&backup) {
+                        Ok(()) => {
+                            self.do_update(Message::ResetSettings);
+                            self.do_update(Message::ResetMods);
+                            self.do_update(Message::Toast(format!(
+                                "Settings restored from backup ({})",
+                                backup.age()
+                            )));
+                        }
+                        Err(e) => self.do_update(Message::Error(e)),
+                    }
+                }
+                Message::RestoreProfile(profile, backup) => {
+                    self.do_task(move |core| tasks::restore_profile(&core, &profile, &backup));
+                }
                 Message::ResetSettings => {
                     self.busy.set(false);
                     self.temp_settings = self.core.settings().clone();
@@ -755,6 +1064,7 @@ impl App {
                                 toast
                             });
                             if let Some(dump) = self.core.settings().dump() { dump.clear_cache() }
+                            crate::logger::LOGGER.configure(&self.core.settings());
                             self.package_builder.borrow_mut().reset(self.platform());
                             self.do_update(Message::ClearSelect);
                             self.do_update(Message::ResetMods);
@@ -769,23 +1079,35 @@ impl App {
                         toast
                     });
                     if let Some(dump) = self.core.settings().dump() { dump.clear_cache() }
+                    crate::logger::LOGGER.configure(&self.core.settings());
                     self.package_builder.borrow_mut().reset(self.platform());
                     self.do_update(Message::ClearSelect);
                     self.do_update(Message::ResetMods);
                 }
                 Message::RequestOptions(mut mod_, update) => {
                     if !update {
-                        mod_.enable_default_options();
+                        match self.core.mod_manager().profile().remembered_options(&mod_) {
+                            Some(options) => mod_.enabled_options = options,
+                            None => mod_.enable_default_options(),
+                        }
                     }
                     self.options_mod = Some((mod_, update));
                 }
                 Message::UpdateOptions(mod_) => {
                     let opts = mod_.enabled_options.clone();
-                    match self
-                        .core
-                        .mod_manager()
-                        .set_enabled_options(mod_.hash(), opts)
-                    {
+                    let excluded = mod_.excluded_files.clone();
+                    let mod_manager = self.core.mod_manager();
+                    match mod_manager
+                        .set_enabled_options(mod_.hash(), opts, None)
+                        .and_then(|manifest| {
+                            let mut manifest = manifest.as_ref().clone();
+                            manifest.extend(&mod_manager.set_excluded_files(
+                                mod_.hash(),
+                                excluded,
+                                None,
+                            )?);
+                            Ok(manifest)
+                        }) {
                         Ok(manifest) => {
                             self.dirty.extend(&manifest);
                             if let Some(old_mod) =
@@ -868,10 +1190,47 @@ impl App {
                 Message::MigrateBcml => {
                     self.do_task(tasks::migrate_bcml);
                 }
+                Message::ImportBcmlSettings => {
+                    match BcmlSettings::load()
+                        .and_then(|bcml| self.temp_settings.import_bcml(&bcml))
+                    {
+                        Ok(imported) if imported.is_empty() => {
+                            self.do_update(Message::Toast(
+                                "BCML has no game dump set, so there is nothing to import".into(),
+                            ));
+                        }
+                        Ok(_) => {
+                            settings::CONFIG.write().clear();
+                            self.do_update(Message::Toast(
+                                "BCML settings imported. Check them over, then save.".into(),
+                            ));
+                        }
+                        Err(e) => self.do_update(Message::Error(e)),
+                    }
+                }
                 Message::RequestMeta(path) => {
                     self.meta_input
                         .open(path, self.platform());
                 }
+                Message::EditMeta(mod_) => self.meta_input.open_edit(mod_),
+                Message::SaveMeta(mod_, meta) => {
+                    self.do_task(move |core| tasks::edit_meta(&core, mod_, meta));
+                }
+                Message::MetaEdited(old_hash, mut mod_) => {
+                    self.busy.set(false);
+                    if let Some(old_mod) = self.mods.iter_mut().find(|m| m.hash() == old_hash) {
+                        // Keep any pending changes to the mod's state
+                        mod_.enabled = old_mod.enabled;
+                        mod_.loose = old_mod.loose;
+                        mod_.enabled_options = old_mod.enabled_options.clone();
+                        mod_.excluded_files = old_mod.excluded_files.clone();
+                        *old_mod = mod_.clone();
+                    }
+                    if let Some(old_mod) = self.selected.iter_mut().find(|m| m.hash() == old_hash) {
+                        *old_mod = mod_;
+                    }
+                    self.do_update(Message::RefreshModsDisplay);
+                }
                 Message::SetChangelog(msg) => self.changelog = Some(msg),
                 Message::CloseChangelog => self.changelog = None,
                 Message::OfferUpdate(version) => {
@@ -881,8 +1240,8 @@ impl App {
                 Message::DoUpdate => {
                     let version = self.new_version.take().unwrap();
                     self.changelog = None;
-                    self.do_task(move |_| {
-                        tasks::do_update(version)
+                    self.do_task(move |core| {
+                        tasks::do_update(core, version)
                     });
                 }
                 Message::Restart => {
@@ -899,6 +1258,7 @@ impl App {
                     frame.close();
                 }
                 Message::Toast(msg) => {
+                    self.busy.set(false);
                     self.toasts.add({
                         let mut toast = Toast::info(msg);
                         toast.set_duration(Some(Duration::new(2, 0)));
@@ -906,7 +1266,13 @@ impl App {
                     });
                 }
                 Message::UpdatePackageMeta(meta) => {
-                    self.package_builder.borrow_mut().meta = meta;
+                    let mut builder = self.package_builder.borrow_mut();
+                    builder.changes = meta
+                        .current_changes()
+                        .map(|e| e.changes.to_string())
+                        .unwrap_or_default();
+                    builder.meta = meta;
+                    drop(builder);
                     self.busy.set(false);
                 },
             }
@@ -919,6 +1285,7 @@ impl App {
 impl eframe::App for App {
     fn update(&mut self, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
         self.handle_update(ctx, frame);
+        self.check_queued_deploy(ctx);
         self.render_menu(ctx, frame);
         self.render_error(ctx);
         self.render_confirm(ctx);
@@ -927,6 +1294,8 @@ impl eframe::App for App {
         self.render_option_picker(ctx);
         self.profiles_state.borrow_mut().render(self, ctx);
         self.render_changelog(ctx);
+        self.render_mod_changes(ctx);
+        self.render_flag_conflicts(ctx);
         self.meta_input.ui(ctx);
         let layer_id = LayerId::background();
         let max_rect = ctx.available_rect();
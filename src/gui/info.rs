@@ -1,14 +1,14 @@
 use std::{
     hash::{Hash, Hasher},
-    io::{BufReader, Read},
     sync::{Arc, LazyLock},
 };
 
 use anyhow::Result;
 use parking_lot::{Mutex, RwLock};
 use rustc_hash::{FxHashMap, FxHasher};
+use uk_content::constants::Language;
 use uk_manager::mods::Mod;
-use uk_mod::Manifest;
+use uk_mod::{unpack::ModReader, ChangelogEntry, Manifest};
 use uk_ui::{
     egui::{self, Align, Label, Layout, RichText, Ui},
     egui_extras::RetainedImage,
@@ -22,24 +22,34 @@ pub enum Message {
     RequestOptions,
 }
 
-#[repr(transparent)]
-pub struct ModInfo<'a>(pub &'a Mod);
+/// Renders changelog entries as Markdown, one section per version.
+pub fn changelog_markdown<'a>(entries: impl IntoIterator<Item = &'a ChangelogEntry>) -> String {
+    entries
+        .into_iter()
+        .map(|e| format!("### {}\n\n{}\n", e.version, e.changes))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A mod's details, with its name and description in the given language if
+/// it has a translation.
+pub struct ModInfo<'a>(pub &'a Mod, pub Language);
 
 impl ModInfo<'_> {
     pub fn preview(&self) -> Option<Arc<RetainedImage>> {
         fn load_preview(mod_: &Mod) -> Result<Option<Arc<RetainedImage>>> {
-            let mut zip = zip::ZipArchive::new(BufReader::new(std::fs::File::open(&mod_.path)?))?;
-            for ext in ["jpg", "jpeg", "png", "svg"] {
-                if let Ok(mut file) = zip.by_name(&format!("thumb.{}", ext)) {
-                    let mut vec = vec![0; file.size() as usize];
-                    file.read_exact(&mut vec)?;
-                    return Ok(Some(Arc::new(
-                        RetainedImage::from_image_bytes(mod_.meta.name.as_str(), &vec)
-                            .map_err(|e| anyhow::anyhow!("{}", e))?,
-                    )));
+            let reader = ModReader::open_peek(&mod_.path, vec![])?;
+            match reader.thumbnail()? {
+                Some((name, data)) => {
+                    let image = if name.ends_with(".svg") {
+                        RetainedImage::from_svg_bytes(mod_.meta.name.as_str(), &data)
+                    } else {
+                        RetainedImage::from_image_bytes(mod_.meta.name.as_str(), &data)
+                    };
+                    Ok(Some(Arc::new(image.map_err(|e| anyhow::anyhow!("{}", e))?)))
                 }
+                None => Ok(None),
             }
-            Ok(None)
         }
         static PREVIEW: LazyLock<RwLock<FxHashMap<usize, Option<Arc<RetainedImage>>>>> =
             LazyLock::new(|| RwLock::new(FxHashMap::default()));
@@ -73,11 +83,26 @@ impl Component for ModInfo<'_> {
                 ui.add_space(8.);
             }
             let ver = mod_.meta.version.to_string();
+            let signer = mod_
+                .signer
+                .as_ref()
+                .map(|key| uk_mod::integrity::fingerprint(key))
+                .unwrap_or_default();
             [
-                ("Name", mod_.meta.name.as_str()),
+                ("Name", mod_.meta.display_name(self.1)),
                 ("Version", ver.as_str()),
                 ("Category", mod_.meta.category.as_str()),
                 ("Author", mod_.meta.author.as_str()),
+                ("License", mod_.meta.license.as_deref().unwrap_or_default()),
+                ("Signed By", signer.as_str()),
+                (
+                    "Game Version",
+                    mod_.meta.game_version.as_deref().unwrap_or_default(),
+                ),
+                (
+                    "Requires DLC",
+                    if mod_.meta.requires_dlc { "Yes" } else { "" },
+                ),
             ]
             .into_iter()
             .filter(|(_, v)| !v.is_empty())
@@ -90,6 +115,22 @@ impl Component for ModInfo<'_> {
                     })
                 });
             });
+            [
+                ("Home Page", mod_.meta.homepage.as_ref()),
+                ("Download", mod_.meta.url.as_ref()),
+                ("Author Page", mod_.meta.author_url.as_ref()),
+            ]
+            .into_iter()
+            .filter_map(|(label, url)| url.map(|url| (label, url)))
+            .for_each(|(label, url)| {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(label).family(egui::FontFamily::Name("Bold".into())));
+                    ui.add_space(8.);
+                    ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                        ui.hyperlink(url.as_str());
+                    })
+                });
+            });
             ui.label(RichText::new("Description").family(egui::FontFamily::Name("Bold".into())));
             ui.add_space(4.);
             let md_cache = ui
@@ -101,10 +142,34 @@ impl Component for ModInfo<'_> {
             egui_commonmark::CommonMarkViewer::new("mod_description").show(
                 ui,
                 &mut md_cache.lock(),
-                &mod_.meta.description,
+                mod_.meta.display_description(self.1),
             );
             ui.add_space(4.);
-            if !mod_.meta.options.is_empty() {
+            if !mod_.meta.long_description.is_empty() {
+                egui::CollapsingHeader::new("Details")
+                    .id_source(egui::Id::new("mod_long_description").with(mod_.hash()))
+                    .show(ui, |ui| {
+                        egui_commonmark::CommonMarkViewer::new("mod_long_description").show(
+                            ui,
+                            &mut md_cache.lock(),
+                            &mod_.meta.long_description,
+                        );
+                    });
+                ui.add_space(4.);
+            }
+            if !mod_.meta.changelog.is_empty() {
+                egui::CollapsingHeader::new("Changelog")
+                    .id_source(egui::Id::new("mod_changelog").with(mod_.hash()))
+                    .show(ui, |ui| {
+                        egui_commonmark::CommonMarkViewer::new("mod_changelog").show(
+                            ui,
+                            &mut md_cache.lock(),
+                            &changelog_markdown(&mod_.meta.changelog),
+                        );
+                    });
+                ui.add_space(4.);
+            }
+            if !mod_.meta.options.is_empty() || !mod_.meta.selectable.is_empty() {
                 ui.horizontal(|ui| {
                     ui.label(
                         RichText::new("Enabled Options")
@@ -124,9 +189,67 @@ impl Component for ModInfo<'_> {
                             ui.checkbox(&mut true, opt.name.as_str());
                         });
                     });
-                } else {
+                } else if !mod_.meta.options.is_empty() {
                     ui.label("No options enabled");
                 }
+                let excluded =
+                    mod_.excluded_files.content_files.len() + mod_.excluded_files.aoc_files.len();
+                if excluded > 0 {
+                    ui.label(format!("{excluded} selectable files left out"));
+                }
+                ui.add_space(4.0);
+            }
+            if !mod_.meta.emulator_flags.is_empty() {
+                ui.label(
+                    RichText::new("Required Emulator Settings")
+                        .family(egui::FontFamily::Name("Bold".into())),
+                );
+                mod_.meta.emulator_flags.iter().for_each(|flag| {
+                    ui.add(Label::new(flag.to_string()).wrap(true));
+                });
+                ui.add_space(4.0);
+            }
+            if !mod_.meta.dependencies.is_empty() {
+                ui.label(
+                    RichText::new("Required Mods").family(egui::FontFamily::Name("Bold".into())),
+                );
+                mod_.meta.dependencies.iter().for_each(|dep| {
+                    ui.add(Label::new(dep.to_string()).wrap(true));
+                });
+                ui.add_space(4.0);
+            }
+            if !mod_.meta.credits.is_empty() {
+                ui.label(RichText::new("Credits").family(egui::FontFamily::Name("Bold".into())));
+                mod_.meta.credits.iter().for_each(|credit| {
+                    ui.horizontal_wrapped(|ui| {
+                        match credit.url.as_ref() {
+                            Some(url) => ui.hyperlink_to(credit.name.as_str(), url.as_str()),
+                            None => ui.label(credit.name.as_str()),
+                        };
+                        if !credit.role.is_empty() {
+                            ui.label(format!("– {}", credit.role));
+                        }
+                    });
+                });
+                ui.add_space(4.0);
+            }
+            if !mod_.meta.permissions.is_unset() {
+                ui.label(
+                    RichText::new("Permissions").family(egui::FontFamily::Name("Bold".into())),
+                );
+                mod_.meta.permissions.iter().for_each(|(label, allowed)| {
+                    ui.horizontal(|ui| {
+                        ui.label(label);
+                        ui.add_space(8.);
+                        ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                            ui.label(match allowed {
+                                Some(true) => "Allowed",
+                                Some(false) => "Not allowed",
+                                None => "Ask the author",
+                            });
+                        })
+                    });
+                });
                 ui.add_space(4.0);
             }
             ui.label(RichText::new("Manifest").family(egui::FontFamily::Name("Bold".into())));
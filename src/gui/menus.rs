@@ -29,10 +29,110 @@ impl App {
             ui.close_menu();
             self.do_update(Message::Remerge);
         }
+        if ui.button("Preview Deploy").clicked() {
+            ui.close_menu();
+            self.do_update(Message::PreviewDeploy);
+        }
+        if ui.button("Verify Deploy").clicked() {
+            ui.close_menu();
+            self.do_update(Message::VerifyDeploy);
+        }
+        if ui
+            .button("Restore Previous Deployment")
+            .on_hover_text(
+                "Undo the last deployment to the first deploy target, putting back the files it \
+                 replaced or removed without merging again",
+            )
+            .clicked()
+        {
+            ui.close_menu();
+            self.do_update(Message::RollbackDeploy);
+        }
+        if ui
+            .button("Restore Previous Merge")
+            .on_hover_text(
+                "Swap the merged files with the backup from before the last merge, without \
+                 merging again. Needs Back Up Merged Files turned on in the settings.",
+            )
+            .clicked()
+        {
+            ui.close_menu();
+            self.do_update(Message::RestoreMerged);
+        }
+        ui.menu_button("Watch Mode", |ui| {
+            let current = self.watcher.as_ref().map(|w| w.mode());
+            for (mode, label) in [
+                (None, " Off"),
+                (Some(WatchMode::Merged), " Merged Folder"),
+                (Some(WatchMode::ModSources), " Mod Sources"),
+            ] {
+                if ui
+                    .icon_text_button(
+                        label,
+                        if current == mode {
+                            Icon::Check
+                        } else {
+                            Icon::Blank
+                        },
+                    )
+                    .on_hover_text(match mode {
+                        None => "Do not redeploy automatically",
+                        Some(WatchMode::Merged) => {
+                            "Deploy automatically when files in the merged folder change"
+                        }
+                        Some(WatchMode::ModSources) => {
+                            "Remerge and deploy automatically when files in unpacked mods change"
+                        }
+                    })
+                    .clicked()
+                {
+                    ui.close_menu();
+                    self.do_update(Message::ToggleWatch(mode));
+                }
+            }
+        });
         if ui.button("Reset Pending").clicked() {
             ui.close_menu();
             self.do_update(Message::ResetPending);
         }
+        if ui
+            .button("Verify Mods")
+            .on_hover_text(
+                "Check installed mod packages against the files they were packaged with and the \
+                 keys they were signed with",
+            )
+            .clicked()
+        {
+            ui.close_menu();
+            self.do_update(Message::VerifyMods);
+        }
+        if ui.button("Clean Up Storage").clicked() {
+            ui.close_menu();
+            self.do_update(Message::FindOrphans);
+        }
+        if ui
+            .button("Check for Mod Updates")
+            .on_hover_text("Check the update feeds of installed mods for newer versions")
+            .clicked()
+        {
+            ui.close_menu();
+            self.do_update(Message::CheckModUpdates);
+        }
+        if ui.button("Update Compatibility Rules").clicked() {
+            ui.close_menu();
+            self.do_update(Message::UpdateCompat);
+        }
+        if ui
+            .button("Run Benchmark")
+            .on_hover_text(
+                "Measure how fast this machine parses, merges, compresses, and deploys a set of \
+                 game files, and save a report to share when asking for help",
+            )
+            .clicked()
+        {
+            ui.close_menu();
+            self.do_update(Message::Benchmark);
+        }
     }
 
     pub fn window_menu(&mut self, ui: &mut Ui) {
@@ -82,12 +182,24 @@ impl App {
         } else {
             ui.button("Verbose Logging")
         };
+        let copy_button = ui
+            .button("Copy Recent Log")
+            .on_hover_text("Copy the last 500 log lines, to paste into a bug report");
         ui.separator();
         if verbose_button.clicked() {
             ui.close_menu();
             crate::logger::LOGGER.set_debug(!verbose);
             log::debug!("Verbose logging enabled"); // Think about it for a second
         }
+        if copy_button.clicked() {
+            ui.close_menu();
+            ui.output().copied_text = crate::logger::LOGGER
+                .recent(500)
+                .iter()
+                .map(|entry| entry.to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
         if ui.button("Help").clicked() {
             ui.close_menu();
             open::that("https://nicenenerd.github.io/ukmm").unwrap_or(());
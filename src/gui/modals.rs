@@ -1,14 +1,40 @@
-use uk_manager::settings::Platform;
-use uk_mod::{Meta, CATEGORIES};
+use uk_manager::{
+    deploy::{DeployProgress, DeployStatus},
+    settings::Platform,
+};
+use uk_mod::{unpack::MERGE_THROTTLE, Meta, CATEGORIES};
 use uk_ui::editor::EditableValue;
 
 use super::*;
 
+/// Pulls a completion fraction out of a progress message ending in either
+/// a percentage (`…: 42%`) or a count (`… (3/16)`).
+fn progress_fraction(status: &str) -> Option<f32> {
+    let status = status.trim_end();
+    if let Some(percent) = status.strip_suffix('%') {
+        let start = percent
+            .rfind(|c: char| !c.is_ascii_digit())
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        percent[start..]
+            .parse::<f32>()
+            .ok()
+            .map(|p| (p / 100.).clamp(0., 1.))
+    } else if let Some(count) = status.strip_suffix(')') {
+        let (done, total) = count.rsplit_once('(')?.1.split_once('/')?;
+        let (done, total) = (done.parse::<f32>().ok()?, total.parse::<f32>().ok()?);
+        (total > 0.).then(|| (done / total).clamp(0., 1.))
+    } else {
+        None
+    }
+}
+
 #[derive(Debug)]
 pub struct MetaInputModal {
-    meta:   Option<Meta>,
-    path:   Option<PathBuf>,
-    sender: Sender<Message>,
+    meta:    Option<Meta>,
+    path:    Option<PathBuf>,
+    editing: Option<Mod>,
+    sender:  Sender<Message>,
 }
 
 impl MetaInputModal {
@@ -16,6 +42,7 @@ impl MetaInputModal {
         Self {
             meta: None,
             path: None,
+            editing: None,
             sender,
         }
     }
@@ -23,6 +50,7 @@ impl MetaInputModal {
     pub fn clear(&mut self) {
         self.meta = None;
         self.path = None;
+        self.editing = None;
         self.sender.send(Message::Noop).expect("Broken channel");
     }
 
@@ -31,20 +59,15 @@ impl MetaInputModal {
     }
 
     pub fn open(&mut self, path: PathBuf, platform: Platform) {
-        self.meta = Some(Meta {
-            name: Default::default(),
-            description: Default::default(),
-            category: "Other".into(),
-            author: Default::default(),
-            masters: Default::default(),
-            options: Default::default(),
-            platform: uk_mod::ModPlatform::Specific(platform.into()),
-            url: Default::default(),
-            version: "1.0.0".into(),
-        });
+        self.meta = Some(uk_manager::mods::loose_mod_meta(&path, platform.into()));
         self.path = Some(path);
     }
 
+    pub fn open_edit(&mut self, mod_: Mod) {
+        self.meta = Some(mod_.meta.clone());
+        self.editing = Some(mod_);
+    }
+
     pub fn is_open(&self) -> bool {
         self.meta.is_some()
     }
@@ -52,18 +75,31 @@ impl MetaInputModal {
     pub fn ui(&mut self, ctx: &egui::Context) {
         let mut should_clear = false;
         if let Some(meta) = self.meta.as_mut() {
-            egui::Window::new("Provide Mod Information")
+            let editing = self.editing.is_some();
+            let title = if editing {
+                "Edit Mod Information"
+            } else {
+                "Provide Mod Information"
+            };
+            egui::Window::new(title)
                 .collapsible(false)
                 .anchor(Align2::CENTER_CENTER, Vec2::default())
                 .frame(Frame::window(&ctx.style()).inner_margin(8.))
                 .show(ctx, |ui| {
                     ui.spacing_mut().item_spacing.y = 8.0;
-                    ui.label(
-                        "The mod you selected does not include any metadata. Please provide the \
-                         basics below:",
-                    );
+                    ui.label(if editing {
+                        "Changes are saved to the installed copy of the mod. If it was signed, \
+                         it will no longer be."
+                    } else {
+                        "The mod you selected does not include any metadata, so some has been \
+                         made up from its file name. Change it below if you like:"
+                    });
                     ui.label("Name");
                     meta.name.edit_ui_with_id(ui, "mod-meta-name");
+                    if editing {
+                        ui.label("Version");
+                        meta.version.edit_ui_with_id(ui, "mod-meta-version");
+                    }
                     egui::ComboBox::new("mod-meta-cat", "Category")
                         .selected_text(meta.category.as_str())
                         .show_ui(ui, |ui| {
@@ -91,13 +127,20 @@ impl MetaInputModal {
                             Layout::right_to_left(Align::Center),
                             |ui| {
                                 if ui.button("OK").clicked() {
-                                    self.sender
-                                        .send(Message::OpenMod(
-                                            self.path
-                                                .take()
-                                                .expect("There should be a mod path here"),
-                                        ))
-                                        .expect("Broken channel");
+                                    if let Some(mod_) = self.editing.take() {
+                                        self.sender
+                                            .send(Message::SaveMeta(mod_, meta.clone()))
+                                            .expect("Broken channel");
+                                        should_clear = true;
+                                    } else {
+                                        self.sender
+                                            .send(Message::OpenMod(
+                                                self.path
+                                                    .take()
+                                                    .expect("There should be a mod path here"),
+                                            ))
+                                            .expect("Broken channel");
+                                    }
                                 }
                                 if ui.button("Close").clicked() {
                                     should_clear = true;
@@ -198,6 +241,102 @@ impl App {
         }
     }
 
+    pub fn render_flag_conflicts(&mut self, ctx: &egui::Context) {
+        let Some(conflicts) = self.flag_conflicts.as_mut() else {
+            return;
+        };
+        let mut save = false;
+        let mut close = false;
+        egui::Window::new("Flag Conflicts")
+            .collapsible(false)
+            .anchor(Align2::CENTER_CENTER, Vec2::default())
+            .auto_sized()
+            .frame(Frame::window(&ctx.style()).inner_margin(8.))
+            .show(ctx, |ui| {
+                ui.add_space(8.);
+                ui.label(
+                    "Some mods define the same game data flags with a different type or hash, \
+                     which can corrupt saves. By default the mod lowest in the load order \
+                     wins. Choose how to handle each one:",
+                );
+                ui.add_space(8.);
+                egui::ScrollArea::vertical()
+                    .max_height(ctx.available_rect().height() * 0.5)
+                    .show(ui, |ui| {
+                        egui::Grid::new("flag_conflicts")
+                            .striped(true)
+                            .num_columns(3)
+                            .show(ui, |ui| {
+                                for (conflict, choice) in conflicts.iter_mut() {
+                                    let ModFlagConflict { mod_name, conflict } = conflict;
+                                    ui.label(mod_name.as_str());
+                                    ui.label(conflict.name.as_str()).on_hover_text(format!(
+                                        "Already defined as {} (hash {}), this mod uses {} \
+                                         (hash {})",
+                                        conflict.existing.0,
+                                        conflict.existing.1,
+                                        conflict.incoming.0,
+                                        conflict.incoming.1
+                                    ));
+                                    ui.horizontal(|ui| {
+                                        if ui.radio(choice.is_none(), "Use this mod's").clicked() {
+                                            *choice = None;
+                                        }
+                                        if ui
+                                            .radio(
+                                                matches!(choice, Some(FlagResolution::Skip)),
+                                                "Skip",
+                                            )
+                                            .clicked()
+                                        {
+                                            *choice = Some(FlagResolution::Skip);
+                                        }
+                                        let remapping =
+                                            matches!(choice, Some(FlagResolution::Remap(_)));
+                                        if ui.radio(remapping, "Rename").clicked() && !remapping {
+                                            *choice = Some(FlagResolution::Remap(
+                                                jstr!("{&conflict.name}_Remap").into(),
+                                            ));
+                                        }
+                                        if let Some(FlagResolution::Remap(new_name)) = choice {
+                                            let mut text = new_name.to_string();
+                                            if ui.text_edit_singleline(&mut text).changed() {
+                                                *new_name = text.into();
+                                            }
+                                        }
+                                    });
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                ui.add_space(8.);
+                let width = ui.min_size().x;
+                ui.horizontal(|ui| {
+                    ui.allocate_ui_with_layout(
+                        Vec2::new(width, ui.min_size().y),
+                        Layout::right_to_left(Align::Center),
+                        |ui| {
+                            save = ui.button("Save and Remerge").clicked();
+                            close = ui.button("Close").clicked();
+                            ui.shrink_width_to_current();
+                        },
+                    );
+                });
+            });
+        if save {
+            let choices = self
+                .flag_conflicts
+                .take()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(c, choice)| (c.mod_name.to_string(), c.conflict.name.to_string(), choice))
+                .collect();
+            self.do_update(Message::ResolveFlagConflicts(choices));
+        } else if close {
+            self.do_update(Message::CloseFlagConflicts);
+        }
+    }
+
     pub fn render_new_profile(&mut self, ctx: &egui::Context) {
         let is_open = self.new_profile.is_some();
         if is_open {
@@ -231,7 +370,87 @@ impl App {
         }
     }
 
+    fn render_usage(&self, ui: &mut Ui) {
+        use sysinfo::{ProcessExt, SystemExt};
+        let pid = sysinfo::get_current_pid().ok();
+        let mut sys = self.sys.borrow_mut();
+        let (system, last_refresh) = &mut *sys;
+        if last_refresh.elapsed() >= Duration::from_secs(1) {
+            if let Some(pid) = pid {
+                system.refresh_process(pid);
+            }
+            *last_refresh = Instant::now();
+        }
+        ui.ctx().request_repaint_after(Duration::from_secs(1));
+        if let Some(process) = pid.and_then(|pid| system.process(pid)) {
+            ui.small(format!(
+                "CPU {:.0}% · RAM {:.0} MB",
+                process.cpu_usage(),
+                process.memory() as f64 / 1024. / 1024.
+            ));
+        }
+        ui.horizontal(|ui| {
+            let paused = MERGE_THROTTLE.is_paused();
+            if ui
+                .small_button(if paused { "Resume" } else { "Pause" })
+                .on_hover_text("Pause or resume merging")
+                .clicked()
+            {
+                MERGE_THROTTLE.set_paused(!paused);
+            }
+            let max = rayon::current_num_threads();
+            let mut jobs = match MERGE_THROTTLE.max_jobs() {
+                0 => max,
+                jobs => jobs.min(max),
+            };
+            if ui
+                .add(egui::Slider::new(&mut jobs, 1..=max).text("Threads"))
+                .on_hover_text("Limit how many files are merged at once")
+                .changed()
+            {
+                MERGE_THROTTLE.set_max_jobs(if jobs == max { 0 } else { jobs });
+            }
+        });
+    }
+
+    fn render_deploy_progress(
+        &self,
+        ui: &mut Ui,
+        progress: &DeployProgress,
+        status: &DeployStatus,
+        max_width: f32,
+    ) {
+        ui.add(
+            egui::ProgressBar::new(status.fraction())
+                .desired_width(max_width)
+                .text(format!(
+                    "{}/{} files · {:.1}/{:.1} MB",
+                    status.files_done,
+                    status.files_total,
+                    status.bytes_done as f64 / 1024. / 1024.,
+                    status.bytes_total as f64 / 1024. / 1024.
+                )),
+        );
+        ui.small(status.current.as_str());
+        ui.ctx().request_repaint_after(Duration::from_millis(250));
+        if progress.is_cancelled() {
+            ui.small("Cancelling…");
+        } else if ui
+            .small_button("Cancel")
+            .on_hover_text(
+                "Stop deploying after the files being written now. The rest stay pending for \
+                 the next deployment.",
+            )
+            .clicked()
+        {
+            progress.cancel();
+        }
+    }
+
     pub fn render_busy(&self, ctx: &egui::Context, frame: &eframe::Frame) {
+        if !self.busy.get() && MERGE_THROTTLE.is_paused() {
+            MERGE_THROTTLE.set_paused(false);
+        }
         if self.busy.get() {
             egui::Window::new("Working")
                 .default_size([240., 80.])
@@ -251,16 +470,15 @@ impl App {
                             ui.add_space(8.);
                             ui.vertical(|ui| {
                                 ui.label("Processing…");
+                                let status = self
+                                    .logs
+                                    .iter()
+                                    .rev()
+                                    .find(|l| l.level == "INFO" || l.args.starts_with("PROGRESS"))
+                                    .map(|l| l.args.as_str().trim_start_matches("PROGRESS"))
+                                    .unwrap_or_default();
                                 let mut job = LayoutJob::single_section(
-                                    self.logs
-                                        .iter()
-                                        .rev()
-                                        .find(|l| {
-                                            l.level == "INFO" || l.args.starts_with("PROGRESS")
-                                        })
-                                        .map(|l| l.args.as_str().trim_start_matches("PROGRESS"))
-                                        .unwrap_or_default()
-                                        .to_owned(),
+                                    status.to_owned(),
                                     TextFormat::default(),
                                 );
                                 job.wrap = TextWrapping {
@@ -270,6 +488,24 @@ impl App {
                                     ..Default::default()
                                 };
                                 ui.add(Label::new(job).wrap(false));
+                                let deploy = self.core.deploy_manager().progress();
+                                let deploy_status = deploy.status();
+                                if deploy_status.active {
+                                    self.render_deploy_progress(
+                                        ui,
+                                        &deploy,
+                                        &deploy_status,
+                                        max_width,
+                                    );
+                                } else {
+                                    if let Some(fraction) = progress_fraction(status) {
+                                        ui.add(
+                                            egui::ProgressBar::new(fraction)
+                                                .desired_width(max_width),
+                                        );
+                                    }
+                                    self.render_usage(ui);
+                                }
                             });
                             ui.shrink_width_to_current();
                         });
@@ -418,6 +654,43 @@ impl App {
         }
     }
 
+    pub fn render_mod_changes(&self, ctx: &egui::Context) {
+        if let Some((title, changes)) = self.mod_changes.as_ref() {
+            egui::Window::new(title.as_str())
+                .collapsible(false)
+                .scroll2([false, true])
+                .anchor(Align2::CENTER_CENTER, Vec2::default())
+                .frame(Frame::window(&ctx.style()).inner_margin(8.))
+                .show(ctx, |ui| {
+                    let md_cache = ui
+                        .data()
+                        .get_temp_mut_or_default::<Arc<Mutex<egui_commonmark::CommonMarkCache>>>(
+                            egui::Id::new("md_cache_mod_changes"),
+                        )
+                        .clone();
+                    egui_commonmark::CommonMarkViewer::new("mod_changes").show(
+                        ui,
+                        &mut md_cache.lock(),
+                        changes,
+                    );
+                    ui.separator();
+                    let width = ui.min_size().x;
+                    ui.horizontal(|ui| {
+                        ui.allocate_ui_with_layout(
+                            Vec2::new(width, ui.min_size().y),
+                            Layout::right_to_left(Align::Center),
+                            |ui| {
+                                if ui.button("OK").clicked() {
+                                    self.do_update(Message::CloseModChanges);
+                                }
+                                ui.shrink_width_to_current();
+                            },
+                        );
+                    });
+                });
+        }
+    }
+
     pub fn render_changelog(&self, ctx: &egui::Context) {
         if let Some(ref last_version) = self.changelog {
             egui::Window::new("What's New")
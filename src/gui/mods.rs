@@ -5,17 +5,19 @@ use uk_manager::mods::Mod;
 use uk_ui::{
     egui::{
         self, style::Margin, text::LayoutJob, Align, Button, Color32, CursorIcon, Id, Key, LayerId,
-        Layout, Response, Sense, TextStyle, Ui, Vec2,
+        Layout, Response, RichText, Sense, TextStyle, Ui, Vec2,
     },
     egui_extras::{Column, TableBuilder, TableRow},
     ext::UiExt,
 };
 
-use super::{App, FocusedPane, Message, Sort};
+use super::{visuals, App, FocusedPane, Message, Sort};
 
 enum ContextMenuMessage {
     Uninstall,
+    EditMeta,
     Toggle(bool),
+    Loose(bool),
     Move(usize),
 }
 
@@ -276,6 +278,7 @@ impl App {
     }
 
     fn render_mod_row(&mut self, index: usize, mut row: TableRow) {
+        let lang = self.language();
         let mod_ = unsafe { self.displayed_mods.get_mut(index).unwrap_unchecked() };
         if let Some(index) = self.mods.iter().position(|m| m == mod_) {
             let selected = self.selected.contains(mod_);
@@ -309,9 +312,17 @@ impl App {
                 })
                 .1,
             );
+            let update = self.mod_updates.iter().find(|u| u.hash == mod_.hash());
             process_col_res(
                 row.col(|ui| {
-                    ui.clipped_label(mod_.meta.name.as_str());
+                    if let Some(update) = update {
+                        ui.label(RichText::new("⏶").color(visuals::GREEN))
+                            .on_hover_text(format!(
+                                "Version {} is available",
+                                update.release.version
+                            ));
+                    }
+                    ui.clipped_label(mod_.meta.display_name(lang));
                 })
                 .1,
             );
@@ -338,9 +349,15 @@ impl App {
                             prompt,
                         ));
                     }
+                    ContextMenuMessage::EditMeta => {
+                        self.do_update(Message::EditMeta(menu_mod.clone()));
+                    }
                     ContextMenuMessage::Toggle(state) => {
                         self.do_update(Message::ToggleMods(None, state));
                     }
+                    ContextMenuMessage::Loose(loose) => {
+                        self.do_update(Message::SetLoose(None, loose));
+                    }
                     ContextMenuMessage::Move(dest) => {
                         self.do_update(Message::MoveSelected(dest));
                     }
@@ -380,6 +397,26 @@ impl App {
             ui.close_menu();
             result = Some(ContextMenuMessage::Toggle(!mod_.enabled));
         }
+        if ui
+            .button(if mod_.loose {
+                "Merge normally"
+            } else {
+                "Deploy loose"
+            })
+            .on_hover_text(
+                "Loose mods are not merged. Their files are deployed as they are on top of the \
+                 merged files, so they can be turned on and off without remerging. Best for \
+                 texture packs.",
+            )
+            .clicked()
+        {
+            ui.close_menu();
+            result = Some(ContextMenuMessage::Loose(!mod_.loose));
+        }
+        if ui.button("Edit info").clicked() {
+            ui.close_menu();
+            result = Some(ContextMenuMessage::EditMeta);
+        }
         if ui.button("View folder").clicked() {
             ui.close_menu();
             let _ = Command::new(if cfg!(windows) {
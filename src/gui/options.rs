@@ -1,4 +1,7 @@
+use std::collections::BTreeSet;
+
 use eframe::egui::Button;
+use smartstring::alias::String;
 use uk_mod::ModOptionGroup;
 use uk_ui::{
     egui::{self, Align, Checkbox, Context, Layout, Vec2},
@@ -91,9 +94,40 @@ impl App {
                         done = false;
                     }
                 });
+                if !mod_.meta.selectable.is_empty() {
+                    egui::CollapsingHeader::new("Files")
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            ui.label("Uncheck any files you don't want to install.");
+                            match mod_.selectable_files() {
+                                Ok(files) => {
+                                    render_file_selection(
+                                        ui,
+                                        &files.content_files,
+                                        &mut mod_.excluded_files.content_files,
+                                        "",
+                                    );
+                                    render_file_selection(
+                                        ui,
+                                        &files.aoc_files,
+                                        &mut mod_.excluded_files.aoc_files,
+                                        " (DLC)",
+                                    );
+                                }
+                                Err(e) => {
+                                    ui.colored_label(visuals::RED, e.to_string());
+                                }
+                            }
+                        });
+                }
                 if !done {
                     ui.colored_label(visuals::RED, "You must set all required option groups");
                 }
+                let violations = mod_.meta.option_rule_violations(&mod_.enabled_options);
+                for violation in violations.iter() {
+                    ui.colored_label(visuals::RED, violation);
+                }
+                done &= violations.is_empty();
                 ui.horizontal(|ui| {
                     ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
                         if ui.add_enabled(done, Button::new("OK")).clicked() {
@@ -112,3 +146,24 @@ impl App {
             });
     }
 }
+
+fn render_file_selection(
+    ui: &mut egui::Ui,
+    files: &BTreeSet<String>,
+    excluded: &mut BTreeSet<String>,
+    suffix: &str,
+) {
+    for file in files {
+        let mut checked = !excluded.contains(file);
+        if ui
+            .checkbox(&mut checked, format!("{file}{suffix}"))
+            .changed()
+        {
+            if checked {
+                excluded.remove(file);
+            } else {
+                excluded.insert(file.clone());
+            }
+        }
+    }
+}
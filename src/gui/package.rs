@@ -3,10 +3,12 @@ use std::{ops::DerefMut, path::PathBuf};
 use eframe::emath::Align;
 use parking_lot::Mutex;
 use rustc_hash::FxHashSet;
+use smartstring::alias::String as SmartString;
 use uk_manager::settings::Platform;
 use uk_mod::{
-    ExclusiveOptionGroup, Meta, ModOption, ModOptionGroup, ModPlatform, MultipleOptionGroup,
-    OptionGroup, CATEGORIES,
+    pack::{Compression, CompressionConfig, FileCategory},
+    Dependency, ExclusiveOptionGroup, Meta, ModOption, ModOptionGroup, ModPlatform,
+    MultipleOptionGroup, OptionGroup, CATEGORIES,
 };
 use uk_ui::{
     editor::EditableValue,
@@ -22,19 +24,61 @@ fn render_field(name: &str, ui: &mut Ui, add_contents: impl FnOnce(&mut Ui) -> R
     ui.horizontal(add_contents);
     ui.add_space(4.0);
 }
+
+fn render_url_field(name: &str, id: Id, value: &mut Option<SmartString>, ui: &mut Ui) {
+    render_field(name, ui, |ui| {
+        let url = ui
+            .get_temp_string(id.with("tmp"))
+            .get_or_insert_with(|| {
+                ui.create_temp_string(id.with("tmp"), value.as_ref().map(|u| u.as_str().into()))
+            })
+            .clone();
+        let res = {
+            let mut url = url.write();
+            url.edit_ui_with_id(ui, id)
+        };
+        if res.changed() {
+            let url = url.read();
+            *value = if url.is_empty() {
+                None
+            } else {
+                Some(url.as_str().into())
+            };
+        }
+        res
+    });
+}
+
 #[derive(Debug, Clone)]
 pub struct ModPackerBuilder {
-    pub source: PathBuf,
-    pub dest:   PathBuf,
-    pub meta:   Meta,
+    pub source:      PathBuf,
+    pub dest:        PathBuf,
+    pub meta:        Meta,
+    /// A package of an earlier version to also make an update from
+    pub delta_from:  Option<PathBuf>,
+    pub compression: CompressionConfig,
+    /// Split the package into parts of at most this many megabytes
+    pub split_mb:    Option<u64>,
+    /// An ed25519 key in PKCS#8 format to sign the package with
+    pub signing_key: Option<PathBuf>,
+    /// Glob patterns for more files to leave out of the package
+    pub exclude:     Vec<std::string::String>,
+    /// What changed in this version, for the changelog
+    pub changes:     std::string::String,
 }
 
 impl ModPackerBuilder {
     pub fn new(platform: Platform) -> Self {
         ModPackerBuilder {
-            source: Default::default(),
-            dest:   Default::default(),
-            meta:   Meta {
+            source:      Default::default(),
+            dest:        Default::default(),
+            delta_from:  None,
+            compression: Default::default(),
+            split_mb:    None,
+            signing_key: None,
+            exclude:     vec![],
+            changes:     Default::default(),
+            meta:        Meta {
                 name: Default::default(),
                 version: "1.0.0".into(),
                 author: Default::default(),
@@ -44,6 +88,22 @@ impl ModPackerBuilder {
                 url: Default::default(),
                 options: Default::default(),
                 masters: Default::default(),
+                emulator_flags: Default::default(),
+                dependencies: Default::default(),
+                thumbnail: None,
+                author_url: None,
+                homepage: None,
+                long_description: Default::default(),
+                changelog: Default::default(),
+                game_version: None,
+                requires_dlc: false,
+                selectable: Default::default(),
+                update_url: None,
+                translations: Default::default(),
+                license: None,
+                credits: Default::default(),
+                permissions: Default::default(),
+                delta: None,
             },
         }
     }
@@ -84,8 +144,23 @@ impl ModPackerBuilder {
                                             mod_.hash(),
                                             (mod_.meta.name.clone(), mod_.meta.version.clone()),
                                         );
+                                        if !self
+                                            .meta
+                                            .dependencies
+                                            .iter()
+                                            .any(|dep| dep.name == mod_.meta.name)
+                                        {
+                                            self.meta.dependencies.push(Dependency {
+                                                name:    mod_.meta.name.clone(),
+                                                version: format!(">={}", mod_.meta.version)
+                                                    .into(),
+                                            });
+                                        }
                                     } else {
                                         self.meta.masters.shift_remove(&mod_.hash());
+                                        self.meta
+                                            .dependencies
+                                            .retain(|dep| dep.name != mod_.meta.name);
                                     }
                                 }
                             }
@@ -227,6 +302,7 @@ impl ModPackerBuilder {
                                     description: Default::default(),
                                     path: Default::default(),
                                     requires: vec![],
+                                    excludes: vec![],
                                 });
                             }
                         });
@@ -359,6 +435,74 @@ impl ModPackerBuilder {
                 }
                 res
             });
+            ui.horizontal(|ui| {
+                let mut split = self.split_mb.is_some();
+                if ui
+                    .checkbox(&mut split, " Split into parts of")
+                    .on_hover_text(
+                        "For hosts with a file size limit. Users open the first part, ending in \
+                         .001, to install the mod.",
+                    )
+                    .changed()
+                {
+                    self.split_mb = split.then_some(1024);
+                }
+                if let Some(size) = self.split_mb.as_mut() {
+                    ui.add(egui::DragValue::new(size).clamp_range(1..=u32::MAX as u64));
+                    ui.label("MB");
+                }
+            });
+            ui.add_space(4.0);
+            render_field("Update From", ui, |ui| {
+                let mut path = self.delta_from.clone().unwrap_or_default();
+                let res = ui.file_picker(&mut path).on_hover_text(
+                    "Optional package of an earlier version. An update package with only the \
+                     files changed since then is made next to the full one.",
+                );
+                if res.changed() {
+                    self.delta_from = (!path.as_os_str().is_empty()).then_some(path);
+                }
+                res
+            });
+            render_field("Signing Key", ui, |ui| {
+                let mut path = self.signing_key.clone().unwrap_or_default();
+                let res = ui.file_picker(&mut path).on_hover_text(
+                    "Optional key to sign the package with, so users can tell it is yours and \
+                     unchanged. Keep it private and use the same one for every version.",
+                );
+                if res.changed() {
+                    self.signing_key = (!path.as_os_str().is_empty()).then_some(path);
+                }
+                if ui
+                    .button("New Key")
+                    .on_hover_text("Generate a new signing key and save it")
+                    .clicked()
+                    && let Some(path) = rfd::FileDialog::new()
+                        .set_file_name("signing-key.pk8")
+                        .save_file()
+                {
+                    match uk_mod::integrity::SigningKey::generate(&path) {
+                        Ok(_) => self.signing_key = Some(path),
+                        Err(e) => log::error!("Failed to create signing key: {e:?}"),
+                    }
+                }
+                res
+            });
+            render_field("Exclude Files", ui, |ui| {
+                let mut patterns = self.exclude.join("\n");
+                let res = ui
+                    .add(egui::TextEdit::multiline(&mut patterns).desired_rows(2))
+                    .on_hover_text(
+                        "Files to leave out of the package, one glob pattern per line, matched \
+                         against paths from the source folder, e.g. content/Model/*.blend. \
+                         Image editor files, Thumbs.db, and .git folders are always left out, as \
+                         is anything listed in a .ukmmignore file in the source folder.",
+                    );
+                if res.changed() {
+                    self.exclude = patterns.split('\n').map(|line| line.into()).collect();
+                }
+                res
+            });
             let mut cross = matches!(self.meta.platform, ModPlatform::Universal);
             if ui
                 .checkbox(&mut cross, " Mark as cross-platform")
@@ -371,6 +515,22 @@ impl ModPackerBuilder {
                     self.meta.platform = ModPlatform::Specific(app.platform().into());
                 }
             }
+            let mut dual = matches!(self.meta.platform, ModPlatform::Dual);
+            if ui
+                .checkbox(&mut dual, " Include Wii U and Switch files")
+                .on_hover_text(
+                    "For mods with both a content/aoc folder and a Switch romfs folder. Each is \
+                     packed against its own platform's dump, so both must be set up. Users get \
+                     the files for their platform from the one package.",
+                )
+                .changed()
+            {
+                if dual {
+                    self.meta.platform = ModPlatform::Dual;
+                } else {
+                    self.meta.platform = ModPlatform::Specific(app.platform().into());
+                }
+            }
             render_field("Name", ui, |ui| {
                 self.meta.name.edit_ui_with_id(ui, id.with("Name"))
             });
@@ -404,31 +564,188 @@ impl ModPackerBuilder {
                     })
                     .response
             });
-            render_field("URL", ui, |ui| {
-                let id = id.with("url");
-                let url = ui
-                    .get_temp_string(id.with("tmp"))
-                    .get_or_insert_with(|| {
-                        ui.create_temp_string(
-                            id.with("tmp"),
-                            self.meta.url.as_ref().map(|u| u.as_str().into()),
-                        )
-                    })
-                    .clone();
-                let res = {
-                    let mut url = url.write();
-                    url.edit_ui_with_id(ui, id)
-                };
+            render_url_field("URL", id.with("url"), &mut self.meta.url, ui);
+            render_url_field("Home Page", id.with("homepage"), &mut self.meta.homepage, ui);
+            render_url_field("Author Page", id.with("author_url"), &mut self.meta.author_url, ui);
+            render_url_field(
+                "Update URL",
+                id.with("update_url"),
+                &mut self.meta.update_url,
+                ui,
+            );
+            render_url_field("License", id.with("license"), &mut self.meta.license, ui);
+            render_url_field(
+                "Min. Game Version",
+                id.with("game_version"),
+                &mut self.meta.game_version,
+                ui,
+            );
+            ui.checkbox(&mut self.meta.requires_dlc, " Requires DLC")
+                .on_hover_text(
+                    "Refuse to install or enable the mod if the game dump lacks the DLC",
+                );
+            render_field("Selectable Files", ui, |ui| {
+                let mut patterns = self.meta.selectable.join("\n");
+                let res = ui
+                    .add(egui::TextEdit::multiline(&mut patterns).desired_rows(2))
+                    .on_hover_text(
+                        "Standalone files users can choose not to install, one glob pattern per \
+                         line, matched against canonical paths, e.g. \
+                         Sound/Resource/Stream/*.bfstm. Good for music or texture replacements \
+                         without making an option for each.",
+                    );
                 if res.changed() {
-                    let url = url.read();
-                    self.meta.url = if url.is_empty() {
-                        None
-                    } else {
-                        Some(url.as_str().into())
-                    };
+                    self.meta.selectable = patterns.split('\n').map(|line| line.into()).collect();
                 }
                 res
             });
+            egui::CollapsingHeader::new("Required Emulator Settings")
+                .id_source(id.with("emulator_flags"))
+                .show(ui, |ui| {
+                    let mut delete = None;
+                    for (i, flag) in self.meta.emulator_flags.iter_mut().enumerate() {
+                        let id = id.with("emulator_flags").with(i);
+                        ui.horizontal(|ui| {
+                            if ui.icon_button(Icon::Delete).clicked() {
+                                delete = Some(i);
+                            }
+                            ui.label("Emulator");
+                            flag.emulator.edit_ui_with_id(ui, id.with("emulator"));
+                            ui.label("Setting");
+                            flag.setting.edit_ui_with_id(ui, id.with("setting"));
+                            ui.label("Value");
+                            flag.value.edit_ui_with_id(ui, id.with("value"));
+                        });
+                    }
+                    if let Some(i) = delete {
+                        self.meta.emulator_flags.remove(i);
+                    }
+                    if ui.icon_text_button("Add Setting", Icon::Add).clicked() {
+                        self.meta.emulator_flags.push(Default::default());
+                    }
+                });
+            egui::CollapsingHeader::new("Credits")
+                .id_source(id.with("credits"))
+                .show(ui, |ui| {
+                    let mut delete = None;
+                    for (i, credit) in self.meta.credits.iter_mut().enumerate() {
+                        let id = id.with("credits").with(i);
+                        ui.horizontal(|ui| {
+                            if ui.icon_button(Icon::Delete).clicked() {
+                                delete = Some(i);
+                            }
+                            ui.label("Name");
+                            credit.name.edit_ui_with_id(ui, id.with("name"));
+                            ui.label("Role");
+                            credit.role.edit_ui_with_id(ui, id.with("role"));
+                        });
+                        render_url_field("Page", id.with("url"), &mut credit.url, ui);
+                    }
+                    if let Some(i) = delete {
+                        self.meta.credits.remove(i);
+                    }
+                    if ui.icon_text_button("Add Credit", Icon::Add).clicked() {
+                        self.meta.credits.push(Default::default());
+                    }
+                });
+            egui::CollapsingHeader::new("Permissions")
+                .id_source(id.with("permissions"))
+                .show(ui, |ui| {
+                    let permissions = &mut self.meta.permissions;
+                    for (label, value) in [
+                        ("Redistribution", &mut permissions.redistribute),
+                        ("Modification", &mut permissions.modify),
+                        ("Asset reuse", &mut permissions.reuse_assets),
+                    ] {
+                        let text = |value: Option<bool>| match value {
+                            Some(true) => "Allowed",
+                            Some(false) => "Not allowed",
+                            None => "Ask me",
+                        };
+                        egui::ComboBox::new(id.with("permissions").with(label), label)
+                            .selected_text(text(*value))
+                            .show_ui(ui, |ui| {
+                                for option in [None, Some(true), Some(false)] {
+                                    ui.selectable_value(value, option, text(option));
+                                }
+                            });
+                    }
+                });
+            egui::CollapsingHeader::new("Compression")
+                .id_source(id.with("compression"))
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Level");
+                        ui.add(egui::Slider::new(
+                            &mut self.compression.level,
+                            CompressionConfig::LEVELS,
+                        ))
+                        .on_hover_text(
+                            "Higher levels make smaller packages but take longer to pack",
+                        );
+                    });
+                    for category in FileCategory::iter().filter(|c| **c != FileCategory::Other) {
+                        ui.horizontal(|ui| {
+                            let mut setting = self.compression.categories.get(category).copied();
+                            let text = |setting: Option<Compression>| {
+                                match setting {
+                                    None => "Same as others".to_owned(),
+                                    Some(Compression::Fastest) => "Fastest".to_owned(),
+                                    Some(Compression::Level(level)) => format!("Level {level}"),
+                                }
+                            };
+                            ui.label(category.to_string());
+                            egui::ComboBox::new(id.with("compression").with(category), "")
+                                .selected_text(text(setting))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut setting, None, text(None));
+                                    ui.selectable_value(
+                                        &mut setting,
+                                        Some(Compression::Fastest),
+                                        text(Some(Compression::Fastest)),
+                                    )
+                                    .on_hover_text("Best for files which are already compressed");
+                                    for level in [1, 3, 9, 19] {
+                                        let value = Some(Compression::Level(level));
+                                        ui.selectable_value(&mut setting, value, text(value));
+                                    }
+                                });
+                            match setting {
+                                Some(setting) => {
+                                    self.compression.categories.insert(*category, setting);
+                                }
+                                None => {
+                                    self.compression.categories.remove(category);
+                                }
+                            }
+                        });
+                    }
+                });
+            egui::CollapsingHeader::new("Required Mods")
+                .id_source(id.with("dependencies"))
+                .show(ui, |ui| {
+                    let mut delete = None;
+                    for (i, dep) in self.meta.dependencies.iter_mut().enumerate() {
+                        let id = id.with("dependencies").with(i);
+                        ui.horizontal(|ui| {
+                            if ui.icon_button(Icon::Delete).clicked() {
+                                delete = Some(i);
+                            }
+                            ui.label("Mod");
+                            dep.name.edit_ui_with_id(ui, id.with("name"));
+                            ui.label("Versions");
+                            dep.version
+                                .edit_ui_with_id(ui, id.with("version"))
+                                .on_hover_text("Semver requirement, e.g. >=1.2, <2");
+                        });
+                    }
+                    if let Some(i) = delete {
+                        self.meta.dependencies.remove(i);
+                    }
+                    if ui.icon_text_button("Add Mod", Icon::Add).clicked() {
+                        self.meta.dependencies.push(Default::default());
+                    }
+                });
             ui.add_space(8.0);
             ui.label("Description");
             ui.small("Some Markdown formatting supported");
@@ -445,6 +762,32 @@ impl ModPackerBuilder {
             {
                 self.meta.description = string.read().as_str().into();
             }
+            ui.add_space(8.0);
+            ui.label("Full Description");
+            ui.small("Markdown, shown under Details in the mod info. Uses README.md if empty.");
+            ui.add_space(4.0);
+            let string = ui.create_temp_string(
+                id.with("LongDescription"),
+                Some(self.meta.long_description.as_str().into()),
+            );
+            if egui::TextEdit::multiline(string.write().deref_mut())
+                .desired_width(f32::INFINITY)
+                .show(ui)
+                .response
+                .changed()
+            {
+                self.meta.long_description = string.read().as_str().into();
+            }
+            ui.add_space(8.0);
+            ui.label("Changes in This Version");
+            ui.small(
+                "Markdown, shown to users when they update. Entries for earlier versions are \
+                 kept from the meta file or the package updated from.",
+            );
+            ui.add_space(4.0);
+            egui::TextEdit::multiline(&mut self.changes)
+                .desired_width(f32::INFINITY)
+                .show(ui);
             let is_valid = || {
                 self.source != PathBuf::default()
                     && self.source.exists()
@@ -458,6 +801,16 @@ impl ModPackerBuilder {
                         if ui.button("Package Mod").clicked() {
                             app.do_update(Message::PackageMod);
                         }
+                        if ui
+                            .button("Check for Problems")
+                            .on_hover_text(
+                                "Look for common mistakes, like broken or misplaced files, files \
+                                 for the wrong platform, and actors which don't exist",
+                            )
+                            .clicked()
+                        {
+                            app.do_update(Message::LintMod(self.source.clone()));
+                        }
                     },
                 );
             });
@@ -52,7 +52,7 @@ impl FilePickerState {
                         .and_then(|e| e.to_str())
                         .unwrap_or("")
                         .to_lowercase();
-                    ((matches!(ext.as_str(), "zip" | "7z" | "bnp") || path.is_dir())
+                    ((matches!(ext.as_str(), "zip" | "7z" | "bnp" | "001") || path.is_dir())
                         && !e.file_name().to_str().unwrap_or("").starts_with('.'))
                     .then_some(path)
                 })
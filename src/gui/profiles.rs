@@ -2,10 +2,11 @@ use std::path::PathBuf;
 
 use fs_err as fs;
 use smartstring::alias::String as SmartString;
-use uk_content::util::HashMap;
-use uk_manager::mods::Profile as ProfileData;
+use uk_content::{actor::params::general::EconomyMergePolicy, util::HashMap};
+use uk_manager::{mods::Profile as ProfileData, settings::ProfilePaths};
 use uk_ui::{
     egui::{self, text::LayoutJob, Layout, TextStyle},
+    ext::UiExt,
     icons::IconButtonExt,
 };
 
@@ -17,6 +18,8 @@ pub struct ProfileManagerState {
     pub profiles: HashMap<SmartString, ProfileData>,
     pub selected: Option<SmartString>,
     pub rename: Option<String>,
+    /// Unsaved changes to the selected profile's folders
+    pub paths: Option<ProfilePaths>,
     pub show: bool,
 }
 
@@ -38,11 +41,13 @@ impl ProfileManagerState {
             profiles,
             selected: None,
             rename: None,
+            paths: None,
             show: false,
         }
     }
 
     pub fn reload(&mut self, core: &uk_manager::core::Manager) {
+        self.paths = None;
         let settings = core.settings();
         self.profiles = settings
             .profiles()
@@ -119,13 +124,130 @@ impl ProfileManagerState {
                         if ui.button("Duplicate").clicked() {
                             app.do_update(Message::DuplicateProfile(name.to_string()));
                         }
+                        if ui
+                            .button("Compare")
+                            .on_hover_text(
+                                "Merge this profile and the current one side by side and list \
+                                 the merged files that differ",
+                            )
+                            .clicked()
+                        {
+                            app.do_update(Message::CompareProfile(name.to_string()));
+                        }
                         if ui.button("Delete").clicked() {
                             app.do_update(Message::Confirm(
                                 Message::DeleteProfile(name.to_string()).into(),
                                 format!("Are you sure you want to delete the profile {}?", name),
                             ));
                         }
+                        egui::ComboBox::from_id_source("profile_backups")
+                            .selected_text("Restore…")
+                            .show_ui(ui, |ui| {
+                                match app.core.mod_manager().profile_backups(name) {
+                                    Ok(backups) if backups.is_empty() => {
+                                        ui.label("No backups yet");
+                                    }
+                                    Ok(backups) => {
+                                        for backup in backups {
+                                            if ui.selectable_label(false, backup.age()).clicked() {
+                                                let age = backup.age();
+                                                app.do_update(Message::Confirm(
+                                                    Message::RestoreProfile(name.to_string(), backup)
+                                                        .into(),
+                                                    format!(
+                                                        "Restore the mod list and load order of \
+                                                         {name} saved {age}?"
+                                                    ),
+                                                ));
+                                            }
+                                        }
+                                    }
+                                    Err(e) => {
+                                        ui.label(format!("Could not list backups: {e}"));
+                                    }
+                                }
+                            })
+                            .response
+                            .on_hover_text(
+                                "UKMM backs up the profile each time it is saved. Choose a \
+                                 backup to put its mod list and load order back.",
+                            );
+                    });
+                    ui.add_space(8.0);
+                    ui.horizontal(|ui| {
+                        ui.label("Price Merging").on_hover_text(
+                            "How prices and rupee values changed by more than one mod are \
+                             combined. Last Wins uses the last mod in the load order. \
+                             Percentage and Additive apply each mod's change from vanilla in turn.",
+                        );
+                        let current = profile.economy_merge();
+                        let mut policy = current;
+                        egui::ComboBox::from_id_source("economy_merge")
+                            .selected_text(policy.name())
+                            .show_ui(ui, |ui| {
+                                for option in [
+                                    EconomyMergePolicy::LastWins,
+                                    EconomyMergePolicy::Percentage,
+                                    EconomyMergePolicy::Additive,
+                                ] {
+                                    ui.selectable_value(&mut policy, option, option.name());
+                                }
+                            });
+                        if policy != current {
+                            app.do_update(Message::SetEconomyMerge(name.to_string(), policy));
+                        }
                     });
+                    ui.add_space(8.0);
+                    ui.label("Folders").on_hover_text(
+                        "Folders this profile uses instead of the usual ones, so that each \
+                         profile can keep its own merged and deployed files. Leave a folder \
+                         blank to use the usual one.",
+                    );
+                    let (saved, targets) = app
+                        .core
+                        .settings()
+                        .platform_config()
+                        .map(|c| {
+                            (
+                                c.profile_paths.get(name).cloned().unwrap_or_default(),
+                                c.deploy_targets
+                                    .iter()
+                                    .map(|t| t.name.clone())
+                                    .collect::<Vec<_>>(),
+                            )
+                        })
+                        .unwrap_or_default();
+                    let paths = self.paths.get_or_insert_with(|| saved.clone());
+                    egui::Grid::new("profile_paths")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("Merged");
+                            let mut merged = paths.merged_dir.clone().unwrap_or_default();
+                            if ui.folder_picker(&mut merged).changed() {
+                                paths.merged_dir =
+                                    (!merged.as_os_str().is_empty()).then_some(merged);
+                            }
+                            ui.end_row();
+                            for target in targets {
+                                ui.label(target.as_str());
+                                let mut output =
+                                    paths.outputs.get(&target).cloned().unwrap_or_default();
+                                if ui.folder_picker(&mut output).changed() {
+                                    if output.as_os_str().is_empty() {
+                                        paths.outputs.remove(&target);
+                                    } else {
+                                        paths.outputs.insert(target, output);
+                                    }
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    if *paths != saved && ui.button("Save Folders").clicked() {
+                        app.do_update(Message::SetProfilePaths(
+                            name.to_string(),
+                            self.paths.take().unwrap_or_default(),
+                        ));
+                    }
                 });
             });
             ui.end_row();
@@ -0,0 +1,158 @@
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use http_req::{
+    request::{Method, RequestBuilder},
+    response::Response,
+    tls,
+    uri::Uri,
+};
+use uk_manager::settings::{ProxyConfig, ProxyKind};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Sends a GET request for `url` through a tunnel opened by the proxy,
+/// writing the body into `buf`.
+pub fn get(url: &Uri, proxy: &ProxyConfig, buf: &mut Vec<u8>) -> Result<Response> {
+    let host = url.host().context("URL has no host")?;
+    let mut stream = connect(proxy, host, url.corr_port())?;
+    let mut request = RequestBuilder::new(url);
+    request
+        .method(Method::GET)
+        .header("User-Agent", "UKMM")
+        .header("Connection", "Close");
+    let res = if url.scheme() == "https" {
+        let mut stream = tls::Config::default()
+            .connect(host, stream)
+            .context("Failed to open secure connection through proxy")?;
+        request.send(&mut stream, buf)
+    } else {
+        request.send(&mut stream, buf)
+    };
+    res.context("HTTP request through proxy failed")
+}
+
+fn connect(proxy: &ProxyConfig, host: &str, port: u16) -> Result<TcpStream> {
+    let mut stream = TcpStream::connect((proxy.host.as_str(), proxy.port))
+        .with_context(|| format!("Failed to connect to proxy {}:{}", proxy.host, proxy.port))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    match proxy.kind {
+        ProxyKind::Http => http_connect(&mut stream, proxy, host, port)?,
+        ProxyKind::Socks5 => socks5_connect(&mut stream, proxy, host, port)?,
+        ProxyKind::Disabled => unreachable!("Proxy is disabled"),
+    }
+    Ok(stream)
+}
+
+fn http_connect(stream: &mut TcpStream, proxy: &ProxyConfig, host: &str, port: u16) -> Result<()> {
+    let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if !proxy.username.is_empty() {
+        let login = format!("{}:{}", proxy.username, proxy.password);
+        request.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            base64(login.as_bytes())
+        ));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes())?;
+    // Read the response head a byte at a time so nothing past it is consumed
+    let mut head = Vec::new();
+    let mut byte = [0u8];
+    while !head.ends_with(b"\r\n\r\n") {
+        stream
+            .read_exact(&mut byte)
+            .context("Proxy closed the connection")?;
+        head.push(byte[0]);
+        ensure!(head.len() < 8192, "Proxy sent an invalid response");
+    }
+    let head = String::from_utf8_lossy(&head);
+    let status = head.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        Some("407") => bail!("Proxy login failed: {status}"),
+        _ => bail!("Proxy refused to connect to {host}: {status}"),
+    }
+}
+
+fn socks5_connect(
+    stream: &mut TcpStream,
+    proxy: &ProxyConfig,
+    host: &str,
+    port: u16,
+) -> Result<()> {
+    let login = !proxy.username.is_empty();
+    // Offer no authentication, plus username/password if we have a login
+    let methods: &[u8] = if login { &[5, 2, 0, 2] } else { &[5, 1, 0] };
+    stream.write_all(methods)?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply)?;
+    match reply {
+        [5, 0] => (),
+        [5, 2] if login => {
+            let (username, password) = (proxy.username.as_bytes(), proxy.password.as_bytes());
+            ensure!(
+                username.len() < 256 && password.len() < 256,
+                "Proxy username and password must be under 256 bytes"
+            );
+            let mut auth = vec![1, username.len() as u8];
+            auth.extend_from_slice(username);
+            auth.push(password.len() as u8);
+            auth.extend_from_slice(password);
+            stream.write_all(&auth)?;
+            stream.read_exact(&mut reply)?;
+            ensure!(reply[1] == 0, "Proxy login failed");
+        }
+        [5, _] => bail!("Proxy requires a login method UKMM does not support"),
+        _ => bail!("Proxy is not a SOCKS5 proxy"),
+    }
+    ensure!(host.len() < 256, "Host name too long for SOCKS5: {host}");
+    let mut request = vec![5, 1, 0, 3, host.len() as u8];
+    request.extend_from_slice(host.as_bytes());
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request)?;
+    let mut head = [0u8; 4];
+    stream.read_exact(&mut head)?;
+    ensure!(
+        head[1] == 0,
+        "Proxy refused to connect to {host} (SOCKS5 error {})",
+        head[1]
+    );
+    // Skip over the bound address and port the proxy reports back
+    let len = match head[3] {
+        1 => 4,
+        4 => 16,
+        3 => {
+            let mut len = [0u8];
+            stream.read_exact(&mut len)?;
+            len[0] as usize
+        }
+        kind => bail!("Proxy sent an unknown address type {kind}"),
+    };
+    let mut bound = vec![0u8; len + 2];
+    stream.read_exact(&mut bound)?;
+    Ok(())
+}
+
+fn base64(data: &[u8]) -> String {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | ((*b as u32) << (16 - i * 8)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(CHARS[(n >> (18 - i * 6)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
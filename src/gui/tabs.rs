@@ -35,10 +35,40 @@ impl TabViewer for super::App {
         match tab {
             Tabs::Info => {
                 if let Some(mod_) = self.selected.first() {
-                    if let Some(info::Message::RequestOptions) = info::ModInfo(mod_).show(ui).inner
+                    if let Some(info::Message::RequestOptions) =
+                        info::ModInfo(mod_, self.language()).show(ui).inner
                     {
                         self.do_update(super::Message::RequestOptions(mod_.clone(), true));
                     }
+                    let warnings = self.compat.check_mod(mod_, &self.mods);
+                    if !warnings.is_empty() {
+                        ui.add_space(8.0);
+                        ui.label(
+                            RichText::new("Compatibility")
+                                .family(egui::FontFamily::Name("Bold".into())),
+                        );
+                        for warning in warnings {
+                            ui.label(RichText::new(warning.to_string()).color(visuals::YELLOW));
+                        }
+                    }
+                    let update = self.mod_updates.iter().find(|u| u.hash == mod_.hash());
+                    if let Some(update) = update {
+                        ui.add_space(8.0);
+                        ui.label(
+                            RichText::new("Update Available")
+                                .family(egui::FontFamily::Name("Bold".into())),
+                        );
+                        ui.label(
+                            RichText::new(format!("Version {}", update.release.version))
+                                .color(visuals::GREEN),
+                        );
+                        if let Some(changes) = update.release.changes.as_ref() {
+                            ui.label(changes.as_str());
+                        }
+                        if let Some(url) = update.release.url.as_ref() {
+                            ui.hyperlink(url.as_str());
+                        }
+                    }
                 } else {
                     ui.centered_and_justified(|ui| {
                         ui.label("No mod selected");
@@ -53,9 +83,9 @@ impl TabViewer for super::App {
                     .core
                     .settings()
                     .platform_config()
-                    .and_then(|c| c.deploy_config.as_ref())
+                    .and_then(|c| c.deploy_config().map(|d| (d, &c.deploy_targets)))
                 {
-                    Some(config) => {
+                    Some((config, targets)) => {
                         egui::Frame::none().inner_margin(4.0).show(ui, |ui| {
                             ui.spacing_mut().item_spacing.y = 8.0;
                             ui.with_layout(Layout::top_down(Align::Center), |ui| {
@@ -122,6 +152,48 @@ impl TabViewer for super::App {
                                         .spawn();
                                     }
                                 });
+                                if targets.len() > 1 {
+                                    let deployed_to = self.core.deploy_manager().deployed_to();
+                                    ui.label(
+                                        RichText::new("Targets")
+                                            .family(egui::FontFamily::Name("Bold".into())),
+                                    );
+                                    for target in targets {
+                                        ui.horizontal(|ui| {
+                                            ui.label(target.name.as_str());
+                                            ui.with_layout(
+                                                Layout::right_to_left(Align::Center),
+                                                |ui| {
+                                                    if ui.small_button("Deploy").clicked() {
+                                                        self.do_update(
+                                                            super::Message::DeployTarget(
+                                                                target.name.clone(),
+                                                            ),
+                                                        );
+                                                    }
+                                                    if pending
+                                                        && deployed_to.contains(&target.name)
+                                                    {
+                                                        ui.label(
+                                                            RichText::new("Up to date")
+                                                                .color(visuals::GREEN),
+                                                        );
+                                                    }
+                                                },
+                                            );
+                                        });
+                                    }
+                                }
+                                let queued = self.core.deploy_manager().queued();
+                                if queued {
+                                    ui.label(
+                                        RichText::new(
+                                            "Deploy queued, waiting for the target folder to \
+                                             become available",
+                                        )
+                                        .color(visuals::ORGANGE),
+                                    );
+                                }
                                 if !config.auto || self.core.deploy_manager().pending() {
                                     ui.add_space(4.);
                                     ui.with_layout(
@@ -137,7 +209,7 @@ impl TabViewer for super::App {
                                                 {
                                                     self.do_update(super::Message::Deploy);
                                                 }
-                                                if config.auto {
+                                                if config.auto && !queued {
                                                     ui.label(
                                                         RichText::new(
                                                             "Auto deploy incomplete, please \
@@ -8,20 +8,35 @@ use anyhow::{Context, Result};
 use fs_err as fs;
 use join_str::jstr;
 use serde::Deserialize;
-use uk_content::constants::Language;
+use uk_content::{
+    actor::params::general::EconomyMergePolicy, data::gamedata::FlagResolution, prelude::Endian,
+};
 use uk_manager::{
-    bnp::convert_bnp,
+    backup::Backup,
+    bnp::{convert_bnp, convert_bnp_with_progress, log_progress},
+    compat::{CompatDb, COMPAT_URL},
     core::Manager,
-    mods::Mod,
-    settings::{DeployConfig, Platform, PlatformSettings, UpdatePreference},
-    util::get_temp_file,
+    mods::{Mod, Orphan},
+    settings::{
+        BcmlSettings, DeployConfig, Platform, PlatformSettings, ProxyConfig, UpdatePreference,
+    },
+    updates::check_updates,
+    util::{get_temp_file, get_temp_folder},
+};
+use uk_mod::{
+    integrity::SigningKey, pack::ModPacker, unpack::ModReader, Manifest, Meta, ModPlatform,
 };
-use uk_mod::{pack::ModPacker, unpack::ModReader, Manifest, Meta};
 use uk_reader::ResourceReader;
 
 use super::{package::ModPackerBuilder, Message};
 
 fn is_probably_a_mod_and_has_meta(path: &Path) -> (bool, bool) {
+    if path.is_dir() {
+        return (
+            uk_manager::mods::has_loose_files(path),
+            path.join("rules.txt").exists() || path.join("info.json").exists(),
+        );
+    }
     let ext = path
         .extension()
         .and_then(|e| e.to_str().map(|e| e.to_lowercase()))
@@ -69,16 +84,32 @@ fn is_probably_a_mod_and_has_meta(path: &Path) -> (bool, bool) {
 
 pub fn open_mod(core: &Manager, path: &Path, meta: Option<Meta>) -> Result<Message> {
     log::info!("Opening mod at {}", path.display());
+    if uk_mod::split::is_split(path) {
+        let joined = uk_manager::mods::join_split(path)?;
+        return open_mod(core, &joined, meta);
+    }
     if path
         .extension()
         .and_then(|e| e.to_str())
         .map(|e| e.to_lowercase() == "bnp")
         .unwrap_or(false)
     {
-        let mod_ = convert_bnp(core, path).context("Failed to convert BNP to UKMM mod")?;
-        return Ok(Message::HandleMod(Mod::from_reader(
+        let (mod_, report) = convert_bnp_with_progress(core, path, log_progress())
+            .context("Failed to convert BNP to UKMM mod")?;
+        let message = Message::HandleMod(Mod::from_reader(
             ModReader::open_peek(mod_, vec![]).context("Failed to open converted mod")?,
-        )));
+        ));
+        return Ok(if report.is_complete() {
+            message
+        } else {
+            Message::Confirm(
+                Box::new(message),
+                format!(
+                    "Some parts of this BNP could not be converted, so their changes will be \
+                     missing:\n\n{report}\nInstall the rest of the mod anyway?"
+                ),
+            )
+        });
     }
     let mod_ = match ModReader::open_peek(path, vec![]) {
         Ok(reader) => Mod::from_reader(reader),
@@ -106,6 +137,35 @@ pub fn open_mod(core: &Manager, path: &Path, meta: Option<Meta>) -> Result<Messa
             }
         }
     };
+    let endian = Endian::from(core.settings().current_mode);
+    if let ModPlatform::Specific(mod_endian) = mod_.meta.platform
+        && mod_endian != endian
+        && mod_.path.is_file()
+    {
+        log::info!("Mod is for {mod_endian}, converting it for {endian}");
+        let (converted, unconverted) = uk_manager::mods::convert_platform(&mod_.path, endian)?;
+        let message = Message::HandleMod(Mod::from_reader(
+            ModReader::open_peek(converted, vec![]).context("Failed to open converted mod")?,
+        ));
+        let mut prompt = format!(
+            "This mod was made for {}, so it has been converted for {}. Most mods work the same \
+             on both, but check it in game.",
+            Platform::from(mod_endian),
+            Platform::from(endian)
+        );
+        if !unconverted.is_empty() {
+            prompt += &format!(
+                "\n\nThese files could not be converted, and may not work:\n{}",
+                unconverted
+                    .iter()
+                    .map(|f| format!("• {f}"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+        prompt += "\n\nInstall the converted mod?";
+        return Ok(Message::Confirm(Box::new(message), prompt));
+    }
     Ok(Message::HandleMod(mod_))
 }
 
@@ -131,17 +191,22 @@ pub fn apply_changes(core: &Manager, mods: Vec<Mod>, dirty: Option<Manifest>) ->
                             )
                         })?;
                     mod_manager
-                        .set_enabled_options(m.hash(), m.enabled_options.clone())
+                        .set_enabled_options(m.hash(), m.enabled_options.clone(), None)
                         .with_context(|| {
                             format!("Failed to update options on {}", m.meta.name.as_str())
                         })?;
+                    mod_manager
+                        .set_loose(m.hash(), m.loose)
+                        .with_context(|| {
+                            format!("Failed to update loose deployment on {}", m.meta.name.as_str())
+                        })?;
                 }
                 Ok(())
             })
             .context("Failed to update mod state")?;
         log::info!("Updating load order");
         let order = mods.iter().map(|m| m.hash()).collect();
-        mod_manager.set_order(order);
+        mod_manager.set_order(order)?;
         mod_manager
             .save()
             .context("Failed to save mod configuration for current profile")?;
@@ -154,31 +219,433 @@ pub fn apply_changes(core: &Manager, mods: Vec<Mod>, dirty: Option<Manifest>) ->
     if core
         .settings()
         .platform_config()
-        .and_then(|c| c.deploy_config.as_ref().map(|c| c.auto))
+        .map(|c| c.deploy_targets.iter().any(|t| t.auto))
         .unwrap_or(false)
     {
         log::info!("Deploying changes");
         deploy_manager
-            .deploy()
+            .deploy_auto()
             .context("Failed to deploy update to merged mod(s)")?;
     }
     log::info!("Done");
-    Ok(Message::ResetMods)
+    let conflicts = deploy_manager.flag_conflicts();
+    if conflicts.is_empty() {
+        Ok(Message::ResetMods)
+    } else {
+        Ok(Message::ShowFlagConflicts(conflicts))
+    }
+}
+
+/// Saves the chosen gamedata flag conflict resolutions to the current
+/// profile and remerges so they take effect.
+pub fn resolve_flag_conflicts(
+    core: &Manager,
+    choices: Vec<(String, String, Option<FlagResolution>)>,
+) -> Result<Message> {
+    let mod_manager = core.mod_manager();
+    for (mod_name, flag, resolution) in choices {
+        mod_manager.set_flag_resolution(&mod_name, &flag, resolution);
+    }
+    mod_manager
+        .save()
+        .context("Failed to save flag conflict resolutions")?;
+    apply_changes(core, vec![], None)
+}
+
+/// Saves new meta for an installed mod. Nothing merged depends on it, so
+/// there is nothing to remerge.
+pub fn edit_meta(core: &Manager, mod_: Mod, meta: Meta) -> Result<Message> {
+    let mod_manager = core.mod_manager();
+    let edited = mod_manager.edit_meta(&mod_, meta, None)?;
+    mod_manager
+        .save()
+        .context("Failed to save mod configuration for current profile")?;
+    Ok(Message::MetaEdited(mod_.hash(), edited))
+}
+
+pub fn set_economy_merge(
+    core: &Manager,
+    profile: &str,
+    policy: EconomyMergePolicy,
+) -> Result<Message> {
+    core.mod_manager()
+        .set_economy_merge(profile, policy)
+        .context("Failed to save price merging setting")?;
+    let current = core
+        .settings()
+        .platform_config()
+        .map(|c| c.profile == profile)
+        .unwrap_or(false);
+    if current {
+        apply_changes(core, vec![], None)
+    } else {
+        Ok(Message::ReloadProfiles)
+    }
+}
+
+/// Restores a backup of a profile, remerging if it is the current one.
+pub fn restore_profile(core: &Manager, profile: &str, backup: &Backup) -> Result<Message> {
+    core.restore_profile(profile, backup)
+        .context("Failed to restore profile backup")?;
+    let current = core
+        .settings()
+        .platform_config()
+        .map(|c| c.profile == profile)
+        .unwrap_or(false);
+    if current {
+        apply_changes(core, vec![], None)
+    } else {
+        Ok(Message::ReloadProfiles)
+    }
+}
+
+pub fn preview_deploy(core: &Manager) -> Result<Message> {
+    let changes = core
+        .deploy_manager()
+        .dry_run(None)
+        .context("Failed to check pending deployment")?;
+    if changes.is_empty() {
+        return Ok(Message::Toast("No changes to deploy".into()));
+    }
+    let total: u64 = changes.iter().map(|c| c.size).sum();
+    let list = changes
+        .iter()
+        .map(|c| format!("• {c}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(Message::Confirm(
+        Box::new(Message::Deploy),
+        format!(
+            "Deploying will change {} files ({:.1} MB). Deploy now?\n\n{list}",
+            changes.len(),
+            total as f64 / 1024.0 / 1024.0
+        ),
+    ))
 }
 
-pub fn package_mod(core: &Manager, builder: ModPackerBuilder) -> Result<Message> {
-    let Some(dump) = core.settings().dump() else {
-        anyhow::bail!("No dump for current platform")
+/// Builds snapshots of the current profile and another one and reports how
+/// many merged files differ. The full report is saved with the snapshots.
+pub fn compare_profile(core: &Manager, other: &str) -> Result<Message> {
+    let current = core
+        .settings()
+        .platform_config()
+        .map(|c| c.profile.clone())
+        .context("No config for current platform")?;
+    let report = core
+        .deploy_manager()
+        .compare_profiles(&current, other)
+        .context("Failed to compare profiles")?;
+    Ok(Message::Toast(format!(
+        "{} merged files differ between {current} and {other}. The full report is in {}.",
+        report.diffs.len(),
+        core.settings().snapshots_dir().display()
+    )))
+}
+
+/// Deploys to every target, or to just the named one, then reports anything
+/// that needs attention afterwards.
+pub fn deploy(core: &Manager, target: Option<&str>) -> Result<Message> {
+    let deploy_manager = core.deploy_manager();
+    let result = match target {
+        Some(target) => deploy_manager.deploy_target(target),
+        None => {
+            log::info!("Deploying current mod configuration");
+            deploy_manager.deploy()
+        }
     };
-    uk_mod::pack::ModPacker::new(
-        builder.source,
-        builder.dest,
-        Some(builder.meta),
-        [dump].into_iter().collect(),
+    if let Err(e) = result {
+        if deploy_manager.progress().is_cancelled() {
+            return Ok(Message::Toast(
+                "Deployment cancelled. The remaining files will deploy next time.".into(),
+            ));
+        }
+        return Err(e);
+    }
+    drop(deploy_manager);
+    Ok(report_failures(core)
+        .or_else(|| report_mismatches(core))
+        .unwrap_or(Message::ResetMods))
+}
+
+/// Offers to retry any files the last network share deployment could not
+/// write.
+pub fn report_failures(core: &Manager) -> Option<Message> {
+    let failures = core.deploy_manager().failures();
+    if failures.is_empty() {
+        return None;
+    }
+    let list = failures
+        .iter()
+        .map(|f| format!("• {f}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(Message::Confirm(
+        Box::new(Message::DeployConfirmed),
+        format!(
+            "{} files could not be written to the network share, even after retrying. The rest \
+             of the deployment finished. Try these files again?\n\n{list}",
+            failures.len()
+        ),
+    ))
+}
+
+/// Offers to repair any deployed files which failed the last verification.
+pub fn report_mismatches(core: &Manager) -> Option<Message> {
+    let mismatches = core.deploy_manager().mismatches();
+    if mismatches.is_empty() {
+        return None;
+    }
+    let list = mismatches
+        .iter()
+        .map(|m| format!("• {m}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(Message::Confirm(
+        Box::new(Message::RepairDeploy),
+        format!(
+            "{} deployed files do not match the merged files. This usually means the drive or \
+             card reader wrote them incorrectly. Copy them again?\n\n{list}",
+            mismatches.len()
+        ),
+    ))
+}
+
+pub fn verify_deploy(core: &Manager) -> Result<Message> {
+    core.deploy_manager()
+        .verify()
+        .context("Failed to verify deployed files")?;
+    Ok(report_mismatches(core)
+        .unwrap_or_else(|| Message::Toast("All deployed files verified".into())))
+}
+
+pub fn repair_deploy(core: &Manager) -> Result<Message> {
+    let remaining = core
+        .deploy_manager()
+        .repair()
+        .context("Failed to repair deployed files")?;
+    if !remaining.is_empty() {
+        anyhow::bail!(
+            "{} deployed files still do not match after repairing. Check the deploy target for \
+             drive errors.",
+            remaining.len()
+        );
+    }
+    Ok(Message::Toast("Repaired deployed files".into()))
+}
+
+pub fn verify_mods(core: &Manager) -> Result<Message> {
+    let problems = core.mod_manager().check_integrity();
+    if problems.is_empty() {
+        return Ok(Message::Toast("All mod packages verified".into()));
+    }
+    anyhow::bail!(
+        "{} mods no longer match the files they were installed with. They may be corrupted or \
+         tampered with, and should be reinstalled from a trusted source.\n\n{}",
+        problems.len(),
+        problems
+            .iter()
+            .map(|(mod_, problem)| format!("• {}: {problem}", mod_.meta.name))
+            .collect::<Vec<_>>()
+            .join("\n")
+    )
+}
+
+pub fn lint_mod(path: &Path) -> Result<Message> {
+    let issues = uk_mod::lint::lint(path)?;
+    if issues.is_empty() {
+        return Ok(Message::Toast("No problems found".into()));
+    }
+    anyhow::bail!(
+        "Found {} possible problems in the mod:\n\n{}",
+        issues.len(),
+        issues
+            .iter()
+            .map(|issue| format!("• {issue}"))
+            .collect::<Vec<_>>()
+            .join("\n")
     )
-    .context("Failed to initialize mod packager")?
-    .pack()
-    .context("Failed to package mod")?;
+}
+
+pub fn benchmark(core: &Manager) -> Result<Message> {
+    let report = uk_manager::bench::run(core).context("Failed to run benchmark")?;
+    log::info!("Benchmark results:\n{report}");
+    Ok(Message::Toast(format!(
+        "Benchmark complete{}. The full report is in {}.",
+        report
+            .recommended_workers
+            .map(|workers| format!(", deploying was fastest with {workers} workers"))
+            .unwrap_or_default(),
+        core.settings().platform_dir().join("benchmark.txt").display()
+    )))
+}
+
+pub fn rollback_deploy(core: &Manager) -> Result<Message> {
+    let restored = core
+        .deploy_manager()
+        .rollback(None)
+        .context("Failed to restore previous deployment")?;
+    Ok(Message::Toast(format!(
+        "Restored {restored} files to the previous deployment"
+    )))
+}
+
+pub fn restore_merged(core: &Manager) -> Result<Message> {
+    core.deploy_manager()
+        .restore_merged()
+        .context("Failed to restore previous merge")?;
+    Ok(Message::Toast("Restored the previous merge, deploy to apply it".into()))
+}
+
+pub fn find_orphans(core: &Manager) -> Result<Message> {
+    let orphans = core
+        .mod_manager()
+        .find_orphans()
+        .context("Failed to check storage for unused files")?;
+    if orphans.is_empty() {
+        return Ok(Message::Toast("No unused files found".into()));
+    }
+    let total: u64 = orphans.iter().map(|o| o.size).sum();
+    let list = orphans
+        .iter()
+        .map(|o| format!("• {o}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(Message::Confirm(
+        Box::new(Message::RemoveOrphans(orphans)),
+        format!(
+            "The following are not used by any profile and can be removed to free {:.1} MB. \
+             Continue?\n\n{list}",
+            total as f64 / 1024.0 / 1024.0
+        ),
+    ))
+}
+
+pub fn remove_orphans(core: &Manager, orphans: Vec<Orphan>) -> Result<Message> {
+    let reclaimed = core
+        .mod_manager()
+        .remove_orphans(&orphans)
+        .context("Failed to remove unused files")?;
+    Ok(Message::Toast(format!(
+        "Freed {:.1} MB",
+        reclaimed as f64 / 1024.0 / 1024.0
+    )))
+}
+
+/// Packages a mod with files for both platforms by packing its source once for
+/// each, against that platform's dump, and combining the results.
+fn package_dual(
+    core: &Manager,
+    builder: &ModPackerBuilder,
+    signing_key: Option<&SigningKey>,
+) -> Result<PathBuf> {
+    let dumps = {
+        let settings = core.settings();
+        [
+            (
+                Endian::Big,
+                settings.wiiu_config.as_ref().map(|c| c.dump.clone()),
+            ),
+            (
+                Endian::Little,
+                settings.switch_config.as_ref().map(|c| c.dump.clone()),
+            ),
+        ]
+    };
+    let mut parts = vec![];
+    for (endian, dump) in dumps {
+        let platform = Platform::from(endian);
+        let dump = dump.with_context(|| {
+            format!("Packaging for both platforms needs a {platform} dump to be set up")
+        })?;
+        let mut meta = builder.meta.clone();
+        meta.platform = ModPlatform::Specific(endian);
+        let temp = get_temp_folder().to_path_buf();
+        let part = ModPacker::new(&builder.source, &temp, Some(meta), vec![dump])
+            .with_context(|| format!("Failed to initialize mod packager for {platform}"))?
+            .with_endian(endian)
+            .with_compression(builder.compression.clone())
+            .with_excludes(&builder.exclude)?
+            .pack()
+            .with_context(|| format!("Failed to package mod for {platform}"))?;
+        parts.push(part);
+    }
+    let dest = if builder.dest.is_dir() {
+        builder
+            .dest
+            .join(uk_mod::pack::sanitise(&builder.meta.name))
+            .with_extension("zip")
+    } else {
+        builder.dest.clone()
+    };
+    uk_mod::dual::combine(&parts[0], &parts[1], &dest, signing_key)
+        .context("Failed to combine Wii U and Switch packages")?;
+    Ok(dest)
+}
+
+pub fn package_mod(core: &Manager, mut builder: ModPackerBuilder) -> Result<Message> {
+    let base = builder
+        .delta_from
+        .take()
+        .map(|base| {
+            if uk_mod::split::is_split(&base) {
+                uk_manager::mods::join_split(&base)
+            } else {
+                Ok(base)
+            }
+        })
+        .transpose()?;
+    let base_meta = base
+        .as_deref()
+        .map(Meta::from_mod)
+        .transpose()
+        .context("Failed to read earlier version of mod")?;
+    if let Some(base_meta) = base_meta.as_ref() {
+        builder.meta.merge_changelog(base_meta);
+    }
+    builder.meta.set_changes(&builder.changes);
+    builder.meta.selectable.retain(|p| !p.trim().is_empty());
+    let signing_key = builder
+        .signing_key
+        .as_deref()
+        .map(SigningKey::open)
+        .transpose()?;
+    let dest = if builder.meta.platform == ModPlatform::Dual {
+        package_dual(core, &builder, signing_key.as_ref())?
+    } else {
+        let Some(dump) = core.settings().dump() else {
+            anyhow::bail!("No dump for current platform")
+        };
+        let packer = uk_mod::pack::ModPacker::new(
+            builder.source,
+            builder.dest,
+            Some(builder.meta),
+            [dump].into_iter().collect(),
+        )
+        .context("Failed to initialize mod packager")?
+        .with_compression(builder.compression)
+        .with_excludes(&builder.exclude)?;
+        let packer = match signing_key {
+            Some(key) => packer.with_signing_key(key),
+            None => packer,
+        };
+        packer.pack().context("Failed to package mod")?
+    };
+    if let Some((base, base_meta)) = base.zip(base_meta) {
+        let out = dest.with_file_name(format!(
+            "{} (update from {}).zip",
+            dest.file_stem().unwrap_or_default().to_string_lossy(),
+            base_meta.version
+        ));
+        uk_mod::delta::create(&base, &dest, &out).context("Failed to create update package")?;
+        log::info!("Created update package at {}", out.display());
+        if let Some(size) = builder.split_mb {
+            uk_mod::split::split(&out, size * 1024 * 1024)
+                .context("Failed to split update package")?;
+        }
+    }
+    if let Some(size) = builder.split_mb {
+        uk_mod::split::split(&dest, size * 1024 * 1024).context("Failed to split package")?;
+    }
     Ok(Message::ResetPacker)
 }
 
@@ -268,7 +735,10 @@ pub fn import_cemu_settings(core: &Manager, path: &Path) -> Result<Message> {
             wiiu_config.dump = dump;
         }
         if let Some(gfx_folder) = gfx_folder {
-            let mut deploy_config = wiiu_config.deploy_config.get_or_insert_default();
+            if wiiu_config.deploy_targets.is_empty() {
+                wiiu_config.deploy_targets.push(Default::default());
+            }
+            let deploy_config = &mut wiiu_config.deploy_targets[0];
             deploy_config.auto = true;
             deploy_config.output = gfx_folder.join("BreathOfTheWild_UKMM");
         }
@@ -277,118 +747,56 @@ pub fn import_cemu_settings(core: &Manager, path: &Path) -> Result<Message> {
             language: uk_content::constants::Language::USen,
             profile: "Default".into(),
             dump,
-            deploy_config: gfx_folder.map(|gfx_folder| {
-                DeployConfig {
-                    auto: true,
-                    method: uk_manager::settings::DeployMethod::Copy,
-                    output: gfx_folder.join("BreathOfTheWild_UKMM"),
-                    cemu_rules: true,
-                }
-            }),
+            deploy_targets: gfx_folder
+                .map(|gfx_folder| {
+                    DeployConfig {
+                        name: "Cemu".into(),
+                        auto: true,
+                        method: uk_manager::settings::DeployMethod::Copy,
+                        output: gfx_folder.join("BreathOfTheWild_UKMM"),
+                        cemu_rules: true,
+                        ..Default::default()
+                    }
+                })
+                .into_iter()
+                .collect(),
+            profile_paths: Default::default(),
+            fallback_languages: vec![],
+            rstb: Default::default(),
         })
     };
     settings.save()?;
     Ok(Message::ResetSettings)
 }
 
-#[derive(Debug, Default, Deserialize)]
-#[serde(default)]
-struct BcmlSettings {
-    lang: Language,
-    cemu_dir: Option<PathBuf>,
-    export_dir: Option<PathBuf>,
-    export_dir_nx: Option<PathBuf>,
-    game_dir: Option<PathBuf>,
-    game_dir_nx: Option<PathBuf>,
-    update_dir: Option<PathBuf>,
-    dlc_dir: Option<PathBuf>,
-    dlc_dir_nx: Option<PathBuf>,
-    store_dir: PathBuf,
-}
-
 pub fn migrate_bcml(core: Arc<Manager>) -> Result<Message> {
     log::info!("Attempting to import BCML settings");
     let current_mode = core.settings().current_mode;
-    let settings_path = if cfg!(windows) {
-        dirs2::data_local_dir()
-    } else {
-        dirs2::config_dir()
+    let bcml_settings = BcmlSettings::load()?;
+    let imported = {
+        let mut settings = core.settings_mut();
+        let imported = settings.import_bcml(&bcml_settings)?;
+        settings.save()?;
+        imported
+    };
+    if imported.is_empty() {
+        log::warn!("BCML has no game dump set, so there is nothing to import");
     }
-    .unwrap()
-    .join("bcml/settings.json");
-    let bcml_settings: BcmlSettings = serde_json::from_str(
-        &fs::read_to_string(settings_path).context("Failed to read BCML settings file")?,
-    )
-    .context("Failed to parse BCML settings file")?;
-    if let Some(game_dir) = bcml_settings.game_dir
-        && let Some(update_dir) = bcml_settings.update_dir
-        && !game_dir.as_os_str().is_empty()
-        && !update_dir.as_os_str().is_empty()
-    {
+    for platform in imported {
         {
-            log::info!("Import BCML Wii U game dump settings");
             let mut settings = core.settings_mut();
-            settings.wiiu_config = Some(PlatformSettings {
-                language: bcml_settings.lang,
-                profile: "Default".into(),
-                deploy_config: bcml_settings
-                    .export_dir
-                    .map(|export_dir| {
-                        DeployConfig {
-                            output: export_dir,
-                            cemu_rules: bcml_settings.cemu_dir.is_some(),
-                            ..Default::default()
-                        }
-                    })
-                    .or_else(|| {
-                        bcml_settings.cemu_dir.map(|cemu_dir| {
-                            DeployConfig {
-                                output: cemu_dir.join("graphicPacks/BreathOfTheWild_UKMM"),
-                                cemu_rules: true,
-                                ..Default::default()
-                            }
-                        })
-                    }),
-                dump: Arc::new(ResourceReader::from_unpacked_dirs(
-                    Some(game_dir),
-                    Some(update_dir),
-                    bcml_settings.dlc_dir,
-                )?),
-            });
-            settings.current_mode = Platform::WiiU;
+            settings.current_mode = platform;
             settings.save()?;
         }
         core.reload()?;
-        log::info!("Attempting to import BCML Wii U mods");
-        import_mods(&core, bcml_settings.store_dir.join("mods"))?;
-    }
-    if let Some(game_dir) = bcml_settings.game_dir_nx
-        && !game_dir.as_os_str().is_empty()
-    {
-        {
-            log::info!("Import BCML Switch game dump settings");
-            let mut settings = core.settings_mut();
-            settings.switch_config = Some(PlatformSettings {
-                language: bcml_settings.lang,
-                profile: "Default".into(),
-                deploy_config: bcml_settings.export_dir_nx.map(|export_dir| {
-                    DeployConfig {
-                        output: export_dir,
-                        ..Default::default()
-                    }
-                }),
-                dump: Arc::new(ResourceReader::from_unpacked_dirs(
-                    Some(game_dir),
-                    None::<PathBuf>,
-                    bcml_settings.dlc_dir_nx,
-                )?),
-            });
-            settings.current_mode = Platform::Switch;
-            settings.save()?;
-        }
-        core.reload()?;
-        log::info!("Attempting to import BCML Switch mods");
-        import_mods(&core, bcml_settings.store_dir.join("mods_nx"))?;
+        log::info!("Attempting to import BCML {} mods", platform);
+        import_mods(
+            &core,
+            bcml_settings.store_dir.join(match platform {
+                Platform::WiiU => "mods",
+                Platform::Switch => "mods_nx",
+            }),
+        )?;
     }
     let mode_changed = core.settings().current_mode != current_mode;
     if mode_changed {
@@ -402,31 +810,58 @@ pub fn migrate_bcml(core: Arc<Manager>) -> Result<Message> {
     Ok(Message::HandleSettings)
 }
 
+/// Imports the mods from a BCML mod folder in BCML's load order. BCML names
+/// each installed mod folder with its priority, lowest first, and marks
+/// disabled mods with an empty `.disabled` file. BCML bakes the selected
+/// options into the installed copy, so any options left in the mod are
+/// set to their defaults.
 fn import_mods(core: &Manager, mod_dir: PathBuf) -> Result<()> {
     if !mod_dir.exists() {
         Ok(())
     } else {
-        for dir in fs::read_dir(mod_dir)?.filter_map(|e| {
-            e.ok().and_then(|e| {
-                e.file_type().ok().and_then(|t| {
-                    (t.is_dir()
-                        && !e
-                            .file_name()
-                            .to_str()
-                            .map(|n| n.starts_with("9999"))
-                            .unwrap_or(false))
-                    .then(|| e.path())
+        let priority = |path: &Path| -> usize {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.split_once('_'))
+                .and_then(|(p, _)| p.parse().ok())
+                .unwrap_or(usize::MAX)
+        };
+        let mut dirs = fs::read_dir(mod_dir)?
+            .filter_map(|e| {
+                e.ok().and_then(|e| {
+                    e.file_type().ok().and_then(|t| {
+                        (t.is_dir()
+                            && !e
+                                .file_name()
+                                .to_str()
+                                .map(|n| n.starts_with("9999"))
+                                .unwrap_or(false))
+                        .then(|| e.path())
+                    })
                 })
             })
-        }) {
+            .collect::<Vec<_>>();
+        dirs.sort_by_key(|dir| priority(dir));
+        let total = dirs.len();
+        let mod_manager = core.mod_manager();
+        for (i, dir) in dirs.into_iter().enumerate() {
+            log::info!("PROGRESSImporting BCML mods ({}/{})", i + 1, total);
             match convert_bnp(core, &dir) {
                 Ok(path) => {
-                    core.mod_manager().add(&path, None)?;
+                    let mut mod_ = mod_manager.add(&path, None)?;
+                    let hash = mod_.hash();
+                    if !mod_.meta.options.is_empty() && mod_.enabled_options.is_empty() {
+                        mod_.enable_default_options();
+                        mod_manager.set_enabled_options(hash, mod_.enabled_options, None)?;
+                    }
+                    if dir.join(".disabled").exists() {
+                        mod_manager.set_enabled(hash, false, None)?;
+                    }
                 }
                 Err(e) => log::warn!("Failed to import BCML mod: {}", e),
             }
         }
-        core.mod_manager().save()?;
+        mod_manager.save()?;
         Ok(())
     }
 }
@@ -455,28 +890,48 @@ impl VersionResponse {
     }
 }
 
-fn response(url: &str) -> Result<Vec<u8>> {
+fn response(url: &str, proxy: &ProxyConfig) -> Result<Vec<u8>> {
     let url = url.try_into()?;
     let mut buf = Vec::new();
-    http_req::request::Request::new(&url)
-        .header("User-Agent", "UKMM")
-        .method(http_req::request::Method::GET)
-        .send(&mut buf)
-        .context("HTTP request file")
-        .and_then(|res| {
-            if res.status_code().is_redirect()
-                && let Some(url) = res.headers().get("Location")
-            {
-                response(url)
-            } else {
-                Ok(buf)
-            }
-        })
+    let res = if proxy.enabled() {
+        super::proxy::get(&url, proxy, &mut buf)
+    } else {
+        http_req::request::Request::new(&url)
+            .header("User-Agent", "UKMM")
+            .method(http_req::request::Method::GET)
+            .send(&mut buf)
+            .context("HTTP request file")
+    };
+    res.and_then(|res| {
+        if res.status_code().is_redirect()
+            && let Some(url) = res.headers().get("Location")
+        {
+            response(url, proxy)
+        } else {
+            Ok(buf)
+        }
+    })
+}
+
+pub fn update_compat(core: Arc<Manager>) -> Result<Message> {
+    let proxy = core.settings().proxy.clone();
+    let bytes = response(COMPAT_URL, &proxy).context("Failed to download compatibility rules")?;
+    let text = std::str::from_utf8(&bytes).context("Compatibility rules are not valid text")?;
+    Ok(Message::SetCompat(CompatDb::update(&core.settings(), text)?))
+}
+
+pub fn check_mod_updates(core: Arc<Manager>) -> Result<Message> {
+    let proxy = core.settings().proxy.clone();
+    let mods = core.mod_manager().all_mods().collect::<Vec<_>>();
+    Ok(Message::SetModUpdates(check_updates(mods.iter(), |url| {
+        response(url, &proxy)
+    })))
 }
 
 pub fn get_releases(core: Arc<Manager>, sender: flume::Sender<Message>) {
     let url = "https://api.github.com/repos/NiceneNerd/ukmm/releases?per_page=10";
-    match response(url).and_then(|bytes| {
+    let proxy = core.settings().proxy.clone();
+    match response(url, &proxy).and_then(|bytes| {
         serde_json::from_slice::<Vec<VersionResponse>>(&bytes)
             .context("Failed to parse GitHub response")
     }) {
@@ -499,7 +954,7 @@ pub fn get_releases(core: Arc<Manager>, sender: flume::Sender<Message>) {
     }
 }
 
-pub fn do_update(version: VersionResponse) -> Result<Message> {
+pub fn do_update(core: Arc<Manager>, version: VersionResponse) -> Result<Message> {
     log::info!("Updating... UKMM will restart when complete");
     let platform =
         option_env!("UPDATE_PLATFORM").unwrap_or(if cfg!(windows) { "windows" } else { "linux" });
@@ -508,7 +963,8 @@ pub fn do_update(version: VersionResponse) -> Result<Message> {
         .iter()
         .find(|asset| asset.name[..asset.name.len() - 4].ends_with(platform))
         .context("No matching platform for update")?;
-    let data = response(asset.browser_download_url.as_str())?;
+    let proxy = core.settings().proxy.clone();
+    let data = response(asset.browser_download_url.as_str(), &proxy)?;
     let tmpfile = get_temp_file();
     dbg!(tmpfile.as_path());
     fs::write(tmpfile.as_path(), data)?;
@@ -1,30 +1,49 @@
 use std::{
+    collections::VecDeque,
+    fmt::Display,
     io::Write,
     ops::Deref,
     path::{Path, PathBuf},
-    sync::{atomic::AtomicBool, LazyLock, OnceLock},
+    sync::{LazyLock, OnceLock},
 };
 
 use log::{LevelFilter, Record};
 use parking_lot::Mutex;
+use uk_manager::settings::Settings;
 
 use crate::gui::Message;
 
+/// The log file is rotated once it grows past this size
+const MAX_LOG_SIZE: u64 = 5 * 1024 * 1024;
+/// How many log files to keep, counting the current one
+const LOG_FILES: usize = 3;
+/// How many entries to keep in memory for [`Logger::recent`]
+const MAX_HISTORY: usize = 1000;
+
+fn debug_flag() -> bool {
+    std::env::args().any(|arg| &arg == "--debug" || &arg == "-d")
+}
+
 pub static LOGGER: LazyLock<Logger> = LazyLock::new(|| {
     Logger {
-        inner:  env_logger::builder().build(),
-        debug:  std::env::args().any(|arg| &arg == "--debug").into(),
-        queue:  Mutex::new(vec![]),
-        sender: OnceLock::new(),
-        record: Mutex::new(vec![]),
-        file:   OnceLock::new(),
+        inner:   env_logger::builder().build(),
+        level:   Mutex::new(if debug_flag() {
+            LevelFilter::Debug
+        } else {
+            LevelFilter::Info
+        }),
+        queue:   Mutex::new(vec![]),
+        sender:  OnceLock::new(),
+        record:  Mutex::new(vec![]),
+        history: Mutex::new(VecDeque::new()),
+        file:    Mutex::new(None),
     }
 });
 
 pub fn init() {
     log::set_logger(LOGGER.deref()).unwrap();
     let level = LOGGER.inner.filter();
-    log::set_max_level(level.max(log::LevelFilter::Debug));
+    log::set_max_level(level.max(LOGGER.level()));
 }
 
 #[derive(Debug, Clone)]
@@ -35,6 +54,12 @@ pub struct Entry {
     pub args: String,
 }
 
+impl Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {} {}", self.timestamp, self.level, self.args)
+    }
+}
+
 impl From<&Record<'_>> for Entry {
     fn from(record: &Record) -> Self {
         Self {
@@ -47,12 +72,13 @@ impl From<&Record<'_>> for Entry {
 }
 
 pub struct Logger {
-    inner:  env_logger::Logger,
-    debug:  AtomicBool,
-    queue:  Mutex<Vec<Entry>>,
-    sender: OnceLock<flume::Sender<Message>>,
-    record: Mutex<Vec<Entry>>,
-    file:   OnceLock<PathBuf>,
+    inner:   env_logger::Logger,
+    level:   Mutex<LevelFilter>,
+    queue:   Mutex<Vec<Entry>>,
+    sender:  OnceLock<flume::Sender<Message>>,
+    record:  Mutex<Vec<Entry>>,
+    history: Mutex<VecDeque<Entry>>,
+    file:    Mutex<Option<PathBuf>>,
 }
 
 impl Drop for Logger {
@@ -63,30 +89,55 @@ impl Drop for Logger {
 
 impl Logger {
     pub fn save_log(&self) {
-        if let Some(path) = self.file.get() {
-            let mut file = if path.exists() {
-                fs_err::OpenOptions::new().append(true).open(path)
-            } else {
-                fs_err::File::create(path)
+        if let Some(path) = self.file.lock().as_deref() {
+            rotate(path);
+            if let Some(parent) = path.parent() {
+                fs_err::create_dir_all(parent).unwrap_or(());
             }
-            .unwrap();
-
+            let Ok(mut file) = fs_err::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+            else {
+                return;
+            };
             for entry in self.record.lock().drain(..) {
-                writeln!(file, "[{}] {} {}", entry.timestamp, entry.level, entry.args)
-                    .unwrap_or(());
+                writeln!(file, "{entry}").unwrap_or(());
             }
-            // if let Ok(mut file) = file {
-            // }
         }
     }
 
+    pub fn level(&self) -> LevelFilter {
+        *self.level.lock()
+    }
+
+    pub fn set_level(&self, level: LevelFilter) {
+        *self.level.lock() = level;
+        log::set_max_level(self.inner.filter().max(level));
+    }
+
     pub fn debug(&self) -> bool {
-        self.debug.load(std::sync::atomic::Ordering::Relaxed)
+        self.level() >= LevelFilter::Debug
     }
 
     pub fn set_debug(&self, debug: bool) {
-        self.debug
-            .store(debug, std::sync::atomic::Ordering::Relaxed);
+        self.set_level(if debug {
+            LevelFilter::Debug
+        } else {
+            LevelFilter::Info
+        });
+    }
+
+    /// Applies the log level and log file from the settings. The `--debug`
+    /// flag still wins over a lower level.
+    pub fn configure(&self, settings: &Settings) {
+        let level = settings.log_level.filter();
+        self.set_level(if debug_flag() {
+            level.max(LevelFilter::Debug)
+        } else {
+            level
+        });
+        self.set_file(settings.log_file());
     }
 
     pub fn set_sender(&self, sender: flume::Sender<Message>) {
@@ -95,11 +146,25 @@ impl Logger {
     }
 
     pub fn set_file(&self, file: PathBuf) {
-        self.file.set(file).unwrap_or(());
+        if self.file.lock().as_ref() != Some(&file) {
+            self.save_log();
+            *self.file.lock() = Some(file);
+        }
+    }
+
+    pub fn log_path(&self) -> Option<PathBuf> {
+        self.file.lock().clone()
     }
 
-    pub fn log_path(&self) -> Option<&Path> {
-        self.file.get().map(|f| f.as_path())
+    /// The last `count` entries logged, oldest first, for attaching to bug
+    /// reports.
+    pub fn recent(&self, count: usize) -> Vec<Entry> {
+        let history = self.history.lock();
+        history
+            .iter()
+            .skip(history.len().saturating_sub(count))
+            .cloned()
+            .collect()
     }
 
     pub fn flush_queue(&self) {
@@ -122,11 +187,14 @@ impl log::Log for Logger {
 
     fn log(&self, record: &Record) {
         let entry: Entry = record.into();
-        if record.target().starts_with("uk")
-            && (self.debug() || record.level() < LevelFilter::Debug)
-        {
+        if record.target().starts_with("uk") && record.level() <= self.level() {
             if !entry.args.starts_with("PROGRESS") {
                 self.record.lock().push(entry.clone());
+                let mut history = self.history.lock();
+                if history.len() >= MAX_HISTORY {
+                    history.pop_front();
+                }
+                history.push_back(entry.clone());
             }
             if let Some(sender) = self.sender.get() {
                 sender.send(Message::Log(entry)).unwrap();
@@ -153,3 +221,28 @@ impl log::Log for Logger {
         self.inner.flush();
     }
 }
+
+/// Moves `log.txt` to `log.1.txt`, `log.1.txt` to `log.2.txt`, and so on once
+/// it gets too big, dropping the oldest.
+fn rotate(path: &Path) {
+    if fs_err::metadata(path)
+        .map(|meta| meta.len() < MAX_LOG_SIZE)
+        .unwrap_or(true)
+    {
+        return;
+    }
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("txt");
+    let rotated = |n: usize| {
+        if n == 0 {
+            path.to_path_buf()
+        } else {
+            path.with_extension(format!("{n}.{ext}"))
+        }
+    };
+    for n in (1..LOG_FILES).rev() {
+        let from = rotated(n - 1);
+        if from.exists() {
+            fs_err::rename(from, rotated(n)).unwrap_or(());
+        }
+    }
+}
@@ -31,11 +31,19 @@ fn main() -> Result<()> {
     }
 
     let gui_flags = ["-p", "--portable", "-d", "--debug"];
-    if std::env::args().count() == 1
-        || std::env::args()
-            .skip(1)
-            .all(|a| gui_flags.contains(&a.as_str()))
-    {
+    let mut gui = true;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config-dir" {
+            args.next();
+        } else if arg.starts_with("--config-dir=") {
+            continue;
+        } else if !gui_flags.contains(&arg.as_str()) {
+            gui = false;
+            break;
+        }
+    }
+    if gui {
         if let Err(e) = std::panic::catch_unwind(gui::main) {
             println!(
                 "An unrecoverable error occured. Error details: {}",
@@ -58,7 +66,16 @@ fn main() -> Result<()> {
             }
         }
     } else {
-        let cmd = Ukmm::from_env_or_exit();
+        let args = std::env::args_os()
+            .skip(1)
+            .flat_map(|arg| {
+                match arg.to_str().and_then(|a| a.strip_prefix("--config-dir=")) {
+                    Some(dir) => vec!["--config-dir".into(), dir.into()],
+                    None => vec![arg],
+                }
+            })
+            .collect();
+        let cmd = Ukmm::from_vec(args).unwrap_or_else(|e| e.exit());
         cli::Runner::new(cmd).run()?;
     }
     Ok(())